@@ -0,0 +1,569 @@
+use bit_cursor::bit_cursor::BitCursor;
+use bitvec::{order::Msb0, vec::BitVec};
+use bytes::Bytes;
+
+use crate::{
+    pretty::HexBytes,
+    rtcp::{
+        rtcp_header::RtcpHeader,
+        rtcp_packet::{parse_single_rtcp_packet, SomeRtcpPacket},
+    },
+    rtp::{
+        header_extensions::{
+            CryptexHeaderExtensions, OneByteHeaderExtension, TwoByteHeaderExtension,
+        },
+        rtp_header::RtpHeader,
+        rtp_packet::read_rtp_packet_bytes,
+    },
+    util::{looks_like_rtcp, looks_like_rtp},
+};
+
+/// Annotate the raw bytes of an RTP or RTCP (possibly compound) packet with the fields they
+/// belong to, e.g.:
+///
+/// ```text
+/// RTCP packet 1: Receiver Report
+///     0  81                                  0-1  Version: 2
+///                                              2  Padding: 0
+///                                            3-7  Count: 1
+///     1  c9                                 8-15  Packet type: 201
+///     2  00 07                             16-31  Length: 7
+///     4  00 00 30 39                       32-63  Sender SSRC: 0x00003039
+///                                                 Report block 1
+///     8  00 00 d4 31                       64-95  SSRC: 0x0000d431
+///     ...
+/// ```
+///
+/// Each line has the byte offset and bytes of a field (bytes shared by several bit fields are
+/// only shown on the first one), the field's bit offsets within the buffer and its value.  The
+/// packets are parsed with the regular parsers to find their layout, so anything they reject is
+/// included as an error after the fields which could be annotated.  Payloads and feedback
+/// control information are annotated as a whole.
+pub fn annotate(data: &[u8]) -> String {
+    let mut annotator = Annotator {
+        data,
+        out: String::new(),
+        next_byte: 0,
+    };
+    if looks_like_rtcp(data) {
+        annotate_rtcp(&mut annotator);
+    } else if looks_like_rtp(data) {
+        annotate_rtp(&mut annotator);
+    } else {
+        annotator.title("Not an RTP or RTCP packet");
+        annotator.bytes("Data", 0, data.len());
+    }
+
+    annotator.out
+}
+
+struct Annotator<'a> {
+    data: &'a [u8],
+    out: String,
+    /// The first byte which hasn't been shown yet
+    next_byte: usize,
+}
+
+impl Annotator<'_> {
+    /// How many bytes of a field are shown before the rest are elided
+    const MAX_BYTES_SHOWN: usize = 8;
+    /// The width of the offset, bytes and bits columns, where subtitles start
+    const DESCRIPTION_COLUMN: usize = 48;
+
+    fn title(&mut self, title: impl AsRef<str>) {
+        self.out.push_str(title.as_ref());
+        self.out.push('\n');
+    }
+
+    fn subtitle(&mut self, subtitle: impl AsRef<str>) {
+        self.out.push_str(&format!(
+            "{:width$}{}\n",
+            "",
+            subtitle.as_ref(),
+            width = Self::DESCRIPTION_COLUMN
+        ));
+    }
+
+    fn error(&mut self, err: &anyhow::Error) {
+        self.out.push_str(&format!("error: {err:#}\n"));
+    }
+
+    /// Read the given bits as an integer, or None if they're past the end of the buffer
+    fn value(&self, start_bit: usize, len_bits: usize) -> Option<u64> {
+        if start_bit + len_bits > self.data.len() * 8 {
+            return None;
+        }
+        Some((start_bit..start_bit + len_bits).fold(0, |value, bit| {
+            (value << 1) | ((self.data[bit / 8] >> (7 - bit % 8)) & 1) as u64
+        }))
+    }
+
+    fn line(&mut self, start_bit: usize, len_bits: usize, description: String) {
+        let end_bit = start_bit + len_bits;
+        let first_byte = (start_bit / 8).max(self.next_byte);
+        let end_byte = end_bit.div_ceil(8).min(self.data.len());
+        let (offset, hex) = if first_byte < end_byte {
+            let shown = &self.data[first_byte..end_byte];
+            let hex = if shown.len() > Self::MAX_BYTES_SHOWN {
+                format!("{} ..", HexBytes(&shown[..Self::MAX_BYTES_SHOWN]))
+            } else {
+                HexBytes(shown).to_string()
+            };
+            self.next_byte = end_byte;
+            (first_byte.to_string(), hex)
+        } else {
+            (String::new(), String::new())
+        };
+        let bits = if len_bits == 1 {
+            start_bit.to_string()
+        } else {
+            format!("{start_bit}-{}", end_bit - 1)
+        };
+        self.out.push_str(&format!(
+            "{offset:>5}  {hex:<26}  {bits:>11}  {description}\n"
+        ));
+    }
+
+    /// Annotate the given bits as an integer field
+    fn field(&mut self, name: &str, start_bit: usize, len_bits: usize) -> Option<u64> {
+        let value = self.value(start_bit, len_bits);
+        let description = match value {
+            Some(value) => format!("{name}: {value}"),
+            None => format!("{name}: (truncated)"),
+        };
+        self.line(start_bit, len_bits, description);
+
+        value
+    }
+
+    /// Annotate the given bits as an integer field shown in hex, e.g. an SSRC
+    fn hex_field(&mut self, name: &str, start_bit: usize, len_bits: usize) -> Option<u64> {
+        let value = self.value(start_bit, len_bits);
+        let description = match value {
+            Some(value) => format!("{name}: {value:#0width$x}", width = 2 + len_bits / 4),
+            None => format!("{name}: (truncated)"),
+        };
+        self.line(start_bit, len_bits, description);
+
+        value
+    }
+
+    /// Annotate the given bytes as a single opaque field.  Nothing is written if it's empty.
+    fn bytes(&mut self, name: &str, start_byte: usize, len_bytes: usize) {
+        if len_bytes == 0 {
+            return;
+        }
+        let plural = if len_bytes == 1 { "" } else { "s" };
+        let description = if start_byte + len_bytes > self.data.len() {
+            format!("{name}: {len_bytes} byte{plural} (truncated)")
+        } else {
+            format!("{name}: {len_bytes} byte{plural}")
+        };
+        self.line(start_byte * 8, len_bytes * 8, description);
+    }
+
+    /// Annotate a trailing padding field of the given length, including the padding count byte
+    fn padding(&mut self, end_byte: usize, padding_len: usize) {
+        if padding_len == 0 {
+            return;
+        }
+        self.bytes("Padding", end_byte - padding_len, padding_len - 1);
+        self.field("Padding length", (end_byte - 1) * 8, 8);
+    }
+}
+
+fn annotate_rtp(a: &mut Annotator<'_>) {
+    let data = a.data;
+    a.title("RTP packet");
+    a.field("Version", 0, 2);
+    a.field("Padding", 2, 1);
+    a.field("Extension", 3, 1);
+    a.field("CSRC count", 4, 4);
+    a.field("Marker", 8, 1);
+    a.field("Payload type", 9, 7);
+    a.field("Sequence number", 16, 16);
+    a.field("Timestamp", 32, 32);
+    a.hex_field("SSRC", 64, 32);
+
+    let packet = match read_rtp_packet_bytes(Bytes::copy_from_slice(data)) {
+        Ok(packet) => packet,
+        Err(e) => return a.error(&e),
+    };
+    for (i, _) in packet.csrcs().enumerate() {
+        a.hex_field("CSRC", (12 + i * 4) * 8, 32);
+    }
+    let padding_len = packet.padding_len() as usize;
+    let extensions_start = RtpHeader::extensions_start_offset(data);
+    // The header extensions are everything between the CSRCs and the payload
+    let payload_start = data.len() - padding_len - packet.payload().len();
+    if payload_start > extensions_start {
+        annotate_rtp_header_extensions(a, extensions_start, payload_start - extensions_start);
+    }
+    a.bytes("Payload", payload_start, packet.payload().len());
+    a.padding(data.len(), padding_len);
+}
+
+fn annotate_rtp_header_extensions(a: &mut Annotator<'_>, start: usize, length: usize) {
+    let data = a.data;
+    let end = start + length;
+    a.subtitle("Header extensions");
+    let profile = a.hex_field("Profile", start * 8, 16).unwrap_or(0) as u16;
+    a.field("Length", (start + 2) * 8, 16);
+
+    let two_byte = if OneByteHeaderExtension::type_matches(profile) {
+        false
+    } else if TwoByteHeaderExtension::type_matches(profile) {
+        true
+    } else if CryptexHeaderExtensions::type_matches(profile) {
+        return a.bytes("Encrypted elements", start + 4, length - 4);
+    } else {
+        return a.bytes("Elements", start + 4, length - 4);
+    };
+    let mut offset = start + 4;
+    while offset < end {
+        if data[offset] == 0 {
+            let padding_len = data[offset..end].iter().take_while(|b| **b == 0).count();
+            a.bytes("Padding", offset, padding_len);
+            offset += padding_len;
+            continue;
+        }
+        let (header_len, data_len) = if two_byte {
+            a.field("Id", offset * 8, 8);
+            let data_len = a.field("Length", (offset + 1) * 8, 8).unwrap_or(0) as usize;
+            (2, data_len)
+        } else {
+            // An id of 15 means the rest of the block must be ignored
+            if a.field("Id", offset * 8, 4) == Some(15) {
+                return a.bytes("Ignored", offset, end - offset);
+            }
+            (
+                1,
+                a.field("Length - 1", offset * 8 + 4, 4).unwrap_or(0) as usize + 1,
+            )
+        };
+        let data_len = data_len.min(end.saturating_sub(offset + header_len));
+        a.bytes("Data", offset + header_len, data_len);
+        offset += header_len + data_len;
+    }
+}
+
+fn annotate_rtcp(a: &mut Annotator<'_>) {
+    let data = a.data;
+    let mut offset = 0;
+    let mut index = 1;
+    while offset < data.len() {
+        if data.len() - offset < RtcpHeader::SIZE_BYTES {
+            a.title("Trailing bytes");
+            a.bytes("Data", offset, data.len() - offset);
+            break;
+        }
+        let length_bytes =
+            (u16::from_be_bytes([data[offset + 2], data[offset + 3]]) as usize + 1) * 4;
+        let end = (offset + length_bytes).min(data.len());
+        let mut buf = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data[offset..end]));
+        let packet = parse_single_rtcp_packet(&mut buf);
+        match &packet {
+            Ok(packet) => a.title(format!("RTCP packet {index}: {}", rtcp_packet_name(packet))),
+            Err(_) => a.title(format!("RTCP packet {index}")),
+        }
+        let start_bit = offset * 8;
+        a.field("Version", start_bit, 2);
+        a.field("Padding", start_bit + 2, 1);
+        a.field("Count", start_bit + 3, 5);
+        a.field("Packet type", start_bit + 8, 8);
+        a.field("Length", start_bit + 16, 16);
+        match packet {
+            Ok(packet) => annotate_rtcp_payload(a, offset, end, &packet),
+            Err(e) => a.error(&e),
+        }
+        offset = end;
+        index += 1;
+    }
+}
+
+fn rtcp_packet_name(packet: &SomeRtcpPacket) -> &'static str {
+    match packet {
+        SomeRtcpPacket::CompoundRtcpPacket(_) => "Compound",
+        SomeRtcpPacket::RtcpByePacket(_) => "BYE",
+        SomeRtcpPacket::RtcpAppPacket(_) => "APP",
+        SomeRtcpPacket::RtcpSrPacket(_) => "Sender Report",
+        SomeRtcpPacket::RtcpRrPacket(_) => "Receiver Report",
+        SomeRtcpPacket::RtcpSdesPacket(_) => "SDES",
+        SomeRtcpPacket::RtcpFbNackPacket(_) => "Generic NACK",
+        SomeRtcpPacket::RtcpFbFirPacket(_) => "FIR",
+        SomeRtcpPacket::RtcpFbTccPacket(_) => "Transport-wide CC feedback",
+        SomeRtcpPacket::RtcpFbPliPacket(_) => "PLI",
+        SomeRtcpPacket::RtcpFbRembPacket(_) => "REMB",
+        SomeRtcpPacket::RtcpFbSliPacket(_) => "SLI",
+        SomeRtcpPacket::RtcpFbRpsiPacket(_) => "RPSI",
+        SomeRtcpPacket::RtcpFbLrrPacket(_) => "LRR",
+        SomeRtcpPacket::RtcpFbTmmbrPacket(_) => "TMMBR",
+        SomeRtcpPacket::RtcpFbTmmbnPacket(_) => "TMMBN",
+        SomeRtcpPacket::RtcpFbCcfbPacket(_) => "Congestion control feedback",
+        SomeRtcpPacket::RtcpXrPacket(_) => "Extended Report",
+        SomeRtcpPacket::CustomRtcpPacket { .. } => "Custom",
+        SomeRtcpPacket::UnknownRtcpPacket { .. } => "Unknown",
+    }
+}
+
+/// Annotate the fields after the header of the RTCP packet in the given bytes
+fn annotate_rtcp_payload(a: &mut Annotator<'_>, start: usize, end: usize, packet: &SomeRtcpPacket) {
+    let data = a.data;
+    let padding_len = match packet.header() {
+        Some(header) if header.has_padding => (data[end - 1] as usize).min(end - start - 4),
+        _ => 0,
+    };
+    let payload_start = start + RtcpHeader::SIZE_BYTES;
+    let payload_end = end - padding_len;
+    match packet {
+        SomeRtcpPacket::RtcpSrPacket(sr) => {
+            a.hex_field("Sender SSRC", payload_start * 8, 32);
+            let sender_info_start = (payload_start + 4) * 8;
+            a.field("NTP timestamp MSW", sender_info_start, 32);
+            a.field("NTP timestamp LSW", sender_info_start + 32, 32);
+            a.field("RTP timestamp", sender_info_start + 64, 32);
+            a.field("Sender packet count", sender_info_start + 96, 32);
+            a.field("Sender octet count", sender_info_start + 128, 32);
+            let report_blocks_end =
+                annotate_report_blocks(a, payload_start + 24, sr.report_blocks.len());
+            a.bytes(
+                "Profile extensions",
+                report_blocks_end,
+                payload_end.saturating_sub(report_blocks_end),
+            );
+        }
+        SomeRtcpPacket::RtcpRrPacket(rr) => {
+            a.hex_field("Sender SSRC", payload_start * 8, 32);
+            let report_blocks_end =
+                annotate_report_blocks(a, payload_start + 4, rr.report_blocks.len());
+            a.bytes(
+                "Profile extensions",
+                report_blocks_end,
+                payload_end.saturating_sub(report_blocks_end),
+            );
+        }
+        SomeRtcpPacket::RtcpByePacket(bye) => {
+            for i in 0..bye.ssrcs.len() {
+                a.hex_field("SSRC", (payload_start + i * 4) * 8, 32);
+            }
+            let reason_start = payload_start + bye.ssrcs.len() * 4;
+            if let Some(reason) = &bye.reason {
+                a.field("Reason length", reason_start * 8, 8);
+                a.bytes("Reason", reason_start + 1, reason.len());
+                let reason_end = reason_start + 1 + reason.len();
+                a.bytes(
+                    "Reason padding",
+                    reason_end,
+                    payload_end.saturating_sub(reason_end),
+                );
+            }
+        }
+        SomeRtcpPacket::RtcpAppPacket(app) => {
+            a.hex_field("SSRC", payload_start * 8, 32);
+            a.line(
+                (payload_start + 4) * 8,
+                32,
+                format!("Name: {}", String::from_utf8_lossy(&app.name)),
+            );
+            a.bytes("Application data", payload_start + 8, app.data.len());
+        }
+        SomeRtcpPacket::RtcpFbNackPacket(_)
+        | SomeRtcpPacket::RtcpFbFirPacket(_)
+        | SomeRtcpPacket::RtcpFbTccPacket(_)
+        | SomeRtcpPacket::RtcpFbPliPacket(_)
+        | SomeRtcpPacket::RtcpFbRembPacket(_)
+        | SomeRtcpPacket::RtcpFbSliPacket(_)
+        | SomeRtcpPacket::RtcpFbRpsiPacket(_)
+        | SomeRtcpPacket::RtcpFbLrrPacket(_)
+        | SomeRtcpPacket::RtcpFbTmmbrPacket(_)
+        | SomeRtcpPacket::RtcpFbTmmbnPacket(_) => {
+            a.hex_field("Sender SSRC", payload_start * 8, 32);
+            a.hex_field("Media source SSRC", (payload_start + 4) * 8, 32);
+            a.bytes(
+                "Feedback control information",
+                payload_start + 8,
+                payload_end.saturating_sub(payload_start + 8),
+            );
+        }
+        SomeRtcpPacket::RtcpFbCcfbPacket(_) => {
+            a.hex_field("Sender SSRC", payload_start * 8, 32);
+            a.bytes(
+                "Report blocks",
+                payload_start + 4,
+                payload_end.saturating_sub(payload_start + 8),
+            );
+            a.field("Report timestamp", (payload_end - 4) * 8, 32);
+        }
+        SomeRtcpPacket::RtcpXrPacket(_) => {
+            a.hex_field("Sender SSRC", payload_start * 8, 32);
+            let mut offset = payload_start + 4;
+            let mut index = 1;
+            while offset + 4 <= payload_end {
+                a.subtitle(format!("Block {index}"));
+                a.field("Block type", offset * 8, 8);
+                a.field("Type-specific", (offset + 1) * 8, 8);
+                let block_length = a.field("Block length", (offset + 2) * 8, 16).unwrap_or(0);
+                let contents_length = (block_length as usize * 4).min(payload_end - offset - 4);
+                a.bytes("Contents", offset + 4, contents_length);
+                offset += 4 + contents_length;
+                index += 1;
+            }
+        }
+        SomeRtcpPacket::RtcpSdesPacket(_) => {
+            a.bytes("Chunks", payload_start, payload_end - payload_start);
+        }
+        SomeRtcpPacket::CompoundRtcpPacket(_)
+        | SomeRtcpPacket::CustomRtcpPacket { .. }
+        | SomeRtcpPacket::UnknownRtcpPacket { .. } => {
+            a.bytes("Payload", payload_start, payload_end - payload_start);
+        }
+    }
+    a.padding(end, padding_len);
+}
+
+/// Annotate the given number of report blocks starting at the given byte, returning where they
+/// end
+fn annotate_report_blocks(a: &mut Annotator<'_>, start: usize, count: usize) -> usize {
+    for i in 0..count {
+        let block_start = (start + i * 24) * 8;
+        a.subtitle(format!("Report block {}", i + 1));
+        a.hex_field("SSRC", block_start, 32);
+        a.field("Fraction lost", block_start + 32, 8);
+        a.field("Cumulative lost", block_start + 40, 24);
+        a.field("Extended highest sequence number", block_start + 64, 32);
+        a.field("Interarrival jitter", block_start + 96, 32);
+        a.field("Last SR timestamp", block_start + 128, 32);
+        a.field("Delay since last SR", block_start + 160, 32);
+    }
+
+    start + count * 24
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_rtcp() {
+        #[rustfmt::skip]
+        let data = [
+            // RR
+            0x81, 0xc9, 0x00, 0x07,
+            0x00, 0x00, 0x30, 0x39,
+            0x00, 0x00, 0xd4, 0x31,
+            0x19, 0x00, 0x00, 0x64,
+            0x00, 0x01, 0x00, 0x64,
+            0x00, 0x00, 0x00, 0x1e,
+            0x00, 0x00, 0x03, 0xe8,
+            0x00, 0x00, 0x01, 0xf4,
+            // PLI
+            0x81, 0xce, 0x00, 0x02,
+            0x00, 0x00, 0x30, 0x39,
+            0x00, 0x00, 0xd4, 0x31,
+        ];
+
+        assert_eq!(
+            annotate(&data),
+            "\
+RTCP packet 1: Receiver Report
+    0  81                                  0-1  Version: 2
+                                             2  Padding: 0
+                                           3-7  Count: 1
+    1  c9                                 8-15  Packet type: 201
+    2  00 07                             16-31  Length: 7
+    4  00 00 30 39                       32-63  Sender SSRC: 0x00003039
+                                                Report block 1
+    8  00 00 d4 31                       64-95  SSRC: 0x0000d431
+   12  19                               96-103  Fraction lost: 25
+   13  00 00 64                        104-127  Cumulative lost: 100
+   16  00 01 00 64                     128-159  Extended highest sequence number: 65636
+   20  00 00 00 1e                     160-191  Interarrival jitter: 30
+   24  00 00 03 e8                     192-223  Last SR timestamp: 1000
+   28  00 00 01 f4                     224-255  Delay since last SR: 500
+RTCP packet 2: PLI
+   32  81                              256-257  Version: 2
+                                           258  Padding: 0
+                                       259-263  Count: 1
+   33  ce                              264-271  Packet type: 206
+   34  00 02                           272-287  Length: 2
+   36  00 00 30 39                     288-319  Sender SSRC: 0x00003039
+   40  00 00 d4 31                     320-351  Media source SSRC: 0x0000d431
+"
+        );
+    }
+
+    #[test]
+    fn test_annotate_rtp() {
+        #[rustfmt::skip]
+        let data = [
+            0xb0, 0xe0, 0x04, 0xd2,
+            0x00, 0x00, 0x16, 0x2e,
+            0xab, 0xcd, 0xef, 0x01,
+            // Header extensions
+            0xbe, 0xde, 0x00, 0x01,
+            0x31, 0xde, 0xad, 0x00,
+            // Payload
+            0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a,
+            // Padding
+            0x00, 0x02,
+        ];
+
+        assert_eq!(
+            annotate(&data),
+            "\
+RTP packet
+    0  b0                                  0-1  Version: 2
+                                             2  Padding: 1
+                                             3  Extension: 1
+                                           4-7  CSRC count: 0
+    1  e0                                    8  Marker: 1
+                                          9-15  Payload type: 96
+    2  04 d2                             16-31  Sequence number: 1234
+    4  00 00 16 2e                       32-63  Timestamp: 5678
+    8  ab cd ef 01                       64-95  SSRC: 0xabcdef01
+                                                Header extensions
+   12  be de                            96-111  Profile: 0xbede
+   14  00 01                           112-127  Length: 1
+   16  31                              128-131  Id: 3
+                                       132-135  Length - 1: 1
+   17  de ad                           136-151  Data: 2 bytes
+   19  00                              152-159  Padding: 1 byte
+   20  01 02 03 04 05 06 07 08 ..      160-239  Payload: 10 bytes
+   30  00                              240-247  Padding: 1 byte
+   31  02                              248-255  Padding length: 2
+"
+        );
+    }
+
+    #[test]
+    fn test_annotate_invalid_extensions() {
+        #[rustfmt::skip]
+        let header = [
+            0x90, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01,
+        ];
+        // An element which is longer than the extensions block, and an extensions block which is
+        // longer than the packet
+        for extensions in [
+            &[0xbe, 0xde, 0x00, 0x01, 0x1f, 0xaa, 0x00, 0x00][..],
+            &[0xbe, 0xde, 0x40, 0x00][..],
+        ] {
+            let annotated = annotate(&[&header[..], extensions].concat());
+            assert!(annotated.starts_with("RTP packet\n"));
+            assert!(annotated.contains("SSRC: 0x00000001"));
+            assert!(annotated.contains("error: "));
+        }
+    }
+
+    #[test]
+    fn test_annotate_truncated() {
+        // An RR claiming a report block which isn't there
+        let data = [0x81, 0xc9, 0x00, 0x07, 0x00, 0x00, 0x30, 0x39];
+
+        let annotated = annotate(&data);
+        assert!(annotated.starts_with("RTCP packet 1\n"));
+        assert!(annotated.contains("Length: 7"));
+        assert!(annotated.contains("error: buffer is truncated"));
+    }
+}
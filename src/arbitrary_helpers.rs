@@ -0,0 +1,51 @@
+//! Helpers for generating the fields whose types don't implement `Arbitrary` (the
+//! non-standard-width integers, `Bytes` and `BitVec`), for use with `#[arbitrary(with = ..)]`.
+//!
+//! Generated packets are "valid-ish": fields are in range, RTCP headers have version 2 and no
+//! padding, and fields which must be 0 (e.g. the media source SSRC of FIR) are 0, but lengths and
+//! counts in headers are arbitrary.  Call the packet's `sync` before writing it to
+//! exercise the write -> read path.
+
+use arbitrary::{Result, Unstructured};
+use bitvec::{order::Msb0, vec::BitVec};
+use bytes::Bytes;
+
+macro_rules! nsw_arbitrary {
+    ($($name:ident: $inner:ty),* $(,)?) => {
+        $(
+            pub fn $name(u: &mut Unstructured) -> Result<bit_cursor::nsw_types::$name> {
+                let max = <$inner>::from(bit_cursor::nsw_types::$name::MAX);
+                Ok(bit_cursor::nsw_types::$name::new(u.int_in_range(0..=max)?))
+            }
+        )*
+    };
+}
+
+nsw_arbitrary!(
+    u2: u8,
+    u3: u8,
+    u4: u8,
+    u5: u8,
+    u6: u8,
+    u7: u8,
+    u9: u16,
+    u12: u16,
+    u13: u16,
+    u15: u16,
+    u17: u32,
+    u18: u32,
+    u24: u32,
+);
+
+pub fn bytes(u: &mut Unstructured) -> Result<Bytes> {
+    Ok(Bytes::copy_from_slice(u.arbitrary::<&[u8]>()?))
+}
+
+/// Bytes for a field which must be a multiple of 32 bits long
+pub fn words(u: &mut Unstructured) -> Result<Vec<u8>> {
+    Ok(u.arbitrary::<Vec<[u8; 4]>>()?.concat())
+}
+
+pub fn bit_vec(u: &mut Unstructured) -> Result<BitVec<u8, Msb0>> {
+    Ok(BitVec::from_vec(u.arbitrary()?))
+}
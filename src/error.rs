@@ -0,0 +1,147 @@
+use std::fmt::Display;
+
+/// The kinds of errors which callers may want to handle programmatically, e.g. to tell a
+/// truncated packet apart from a malformed one.
+///
+/// Functions still return [`anyhow::Result`]s so the context of where an error happened (e.g.
+/// "sub packet 2: report block 1: ssrc") is preserved.  The `RtpParseError` at the root of an
+/// error's chain can be found with [`RtpParseError::find`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RtpParseError {
+    /// The buffer ended before the packet or field being read did.  The needed and available
+    /// lengths are included when they're known.
+    Truncated {
+        needed_bytes: Option<usize>,
+        available_bytes: Option<usize>,
+    },
+    /// A field has a value which is invalid or can't be represented
+    InvalidFieldValue {
+        field: &'static str,
+        expected: String,
+        actual: String,
+    },
+    /// A type of packet (or payload structure) which isn't supported, e.g. an H.264 NAL unit
+    /// type
+    UnsupportedPacketType {
+        kind: &'static str,
+        packet_type: u16,
+    },
+}
+
+impl RtpParseError {
+    pub fn truncated(needed_bytes: usize, available_bytes: usize) -> Self {
+        RtpParseError::Truncated {
+            needed_bytes: Some(needed_bytes),
+            available_bytes: Some(available_bytes),
+        }
+    }
+
+    pub fn invalid_field(
+        field: &'static str,
+        expected: impl Display,
+        actual: impl Display,
+    ) -> Self {
+        RtpParseError::InvalidFieldValue {
+            field,
+            expected: expected.to_string(),
+            actual: actual.to_string(),
+        }
+    }
+
+    /// Find the `RtpParseError` at the root of the given error's chain.  Errors caused by reading
+    /// past the end of a buffer are reported as [`RtpParseError::Truncated`], even when they
+    /// weren't explicitly created as one.
+    pub fn find(err: &anyhow::Error) -> Option<RtpParseError> {
+        err.chain().find_map(|cause| {
+            if let Some(rtp_parse_error) = cause.downcast_ref::<RtpParseError>() {
+                Some(rtp_parse_error.clone())
+            } else if cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|io_error| io_error.kind() == std::io::ErrorKind::UnexpectedEof)
+            {
+                Some(RtpParseError::Truncated {
+                    needed_bytes: None,
+                    available_bytes: None,
+                })
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl Display for RtpParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RtpParseError::Truncated {
+                needed_bytes,
+                available_bytes,
+            } => {
+                write!(f, "buffer is truncated")?;
+                match (needed_bytes, available_bytes) {
+                    (Some(needed), Some(available)) => {
+                        write!(f, ": need {needed} bytes, have {available}")
+                    }
+                    (Some(needed), None) => write!(f, ": need {needed} bytes"),
+                    (None, Some(available)) => write!(f, ": have {available} bytes"),
+                    (None, None) => Ok(()),
+                }
+            }
+            RtpParseError::InvalidFieldValue {
+                field,
+                expected,
+                actual,
+            } => write!(f, "invalid {field}: expected {expected}, got {actual}"),
+            RtpParseError::UnsupportedPacketType { kind, packet_type } => {
+                write!(f, "unsupported {kind} type {packet_type}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for RtpParseError {}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::{Context, Result};
+    use bit_cursor::{bit_cursor::BitCursor, bit_read_exts::BitReadExts, byte_order::NetworkOrder};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_find_through_context() {
+        let result: Result<()> = Err(RtpParseError::truncated(12, 4).into());
+        let err = result
+            .context("rtp header")
+            .context("packet 3")
+            .unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(12, 4))
+        );
+        assert_eq!(
+            format!("{err:#}"),
+            "packet 3: rtp header: buffer is truncated: need 12 bytes, have 4"
+        );
+    }
+
+    #[test]
+    fn test_find_eof() {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 2]));
+        let err = cursor
+            .read_u32::<NetworkOrder>()
+            .context("ssrc")
+            .unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::Truncated {
+                needed_bytes: None,
+                available_bytes: None
+            })
+        );
+
+        let err = anyhow::anyhow!("some other error");
+        assert_eq!(RtpParseError::find(&err), None);
+    }
+}
@@ -7,8 +7,19 @@ use std::{
 use bit_cursor::{bit_cursor::BitCursor, bit_read::BitRead, bit_write::BitWrite};
 use bitvec::{order::Msb0, slice::BitSlice, vec::BitVec};
 
+pub mod annotate;
+#[cfg(feature = "arbitrary")]
+mod arbitrary_helpers;
+pub mod error;
+pub mod ntp;
+pub mod parse_mode;
+pub mod pretty;
 pub mod rtcp;
 pub mod rtp;
+#[cfg(feature = "serde")]
+mod serde_nsw;
+#[cfg(feature = "proptest")]
+pub mod strategies;
 pub mod util;
 
 pub trait PacketBuffer: BitRead + Seek + Debug + LowerHex {
@@ -56,5 +67,12 @@ impl PacketBuffer for BitCursor<&BitSlice<u8, Msb0>> {
     }
 }
 
+/// Types which know how many bytes they take up when written, so buffers can be sized (e.g. to
+/// fit within an MTU) before writing.
+pub trait LengthBytes {
+    /// The length of this item in bytes when written, including any headers and padding
+    fn length_bytes(&self) -> usize;
+}
+
 pub trait PacketBufferMut: PacketBuffer + BitWrite {}
 impl<T> PacketBufferMut for T where T: PacketBuffer + BitWrite {}
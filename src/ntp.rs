@@ -0,0 +1,134 @@
+use std::{
+    fmt::Display,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The number of seconds between the NTP epoch (1900) and the Unix epoch (1970)
+pub const NTP_UNIX_EPOCH_OFFSET_SECS: u64 = 2_208_988_800;
+
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-4
+/// A 64 bit NTP timestamp: a 32.32 fixed point number of seconds since 1900.  RTCP (e.g. the LSR
+/// and DLSR fields of reception reports) also uses a compact form of it: the middle 32 bits, i.e.
+/// a 16.16 fixed point number of seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct NtpTimestamp(pub u64);
+
+impl NtpTimestamp {
+    /// Create a timestamp from its most significant (whole seconds) and least significant
+    /// (fractional seconds) words
+    pub fn from_parts(msw: u32, lsw: u32) -> Self {
+        NtpTimestamp(((msw as u64) << 32) | lsw as u64)
+    }
+
+    /// The whole seconds
+    pub fn msw(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    /// The fractional seconds, in units of 1/2^32 seconds
+    pub fn lsw(&self) -> u32 {
+        self.0 as u32
+    }
+
+    /// The middle 32 bits of the timestamp, as used in the LSR field of reception reports
+    pub fn compact(&self) -> u32 {
+        (self.0 >> 16) as u32
+    }
+
+    /// Create a timestamp from the time since the NTP epoch.  Times too large to represent wrap,
+    /// as the seconds field itself does (in 2036).
+    pub fn from_duration(since_ntp_epoch: Duration) -> Self {
+        let fraction = ((since_ntp_epoch.subsec_nanos() as u64) << 32) / 1_000_000_000;
+        NtpTimestamp((since_ntp_epoch.as_secs() << 32) | fraction)
+    }
+
+    /// The time since the NTP epoch
+    pub fn to_duration(&self) -> Duration {
+        let nanos = (self.lsw() as u64 * 1_000_000_000) >> 32;
+        Duration::new(self.msw() as u64, nanos as u32)
+    }
+
+    /// Create a timestamp from a wall clock time.  Times before the Unix epoch are clamped to it.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let since_unix_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        Self::from_duration(since_unix_epoch + Duration::from_secs(NTP_UNIX_EPOCH_OFFSET_SECS))
+    }
+
+    /// The timestamp as a wall clock time.  Times before the Unix epoch are clamped to it.
+    pub fn to_system_time(&self) -> SystemTime {
+        UNIX_EPOCH
+            + self
+                .to_duration()
+                .saturating_sub(Duration::from_secs(NTP_UNIX_EPOCH_OFFSET_SECS))
+    }
+}
+
+impl From<u64> for NtpTimestamp {
+    fn from(value: u64) -> Self {
+        NtpTimestamp(value)
+    }
+}
+
+impl From<NtpTimestamp> for u64 {
+    fn from(timestamp: NtpTimestamp) -> Self {
+        timestamp.0
+    }
+}
+
+impl Display for NtpTimestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let duration = self.to_duration();
+        write!(f, "{}.{:09}", duration.as_secs(), duration.subsec_nanos())
+    }
+}
+
+/// Convert a duration to the compact (16.16 fixed point seconds) NTP format, as used in the DLSR
+/// field of reception reports.  Durations too large to represent are clamped.
+pub fn duration_to_compact_ntp(duration: Duration) -> u32 {
+    let value = (duration.as_nanos() << 16) / 1_000_000_000;
+    value.min(u32::MAX as u128) as u32
+}
+
+/// Convert a value in the compact (16.16 fixed point seconds) NTP format to a duration
+pub fn compact_ntp_to_duration(value: u32) -> Duration {
+    Duration::from_nanos((value as u64 * 1_000_000_000) >> 16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_system_time_roundtrip() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let timestamp = NtpTimestamp::from_system_time(time);
+        assert_eq!(
+            timestamp.msw() as u64,
+            1_700_000_000 + NTP_UNIX_EPOCH_OFFSET_SECS
+        );
+        assert_eq!(timestamp.lsw(), 0x8000_0000);
+        assert_eq!(timestamp.to_system_time(), time);
+        assert_eq!(
+            NtpTimestamp::from_parts(timestamp.msw(), timestamp.lsw()),
+            timestamp
+        );
+    }
+
+    #[test]
+    fn test_compact() {
+        let timestamp = NtpTimestamp::from_parts(0x1234_5678, 0x9ABC_DEF0);
+        assert_eq!(timestamp.compact(), 0x5678_9ABC);
+        assert_eq!(
+            duration_to_compact_ntp(Duration::from_millis(1500)),
+            0x0001_8000
+        );
+        assert_eq!(
+            compact_ntp_to_duration(0x0001_8000),
+            Duration::from_millis(1500)
+        );
+        assert_eq!(
+            duration_to_compact_ntp(Duration::from_secs(100_000)),
+            u32::MAX
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+/// How strictly packets are checked against the spec when they're parsed.  Packets which can't
+/// be parsed at all (e.g. truncated ones) are rejected in both modes; the mode only affects
+/// violations which the parser can safely skip past.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ParseMode {
+    /// Reject packets with a version other than 2, inconsistent lengths or non-zero reserved bits
+    Strict,
+    /// Accept such packets, recording a warning for each violation
+    #[default]
+    Lenient,
+}
+
+impl ParseMode {
+    /// Handle a spec violation found while parsing: in strict mode it's returned as an error, in
+    /// lenient mode it's added to the given warnings.
+    pub fn on_violation(
+        &self,
+        warnings: &mut ParseWarnings,
+        violation: RtpParseError,
+    ) -> Result<()> {
+        match self {
+            ParseMode::Strict => bail!(violation),
+            ParseMode::Lenient => {
+                warnings.push(violation);
+                Ok(())
+            }
+        }
+    }
+
+    /// Handle a reserved field which isn't zero as a violation
+    pub fn check_reserved(
+        &self,
+        warnings: &mut ParseWarnings,
+        field: &'static str,
+        value: u32,
+    ) -> Result<()> {
+        if value != 0 {
+            self.on_violation(warnings, RtpParseError::invalid_field(field, 0, value))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The spec violations which were accepted while parsing a packet in [`ParseMode::Lenient`].
+/// The `_with_context` parse functions return them alongside the packet.
+pub type ParseWarnings = Vec<RtpParseError>;
@@ -0,0 +1,197 @@
+use std::fmt::{Display, Formatter, Result};
+
+/// Types which can be printed as a tree of fields, like the packet details in Wireshark, e.g.:
+///
+/// ```text
+/// RTCP Receiver Report
+///   Version: 2
+///   Padding: false
+///   Count: 1
+///   Packet type: 201
+///   Length: 7 (32 bytes)
+///   Sender SSRC: 0x00003039
+///   Report block 1
+///     SSRC: 0x0000d431
+///     ...
+/// ```
+pub trait PrettyPrint {
+    /// Add this item's fields to the given tree
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> Result;
+
+    /// A wrapper which displays this item as a tree of fields
+    fn pretty(&self) -> Pretty<'_, Self>
+    where
+        Self: Sized,
+    {
+        Pretty(self)
+    }
+}
+
+/// Displays the item it wraps as a tree of fields.  See [`PrettyPrint::pretty`].
+pub struct Pretty<'a, T>(&'a T);
+
+impl<T: PrettyPrint> Display for Pretty<'_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        self.0.pretty_print(&mut FieldTree { f, depth: 0 })
+    }
+}
+
+/// Writes fields, one per line, indented by how deeply nested they are
+pub struct FieldTree<'a, 'b> {
+    f: &'a mut Formatter<'b>,
+    depth: usize,
+}
+
+impl FieldTree<'_, '_> {
+    const INDENT: usize = 2;
+
+    pub fn field(&mut self, name: &str, value: impl Display) -> Result {
+        writeln!(
+            self.f,
+            "{:indent$}{name}: {value}",
+            "",
+            indent = self.depth * Self::INDENT
+        )
+    }
+
+    /// Write the given title and then the contents, nested one level deeper
+    pub fn subtree(
+        &mut self,
+        title: impl Display,
+        contents: impl FnOnce(&mut Self) -> Result,
+    ) -> Result {
+        writeln!(
+            self.f,
+            "{:indent$}{title}",
+            "",
+            indent = self.depth * Self::INDENT
+        )?;
+        self.depth += 1;
+        let result = contents(self);
+        self.depth -= 1;
+
+        result
+    }
+}
+
+/// Displays bytes as space-separated hex pairs
+pub struct HexBytes<'a>(pub &'a [u8]);
+
+impl Display for HexBytes<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Displays an SSRC (or other 32 bit identifier) as zero-padded hex
+pub struct Ssrc(pub u32);
+
+impl Display for Ssrc {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        write!(f, "{:#010x}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::nsw_types::{u2, u24, u5, u7};
+
+    use crate::{
+        rtcp::{
+            rtcp_header::RtcpHeader, rtcp_packet::SomeRtcpPacket,
+            rtcp_report_block::RtcpReportBlock, rtcp_rr::RtcpRrPacket,
+        },
+        rtp::rtp_packet::RtpPacketBuilder,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_pretty_print_rtcp() {
+        let mut rr = RtcpRrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            sender_ssrc: 12345,
+            report_blocks: vec![RtcpReportBlock {
+                ssrc: 54321,
+                fraction_lost: 25,
+                cumulative_lost: u24::new(100),
+                extended_highest_seq_num: 65636,
+                interarrival_jitter: 30,
+                last_sr_timestamp: 1000,
+                delay_since_last_sr: 500,
+            }],
+            profile_extensions: Vec::new(),
+        };
+        rr.sync().unwrap();
+        let packet = SomeRtcpPacket::CompoundRtcpPacket(vec![SomeRtcpPacket::RtcpRrPacket(rr)]);
+
+        assert_eq!(
+            packet.pretty().to_string(),
+            "\
+Compound RTCP packet
+  RTCP Receiver Report
+    Version: 2
+    Padding: false
+    Count: 1
+    Packet type: 201
+    Length: 7 (32 bytes)
+    Sender SSRC: 0x00003039
+    Report block 1
+      SSRC: 0x0000d431
+      Fraction lost: 25/256
+      Cumulative lost: 100
+      Extended highest sequence number: 65636
+      Interarrival jitter: 30
+      Last SR timestamp: 1000
+      Delay since last SR: 500
+"
+        );
+    }
+
+    #[test]
+    fn test_pretty_print_rtp() {
+        let packet = RtpPacketBuilder::new()
+            .marked(true)
+            .payload_type(u7::new(96))
+            .seq_num(1234)
+            .timestamp(5678)
+            .ssrc(0xabcdef01)
+            .csrc(1)
+            .extension(3, &[0xde, 0xad])
+            .payload(&[0; 100])
+            .build()
+            .unwrap();
+
+        assert_eq!(
+            packet.pretty().to_string(),
+            "\
+RTP packet
+  Version: 2
+  Padding: false
+  Extension: true
+  CSRC count: 1
+  Marker: true
+  Payload type: 96
+  Sequence number: 1234
+  Timestamp: 5678
+  SSRC: 0xabcdef01
+  CSRC: 0x00000001
+  Header extensions
+    Id 3: de ad
+  Payload: 100 bytes
+"
+        );
+    }
+}
@@ -1,14 +1,44 @@
+pub mod rtcp_app;
 pub mod rtcp_bye;
+pub mod rtcp_compound_builder;
+pub mod rtcp_fb_ccfb;
 pub mod rtcp_fb_fir;
 pub mod rtcp_fb_header;
+pub mod rtcp_fb_lrr;
 pub mod rtcp_fb_nack;
+pub mod rtcp_fb_nack_aggregator;
 pub mod rtcp_fb_packet;
 pub mod rtcp_fb_pli;
+pub mod rtcp_fb_remb;
+pub mod rtcp_fb_rpsi;
+pub mod rtcp_fb_sli;
 pub mod rtcp_fb_tcc;
+pub mod rtcp_fb_tcc_builder;
+pub mod rtcp_fb_tcc_send_history;
+pub mod rtcp_fb_tmmbn;
+pub mod rtcp_fb_tmmbr;
 pub mod rtcp_header;
+pub mod rtcp_jitter_estimator;
+pub mod rtcp_media_clock_mapper;
 pub mod rtcp_packet;
+pub mod rtcp_parser_registry;
 pub mod rtcp_report_block;
+pub mod rtcp_report_block_generator;
 pub mod rtcp_rr;
+pub mod rtcp_scheduler;
 pub mod rtcp_sdes;
 pub mod rtcp_sender_info;
 pub mod rtcp_sr;
+pub mod rtcp_sr_generator;
+pub mod rtcp_xr;
+pub mod rtcp_xr_burst_gap_loss;
+pub mod rtcp_xr_delay_metrics;
+pub mod rtcp_xr_dlrr;
+pub mod rtcp_xr_duplicate_rle;
+pub mod rtcp_xr_loss_rle;
+pub mod rtcp_xr_measurement_info;
+pub mod rtcp_xr_packet_receipt_times;
+pub mod rtcp_xr_rrt;
+pub mod rtcp_xr_statistics_summary;
+pub mod rtcp_xr_voip_metrics;
+pub mod srtcp;
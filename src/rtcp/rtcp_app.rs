@@ -0,0 +1,179 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    nsw_types::u5,
+};
+
+use crate::{
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_header::{write_rtcp_header, RtcpHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.7
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| subtype |   PT=APP=204  |             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           SSRC/CSRC                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                          name (ASCII)                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   application-dependent data                ...
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The subtype is carried in the report count field of the header.  The application-dependent
+/// data is opaque to us and is preserved as-is; its length must be a multiple of 32 bits.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpAppPacket {
+    pub header: RtcpHeader,
+    pub ssrc: u32,
+    pub name: [u8; 4],
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::words))]
+    pub data: Vec<u8>,
+}
+
+impl RtcpAppPacket {
+    pub const PT: u8 = 204;
+
+    pub fn subtype(&self) -> u5 {
+        self.header.report_count
+    }
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("APP payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        if !self.data.len().is_multiple_of(4) {
+            bail!(
+                "APP data must be a multiple of 4 bytes, was {} bytes",
+                self.data.len()
+            );
+        }
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = Self::PT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpAppPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES + 8 + self.data.len()
+    }
+}
+
+pub fn read_rtcp_app<B: PacketBuffer>(buf: &mut B, header: RtcpHeader) -> Result<RtcpAppPacket> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let mut name = [0u8; 4];
+    buf.read_exact(&mut name).context("name")?;
+    let mut data = vec![0u8; buf.bytes_remaining()];
+    buf.read_exact(&mut data).context("data")?;
+
+    Ok(RtcpAppPacket {
+        header,
+        ssrc,
+        name,
+        data,
+    })
+}
+
+pub fn write_rtcp_app<B: PacketBufferMut>(buf: &mut B, packet: &RtcpAppPacket) -> Result<()> {
+    if !packet.data.len().is_multiple_of(4) {
+        bail!(
+            "APP data must be a multiple of 4 bytes, was {} bytes",
+            packet.data.len()
+        );
+    }
+    write_rtcp_header(buf, &packet.header).context("header")?;
+    buf.write_u32::<NetworkOrder>(packet.ssrc).context("ssrc")?;
+    buf.write_all(&packet.name).context("name")?;
+    buf.write_all(&packet.data).context("data")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpAppPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Application-defined", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Subtype", u8::from(self.subtype()))?;
+            tree.field("Name", String::from_utf8_lossy(&self.name))?;
+            tree.field("Data", HexBytes(&self.data))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_packet::{parse_rtcp_packet, SomeRtcpPacket};
+
+    use super::*;
+
+    #[test]
+    fn test_write_rtcp_app_roundtrip() {
+        let mut packet = RtcpAppPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(3),
+                packet_type: 0,
+                length_field: 0,
+            },
+            ssrc: 42,
+            name: *b"TEST",
+            data: vec![0xDE, 0xAD, 0xBE, 0xEF, 0x01, 0x02, 0x03, 0x04],
+        };
+        packet.sync().unwrap();
+        assert_eq!(packet.header.packet_type, RtcpAppPacket::PT);
+        assert_eq!(packet.header.length_field, 4);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_rtcp_app(&mut cursor, &packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        match parse_rtcp_packet(&mut read_cursor).unwrap() {
+            SomeRtcpPacket::RtcpAppPacket(read_packet) => {
+                assert_eq!(read_packet.subtype(), u5::new(3));
+                assert_eq!(read_packet.ssrc, 42);
+                assert_eq!(&read_packet.name, b"TEST");
+                assert_eq!(read_packet.data, packet.data);
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_sync_unaligned_data() {
+        let mut packet = RtcpAppPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            ssrc: 42,
+            name: *b"TEST",
+            data: vec![0x01, 0x02],
+        };
+        assert!(packet.sync().is_err());
+    }
+}
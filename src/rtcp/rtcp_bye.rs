@@ -1,12 +1,17 @@
 use std::str::from_utf8;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
     bit_read::BitRead, bit_read_exts::BitReadExts, bit_write::BitWrite,
     bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
 };
 
-use super::rtcp_header::{write_rtcp_header, RtcpHeader};
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes,
+};
+
+use super::rtcp_header::{report_count_from_len, write_rtcp_header, RtcpHeader};
 
 /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.6
 ///        0                   1                   2                   3
@@ -21,6 +26,8 @@ use super::rtcp_header::{write_rtcp_header, RtcpHeader};
 /// (opt) |     length    |               reason for leaving            ...
 ///       +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpByePacket {
     pub header: RtcpHeader,
     pub ssrcs: Vec<u32>,
@@ -29,6 +36,46 @@ pub struct RtcpByePacket {
 
 impl RtcpByePacket {
     pub const PT: u8 = 203;
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes.  The reason
+    /// (if present) is padded with null octets to a 32-bit boundary.
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("BYE payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, source count and length) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        if let Some(reason) = &self.reason {
+            if reason.len() > u8::MAX as usize {
+                bail!(
+                    "BYE reason can be at most 255 bytes, was {} bytes",
+                    reason.len()
+                );
+            }
+        }
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = Self::PT;
+        self.header.report_count =
+            report_count_from_len(self.ssrcs.len()).context("source count")?;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpByePacket {
+    fn length_bytes(&self) -> usize {
+        let reason_length_bytes = match &self.reason {
+            Some(reason) => (1 + reason.len()).next_multiple_of(4),
+            None => 0,
+        };
+        RtcpHeader::SIZE_BYTES + self.ssrcs.len() * 4 + reason_length_bytes
+    }
 }
 
 pub fn read_rtcp_bye<R: BitRead>(buf: &mut R, header: RtcpHeader) -> Result<RtcpByePacket> {
@@ -44,6 +91,11 @@ pub fn read_rtcp_bye<R: BitRead>(buf: &mut R, header: RtcpHeader) -> Result<Rtcp
         if let Ok(reason_length) = buf.read_u8() {
             let mut reason_bytes = vec![0; reason_length.into()];
             std::io::Read::read(buf, &mut reason_bytes).context("bye reason bytes")?;
+            // The reason is padded with zeros to a 32-bit boundary
+            let mut padding = [0u8; 3];
+            let padding_length = (4 - (1 + reason_bytes.len()) % 4) % 4;
+            std::io::Read::read(buf, &mut padding[..padding_length])
+                .context("bye reason padding")?;
             Some(
                 from_utf8(&reason_bytes)
                     .context("convert bye reason from urf8")
@@ -78,14 +130,38 @@ pub fn write_rtcp_bye<W: BitWrite>(buf: &mut W, packet: &RtcpByePacket) -> Resul
 
     if let Some(reason) = &packet.reason {
         let utf8_bytes = reason.as_bytes();
+        if utf8_bytes.len() > u8::MAX as usize {
+            bail!(
+                "BYE reason can be at most 255 bytes, was {} bytes",
+                utf8_bytes.len()
+            );
+        }
         buf.write_u8(utf8_bytes.len() as u8)
             .context("reason length")?;
-        std::io::Write::write(buf, utf8_bytes).context("reason string")?;
+        std::io::Write::write_all(buf, utf8_bytes).context("reason string")?;
+        // The reason is padded with null octets to the next 32-bit boundary
+        let padding_bytes = (1 + utf8_bytes.len()).next_multiple_of(4) - (1 + utf8_bytes.len());
+        std::io::Write::write_all(buf, &vec![0u8; padding_bytes]).context("reason padding")?;
     }
 
     Ok(())
 }
 
+impl PrettyPrint for RtcpByePacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Goodbye", |tree| {
+            self.header.pretty_print(tree)?;
+            for ssrc in &self.ssrcs {
+                tree.field("SSRC", Ssrc(*ssrc))?;
+            }
+            if let Some(reason) = &self.reason {
+                tree.field("Reason", reason)?;
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bit_cursor::{bit_cursor::BitCursor, nsw_types::*};
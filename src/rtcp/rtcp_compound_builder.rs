@@ -0,0 +1,240 @@
+use std::fmt::Display;
+
+use anyhow::{Context, Result};
+
+use super::{rtcp_packet::SomeRtcpPacket, rtcp_sdes::SdesItem};
+
+/// A way in which a compound RTCP packet doesn't follow the rules in
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.1
+#[derive(Debug, PartialEq, Eq)]
+pub enum RtcpCompoundViolation {
+    /// The compound packet contains no packets
+    Empty,
+    /// The first packet in the compound packet must be an SR or RR
+    FirstPacketNotReport,
+    /// The compound packet must contain an SDES packet with a CNAME item
+    MissingCname,
+    /// A BYE packet must be the last packet in the compound packet.  Contains the index of the
+    /// offending BYE.
+    ByeNotLast(usize),
+}
+
+impl Display for RtcpCompoundViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RtcpCompoundViolation::Empty => write!(f, "compound packet is empty"),
+            RtcpCompoundViolation::FirstPacketNotReport => {
+                write!(f, "first packet must be an SR or RR")
+            }
+            RtcpCompoundViolation::MissingCname => {
+                write!(f, "no SDES packet with a CNAME item")
+            }
+            RtcpCompoundViolation::ByeNotLast(index) => {
+                write!(f, "BYE at index {index} is not the last packet")
+            }
+        }
+    }
+}
+
+/// Returned (wrapped in an [`anyhow::Error`]) by [`RtcpCompoundBuilder::build`] when the packets
+/// don't form a valid compound packet.  Contains every violation that was found.
+#[derive(Debug)]
+pub struct RtcpCompoundError {
+    pub violations: Vec<RtcpCompoundViolation>,
+}
+
+impl Display for RtcpCompoundError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid compound RTCP packet: ")?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{violation}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RtcpCompoundError {}
+
+/// Check the given packets against the compound packet rules, returning every violation found.
+pub fn validate_compound_rtcp(packets: &[SomeRtcpPacket]) -> Vec<RtcpCompoundViolation> {
+    let mut violations = Vec::new();
+    match packets.first() {
+        None => {
+            violations.push(RtcpCompoundViolation::Empty);
+            return violations;
+        }
+        Some(SomeRtcpPacket::RtcpSrPacket(_)) | Some(SomeRtcpPacket::RtcpRrPacket(_)) => {}
+        Some(_) => violations.push(RtcpCompoundViolation::FirstPacketNotReport),
+    }
+
+    let has_cname = packets.iter().any(|packet| match packet {
+        SomeRtcpPacket::RtcpSdesPacket(sdes) => sdes.chunks.iter().any(|chunk| {
+            chunk
+                .sdes_items
+                .iter()
+                .any(|item| matches!(item, SdesItem::Cname(_)))
+        }),
+        _ => false,
+    });
+    if !has_cname {
+        violations.push(RtcpCompoundViolation::MissingCname);
+    }
+
+    let last_index = packets.len() - 1;
+    for (i, packet) in packets.iter().enumerate() {
+        if matches!(packet, SomeRtcpPacket::RtcpByePacket(_)) && i != last_index {
+            violations.push(RtcpCompoundViolation::ByeNotLast(i));
+        }
+    }
+
+    violations
+}
+
+/// Builds a compound RTCP packet, syncing each of the added packets and validating that together
+/// they follow the compound packet rules.
+#[derive(Debug, Default)]
+pub struct RtcpCompoundBuilder {
+    packets: Vec<SomeRtcpPacket>,
+}
+
+impl RtcpCompoundBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_packet(mut self, packet: SomeRtcpPacket) -> Self {
+        self.packets.push(packet);
+        self
+    }
+
+    /// Sync every packet and validate the result.  If the packets don't form a valid compound
+    /// packet, the returned error can be downcast to an [`RtcpCompoundError`].
+    pub fn build(mut self) -> Result<SomeRtcpPacket> {
+        for (i, packet) in self.packets.iter_mut().enumerate() {
+            packet
+                .sync()
+                .with_context(|| format!("sub packet {}", i + 1))?;
+        }
+        let violations = validate_compound_rtcp(&self.packets);
+        if !violations.is_empty() {
+            return Err(RtcpCompoundError { violations }.into());
+        }
+
+        Ok(SomeRtcpPacket::CompoundRtcpPacket(self.packets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::nsw_types::{u2, u5};
+
+    use crate::rtcp::{
+        rtcp_bye::RtcpByePacket,
+        rtcp_fb_header::RtcpFbHeader,
+        rtcp_fb_pli::RtcpFbPliPacket,
+        rtcp_header::RtcpHeader,
+        rtcp_rr::RtcpRrPacket,
+        rtcp_sdes::{RtcpSdesPacket, SdesChunk},
+    };
+
+    use super::*;
+
+    fn empty_header() -> RtcpHeader {
+        RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: 0,
+            length_field: 0,
+        }
+    }
+
+    fn rr() -> SomeRtcpPacket {
+        SomeRtcpPacket::RtcpRrPacket(RtcpRrPacket {
+            header: empty_header(),
+            sender_ssrc: 1,
+            report_blocks: Vec::new(),
+            profile_extensions: Vec::new(),
+        })
+    }
+
+    fn sdes() -> SomeRtcpPacket {
+        SomeRtcpPacket::RtcpSdesPacket(RtcpSdesPacket {
+            header: empty_header(),
+            chunks: vec![SdesChunk {
+                ssrc: 1,
+                sdes_items: vec![SdesItem::Cname("cname".to_owned())],
+            }],
+        })
+    }
+
+    fn bye() -> SomeRtcpPacket {
+        SomeRtcpPacket::RtcpByePacket(RtcpByePacket {
+            header: empty_header(),
+            ssrcs: vec![1],
+            reason: None,
+        })
+    }
+
+    fn pli() -> SomeRtcpPacket {
+        SomeRtcpPacket::RtcpFbPliPacket(RtcpFbPliPacket {
+            header: empty_header(),
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+        })
+    }
+
+    #[test]
+    fn test_build_valid() {
+        let packet = RtcpCompoundBuilder::new()
+            .add_packet(rr())
+            .add_packet(sdes())
+            .add_packet(pli())
+            .add_packet(bye())
+            .build()
+            .unwrap();
+        match packet {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+                assert_eq!(packets.len(), 4);
+                match &packets[0] {
+                    SomeRtcpPacket::RtcpRrPacket(rr) => {
+                        assert_eq!(rr.header.packet_type, RtcpRrPacket::PT)
+                    }
+                    p => panic!("Unexpected packet: {p:?}"),
+                }
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_build_lists_all_violations() {
+        let err = RtcpCompoundBuilder::new()
+            .add_packet(pli())
+            .add_packet(bye())
+            .add_packet(rr())
+            .build()
+            .unwrap_err();
+        let err = err.downcast_ref::<RtcpCompoundError>().unwrap();
+        assert_eq!(
+            err.violations,
+            vec![
+                RtcpCompoundViolation::FirstPacketNotReport,
+                RtcpCompoundViolation::MissingCname,
+                RtcpCompoundViolation::ByeNotLast(1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_empty() {
+        let err = RtcpCompoundBuilder::new().build().unwrap_err();
+        let err = err.downcast_ref::<RtcpCompoundError>().unwrap();
+        assert_eq!(err.violations, vec![RtcpCompoundViolation::Empty]);
+    }
+}
@@ -0,0 +1,468 @@
+use std::{io::SeekFrom, time::Duration};
+
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u13, u2, u5},
+};
+
+use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::RtcpFbHeader,
+    rtcp_fb_packet::RtcpFbTlPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// RTP Control Protocol (RTCP) Feedback for Congestion Control
+/// https://datatracker.ietf.org/doc/html/rfc8888#section-3.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| FMT=11  |   PT = 205    |          length               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 SSRC of RTCP packet sender                    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   SSRC of 1st RTP Stream                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |          num_reports          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|ECN|  Arrival time offset    | ...                           .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// .                                                               .
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   SSRC of nth RTP Stream                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |          num_reports          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|ECN|  Arrival time offset    | ...                           |
+/// .                                                               .
+/// .                                                               .
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        Report Timestamp (32 bits)             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// Unlike the other feedback messages, CCFB has no "SSRC of media source" field: the SSRC of the
+/// first report block comes directly after the sender SSRC.
+///
+/// Report Timestamp: 32 bits
+///   The time at which this report was sent, in the middle 32 bits of an NTP timestamp.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbCcfbPacket {
+    pub header: RtcpHeader,
+    pub sender_ssrc: u32,
+    pub report_blocks: Vec<CcfbReportBlock>,
+    pub report_timestamp: u32,
+}
+
+impl RtcpFbCcfbPacket {
+    pub const FMT: u5 = u5::new(11);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("CCFB payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbTlPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbCcfbPacket {
+    fn length_bytes(&self) -> usize {
+        // sender ssrc + report blocks + report timestamp
+        RtcpHeader::SIZE_BYTES
+            + 4
+            + self
+                .report_blocks
+                .iter()
+                .map(|b| b.length_bytes())
+                .sum::<usize>()
+            + 4
+    }
+}
+
+/// The feedback for a single RTP stream.  Each metric block corresponds to a consecutive
+/// sequence number, starting with `begin_seq`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CcfbReportBlock {
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub metric_blocks: Vec<CcfbMetricBlock>,
+}
+
+impl CcfbReportBlock {
+    /// The max number of metric blocks allowed in a report block
+    pub const MAX_REPORTS: usize = 16384;
+
+    /// Returns an iterator of (sequence number, metric block) for each metric block in this
+    /// report block.
+    pub fn iter_metrics(&self) -> impl Iterator<Item = (u16, &CcfbMetricBlock)> {
+        self.metric_blocks
+            .iter()
+            .enumerate()
+            .map(|(i, m)| (self.begin_seq.wrapping_add(i as u16), m))
+    }
+}
+
+impl LengthBytes for CcfbReportBlock {
+    /// The length of this report block in bytes, including the padding after an odd number of
+    /// metric blocks.
+    fn length_bytes(&self) -> usize {
+        8 + (self.metric_blocks.len() * CcfbMetricBlock::SIZE_BYTES).next_multiple_of(4)
+    }
+}
+
+///  0                   1
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |R|ECN|  Arrival time offset    |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// R: 1 bit
+///   Whether the packet was received.  If not, the ECN and arrival time offset fields are 0.
+///
+/// ECN: 2 bits
+///   The echoed ECN mark of the packet.
+///
+/// Arrival time offset: 13 bits
+///   The arrival time of the packet, expressed as an offset before the report timestamp, in units
+///   of 1/1024 seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct CcfbMetricBlock {
+    pub received: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u2"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u2))]
+    pub ecn: u2,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u13"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u13))]
+    pub arrival_time_offset: u13,
+}
+
+impl CcfbMetricBlock {
+    pub const SIZE_BYTES: usize = 2;
+    /// The value of the arrival time offset used when the actual offset is too large to be
+    /// represented
+    pub const OVER_RANGE: u13 = u13::new(0x1FFF);
+
+    /// The arrival time offset as a [`Duration`], or None if the packet wasn't received or the
+    /// offset was over range.
+    pub fn arrival_time_offset(&self) -> Option<Duration> {
+        if !self.received || self.arrival_time_offset == Self::OVER_RANGE {
+            return None;
+        }
+        let ato: u16 = self.arrival_time_offset.into();
+        Some(Duration::from_micros(ato as u64 * 1_000_000 / 1024))
+    }
+
+    /// Create a metric block for a packet which was received with the given ECN mark and
+    /// arrival time offset.  Offsets which are too large are set to [`Self::OVER_RANGE`].
+    pub fn received(ecn: u2, arrival_time_offset: Duration) -> Self {
+        let ato = arrival_time_offset.as_micros() * 1024 / 1_000_000;
+        let over_range: u16 = Self::OVER_RANGE.into();
+        Self {
+            received: true,
+            ecn,
+            arrival_time_offset: u13::new(ato.min(over_range as u128) as u16),
+        }
+    }
+
+    pub fn not_received() -> Self {
+        Self {
+            received: false,
+            ecn: u2::new(0),
+            arrival_time_offset: u13::new(0),
+        }
+    }
+}
+
+impl LengthBytes for CcfbMetricBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_ccfb<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpFbCcfbPacket> {
+    // CCFB doesn't have a media source ssrc field, so rewind back over what was parsed as one by
+    // the fb header.
+    buf.seek(SeekFrom::Current(-32))
+        .context("rewind media source ssrc")?;
+    let mut report_blocks = Vec::new();
+    // The last 4 bytes are the report timestamp
+    while buf.bytes_remaining() > 4 {
+        let report_block = read_ccfb_report_block(buf, mode, warnings)
+            .with_context(|| format!("report block {}", report_blocks.len()))?;
+        report_blocks.push(report_block);
+    }
+    let report_timestamp = buf.read_u32::<NetworkOrder>().context("report timestamp")?;
+
+    Ok(RtcpFbCcfbPacket {
+        header,
+        sender_ssrc: fb_header.sender_ssrc,
+        report_blocks,
+        report_timestamp,
+    })
+}
+
+pub fn write_rtcp_fb_ccfb<B: PacketBufferMut>(
+    buf: &mut B,
+    fb_ccfb: &RtcpFbCcfbPacket,
+) -> Result<()> {
+    write_rtcp_header(buf, &fb_ccfb.header).context("rtcp header")?;
+    buf.write_u32::<NetworkOrder>(fb_ccfb.sender_ssrc)
+        .context("sender ssrc")?;
+    for (i, report_block) in fb_ccfb.report_blocks.iter().enumerate() {
+        write_ccfb_report_block(buf, report_block).with_context(|| format!("report block {i}"))?;
+    }
+    buf.write_u32::<NetworkOrder>(fb_ccfb.report_timestamp)
+        .context("report timestamp")?;
+
+    Ok(())
+}
+
+pub fn read_ccfb_report_block<B: PacketBuffer>(
+    buf: &mut B,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<CcfbReportBlock> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let begin_seq = buf.read_u16::<NetworkOrder>().context("begin seq")?;
+    let num_reports = buf.read_u16::<NetworkOrder>().context("num reports")? as usize;
+    if num_reports > CcfbReportBlock::MAX_REPORTS {
+        bail!(RtpParseError::invalid_field(
+            "num reports",
+            format!("at most {}", CcfbReportBlock::MAX_REPORTS),
+            num_reports
+        ));
+    }
+    let metric_blocks = (0..num_reports)
+        .map(|i| read_ccfb_metric_block(buf).with_context(|| format!("metric block {i}")))
+        .collect::<Result<Vec<CcfbMetricBlock>>>()?;
+    if !num_reports.is_multiple_of(2) {
+        let padding = buf.read_u16::<NetworkOrder>().context("padding")?;
+        mode.check_reserved(warnings, "CCFB report block padding", padding.into())?;
+    }
+
+    Ok(CcfbReportBlock {
+        ssrc,
+        begin_seq,
+        metric_blocks,
+    })
+}
+
+pub fn write_ccfb_report_block<B: PacketBufferMut>(
+    buf: &mut B,
+    report_block: &CcfbReportBlock,
+) -> Result<()> {
+    let num_reports = report_block.metric_blocks.len();
+    if num_reports > CcfbReportBlock::MAX_REPORTS {
+        bail!(
+            "Too many metric blocks {num_reports}, max is {}",
+            CcfbReportBlock::MAX_REPORTS
+        );
+    }
+    buf.write_u32::<NetworkOrder>(report_block.ssrc)
+        .context("ssrc")?;
+    buf.write_u16::<NetworkOrder>(report_block.begin_seq)
+        .context("begin seq")?;
+    buf.write_u16::<NetworkOrder>(num_reports as u16)
+        .context("num reports")?;
+    for (i, metric_block) in report_block.metric_blocks.iter().enumerate() {
+        write_ccfb_metric_block(buf, metric_block).with_context(|| format!("metric block {i}"))?;
+    }
+    if !num_reports.is_multiple_of(2) {
+        buf.write_u16::<NetworkOrder>(0).context("padding")?;
+    }
+
+    Ok(())
+}
+
+pub fn read_ccfb_metric_block<B: PacketBuffer>(buf: &mut B) -> Result<CcfbMetricBlock> {
+    Ok(CcfbMetricBlock {
+        received: buf.read_bool().context("received")?,
+        ecn: buf.read_u2().context("ecn")?,
+        arrival_time_offset: buf
+            .read_u13::<NetworkOrder>()
+            .context("arrival time offset")?,
+    })
+}
+
+pub fn write_ccfb_metric_block<B: PacketBufferMut>(
+    buf: &mut B,
+    metric_block: &CcfbMetricBlock,
+) -> Result<()> {
+    buf.write_bool(metric_block.received).context("received")?;
+    buf.write_u2(metric_block.ecn).context("ecn")?;
+    buf.write_u13::<NetworkOrder>(metric_block.arrival_time_offset)
+        .context("arrival time offset")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbCcfbPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Congestion Control Feedback", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Sender SSRC", Ssrc(self.sender_ssrc))?;
+            for (i, report_block) in self.report_blocks.iter().enumerate() {
+                tree.subtree(format_args!("Report block {}", i + 1), |tree| {
+                    tree.field("SSRC", Ssrc(report_block.ssrc))?;
+                    tree.field("Begin sequence number", report_block.begin_seq)?;
+                    for (i, metric_block) in report_block.metric_blocks.iter().enumerate() {
+                        let seq_num =
+                            format!("Seq {}", report_block.begin_seq.wrapping_add(i as u16));
+                        if metric_block.received {
+                            tree.field(
+                                &seq_num,
+                                format_args!(
+                                    "received, ECN {}, arrival time offset {}",
+                                    u8::from(metric_block.ecn),
+                                    u16::from(metric_block.arrival_time_offset)
+                                ),
+                            )?;
+                        } else {
+                            tree.field(&seq_num, "not received")?;
+                        }
+                    }
+                    Ok(())
+                })?;
+            }
+            tree.field("Report timestamp", self.report_timestamp)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_packet::{parse_single_rtcp_packet, SomeRtcpPacket};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rtcp_fb_ccfb() {
+        #[rustfmt::skip]
+        let data = vec![
+            // header: fmt 11, pt 205, length 6
+            0x8B, 0xCD, 0x00, 0x06,
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x2A,
+            // ssrc of 1st stream
+            0x00, 0x00, 0x00, 0x01,
+            // begin seq 100, num reports 3
+            0x00, 0x64, 0x00, 0x03,
+            // received, ecn 0, ato 512 | not received
+            0x82, 0x00, 0x00, 0x00,
+            // received, ecn 3 (CE), over range | padding
+            0xFF, 0xFF, 0x00, 0x00,
+            // report timestamp
+            0x12, 0x34, 0x56, 0x78,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let fb_ccfb = match parse_single_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::RtcpFbCcfbPacket(p) => p,
+            p => panic!("Unexpected packet: {p:?}"),
+        };
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(fb_ccfb.sender_ssrc, 42);
+        assert_eq!(fb_ccfb.report_timestamp, 0x12345678);
+        assert_eq!(fb_ccfb.report_blocks.len(), 1);
+        let metrics: Vec<(u16, &CcfbMetricBlock)> =
+            fb_ccfb.report_blocks[0].iter_metrics().collect();
+        assert_eq!(metrics.len(), 3);
+        assert_eq!(metrics[0].0, 100);
+        assert_eq!(
+            metrics[0].1.arrival_time_offset(),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(metrics[1].0, 101);
+        assert!(!metrics[1].1.received);
+        assert_eq!(metrics[2].1.ecn, u2::new(3));
+        assert_eq!(metrics[2].1.arrival_time_offset(), None);
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_ccfb_roundtrip() {
+        let mut fb_ccfb = RtcpFbCcfbPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            sender_ssrc: 42,
+            report_blocks: vec![
+                CcfbReportBlock {
+                    ssrc: 1,
+                    begin_seq: 65535,
+                    metric_blocks: vec![
+                        CcfbMetricBlock::received(u2::new(1), Duration::from_millis(250)),
+                        CcfbMetricBlock::not_received(),
+                    ],
+                },
+                CcfbReportBlock {
+                    ssrc: 2,
+                    begin_seq: 10,
+                    metric_blocks: vec![CcfbMetricBlock::received(
+                        u2::new(0),
+                        Duration::from_secs(100),
+                    )],
+                },
+            ],
+            report_timestamp: 1,
+        };
+        fb_ccfb.sync().unwrap();
+        // sender ssrc (4) + block 1 (12) + block 2 (12) + timestamp (4)
+        assert_eq!(fb_ccfb.header.length_field, 8);
+        assert_eq!(
+            fb_ccfb.report_blocks[1].metric_blocks[0].arrival_time_offset,
+            CcfbMetricBlock::OVER_RANGE
+        );
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 36]));
+        write_rtcp_fb_ccfb(&mut cursor, &fb_ccfb).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let read_fb_ccfb = match parse_single_rtcp_packet(&mut read_cursor).unwrap() {
+            SomeRtcpPacket::RtcpFbCcfbPacket(p) => p,
+            p => panic!("Unexpected packet: {p:?}"),
+        };
+        assert_eq!(read_fb_ccfb.sender_ssrc, fb_ccfb.sender_ssrc);
+        assert_eq!(read_fb_ccfb.report_blocks, fb_ccfb.report_blocks);
+        assert_eq!(read_fb_ccfb.report_timestamp, fb_ccfb.report_timestamp);
+    }
+}
@@ -1,5 +1,10 @@
-use crate::{PacketBuffer, PacketBufferMut};
-use anyhow::{bail, Context, Result};
+use crate::error::RtpParseError;
+use crate::{
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
     bit_read_exts::BitReadExts,
     bit_write_exts::BitWriteExts,
@@ -9,6 +14,7 @@ use bit_cursor::{
 
 use super::{
     rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
     rtcp_header::{write_rtcp_header, RtcpHeader},
 };
 
@@ -31,31 +37,71 @@ use super::{
 /// A FIR message MAY contain requests to multiple media senders, using
 /// one FCI entry per target media sender.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpFbFirPacket {
     pub header: RtcpHeader,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = super::rtcp_fb_header::arbitrary_without_media_source)
+    )]
     pub fb_header: RtcpFbHeader,
     pub fcis: Vec<RtcpFbFirFci>,
 }
 
 impl RtcpFbFirPacket {
     pub const FMT: u5 = u5::new(4);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("FIR payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbFirPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + RtcpFbHeader::SIZE_BYTES
+            + self.fcis.len() * RtcpFbFirFci::SIZE_BYTES
+    }
 }
 
 pub fn read_rtcp_fb_fir<B: PacketBuffer>(
     buf: &mut B,
     header: RtcpHeader,
     fb_header: RtcpFbHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
 ) -> Result<RtcpFbFirPacket> {
     // TODO: there can be multiple FCI chunks here, we need to keep reading until reaching the end
     // of the packet.  That means the buf we're given needs to be a slice based on the length in
     // the header so this can read until the end
     if fb_header.media_source_ssrc != 0 {
-        bail!("SSRC of media source must be set to 0");
+        bail!(RtpParseError::invalid_field(
+            "media source SSRC",
+            0,
+            fb_header.media_source_ssrc
+        ));
     }
     let mut num_fci = 1;
     let mut fcis = Vec::new();
     while buf.bytes_remaining() >= RtcpFbFirFci::SIZE_BYTES {
-        let fci = read_rtcp_fb_fir_fci(buf).with_context(|| format!("fci {num_fci}"))?;
+        let fci =
+            read_rtcp_fb_fir_fci(buf, mode, warnings).with_context(|| format!("fci {num_fci}"))?;
         fcis.push(fci);
         num_fci += 1;
     }
@@ -77,6 +123,8 @@ pub fn write_rtcp_fb_fir<B: PacketBufferMut>(buf: &mut B, fb_fir: &RtcpFbFirPack
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpFbFirFci {
     ssrc: u32,
     seq_num: u8,
@@ -86,11 +134,21 @@ impl RtcpFbFirFci {
     pub const SIZE_BYTES: usize = 8;
 }
 
-pub fn read_rtcp_fb_fir_fci<B: PacketBuffer>(buf: &mut B) -> Result<RtcpFbFirFci> {
+impl LengthBytes for RtcpFbFirFci {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_fir_fci<B: PacketBuffer>(
+    buf: &mut B,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpFbFirFci> {
     let ssrc = buf.read_u32::<NetworkOrder>().context("source")?;
     let seq_num = buf.read_u8().context("seq num")?;
-    // Consume the reserved chunk
-    let _ = buf.read_u24::<NetworkOrder>().context("reserved")?;
+    let reserved = buf.read_u24::<NetworkOrder>().context("reserved")?;
+    mode.check_reserved(warnings, "FIR reserved bits", reserved.into())?;
 
     Ok(RtcpFbFirFci { ssrc, seq_num })
 }
@@ -107,3 +165,19 @@ pub fn write_rtcp_fb_fir_fci<B: PacketBufferMut>(
 
     Ok(())
 }
+
+impl PrettyPrint for RtcpFbFirPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Full Intra Request", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            for (i, fci) in self.fcis.iter().enumerate() {
+                tree.subtree(format_args!("FCI {}", i + 1), |tree| {
+                    tree.field("SSRC", Ssrc(fci.ssrc))?;
+                    tree.field("Sequence number", fci.seq_num)
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
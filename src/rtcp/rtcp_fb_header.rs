@@ -3,7 +3,10 @@ use bit_cursor::{
     bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
 };
 
-use crate::{PacketBuffer, PacketBufferMut};
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
 
 /// https://datatracker.ietf.org/doc/html/rfc4585#section-6.1
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
@@ -12,11 +15,35 @@ use crate::{PacketBuffer, PacketBufferMut};
 /// |                  SSRC of media source                         |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpFbHeader {
     pub sender_ssrc: u32,
     pub media_source_ssrc: u32,
 }
 
+impl RtcpFbHeader {
+    pub const SIZE_BYTES: usize = 8;
+}
+
+/// For the feedback messages whose media source SSRC must be 0 (the target SSRCs are in their
+/// FCI entries instead)
+#[cfg(feature = "arbitrary")]
+pub(crate) fn arbitrary_without_media_source(
+    u: &mut arbitrary::Unstructured,
+) -> arbitrary::Result<RtcpFbHeader> {
+    Ok(RtcpFbHeader {
+        sender_ssrc: u.arbitrary()?,
+        media_source_ssrc: 0,
+    })
+}
+
+impl LengthBytes for RtcpFbHeader {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
 pub fn read_rtcp_fb_header<B: PacketBuffer>(buf: &mut B) -> Result<RtcpFbHeader> {
     let sender_ssrc = buf.read_u32::<NetworkOrder>().context("sender ssrc")?;
     let media_source_ssrc = buf.read_u32::<NetworkOrder>().context("media ssrc")?;
@@ -38,3 +65,10 @@ pub fn write_rtcp_fb_header<B: PacketBufferMut>(
 
     Ok(())
 }
+
+impl PrettyPrint for RtcpFbHeader {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("Sender SSRC", Ssrc(self.sender_ssrc))?;
+        tree.field("Media source SSRC", Ssrc(self.media_source_ssrc))
+    }
+}
@@ -0,0 +1,387 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u3, u5, u7},
+};
+
+use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// Layer Refresh Request
+/// https://datatracker.ietf.org/doc/html/draft-ietf-avtext-lrr-07#section-3
+///
+/// Within the common packet header for feedback messages, the "SSRC of
+/// packet sender" field indicates the source of the request, and the
+/// "SSRC of media source" is not used and SHALL be set to 0.  The SSRCs
+/// of the media senders to which the LRR command applies are in the
+/// corresponding FCI entries.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbLrrPacket {
+    pub header: RtcpHeader,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = super::rtcp_fb_header::arbitrary_without_media_source)
+    )]
+    pub fb_header: RtcpFbHeader,
+    pub fcis: Vec<RtcpFbLrrFci>,
+}
+
+impl RtcpFbLrrPacket {
+    pub const FMT: u5 = u5::new(10);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("LRR payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbLrrPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + RtcpFbHeader::SIZE_BYTES
+            + self.fcis.len() * RtcpFbLrrFci::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_lrr<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpFbLrrPacket> {
+    if fb_header.media_source_ssrc != 0 {
+        bail!(RtpParseError::invalid_field(
+            "media source SSRC",
+            0,
+            fb_header.media_source_ssrc
+        ));
+    }
+    let mut fcis = Vec::new();
+    while buf.bytes_remaining() >= RtcpFbLrrFci::SIZE_BYTES {
+        let fci = read_rtcp_fb_lrr_fci(buf, mode, warnings)
+            .with_context(|| format!("fci {}", fcis.len()))?;
+        fcis.push(fci);
+    }
+
+    Ok(RtcpFbLrrPacket {
+        header,
+        fb_header,
+        fcis,
+    })
+}
+
+pub fn write_rtcp_fb_lrr<B: PacketBufferMut>(buf: &mut B, fb_lrr: &RtcpFbLrrPacket) -> Result<()> {
+    write_rtcp_header(buf, &fb_lrr.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_lrr.fb_header).context("fb header")?;
+    for (i, fci) in fb_lrr.fcis.iter().enumerate() {
+        write_rtcp_fb_lrr_fci(buf, fci).with_context(|| format!("fci {i}"))?;
+    }
+
+    Ok(())
+}
+
+/// https://datatracker.ietf.org/doc/html/draft-ietf-avtext-lrr-07#section-3.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              SSRC                             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | Seq nr.       |C| Payload Type| Reserved                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | RES     | TTID| TLID          | RES     | CTID| CLID          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// C: 1 bit
+///    A flag bit indicating whether the "Current Temporal-layer ID" and
+///    "Current Layer ID" fields are present in the FCI.  If this bit is
+///    false, the sender of the LRR message is requesting refresh of all
+///    layers up to and including the target layer.
+///
+/// The current layer index is None when the C bit is not set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbLrrFci {
+    pub ssrc: u32,
+    pub seq_num: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u7"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u7))]
+    pub payload_type: u7,
+    pub target_layer_index: LayerIndex,
+    pub current_layer_index: Option<LayerIndex>,
+}
+
+impl RtcpFbLrrFci {
+    pub const SIZE_BYTES: usize = 12;
+}
+
+impl LengthBytes for RtcpFbLrrFci {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+/// A layer index as used in the LRR FCI.  The meaning of the TID and LID values is codec
+/// specific.
+///  0                   1
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | RES     | TID | LID           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// RES: 5 bits
+///    Reserved.  SHALL be set to 0 by senders and SHALL be ignored by receivers.
+///
+/// TID: 3 bits
+///    The temporal-layer ID of the layer.
+///
+/// LID: 8 bits
+///    The layer ID of the layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct LayerIndex {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u5"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(value = u5::new(0)))]
+    pub res: u5,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u3"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u3))]
+    pub tid: u3,
+    pub lid: u8,
+}
+
+impl LayerIndex {
+    pub fn new(tid: u3, lid: u8) -> Self {
+        Self {
+            res: u5::new(0),
+            tid,
+            lid,
+        }
+    }
+}
+
+pub fn read_layer_index<B: PacketBuffer>(buf: &mut B) -> Result<LayerIndex> {
+    Ok(LayerIndex {
+        res: buf.read_u5().context("res")?,
+        tid: buf.read_u3().context("tid")?,
+        lid: buf.read_u8().context("lid")?,
+    })
+}
+
+pub fn write_layer_index<B: PacketBufferMut>(buf: &mut B, layer_index: &LayerIndex) -> Result<()> {
+    buf.write_u5(layer_index.res).context("res")?;
+    buf.write_u3(layer_index.tid).context("tid")?;
+    buf.write_u8(layer_index.lid).context("lid")?;
+
+    Ok(())
+}
+
+pub fn read_rtcp_fb_lrr_fci<B: PacketBuffer>(
+    buf: &mut B,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpFbLrrFci> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let seq_num = buf.read_u8().context("seq num")?;
+    let current_present = buf.read_bool().context("c")?;
+    let payload_type = buf.read_u7().context("payload type")?;
+    let reserved = buf.read_u16::<NetworkOrder>().context("reserved")?;
+    mode.check_reserved(warnings, "LRR reserved bits", reserved.into())?;
+    let target_layer_index = read_layer_index(buf).context("target layer index")?;
+    let current_layer_index = read_layer_index(buf).context("current layer index")?;
+
+    Ok(RtcpFbLrrFci {
+        ssrc,
+        seq_num,
+        payload_type,
+        target_layer_index,
+        current_layer_index: current_present.then_some(current_layer_index),
+    })
+}
+
+pub fn write_rtcp_fb_lrr_fci<B: PacketBufferMut>(buf: &mut B, fci: &RtcpFbLrrFci) -> Result<()> {
+    buf.write_u32::<NetworkOrder>(fci.ssrc).context("ssrc")?;
+    buf.write_u8(fci.seq_num).context("seq num")?;
+    buf.write_bool(fci.current_layer_index.is_some())
+        .context("c")?;
+    buf.write_u7(fci.payload_type).context("payload type")?;
+    buf.write_u16::<NetworkOrder>(0).context("reserved")?;
+    write_layer_index(buf, &fci.target_layer_index).context("target layer index")?;
+    match fci.current_layer_index {
+        Some(ref current_layer_index) => {
+            write_layer_index(buf, current_layer_index).context("current layer index")?
+        }
+        None => buf
+            .write_u16::<NetworkOrder>(0)
+            .context("current layer index")?,
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbLrrPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Layer Refresh Request", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            for (i, fci) in self.fcis.iter().enumerate() {
+                tree.subtree(format_args!("FCI {}", i + 1), |tree| {
+                    tree.field("SSRC", Ssrc(fci.ssrc))?;
+                    tree.field("Sequence number", fci.seq_num)?;
+                    tree.field("Payload type", u8::from(fci.payload_type))?;
+                    tree.field("Target layer index", &fci.target_layer_index)?;
+                    if let Some(current_layer_index) = &fci.current_layer_index {
+                        tree.field("Current layer index", current_layer_index)?;
+                    }
+                    Ok(())
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Display for LayerIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "TID {} LID {}", u8::from(self.tid), self.lid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::{rtcp_fb_header::read_rtcp_fb_header, rtcp_header::read_rtcp_header};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rtcp_fb_lrr_fci() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // seq nr 5, c 1, payload type 96, reserved
+            0x05, 0xE0, 0x00, 0x00,
+            // target: tid 2, lid 1, current: tid 1, lid 0
+            0x02, 0x01, 0x01, 0x00,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let mut warnings = ParseWarnings::new();
+        let fci = read_rtcp_fb_lrr_fci(&mut cursor, ParseMode::Strict, &mut warnings).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert!(warnings.is_empty());
+        assert_eq!(fci.ssrc, 1);
+        assert_eq!(fci.seq_num, 5);
+        assert_eq!(fci.payload_type, u7::new(96));
+        assert_eq!(fci.target_layer_index, LayerIndex::new(u3::new(2), 1));
+        assert_eq!(
+            fci.current_layer_index,
+            Some(LayerIndex::new(u3::new(1), 0))
+        );
+
+        // Non-zero reserved bits
+        let mut data = data;
+        data[7] = 0x01;
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let err = read_rtcp_fb_lrr_fci(&mut cursor, ParseMode::Strict, &mut warnings).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::invalid_field("LRR reserved bits", 0, 1))
+        );
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let read_fci =
+            read_rtcp_fb_lrr_fci(&mut cursor, ParseMode::Lenient, &mut warnings).unwrap();
+        assert_eq!(read_fci, fci);
+        assert_eq!(
+            warnings,
+            vec![RtpParseError::invalid_field("LRR reserved bits", 0, 1)]
+        );
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_lrr_roundtrip() {
+        let mut fb_lrr = RtcpFbLrrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 42,
+                media_source_ssrc: 0,
+            },
+            fcis: vec![
+                RtcpFbLrrFci {
+                    ssrc: 1,
+                    seq_num: 1,
+                    payload_type: u7::new(100),
+                    target_layer_index: LayerIndex::new(u3::new(3), 2),
+                    current_layer_index: None,
+                },
+                RtcpFbLrrFci {
+                    ssrc: 2,
+                    seq_num: 2,
+                    payload_type: u7::new(101),
+                    target_layer_index: LayerIndex::new(u3::new(1), 1),
+                    current_layer_index: Some(LayerIndex::new(u3::new(0), 0)),
+                },
+            ],
+        };
+        fb_lrr.sync().unwrap();
+        assert_eq!(fb_lrr.header.length_field, 8);
+        assert_eq!(fb_lrr.header.report_count, RtcpFbLrrPacket::FMT);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 36]));
+        write_rtcp_fb_lrr(&mut cursor, &fb_lrr).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_lrr.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_lrr = read_rtcp_fb_lrr(
+            &mut read_cursor,
+            header,
+            fb_header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(read_fb_lrr.fcis, fb_lrr.fcis);
+    }
+}
@@ -1,13 +1,20 @@
 use std::collections::BTreeSet;
 
 use crate::{
-    rtcp::{rtcp_fb_header::write_rtcp_fb_header, rtcp_header::write_rtcp_header},
-    PacketBuffer, PacketBufferMut,
+    pretty::{FieldTree, PrettyPrint},
+    rtcp::{
+        rtcp_fb_header::write_rtcp_fb_header, rtcp_fb_packet::RtcpFbTlPacket,
+        rtcp_header::write_rtcp_header,
+    },
+    rtp::seq_num::SeqNum,
+    LengthBytes, PacketBuffer, PacketBufferMut,
 };
 use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
-    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
-    nsw_types::u5,
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u2, u5},
 };
 
 use super::{rtcp_fb_header::RtcpFbHeader, rtcp_header::RtcpHeader};
@@ -19,18 +26,102 @@ use super::{rtcp_fb_header::RtcpFbHeader, rtcp_header::RtcpHeader};
 /// |            PID                |             BLP               |
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpFbNackPacket {
     pub header: RtcpHeader,
     pub fb_header: RtcpFbHeader,
     pub missing_seq_nums: BTreeSet<u16>,
 }
 
-// TODO: somewhere in here we need to enforce some kind of maximum number of sequence numbers that
-// can be included in a nack packet (or, a way for a nack to fail to serialize due to having more
-// sequence numbers that couldn't be added to the buffer)
-
 impl RtcpFbNackPacket {
     pub const FMT: u5 = u5::new(1);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("NACK payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbTlPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+
+    /// Iterate over the missing sequence numbers in order, taking wraparound into account.
+    pub fn iter_missing(&self) -> impl Iterator<Item = u16> + '_ {
+        iter_wrapping(&self.missing_seq_nums)
+    }
+
+    /// The missing sequence numbers as contiguous, inclusive (start, end) ranges, in order.  A
+    /// range which wraps around will have an end which is less than its start.
+    pub fn missing_ranges(&self) -> Vec<(u16, u16)> {
+        let mut ranges: Vec<(u16, u16)> = Vec::new();
+        for seq_num in self.iter_missing() {
+            match ranges.last_mut() {
+                Some((_, end)) if end.wrapping_add(1) == seq_num => *end = seq_num,
+                _ => ranges.push((seq_num, seq_num)),
+            }
+        }
+
+        ranges
+    }
+
+    /// Create as many NACK packets as are needed to hold the given missing sequence numbers
+    /// without any single packet (including its RTCP header) exceeding `max_packet_size_bytes`.
+    /// The returned packets are synced.
+    pub fn create_split(
+        sender_ssrc: u32,
+        media_source_ssrc: u32,
+        missing_seq_nums: &BTreeSet<u16>,
+        max_packet_size_bytes: usize,
+    ) -> Result<Vec<RtcpFbNackPacket>> {
+        let overhead_bytes = RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES;
+        let max_nack_blocks =
+            max_packet_size_bytes.saturating_sub(overhead_bytes) / NackBlock::SIZE_BYTES;
+        if max_nack_blocks == 0 {
+            bail!(
+                "Max packet size {max_packet_size_bytes} bytes is too small to fit a single nack block"
+            );
+        }
+        missing_seq_nums
+            .chunk_by_max_difference(16)
+            .chunks(max_nack_blocks)
+            .map(|nack_blocks| {
+                let mut packet = RtcpFbNackPacket {
+                    header: RtcpHeader {
+                        version: u2::new(2),
+                        has_padding: false,
+                        report_count: Self::FMT,
+                        packet_type: RtcpFbTlPacket::PT,
+                        length_field: 0,
+                    },
+                    fb_header: RtcpFbHeader {
+                        sender_ssrc,
+                        media_source_ssrc,
+                    },
+                    missing_seq_nums: nack_blocks.iter().flatten().copied().collect(),
+                };
+                packet.sync()?;
+                Ok(packet)
+            })
+            .collect()
+    }
+}
+
+impl LengthBytes for RtcpFbNackPacket {
+    fn length_bytes(&self) -> usize {
+        let num_nack_blocks = self.missing_seq_nums.chunk_by_max_difference(16).len();
+        RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES + num_nack_blocks * NackBlock::SIZE_BYTES
+    }
 }
 
 pub fn read_rtcp_fb_nack<B: PacketBuffer>(
@@ -90,6 +181,12 @@ impl NackBlock {
     pub const SIZE_BYTES: usize = 4;
 }
 
+impl LengthBytes for NackBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
 pub fn read_nack_block<B: PacketBuffer>(buf: &mut B) -> Result<NackBlock> {
     let packet_id = buf.read_u16::<NetworkOrder>().context("packet id")?;
     let blp = buf.read_u16::<NetworkOrder>().context("blp")?;
@@ -98,7 +195,7 @@ pub fn read_nack_block<B: PacketBuffer>(buf: &mut B) -> Result<NackBlock> {
     missing_seq_nums.insert(packet_id);
     for shift_amount in 0..16 {
         if (blp >> shift_amount) & 0x1 == 1 {
-            missing_seq_nums.insert(packet_id + shift_amount + 1);
+            missing_seq_nums.insert(packet_id.wrapping_add(shift_amount + 1));
         }
     }
 
@@ -106,15 +203,16 @@ pub fn read_nack_block<B: PacketBuffer>(buf: &mut B) -> Result<NackBlock> {
 }
 
 pub fn write_nack_block<B: PacketBufferMut>(buf: &mut B, nack_block: &NackBlock) -> Result<()> {
-    let packet_id = nack_block.missing_seq_nums.first().ok_or(anyhow!(
+    let mut missing_seq_nums = iter_wrapping(&nack_block.missing_seq_nums);
+    let packet_id = missing_seq_nums.next().ok_or(anyhow!(
         "NackBlock must contain at least one sequence number"
     ))?;
-    buf.write_u16::<NetworkOrder>(*packet_id)
+    buf.write_u16::<NetworkOrder>(packet_id)
         .context("packet id")?;
     let mut blp = 0u16;
-    // Skip past the first one, since that was used for the packet id
-    for missing_seq_num in nack_block.missing_seq_nums.iter().skip(1) {
-        let delta = missing_seq_num - packet_id;
+    // The first one was used for the packet id, so this continues from the one after it
+    for missing_seq_num in missing_seq_nums {
+        let delta = SeqNum(missing_seq_num).distance_from(SeqNum(packet_id));
         if delta > 16 {
             bail!("NACK missing sequence numbers can not span more than 16 sequence numbers");
         }
@@ -126,6 +224,32 @@ pub fn write_nack_block<B: PacketBufferMut>(buf: &mut B, nack_block: &NackBlock)
     Ok(())
 }
 
+/// Iterate over the given sequence numbers in order, taking into account that they may wrap around
+/// from 65535 to 0.  Iteration starts after the largest gap between consecutive sequence numbers,
+/// so e.g. {0, 1, 65534, 65535} is iterated as 65534, 65535, 0, 1.
+fn iter_wrapping(seq_nums: &BTreeSet<u16>) -> impl Iterator<Item = u16> + '_ {
+    let start = match (seq_nums.first(), seq_nums.last()) {
+        (Some(first), Some(last)) => {
+            // The gap from the last sequence number wrapping back around to the first
+            let wrap_gap = SeqNum(*first).distance_from(SeqNum(*last));
+            seq_nums
+                .iter()
+                .zip(seq_nums.iter().skip(1))
+                .map(|(prev, next)| (next - prev, *next))
+                .max_by_key(|(gap, _)| *gap)
+                .filter(|(gap, _)| *gap > wrap_gap)
+                .map(|(_, next)| next)
+                .unwrap_or(*first)
+        }
+        _ => 0,
+    };
+
+    seq_nums
+        .range(start..)
+        .chain(seq_nums.range(..start))
+        .copied()
+}
+
 trait ChunkByMaxDifference<T> {
     fn chunk_by_max_difference(&self, max_diff: T) -> Vec<BTreeSet<T>>;
 }
@@ -133,31 +257,53 @@ trait ChunkByMaxDifference<T> {
 impl ChunkByMaxDifference<u16> for BTreeSet<u16> {
     fn chunk_by_max_difference(&self, max_diff: u16) -> Vec<BTreeSet<u16>> {
         let mut all_chunks: Vec<BTreeSet<u16>> = Vec::new();
-        let Some(first) = self.first() else {
+        let mut values = iter_wrapping(self);
+        let Some(first) = values.next() else {
             return all_chunks;
         };
-        let mut curr_chunk: BTreeSet<u16> = BTreeSet::from([*first]);
-        for value in self.iter().skip(1) {
-            if value - curr_chunk.first().unwrap() > max_diff {
+        // Since the chunk may wrap around, we can't use its first element as its start
+        let mut curr_chunk_start = first;
+        let mut curr_chunk: BTreeSet<u16> = BTreeSet::from([first]);
+        for value in values {
+            if SeqNum(value).distance_from(SeqNum(curr_chunk_start)) > max_diff {
                 all_chunks.push(curr_chunk);
-                curr_chunk = BTreeSet::from([*value]);
+                curr_chunk_start = value;
+                curr_chunk = BTreeSet::from([value]);
             } else {
-                curr_chunk.insert(*value);
+                curr_chunk.insert(value);
             }
         }
+        all_chunks.push(curr_chunk);
 
         all_chunks
     }
 }
 
+impl PrettyPrint for RtcpFbNackPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Generic NACK", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            for (start, end) in self.missing_ranges() {
+                if start == end {
+                    tree.field("Missing", start)?;
+                } else {
+                    tree.field("Missing", format_args!("{start}-{end}"))?;
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::BTreeSet;
 
-    use bit_cursor::bit_cursor::BitCursor;
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::*};
     use bitvec::{order::Msb0, vec::BitVec};
 
-    use super::{read_nack_block, write_nack_block};
+    use super::*;
 
     #[test]
     fn test_read_nack_block() {
@@ -184,4 +330,99 @@ mod test {
         let write_data = write_cursor.into_inner().into_vec();
         assert_eq!(&data_buf, &write_data[..]);
     }
+
+    #[test]
+    fn test_write_nack_multiple_blocks() {
+        let mut nack = RtcpFbNackPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+            missing_seq_nums: BTreeSet::from([10, 11, 40, 56]),
+        };
+        nack.sync().unwrap();
+        // fb header + 2 nack blocks
+        assert_eq!(nack.header.length_field, 4);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_rtcp_fb_nack(&mut cursor, &nack).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+    }
+
+    #[test]
+    fn test_create_split() {
+        // Each of these is too far from the others to share a nack block
+        let missing_seq_nums = BTreeSet::from([0, 100, 200, 300, 400]);
+        // Room for 2 nack blocks
+        let packets = RtcpFbNackPacket::create_split(1, 2, &missing_seq_nums, 20).unwrap();
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0].missing_seq_nums, BTreeSet::from([0, 100]));
+        assert_eq!(packets[1].missing_seq_nums, BTreeSet::from([200, 300]));
+        assert_eq!(packets[2].missing_seq_nums, BTreeSet::from([400]));
+        assert_eq!(packets[0].header.length_field, 4);
+        assert_eq!(packets[2].header.length_field, 3);
+        assert_eq!(packets[0].fb_header.media_source_ssrc, 2);
+    }
+
+    #[test]
+    fn test_create_split_too_small() {
+        let missing_seq_nums = BTreeSet::from([0]);
+        assert!(RtcpFbNackPacket::create_split(1, 2, &missing_seq_nums, 15).is_err());
+    }
+
+    #[test]
+    fn test_nack_block_wraparound() {
+        // Packet id 65534, followed by 65535, 0 and 14
+        let data_buf: [u8; 4] = [0xFF, 0xFE, 0x80, 0x03];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data_buf));
+        let nack_block = read_nack_block(&mut cursor).unwrap();
+        assert_eq!(
+            nack_block.missing_seq_nums,
+            BTreeSet::from([65534, 65535, 0, 14])
+        );
+
+        let mut write_cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0u8; 4]));
+        write_nack_block(&mut write_cursor, &nack_block).unwrap();
+        assert_eq!(&data_buf, &write_cursor.into_inner().into_vec()[..]);
+    }
+
+    #[test]
+    fn test_chunk_by_max_difference_wraparound() {
+        let seq_nums = BTreeSet::from([0, 1, 20, 65530, 65535]);
+        let chunks = seq_nums.chunk_by_max_difference(16);
+        assert_eq!(
+            chunks,
+            vec![BTreeSet::from([65530, 65535, 0, 1]), BTreeSet::from([20])]
+        );
+    }
+
+    #[test]
+    fn test_missing_ranges() {
+        let nack = RtcpFbNackPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: RtcpFbNackPacket::FMT,
+                packet_type: 205,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+            missing_seq_nums: BTreeSet::from([1, 2, 5, 65534, 65535, 0]),
+        };
+        assert_eq!(
+            nack.iter_missing().collect::<Vec<u16>>(),
+            vec![65534, 65535, 0, 1, 2, 5]
+        );
+        assert_eq!(nack.missing_ranges(), vec![(65534, 2), (5, 5)]);
+    }
 }
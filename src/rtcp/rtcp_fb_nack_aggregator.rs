@@ -0,0 +1,203 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use super::rtcp_fb_nack::RtcpFbNackPacket;
+
+/// Controls when, and how many times, the [`NackAggregator`] requests a missing packet.
+#[derive(Debug, Clone)]
+pub struct NackAggregatorConfig {
+    /// How long to wait after the first request for a packet before requesting it again
+    pub retransmission_interval: Duration,
+    /// The interval is multiplied by this after every request for a packet, so subsequent
+    /// requests are sent further and further apart.  A multiplier of 1 means no backoff.
+    pub backoff_multiplier: u32,
+    /// How many times a packet will be requested before giving up on it
+    pub max_requests: u32,
+    /// The maximum size (including the RTCP header) of each generated NACK packet
+    pub max_packet_size_bytes: usize,
+}
+
+impl Default for NackAggregatorConfig {
+    fn default() -> Self {
+        Self {
+            retransmission_interval: Duration::from_millis(100),
+            backoff_multiplier: 2,
+            max_requests: 10,
+            max_packet_size_bytes: 1200,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MissingPacketState {
+    num_requests: u32,
+    next_request_time: Instant,
+}
+
+/// Tracks missing sequence numbers for any number of media SSRCs and generates NACK packets for
+/// them.  A sequence number which is reported missing multiple times is only tracked once, and
+/// once it's been requested it won't be requested again until its (backed-off) retransmission
+/// interval has passed.  Sequence numbers are no longer requested once they're marked as received
+/// or have been requested [`NackAggregatorConfig::max_requests`] times.
+#[derive(Debug)]
+pub struct NackAggregator {
+    sender_ssrc: u32,
+    config: NackAggregatorConfig,
+    missing: BTreeMap<u32, BTreeMap<u16, MissingPacketState>>,
+}
+
+impl NackAggregator {
+    pub fn new(sender_ssrc: u32, config: NackAggregatorConfig) -> Self {
+        Self {
+            sender_ssrc,
+            config,
+            missing: BTreeMap::new(),
+        }
+    }
+
+    /// Mark the given sequence number from the given media SSRC as missing.  It'll be included in
+    /// the next call to [`NackAggregator::get_nacks`].  If it's already being tracked, this has no
+    /// effect.
+    pub fn add_missing(&mut self, media_source_ssrc: u32, seq_num: u16, now: Instant) {
+        self.missing
+            .entry(media_source_ssrc)
+            .or_default()
+            .entry(seq_num)
+            .or_insert(MissingPacketState {
+                num_requests: 0,
+                next_request_time: now,
+            });
+    }
+
+    /// Mark the given sequence number from the given media SSRC as received, so it will no longer
+    /// be requested.
+    pub fn packet_received(&mut self, media_source_ssrc: u32, seq_num: u16) {
+        if let Some(stream_missing) = self.missing.get_mut(&media_source_ssrc) {
+            stream_missing.remove(&seq_num);
+            if stream_missing.is_empty() {
+                self.missing.remove(&media_source_ssrc);
+            }
+        }
+    }
+
+    /// Stop tracking all missing sequence numbers for the given media SSRC.
+    pub fn remove_stream(&mut self, media_source_ssrc: u32) {
+        self.missing.remove(&media_source_ssrc);
+    }
+
+    /// How many sequence numbers are currently being tracked for the given media SSRC
+    pub fn num_missing(&self, media_source_ssrc: u32) -> usize {
+        self.missing
+            .get(&media_source_ssrc)
+            .map(|stream_missing| stream_missing.len())
+            .unwrap_or(0)
+    }
+
+    /// Generate NACK packets for every missing sequence number which is due to be requested at
+    /// `now`.  The returned packets are synced and ready to be written.
+    pub fn get_nacks(&mut self, now: Instant) -> Result<Vec<RtcpFbNackPacket>> {
+        let mut nacks = Vec::new();
+        for (media_source_ssrc, stream_missing) in self.missing.iter_mut() {
+            let mut seq_nums = BTreeSet::new();
+            for (seq_num, state) in stream_missing.iter_mut() {
+                if state.next_request_time > now {
+                    continue;
+                }
+                seq_nums.insert(*seq_num);
+                let backoff = self
+                    .config
+                    .backoff_multiplier
+                    .saturating_pow(state.num_requests);
+                state.num_requests += 1;
+                state.next_request_time =
+                    now + self.config.retransmission_interval.saturating_mul(backoff);
+            }
+            stream_missing.retain(|_, state| state.num_requests < self.config.max_requests);
+            if seq_nums.is_empty() {
+                continue;
+            }
+            let mut stream_nacks = RtcpFbNackPacket::create_split(
+                self.sender_ssrc,
+                *media_source_ssrc,
+                &seq_nums,
+                self.config.max_packet_size_bytes,
+            )
+            .with_context(|| format!("nacks for ssrc {media_source_ssrc}"))?;
+            nacks.append(&mut stream_nacks);
+        }
+        self.missing
+            .retain(|_, stream_missing| !stream_missing.is_empty());
+
+        Ok(nacks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> NackAggregatorConfig {
+        NackAggregatorConfig {
+            retransmission_interval: Duration::from_millis(100),
+            backoff_multiplier: 2,
+            max_requests: 3,
+            max_packet_size_bytes: 1200,
+        }
+    }
+
+    #[test]
+    fn test_dedup_and_backoff() {
+        let start = Instant::now();
+        let mut aggregator = NackAggregator::new(1, config());
+        aggregator.add_missing(2, 10, start);
+        aggregator.add_missing(2, 10, start);
+        aggregator.add_missing(2, 11, start);
+
+        let nacks = aggregator.get_nacks(start).unwrap();
+        assert_eq!(nacks.len(), 1);
+        assert_eq!(nacks[0].missing_seq_nums, BTreeSet::from([10, 11]));
+        assert_eq!(nacks[0].fb_header.media_source_ssrc, 2);
+
+        // Not due again until the retransmission interval has passed
+        assert!(aggregator.get_nacks(start).unwrap().is_empty());
+        let nacks = aggregator
+            .get_nacks(start + Duration::from_millis(100))
+            .unwrap();
+        assert_eq!(nacks.len(), 1);
+
+        // The second interval is backed off
+        assert!(aggregator
+            .get_nacks(start + Duration::from_millis(200))
+            .unwrap()
+            .is_empty());
+        let nacks = aggregator
+            .get_nacks(start + Duration::from_millis(300))
+            .unwrap();
+        assert_eq!(nacks.len(), 1);
+
+        // Max requests reached
+        assert_eq!(aggregator.num_missing(2), 0);
+        assert!(aggregator
+            .get_nacks(start + Duration::from_secs(10))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_packet_received() {
+        let start = Instant::now();
+        let mut aggregator = NackAggregator::new(1, config());
+        aggregator.add_missing(2, 10, start);
+        aggregator.add_missing(3, 20, start);
+        aggregator.packet_received(2, 10);
+
+        let nacks = aggregator.get_nacks(start).unwrap();
+        assert_eq!(nacks.len(), 1);
+        assert_eq!(nacks[0].fb_header.media_source_ssrc, 3);
+        assert_eq!(nacks[0].missing_seq_nums, BTreeSet::from([20]));
+    }
+}
@@ -1,9 +1,13 @@
 use anyhow::{Context, Result};
 
-use crate::{PacketBuffer, PacketBufferMut};
+use crate::{
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
 
 use super::{
     rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
     rtcp_header::{write_rtcp_header, RtcpHeader},
 };
 use bit_cursor::nsw_types::u5;
@@ -14,6 +18,8 @@ use bit_cursor::nsw_types::u5;
 /// PLI does not require parameters.  Therefore, the length field MUST be
 ///  2, and there MUST NOT be any Feedback Control Information.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpFbPliPacket {
     pub header: RtcpHeader,
     pub fb_header: RtcpFbHeader,
@@ -21,6 +27,26 @@ pub struct RtcpFbPliPacket {
 
 impl RtcpFbPliPacket {
     pub const FMT: u5 = u5::new(1);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes.  PLI has no
+    /// FCI, so this is always just the size of the fb header.
+    pub fn payload_length_bytes(&self) -> u16 {
+        RtcpFbHeader::SIZE_BYTES as u16
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) {
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = self.payload_length_bytes() / 4;
+        self.header.has_padding = false;
+    }
+}
+
+impl LengthBytes for RtcpFbPliPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES + self.payload_length_bytes() as usize
+    }
 }
 
 pub fn read_rtcp_fb_pli<B: PacketBuffer>(
@@ -37,3 +63,54 @@ pub fn write_rtcp_fb_pli<B: PacketBufferMut>(buf: &mut B, fb_pli: &RtcpFbPliPack
 
     Ok(())
 }
+
+impl PrettyPrint for RtcpFbPliPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Picture Loss Indication", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_packet::{parse_single_rtcp_packet, SomeRtcpPacket};
+
+    use super::*;
+
+    #[test]
+    fn test_sync_and_parse() {
+        let mut fb_pli = RtcpFbPliPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+        };
+        fb_pli.sync();
+        assert_eq!(fb_pli.header.length_field, 2);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 12]));
+        write_rtcp_fb_pli(&mut cursor, &fb_pli).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        match parse_single_rtcp_packet(&mut read_cursor).unwrap() {
+            SomeRtcpPacket::RtcpFbPliPacket(p) => {
+                assert_eq!(p.fb_header.sender_ssrc, 1);
+                assert_eq!(p.fb_header.media_source_ssrc, 2);
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+}
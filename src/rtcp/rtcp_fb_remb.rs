@@ -0,0 +1,316 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u18, u5, u6},
+};
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// https://datatracker.ietf.org/doc/html/draft-alvestrand-rmcat-remb-03#section-2.2
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P| FMT=15  |   PT=206      |             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of packet sender                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                  SSRC of media source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Unique identifier 'R' 'E' 'M' 'B'                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  Num SSRC     | BR Exp    |  BR Mantissa                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   SSRC feedback                                               |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |  ...                                                          |
+///
+/// The bitrate is mantissa * 2^exp bits per second.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbRembPacket {
+    pub header: RtcpHeader,
+    pub fb_header: RtcpFbHeader,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u6"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u6))]
+    pub br_exp: u6,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u18"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u18))]
+    pub br_mantissa: u18,
+    pub ssrcs: Vec<u32>,
+}
+
+impl RtcpFbRembPacket {
+    pub const FMT: u5 = u5::new(15);
+    pub const IDENTIFIER: &'static [u8; 4] = b"REMB";
+
+    /// The bitrate described by this packet, in bits per second
+    pub fn bitrate_bps(&self) -> u64 {
+        let mantissa: u32 = self.br_mantissa.into();
+        let exp: u8 = self.br_exp.into();
+        (mantissa as u64)
+            .checked_shl(exp as u32)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Set the bitrate of this packet, in bits per second.  Precision may be lost if the bitrate
+    /// doesn't fit in the 18 bit mantissa.
+    pub fn set_bitrate_bps(&mut self, bitrate_bps: u64) {
+        let (exp, mantissa) = bitrate_to_exp_mantissa(bitrate_bps);
+        self.br_exp = exp;
+        self.br_mantissa = mantissa;
+    }
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("REMB payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        if self.ssrcs.len() > u8::MAX as usize {
+            bail!("Too many REMB ssrcs: {}", self.ssrcs.len());
+        }
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbRembPacket {
+    fn length_bytes(&self) -> usize {
+        // fb header + identifier + num ssrc/exp/mantissa + ssrcs
+        RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES + 8 + self.ssrcs.len() * 4
+    }
+}
+
+/// Convert the given bitrate to the smallest exponent and corresponding mantissa which can
+/// represent it.
+fn bitrate_to_exp_mantissa(bitrate_bps: u64) -> (u6, u18) {
+    let max_mantissa: u32 = u18::MAX.into();
+    let mut exp = 0u8;
+    while (bitrate_bps >> exp) > max_mantissa as u64 {
+        exp += 1;
+    }
+    (u6::new(exp), u18::new((bitrate_bps >> exp) as u32))
+}
+
+pub fn read_rtcp_fb_remb<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+) -> Result<RtcpFbRembPacket> {
+    let mut identifier = [0u8; 4];
+    buf.read_exact(&mut identifier).context("identifier")?;
+    if &identifier != RtcpFbRembPacket::IDENTIFIER {
+        bail!(RtpParseError::invalid_field(
+            "REMB identifier",
+            "REMB",
+            format!("{identifier:x?}")
+        ));
+    }
+    let num_ssrcs = buf.read_u8().context("num ssrcs")?;
+    let br_exp = buf.read_u6().context("br exp")?;
+    let br_mantissa = buf.read_u18::<NetworkOrder>().context("br mantissa")?;
+    let ssrcs = (0..num_ssrcs)
+        .map(|i| {
+            buf.read_u32::<NetworkOrder>()
+                .with_context(|| format!("ssrc {i}"))
+        })
+        .collect::<Result<Vec<u32>>>()?;
+
+    Ok(RtcpFbRembPacket {
+        header,
+        fb_header,
+        br_exp,
+        br_mantissa,
+        ssrcs,
+    })
+}
+
+pub fn write_rtcp_fb_remb<B: PacketBufferMut>(
+    buf: &mut B,
+    fb_remb: &RtcpFbRembPacket,
+) -> Result<()> {
+    let num_ssrcs: u8 = fb_remb
+        .ssrcs
+        .len()
+        .try_into()
+        .map_err(|_| anyhow!("Too many REMB ssrcs: {}", fb_remb.ssrcs.len()))?;
+    write_rtcp_header(buf, &fb_remb.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_remb.fb_header).context("fb header")?;
+    buf.write_all(RtcpFbRembPacket::IDENTIFIER)
+        .context("identifier")?;
+    buf.write_u8(num_ssrcs).context("num ssrcs")?;
+    buf.write_u6(fb_remb.br_exp).context("br exp")?;
+    buf.write_u18::<NetworkOrder>(fb_remb.br_mantissa)
+        .context("br mantissa")?;
+    for (i, ssrc) in fb_remb.ssrcs.iter().enumerate() {
+        buf.write_u32::<NetworkOrder>(*ssrc)
+            .with_context(|| format!("ssrc {i}"))?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbRembPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Receiver Estimated Maximum Bitrate", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            tree.field("Bitrate", format_args!("{} bps", self.bitrate_bps()))?;
+            for ssrc in &self.ssrcs {
+                tree.field("SSRC", Ssrc(*ssrc))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::{rtcp_fb_header::read_rtcp_fb_header, rtcp_header::read_rtcp_header};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rtcp_fb_remb() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // 'REMB'
+            0x52, 0x45, 0x4D, 0x42,
+            // num ssrc 2, exp 2, mantissa 250000
+            0x02, 0x0B, 0xD0, 0x90,
+            // ssrcs
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x02,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: RtcpFbRembPacket::FMT,
+            packet_type: RtcpFbPsPacket::PT,
+            length_field: 6,
+        };
+        let fb_header = RtcpFbHeader {
+            sender_ssrc: 42,
+            media_source_ssrc: 0,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        let fb_remb = read_rtcp_fb_remb(&mut cursor, header, fb_header).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(fb_remb.br_exp, u6::new(2));
+        assert_eq!(fb_remb.br_mantissa, u18::new(250_000));
+        assert_eq!(fb_remb.bitrate_bps(), 1_000_000);
+        assert_eq!(fb_remb.ssrcs, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_read_rtcp_fb_remb_bad_identifier() {
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: RtcpFbRembPacket::FMT,
+            packet_type: RtcpFbPsPacket::PT,
+            length_field: 4,
+        };
+        let fb_header = RtcpFbHeader {
+            sender_ssrc: 42,
+            media_source_ssrc: 0,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0x52, 0x45, 0x4D, 0x43, 0x00, 0x00, 0x00, 0x00,
+        ]));
+        assert!(read_rtcp_fb_remb(&mut cursor, header, fb_header).is_err());
+    }
+
+    #[test]
+    fn test_set_bitrate_bps() {
+        let mut fb_remb = RtcpFbRembPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 42,
+                media_source_ssrc: 0,
+            },
+            br_exp: u6::new(0),
+            br_mantissa: u18::new(0),
+            ssrcs: vec![1],
+        };
+        fb_remb.set_bitrate_bps(100_000);
+        assert_eq!(fb_remb.br_exp, u6::new(0));
+        assert_eq!(fb_remb.bitrate_bps(), 100_000);
+
+        fb_remb.set_bitrate_bps(5_000_000);
+        assert_eq!(fb_remb.br_exp, u6::new(5));
+        assert_eq!(fb_remb.bitrate_bps(), 5_000_000);
+
+        // Not exactly representable, so some precision is lost
+        fb_remb.set_bitrate_bps(1_000_001);
+        assert_eq!(fb_remb.bitrate_bps(), 1_000_000);
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_remb_roundtrip() {
+        let mut fb_remb = RtcpFbRembPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 42,
+                media_source_ssrc: 0,
+            },
+            br_exp: u6::new(0),
+            br_mantissa: u18::new(0),
+            ssrcs: vec![1, 2, 3],
+        };
+        fb_remb.set_bitrate_bps(2_500_000);
+        fb_remb.sync().unwrap();
+        assert_eq!(fb_remb.header.length_field, 7);
+        assert_eq!(fb_remb.header.report_count, RtcpFbRembPacket::FMT);
+        assert_eq!(fb_remb.header.packet_type, RtcpFbPsPacket::PT);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 32]));
+        write_rtcp_fb_remb(&mut cursor, &fb_remb).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_remb.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_remb = read_rtcp_fb_remb(&mut read_cursor, header, fb_header).unwrap();
+        assert_eq!(read_fb_remb.bitrate_bps(), 2_500_000);
+        assert_eq!(read_fb_remb.ssrcs, fb_remb.ssrcs);
+    }
+}
@@ -0,0 +1,276 @@
+use std::io::SeekFrom;
+
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    nsw_types::{num_traits::ConstZero, u1, u5, u7},
+};
+use bitvec::{order::Msb0, vec::BitVec};
+
+use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc4585#section-6.3.3
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      PB       |0| Payload Type|    Native RPSI bit string     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   defined per codec          ...                | Padding (0) |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// PB: 8 bits
+///    The number of unused bits required to pad the length of the RPSI
+///    message to a multiple of 32 bits.
+///
+/// Payload Type: 7 bits
+///    Indicates the RTP payload type in the context of which the native
+///    RPSI bit string MUST be interpreted.
+///
+/// Native RPSI bit string: variable length
+///    The RPSI information as natively defined by the video codec.
+///
+/// The PB field isn't stored: it's derived from the length of the native RPSI bit string when
+/// writing.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbRpsiPacket {
+    pub header: RtcpHeader,
+    pub fb_header: RtcpFbHeader,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u7"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u7))]
+    pub payload_type: u7,
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::bit_vec))]
+    pub native_rpsi: BitVec<u8, Msb0>,
+}
+
+impl RtcpFbRpsiPacket {
+    pub const FMT: u5 = u5::new(3);
+
+    /// The number of padding bits needed after the native RPSI bit string to bring the FCI to a
+    /// multiple of 32 bits.
+    pub fn padding_bits(&self) -> usize {
+        (16 + self.native_rpsi.len()).next_multiple_of(32) - (16 + self.native_rpsi.len())
+    }
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("RPSI payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbRpsiPacket {
+    fn length_bytes(&self) -> usize {
+        let fci_length_bits = 16 + self.native_rpsi.len() + self.padding_bits();
+        RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES + fci_length_bits / 8
+    }
+}
+
+pub fn read_rtcp_fb_rpsi<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpFbRpsiPacket> {
+    let padding_bits = buf.read_u8().context("pb")? as usize;
+    let zero = buf.read_u1().context("zero bit")?;
+    mode.check_reserved(warnings, "RPSI zero bit", u8::from(zero).into())?;
+    let payload_type = buf.read_u7().context("payload type")?;
+    let remaining_bits = buf.bytes_remaining() * 8;
+    if padding_bits > remaining_bits {
+        bail!(RtpParseError::invalid_field(
+            "RPSI padding length",
+            format!("at most {remaining_bits} bits"),
+            format!("{padding_bits} bits")
+        ));
+    }
+    let native_rpsi = (0..remaining_bits - padding_bits)
+        .map(|i| {
+            buf.read_bool()
+                .with_context(|| format!("native rpsi bit {i}"))
+        })
+        .collect::<Result<BitVec<u8, Msb0>>>()?;
+    buf.seek(SeekFrom::Current(padding_bits as i64))
+        .context("padding")?;
+
+    Ok(RtcpFbRpsiPacket {
+        header,
+        fb_header,
+        payload_type,
+        native_rpsi,
+    })
+}
+
+pub fn write_rtcp_fb_rpsi<B: PacketBufferMut>(
+    buf: &mut B,
+    fb_rpsi: &RtcpFbRpsiPacket,
+) -> Result<()> {
+    let padding_bits = fb_rpsi.padding_bits();
+    write_rtcp_header(buf, &fb_rpsi.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_rpsi.fb_header).context("fb header")?;
+    buf.write_u8(padding_bits as u8).context("pb")?;
+    buf.write_u1(u1::ZERO).context("zero bit")?;
+    buf.write_u7(fb_rpsi.payload_type).context("payload type")?;
+    for (i, bit) in fb_rpsi.native_rpsi.iter().enumerate() {
+        buf.write_bool(*bit)
+            .with_context(|| format!("native rpsi bit {i}"))?;
+    }
+    for _ in 0..padding_bits {
+        buf.write_bool(false).context("padding")?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbRpsiPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Reference Picture Selection Indication", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            tree.field("Payload type", u8::from(self.payload_type))?;
+            tree.field(
+                "Native RPSI bit string",
+                format_args!("{} bits", self.native_rpsi.len()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::bits;
+
+    use crate::rtcp::{rtcp_fb_header::read_rtcp_fb_header, rtcp_header::read_rtcp_header};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rtcp_fb_rpsi() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // pb 8, payload type 96, native rpsi 0xAB
+            0x08, 0x60, 0xAB, 0x00,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: RtcpFbRpsiPacket::FMT,
+            packet_type: RtcpFbPsPacket::PT,
+            length_field: 3,
+        };
+        let fb_header = RtcpFbHeader {
+            sender_ssrc: 1,
+            media_source_ssrc: 2,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        let fb_rpsi = read_rtcp_fb_rpsi(
+            &mut cursor,
+            header,
+            fb_header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(fb_rpsi.payload_type, u7::new(96));
+        assert_eq!(
+            fb_rpsi.native_rpsi,
+            BitVec::<u8, Msb0>::from_vec(vec![0xAB])
+        );
+    }
+
+    #[test]
+    fn test_read_rtcp_fb_rpsi_invalid_padding() {
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: RtcpFbRpsiPacket::FMT,
+            packet_type: RtcpFbPsPacket::PT,
+            length_field: 3,
+        };
+        let fb_header = RtcpFbHeader {
+            sender_ssrc: 1,
+            media_source_ssrc: 2,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0x20, 0x60, 0xAB, 0x00]));
+        assert!(read_rtcp_fb_rpsi(
+            &mut cursor,
+            header,
+            fb_header,
+            ParseMode::Lenient,
+            &mut ParseWarnings::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_rpsi_roundtrip() {
+        let mut fb_rpsi = RtcpFbRpsiPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+            payload_type: u7::new(100),
+            native_rpsi: bits![u8, Msb0; 1, 0, 1, 1, 0, 0, 1, 1, 1, 1, 0, 1, 0, 1, 1, 1, 0, 1, 1]
+                .to_bitvec(),
+        };
+        // 16 + 19 bits needs 29 bits of padding to reach 64
+        assert_eq!(fb_rpsi.padding_bits(), 29);
+        fb_rpsi.sync().unwrap();
+        assert_eq!(fb_rpsi.header.length_field, 4);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_rtcp_fb_rpsi(&mut cursor, &fb_rpsi).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_rpsi.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_rpsi = read_rtcp_fb_rpsi(
+            &mut read_cursor,
+            header,
+            fb_header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(read_fb_rpsi.payload_type, fb_rpsi.payload_type);
+        assert_eq!(read_fb_rpsi.native_rpsi, fb_rpsi.native_rpsi);
+    }
+}
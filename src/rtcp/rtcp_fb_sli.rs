@@ -0,0 +1,229 @@
+use anyhow::{anyhow, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u13, u5, u6},
+};
+
+use crate::{
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbPsPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc4585#section-6.3.2
+///
+/// The SLI FCI field MUST contain at least one and MAY contain more than
+/// one SLI.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbSliPacket {
+    pub header: RtcpHeader,
+    pub fb_header: RtcpFbHeader,
+    pub fcis: Vec<RtcpFbSliFci>,
+}
+
+impl RtcpFbSliPacket {
+    pub const FMT: u5 = u5::new(2);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("SLI payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbPsPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbSliPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + RtcpFbHeader::SIZE_BYTES
+            + self.fcis.len() * RtcpFbSliFci::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_sli<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+) -> Result<RtcpFbSliPacket> {
+    let mut fcis = Vec::new();
+    while buf.bytes_remaining() >= RtcpFbSliFci::SIZE_BYTES {
+        let fci = read_rtcp_fb_sli_fci(buf).with_context(|| format!("fci {}", fcis.len()))?;
+        fcis.push(fci);
+    }
+
+    Ok(RtcpFbSliPacket {
+        header,
+        fb_header,
+        fcis,
+    })
+}
+
+pub fn write_rtcp_fb_sli<B: PacketBufferMut>(buf: &mut B, fb_sli: &RtcpFbSliPacket) -> Result<()> {
+    write_rtcp_header(buf, &fb_sli.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_sli.fb_header).context("fb header")?;
+    for (i, fci) in fb_sli.fcis.iter().enumerate() {
+        write_rtcp_fb_sli_fci(buf, fci).with_context(|| format!("fci {i}"))?;
+    }
+
+    Ok(())
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc4585#section-6.3.2.2
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            First        |        Number           | PictureID |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// First: 13 bits
+///    The macroblock (MB) address of the first lost macroblock.
+///
+/// Number: 13 bits
+///    The number of lost macroblocks, in scan order as discussed above.
+///
+/// PictureID: 6 bits
+///    The six least significant bits of the codec-specific identifier
+///    that is used to reference the picture in which the loss of the
+///    macroblock(s) has occurred.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbSliFci {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u13"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u13))]
+    pub first: u13,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u13"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u13))]
+    pub number: u13,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u6"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u6))]
+    pub picture_id: u6,
+}
+
+impl RtcpFbSliFci {
+    pub const SIZE_BYTES: usize = 4;
+}
+
+impl LengthBytes for RtcpFbSliFci {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_sli_fci<B: PacketBuffer>(buf: &mut B) -> Result<RtcpFbSliFci> {
+    Ok(RtcpFbSliFci {
+        first: buf.read_u13::<NetworkOrder>().context("first")?,
+        number: buf.read_u13::<NetworkOrder>().context("number")?,
+        picture_id: buf.read_u6().context("picture id")?,
+    })
+}
+
+pub fn write_rtcp_fb_sli_fci<B: PacketBufferMut>(buf: &mut B, fci: &RtcpFbSliFci) -> Result<()> {
+    buf.write_u13::<NetworkOrder>(fci.first).context("first")?;
+    buf.write_u13::<NetworkOrder>(fci.number)
+        .context("number")?;
+    buf.write_u6(fci.picture_id).context("picture id")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbSliPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Slice Loss Indication", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            for (i, fci) in self.fcis.iter().enumerate() {
+                tree.subtree(format_args!("FCI {}", i + 1), |tree| {
+                    tree.field("First", u16::from(fci.first))?;
+                    tree.field("Number", u16::from(fci.number))?;
+                    tree.field("Picture ID", u8::from(fci.picture_id))
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::{rtcp_fb_header::read_rtcp_fb_header, rtcp_header::read_rtcp_header};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rtcp_fb_sli_fci() {
+        // first 5, number 10, picture id 63
+        let data = vec![0x00, 0x28, 0x02, 0xBF];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let fci = read_rtcp_fb_sli_fci(&mut cursor).unwrap();
+        assert_eq!(fci.first, u13::new(5));
+        assert_eq!(fci.number, u13::new(10));
+        assert_eq!(fci.picture_id, u6::new(63));
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_sli_roundtrip() {
+        let mut fb_sli = RtcpFbSliPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+            fcis: vec![
+                RtcpFbSliFci {
+                    first: u13::new(0),
+                    number: u13::new(100),
+                    picture_id: u6::new(1),
+                },
+                RtcpFbSliFci {
+                    first: u13::new(8191),
+                    number: u13::new(1),
+                    picture_id: u6::new(2),
+                },
+            ],
+        };
+        fb_sli.sync().unwrap();
+        assert_eq!(fb_sli.header.length_field, 4);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_rtcp_fb_sli(&mut cursor, &fb_sli).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_sli.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_sli = read_rtcp_fb_sli(&mut read_cursor, header, fb_header).unwrap();
+        assert_eq!(read_fb_sli.fcis, fb_sli.fcis);
+    }
+}
@@ -1,6 +1,9 @@
+use std::time::Duration;
+
 use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
     bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
     byte_order::NetworkOrder,
     nsw_types::{
         num_traits::{ConstOne, ConstZero},
@@ -8,9 +11,19 @@ use bit_cursor::{
     },
 };
 
-use crate::{util::consume_padding, PacketBuffer};
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint},
+    rtp::seq_num::SeqNum,
+    util::consume_padding,
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
 
-use super::{rtcp_fb_header::RtcpFbHeader, rtcp_header::RtcpHeader};
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbTlPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
 
 const U2_TWO: u2 = u2::new(2);
 
@@ -49,16 +62,368 @@ const U2_TWO: u2 = u2::new(2);
 ///  feedback packet sent.  Used to detect feedback packet
 ///  losses.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RtcpFbTccPacket {
     pub header: RtcpHeader,
     pub fb_header: RtcpFbHeader,
-    pub packet_reports: Vec<PacketReport>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u24"))]
     pub reference_time: u24,
     pub feedback_packet_count: u8,
+    base_seq_num: u16,
+    packet_status_count: u16,
+    /// The packet status chunks, as they appear on the wire.  Feedback packets can report on
+    /// thousands of packets, so the statuses are kept in their encoded form and only expanded into
+    /// [`PacketReport`]s when iterated over.
+    chunks: Vec<SomePacketStatusChunk>,
+    /// The receive delta (in ticks) of each received packet, in order
+    deltas: Vec<i16>,
 }
 
 impl RtcpFbTccPacket {
     pub const FMT: u5 = u5::new(15);
+    /// The resolution of the reference time field
+    pub const REFERENCE_TIME_RESOLUTION: Duration = Duration::from_millis(64);
+    /// The resolution of the receive delta fields
+    pub const DELTA_TICK_RESOLUTION: Duration = Duration::from_micros(250);
+
+    /// Create a feedback packet from the given packet reports, which must be non-empty and have
+    /// consecutive sequence numbers.  The header is not synced.
+    pub fn from_packet_reports(
+        header: RtcpHeader,
+        fb_header: RtcpFbHeader,
+        packet_reports: &[PacketReport],
+        reference_time: u24,
+        feedback_packet_count: u8,
+    ) -> Result<Self> {
+        let base_seq_num = packet_reports
+            .first()
+            .map(|pr| pr.seq_num())
+            .ok_or(anyhow!(
+                "TCC feedback must contain at least one packet report"
+            ))?;
+        let packet_status_count: u16 = packet_reports
+            .len()
+            .try_into()
+            .context("packet status count")?;
+        let chunks = create_packet_status_chunks(packet_reports).context("packet status chunks")?;
+        let deltas = packet_reports
+            .iter()
+            .filter_map(|pr| pr.delta_ticks())
+            .collect();
+
+        Ok(RtcpFbTccPacket {
+            header,
+            fb_header,
+            reference_time,
+            feedback_packet_count,
+            base_seq_num,
+            packet_status_count,
+            chunks,
+            deltas,
+        })
+    }
+
+    /// The sequence number of the first packet this feedback reports on
+    pub fn base_seq_num(&self) -> u16 {
+        self.base_seq_num
+    }
+
+    /// The number of packets this feedback reports on
+    pub fn packet_status_count(&self) -> u16 {
+        self.packet_status_count
+    }
+
+    /// Iterate over the report for every packet this feedback reports on, in sequence number
+    /// order.  Reports are decoded from the packet status chunks and receive deltas as they're
+    /// iterated over.
+    pub fn packet_reports(&self) -> impl Iterator<Item = PacketReport> + '_ {
+        let mut deltas = self.deltas.iter().copied();
+        self.status_symbols().enumerate().map(move |(i, symbol)| {
+            let seq_num = self.base_seq_num.wrapping_add(i as u16);
+            // There's always a delta for every received packet: the deltas are read (or
+            // created) from the same symbols.
+            match symbol {
+                PacketStatusSymbol::NotReceived => PacketReport::UnreceivedPacket { seq_num },
+                PacketStatusSymbol::ReceivedSmallDelta => PacketReport::ReceivedPacketSmallDelta {
+                    seq_num,
+                    delta_ticks: deltas.next().unwrap_or_default() as u8,
+                },
+                PacketStatusSymbol::ReceivedLargeOrNegativeDelta => {
+                    PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                        seq_num,
+                        delta_ticks: deltas.next().unwrap_or_default(),
+                    }
+                }
+            }
+        })
+    }
+
+    /// The status symbol of every packet this feedback reports on, in sequence number order.
+    fn status_symbols(&self) -> impl Iterator<Item = PacketStatusSymbol> + '_ {
+        self.chunks
+            .iter()
+            .flatten()
+            .take(self.packet_status_count as usize)
+    }
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes, including
+    /// the zero padding after the receive deltas.
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("TCC payload length {length_bytes} bytes is too large"))
+    }
+
+    /// The length in bytes of the TCC data (everything after the FB header), not including the
+    /// zero padding at the end.
+    fn data_length_bytes(&self) -> usize {
+        // The base seq num, packet status count, reference time and feedback packet count take up
+        // 8 bytes and each chunk is 2 bytes.
+        let delta_bytes: usize = self
+            .status_symbols()
+            .map(|symbol| symbol.delta_size_bytes())
+            .sum();
+
+        8 + self.chunks.len() * 2 + delta_bytes
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbTlPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+
+    /// The reference time as a duration (i.e. with the 64ms resolution applied)
+    pub fn reference_time_duration(&self) -> Duration {
+        RtcpFbTccPacket::REFERENCE_TIME_RESOLUTION * u32::from(self.reference_time)
+    }
+
+    /// Iterate over the sequence number of every packet this feedback reports on, along with its
+    /// arrival time (or None if it wasn't received).  Arrival times are the reference time plus
+    /// all receive deltas up to and including the packet's own, so they're relative to the same
+    /// epoch as the reference time.  An arrival time which would be negative is clamped to 0.
+    pub fn iter_arrivals(&self) -> impl Iterator<Item = (u16, Option<Duration>)> + '_ {
+        let delta_tick_us = RtcpFbTccPacket::DELTA_TICK_RESOLUTION.as_micros() as i64;
+        let mut arrival_time_us = self.reference_time_duration().as_micros() as i64;
+        self.packet_reports().map(move |packet_report| {
+            let Some(delta_ticks) = packet_report.delta_ticks() else {
+                return (packet_report.seq_num(), None);
+            };
+            arrival_time_us += delta_ticks as i64 * delta_tick_us;
+            (
+                packet_report.seq_num(),
+                Some(Duration::from_micros(arrival_time_us.max(0) as u64)),
+            )
+        })
+    }
+
+    /// How many feedback packets were sent between `previous` and this one (according to their
+    /// feedback packet counts) which weren't seen, i.e. 0 if this packet directly follows
+    /// `previous`.
+    pub fn num_feedback_packets_missed_since(&self, previous: &RtcpFbTccPacket) -> u8 {
+        self.feedback_packet_count
+            .wrapping_sub(previous.feedback_packet_count)
+            .wrapping_sub(1)
+    }
+
+    /// Merge this feedback packet with the one that followed it, returning a single (synced)
+    /// feedback packet which reports on the packets covered by either of them.  The merged packet
+    /// keeps this packet's reference time and `next`'s feedback packet count, so further packets
+    /// can be merged into it.  If a packet is reported as lost by one of them and received by the
+    /// other, it's considered received.
+    ///
+    /// Fails if `next` doesn't directly follow this packet (i.e. there was a feedback packet in
+    /// between which was lost), if the sequence numbers they report on aren't overlapping or
+    /// adjacent, or if the merged receive deltas or packet status count can't be represented in a
+    /// single feedback packet.
+    pub fn merge(&self, next: &RtcpFbTccPacket) -> Result<RtcpFbTccPacket> {
+        let num_missed = next.num_feedback_packets_missed_since(self);
+        if num_missed != 0 {
+            bail!(
+                "Can't merge feedback packet {} into {}: missed {num_missed} feedback packets in between",
+                next.feedback_packet_count,
+                self.feedback_packet_count
+            );
+        }
+        let next_start =
+            SeqNum(next.base_seq_num).signed_distance_from(SeqNum(self.base_seq_num)) as i32;
+        let next_end = next_start + next.packet_status_count as i32;
+        if next_start > self.packet_status_count as i32 || next_end < 0 {
+            bail!(
+                "Can't merge feedback packets: sequence numbers {}+{} and {}+{} aren't overlapping or adjacent",
+                self.base_seq_num,
+                self.packet_status_count,
+                next.base_seq_num,
+                next.packet_status_count
+            );
+        }
+        let start = next_start.min(0);
+        let end = next_end.max(self.packet_status_count as i32);
+
+        // The arrival time (in delta ticks, relative to this packet's reference time) of every
+        // packet covered by the merged packet
+        let mut arrivals: Vec<Option<i64>> = vec![None; (end - start) as usize];
+        let reference_time_diff = (u32::from(next.reference_time) as i64
+            - u32::from(self.reference_time) as i64)
+            .rem_euclid(REFERENCE_TIME_RANGE);
+        let reference_time_diff = if reference_time_diff >= REFERENCE_TIME_RANGE / 2 {
+            reference_time_diff - REFERENCE_TIME_RANGE
+        } else {
+            reference_time_diff
+        };
+        for (fb_tcc, offset, initial_arrival_ticks) in [
+            (self, -start, 0),
+            (
+                next,
+                next_start - start,
+                reference_time_diff * TICKS_PER_REFERENCE_TIME_TICK,
+            ),
+        ] {
+            let mut arrival_ticks = initial_arrival_ticks;
+            for (i, packet_report) in fb_tcc.packet_reports().enumerate() {
+                if let Some(delta_ticks) = packet_report.delta_ticks() {
+                    arrival_ticks += delta_ticks as i64;
+                    arrivals[offset as usize + i].get_or_insert(arrival_ticks);
+                }
+            }
+        }
+
+        let base_seq_num = self.base_seq_num.wrapping_add(start as u16);
+        let mut prev_arrival_ticks = 0;
+        let mut packet_reports: Vec<PacketReport> = Vec::with_capacity(arrivals.len());
+        for (i, arrival_ticks) in arrivals.into_iter().enumerate() {
+            let seq_num = base_seq_num.wrapping_add(i as u16);
+            let packet_report = match arrival_ticks {
+                None => PacketReport::UnreceivedPacket { seq_num },
+                Some(arrival_ticks) => {
+                    let delta_ticks = arrival_ticks - prev_arrival_ticks;
+                    prev_arrival_ticks = arrival_ticks;
+                    PacketReport::received(seq_num, delta_ticks).ok_or(anyhow!(
+                        "Receive delta of {delta_ticks} ticks for packet {seq_num} is too large"
+                    ))?
+                }
+            };
+            packet_reports.push(packet_report);
+        }
+
+        let mut merged = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: self.header.version,
+                has_padding: false,
+                report_count: Self::FMT,
+                packet_type: RtcpFbTlPacket::PT,
+                length_field: 0,
+            },
+            RtcpFbHeader {
+                sender_ssrc: self.fb_header.sender_ssrc,
+                media_source_ssrc: self.fb_header.media_source_ssrc,
+            },
+            &packet_reports,
+            self.reference_time,
+            next.feedback_packet_count,
+        )
+        .context("merged packet reports")?;
+        merged.sync().context("sync")?;
+
+        Ok(merged)
+    }
+}
+
+impl LengthBytes for RtcpFbTccPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + RtcpFbHeader::SIZE_BYTES
+            + self.data_length_bytes().next_multiple_of(4)
+    }
+}
+
+/// Generates feedback through [`RtcpFbTccBuilder`](super::rtcp_fb_tcc_builder::RtcpFbTccBuilder),
+/// so the chunks and deltas are consistent: a run of packets, some of which weren't received,
+/// with small, large and negative receive deltas.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RtcpFbTccPacket {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut builder = super::rtcp_fb_tcc_builder::RtcpFbTccBuilder::new(
+            u.arbitrary()?,
+            u.arbitrary()?,
+            u.arbitrary()?,
+        );
+        let mut seq_num: u16 = u.arbitrary()?;
+        let mut arrival_time_us: i64 = u.int_in_range(0..=1_000_000_000)?;
+        builder.add_received_packet(seq_num, Duration::from_micros(arrival_time_us as u64));
+        for _ in 0..u.int_in_range(0..=200)? {
+            seq_num = seq_num.wrapping_add(1);
+            arrival_time_us = (arrival_time_us + u.int_in_range(-10_000..=100_000)?).max(0);
+            if u.arbitrary()? {
+                builder.add_received_packet(seq_num, Duration::from_micros(arrival_time_us as u64));
+            }
+        }
+
+        builder
+            .build()
+            .ok()
+            .and_then(|packets| packets.into_iter().next())
+            .ok_or(arbitrary::Error::IncorrectFormat)
+    }
+}
+
+/// The number of receive delta ticks in one reference time tick
+const TICKS_PER_REFERENCE_TIME_TICK: i64 = (RtcpFbTccPacket::REFERENCE_TIME_RESOLUTION.as_micros()
+    / RtcpFbTccPacket::DELTA_TICK_RESOLUTION.as_micros())
+    as i64;
+
+/// The number of distinct values of the 24 bit reference time field
+const REFERENCE_TIME_RANGE: i64 = 1 << 24;
+
+/// Unwraps the 24 bit reference times of successive feedback packets (which wrap roughly every
+/// 18 minutes) into absolute reference times, in the same way a rollover counter is used to
+/// extend RTP sequence numbers.  Each reference time is assumed to be the closest possible
+/// value to the previous one, so feedback packets can be reordered as long as they're within
+/// half the range (roughly 9 minutes) of each other.
+#[derive(Debug, Default)]
+pub struct TccReferenceTimeUnwrapper {
+    last_unwrapped: Option<i64>,
+}
+
+impl TccReferenceTimeUnwrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unwrap the given reference time into an absolute number of 64ms ticks.  The first
+    /// reference time is returned as-is; a reordered packet from before a wrap may produce a
+    /// negative value.
+    pub fn unwrap(&mut self, reference_time: u24) -> i64 {
+        let value = u32::from(reference_time) as i64;
+        let unwrapped = match self.last_unwrapped {
+            None => value,
+            Some(last_unwrapped) => {
+                let mut diff = value - last_unwrapped.rem_euclid(REFERENCE_TIME_RANGE);
+                if diff > REFERENCE_TIME_RANGE / 2 {
+                    diff -= REFERENCE_TIME_RANGE;
+                } else if diff < -REFERENCE_TIME_RANGE / 2 {
+                    diff += REFERENCE_TIME_RANGE;
+                }
+                last_unwrapped + diff
+            }
+        };
+        self.last_unwrapped = Some(unwrapped);
+
+        unwrapped
+    }
+
+    /// Unwrap the reference time of the given feedback packet into a duration (i.e. with the 64ms
+    /// resolution applied).  Values which would be negative are clamped to 0.
+    pub fn unwrap_packet(&mut self, fb_tcc: &RtcpFbTccPacket) -> Duration {
+        let ticks = self.unwrap(fb_tcc.reference_time).max(0) as u64;
+        Duration::from_millis(ticks * RtcpFbTccPacket::REFERENCE_TIME_RESOLUTION.as_millis() as u64)
+    }
 }
 
 pub fn read_rtcp_fb_tcc<B: PacketBuffer>(
@@ -66,17 +431,6 @@ pub fn read_rtcp_fb_tcc<B: PacketBuffer>(
     header: RtcpHeader,
     fb_header: RtcpFbHeader,
 ) -> Result<RtcpFbTccPacket> {
-    let (packet_reports, reference_time, feedback_packet_count) = read_rtcp_fb_tcc_data(buf)?;
-    Ok(RtcpFbTccPacket {
-        header,
-        fb_header,
-        packet_reports,
-        reference_time,
-        feedback_packet_count,
-    })
-}
-
-fn read_rtcp_fb_tcc_data<B: PacketBuffer>(buf: &mut B) -> Result<(Vec<PacketReport>, u24, u8)> {
     let base_seq_num = buf.read_u16::<NetworkOrder>().context("base seq num")?;
     let packet_status_count = buf
         .read_u16::<NetworkOrder>()
@@ -85,58 +439,160 @@ fn read_rtcp_fb_tcc_data<B: PacketBuffer>(buf: &mut B) -> Result<(Vec<PacketRepo
     let feedback_packet_count = buf.read_u8().context("feedback packet count")?;
 
     let mut num_status_remaining = packet_status_count;
-
     let mut chunks: Vec<SomePacketStatusChunk> = Vec::new();
     while num_status_remaining > 0 {
         let chunk = read_some_packet_status_chunk(buf, num_status_remaining as usize)
             .context("packet status chunk")?;
-        num_status_remaining -= chunk.num_symbols();
+        num_status_remaining = num_status_remaining.saturating_sub(chunk.num_symbols());
         chunks.push(chunk);
     }
-    let mut curr_seq_num = base_seq_num;
-    let mut packet_reports: Vec<PacketReport> = Vec::new();
-    for chunk in chunks {
-        for status_symbol in chunk {
-            match status_symbol.delta_size_bytes() {
-                0 => packet_reports.push(PacketReport::UnreceivedPacket {
-                    seq_num: curr_seq_num,
-                }),
-                1 => {
-                    let delta_ticks = buf
-                        .read_u8()
-                        .with_context(|| format!("delta ticks for packet {curr_seq_num}"))?;
-                    packet_reports.push(PacketReport::ReceivedPacketSmallDelta {
-                        seq_num: curr_seq_num,
-                        delta_ticks,
-                    });
-                }
-                2 => {
-                    let delta_ticks = buf
-                        .read_u16::<NetworkOrder>()
-                        .with_context(|| format!("delta ticks for packet {curr_seq_num}"))?
-                        as i16;
-                    packet_reports.push(PacketReport::ReceivedPacketLargeOrNegativeDelta {
-                        seq_num: curr_seq_num,
-                        delta_ticks,
-                    });
-                }
-                delta_size_bytes => bail!("Invalid delta size: {delta_size_bytes} bytes"),
+
+    let mut fb_tcc = RtcpFbTccPacket {
+        header,
+        fb_header,
+        reference_time,
+        feedback_packet_count,
+        base_seq_num,
+        packet_status_count,
+        chunks,
+        deltas: Vec::new(),
+    };
+    let mut deltas: Vec<i16> = Vec::new();
+    for (i, status_symbol) in fb_tcc.status_symbols().enumerate() {
+        let seq_num = base_seq_num.wrapping_add(i as u16);
+        match status_symbol {
+            PacketStatusSymbol::NotReceived => {}
+            PacketStatusSymbol::ReceivedSmallDelta => {
+                let delta_ticks = buf
+                    .read_u8()
+                    .with_context(|| format!("delta ticks for packet {seq_num}"))?;
+                deltas.push(delta_ticks as i16);
+            }
+            PacketStatusSymbol::ReceivedLargeOrNegativeDelta => {
+                let delta_ticks = buf
+                    .read_u16::<NetworkOrder>()
+                    .with_context(|| format!("delta ticks for packet {seq_num}"))?;
+                deltas.push(delta_ticks as i16);
             }
-            curr_seq_num = curr_seq_num.wrapping_add(1);
         }
     }
+    fb_tcc.deltas = deltas;
     consume_padding(buf);
-    Ok((packet_reports, reference_time, feedback_packet_count))
+
+    Ok(fb_tcc)
+}
+
+pub fn write_rtcp_fb_tcc<B: PacketBufferMut>(buf: &mut B, fb_tcc: &RtcpFbTccPacket) -> Result<()> {
+    write_rtcp_header(buf, &fb_tcc.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_tcc.fb_header).context("fb header")?;
+    write_rtcp_fb_tcc_data(buf, fb_tcc).context("tcc data")?;
+
+    Ok(())
+}
+
+fn write_rtcp_fb_tcc_data<B: PacketBufferMut>(buf: &mut B, fb_tcc: &RtcpFbTccPacket) -> Result<()> {
+    buf.write_u16::<NetworkOrder>(fb_tcc.base_seq_num)
+        .context("base seq num")?;
+    buf.write_u16::<NetworkOrder>(fb_tcc.packet_status_count)
+        .context("packet status count")?;
+    buf.write_u24::<NetworkOrder>(fb_tcc.reference_time)
+        .context("reference time")?;
+    buf.write_u8(fb_tcc.feedback_packet_count)
+        .context("feedback packet count")?;
+
+    for (i, chunk) in fb_tcc.chunks.iter().enumerate() {
+        write_some_packet_status_chunk(buf, chunk)
+            .with_context(|| format!("packet status chunk {i}"))?;
+    }
+    for packet_report in fb_tcc.packet_reports() {
+        match packet_report {
+            PacketReport::UnreceivedPacket { .. } => {}
+            PacketReport::ReceivedPacketSmallDelta {
+                seq_num,
+                delta_ticks,
+            } => {
+                buf.write_u8(delta_ticks)
+                    .with_context(|| format!("delta ticks for packet {seq_num}"))?;
+            }
+            PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                seq_num,
+                delta_ticks,
+            } => {
+                buf.write_u16::<NetworkOrder>(delta_ticks as u16)
+                    .with_context(|| format!("delta ticks for packet {seq_num}"))?;
+            }
+        }
+    }
+    // The data must be zero-padded to a 32 bit boundary
+    let data_length_bytes = fb_tcc.data_length_bytes();
+    for _ in data_length_bytes..data_length_bytes.next_multiple_of(4) {
+        buf.write_u8(0).context("padding")?;
+    }
+
+    Ok(())
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum PacketReport {
     UnreceivedPacket { seq_num: u16 },
     ReceivedPacketSmallDelta { seq_num: u16, delta_ticks: u8 },
     ReceivedPacketLargeOrNegativeDelta { seq_num: u16, delta_ticks: i16 },
 }
 
+impl PacketReport {
+    pub fn seq_num(&self) -> u16 {
+        match self {
+            PacketReport::UnreceivedPacket { seq_num } => *seq_num,
+            PacketReport::ReceivedPacketSmallDelta { seq_num, .. } => *seq_num,
+            PacketReport::ReceivedPacketLargeOrNegativeDelta { seq_num, .. } => *seq_num,
+        }
+    }
+
+    /// A report for a received packet with the given receive delta, using the smallest
+    /// representation the delta fits in.  Returns None if the delta is too large to be represented.
+    pub fn received(seq_num: u16, delta_ticks: i64) -> Option<PacketReport> {
+        if let Ok(delta_ticks) = u8::try_from(delta_ticks) {
+            Some(PacketReport::ReceivedPacketSmallDelta {
+                seq_num,
+                delta_ticks,
+            })
+        } else if let Ok(delta_ticks) = i16::try_from(delta_ticks) {
+            Some(PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                seq_num,
+                delta_ticks,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// The receive delta of this packet in ticks, or None if it wasn't received
+    pub fn delta_ticks(&self) -> Option<i16> {
+        match self {
+            PacketReport::UnreceivedPacket { .. } => None,
+            PacketReport::ReceivedPacketSmallDelta { delta_ticks, .. } => Some(*delta_ticks as i16),
+            PacketReport::ReceivedPacketLargeOrNegativeDelta { delta_ticks, .. } => {
+                Some(*delta_ticks)
+            }
+        }
+    }
+
+    pub fn status_symbol(&self) -> PacketStatusSymbol {
+        match self {
+            PacketReport::UnreceivedPacket { .. } => PacketStatusSymbol::NotReceived,
+            PacketReport::ReceivedPacketSmallDelta { .. } => PacketStatusSymbol::ReceivedSmallDelta,
+            PacketReport::ReceivedPacketLargeOrNegativeDelta { .. } => {
+                PacketStatusSymbol::ReceivedLargeOrNegativeDelta
+            }
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum PacketStatusSymbol {
     NotReceived = 0,
     ReceivedSmallDelta = 1,
@@ -171,7 +627,11 @@ impl TryFrom<u2> for PacketStatusSymbol {
             u2::ZERO => Ok(PacketStatusSymbol::NotReceived),
             u2::ONE => Ok(PacketStatusSymbol::ReceivedSmallDelta),
             U2_TWO => Ok(PacketStatusSymbol::ReceivedLargeOrNegativeDelta),
-            pss => Err(anyhow!("Invalid 2 bit packet status symbol: {pss}")),
+            pss => bail!(RtpParseError::invalid_field(
+                "packet status symbol",
+                "0-2",
+                pss
+            )),
         }
     }
 }
@@ -200,6 +660,8 @@ impl TryFrom<u2> for PacketStatusSymbol {
 ///             total.
 /// ```
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct StatusVectorChunk(Vec<PacketStatusSymbol>);
 
 impl IntoIterator for StatusVectorChunk {
@@ -254,6 +716,48 @@ pub fn read_status_vector_chunk<B: PacketBuffer>(
     Ok(StatusVectorChunk(packet_status_symbols))
 }
 
+/// Status vector chunks with more than 7 symbols are written using 1 bit symbols, otherwise they
+/// are written using 2 bit symbols.  Chunks with fewer symbols than their capacity (which is only
+/// valid for the last chunk in a packet) are padded with [`PacketStatusSymbol::NotReceived`].
+///
+/// This method assumes buf's position is at the symbol-size bit.
+pub fn write_status_vector_chunk<B: PacketBufferMut>(
+    buf: &mut B,
+    chunk: &StatusVectorChunk,
+) -> Result<()> {
+    let symbols = &chunk.0;
+    match symbols.len() {
+        0..=7 => {
+            buf.write_u1(u1::ONE).context("symbol size")?;
+            for i in 0..7 {
+                let symbol = symbols
+                    .get(i)
+                    .copied()
+                    .unwrap_or(PacketStatusSymbol::NotReceived);
+                buf.write_u2(u2::new(symbol as u8))
+                    .with_context(|| format!("packet status symbol {i}"))?;
+            }
+        }
+        8..=14 => {
+            buf.write_u1(u1::ZERO).context("symbol size")?;
+            for i in 0..14 {
+                let symbol = match symbols.get(i) {
+                    None | Some(PacketStatusSymbol::NotReceived) => u1::ZERO,
+                    Some(PacketStatusSymbol::ReceivedSmallDelta) => u1::ONE,
+                    Some(PacketStatusSymbol::ReceivedLargeOrNegativeDelta) => {
+                        bail!("Symbol {i} can't be represented in a 1 bit status vector chunk")
+                    }
+                };
+                buf.write_u1(symbol)
+                    .with_context(|| format!("packet status symbol {i}"))?;
+            }
+        }
+        len => bail!("Status vector chunk can contain at most 14 symbols, has {len}"),
+    }
+
+    Ok(())
+}
+
 /// A run length chunk starts with 0 bit, followed by a packet status
 /// symbol and the run length of that symbol.
 /// ```text
@@ -271,8 +775,12 @@ pub fn read_status_vector_chunk<B: PacketBuffer>(
 /// run length (L):  13 bits An unsigned integer denoting the run length.
 /// ```
 #[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RunLengthEncodingChunk {
     pub symbol: PacketStatusSymbol,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u13"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u13))]
     pub run_length: u13,
 }
 
@@ -324,17 +832,33 @@ pub fn read_run_length_encoding_chunk<B: PacketBuffer>(
     Ok(RunLengthEncodingChunk { symbol, run_length })
 }
 
+///
+/// This method assumes buf's position is at the packet status symbol bit
+pub fn write_run_length_encoding_chunk<B: PacketBufferMut>(
+    buf: &mut B,
+    chunk: &RunLengthEncodingChunk,
+) -> Result<()> {
+    buf.write_u2(u2::new(chunk.symbol as u8))
+        .context("packet status symbol")?;
+    buf.write_u13::<NetworkOrder>(chunk.run_length)
+        .context("run length")?;
+
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum SomePacketStatusChunk {
     StatusVectorChunk(StatusVectorChunk),
     RunLengthEncodingChunk(RunLengthEncodingChunk),
 }
 
-enum SomePacketStatusChunkIterator {
+enum SomePacketStatusChunkIterator<'a> {
     RunLengthEncodingChunkIterator(RunLengthEncodingIterator),
-    StatusVectorChunkIterator(<StatusVectorChunk as IntoIterator>::IntoIter),
+    StatusVectorChunkIterator(std::iter::Copied<std::slice::Iter<'a, PacketStatusSymbol>>),
 }
 
-impl Iterator for SomePacketStatusChunkIterator {
+impl Iterator for SomePacketStatusChunkIterator<'_> {
     type Item = PacketStatusSymbol;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -354,18 +878,24 @@ impl SomePacketStatusChunk {
     }
 }
 
-impl IntoIterator for SomePacketStatusChunk {
+impl<'a> IntoIterator for &'a SomePacketStatusChunk {
     type Item = PacketStatusSymbol;
 
-    type IntoIter = SomePacketStatusChunkIterator;
+    type IntoIter = SomePacketStatusChunkIterator<'a>;
 
     fn into_iter(self) -> Self::IntoIter {
         match self {
             SomePacketStatusChunk::StatusVectorChunk(svc) => {
-                SomePacketStatusChunkIterator::StatusVectorChunkIterator(svc.into_iter())
+                SomePacketStatusChunkIterator::StatusVectorChunkIterator(svc.0.iter().copied())
             }
             SomePacketStatusChunk::RunLengthEncodingChunk(rlec) => {
-                SomePacketStatusChunkIterator::RunLengthEncodingChunkIterator(rlec.into_iter())
+                SomePacketStatusChunkIterator::RunLengthEncodingChunkIterator(
+                    RunLengthEncodingIterator {
+                        symbol: rlec.symbol,
+                        curr_idx: 0,
+                        length: rlec.run_length.into(),
+                    },
+                )
             }
         }
     }
@@ -387,14 +917,148 @@ fn read_some_packet_status_chunk<B: PacketBuffer>(
     }
 }
 
+fn write_some_packet_status_chunk<B: PacketBufferMut>(
+    buf: &mut B,
+    chunk: &SomePacketStatusChunk,
+) -> Result<()> {
+    match chunk {
+        SomePacketStatusChunk::RunLengthEncodingChunk(rlec) => {
+            buf.write_u1(u1::ZERO).context("chunk type")?;
+            write_run_length_encoding_chunk(buf, rlec).context("run length encoding chunk")
+        }
+        SomePacketStatusChunk::StatusVectorChunk(svc) => {
+            buf.write_u1(u1::ONE).context("chunk type")?;
+            write_status_vector_chunk(buf, svc).context("status vector chunk")
+        }
+    }
+}
+
+/// The largest run length that fits in the 13 bit run length field of a run length chunk
+const MAX_RUN_LENGTH: usize = 8191;
+
+/// Encode the status symbols of the given packet reports into packet status chunks.  A run length
+/// chunk is used whenever a run of identical symbols is at least as long as the status vector
+/// chunk that would otherwise be used, otherwise a status vector chunk is used.
+fn create_packet_status_chunks(
+    packet_reports: &[PacketReport],
+) -> Result<Vec<SomePacketStatusChunk>> {
+    let mut expected_seq_num = packet_reports.first().map(|pr| pr.seq_num());
+    let mut symbols: Vec<PacketStatusSymbol> = Vec::with_capacity(packet_reports.len());
+    for packet_report in packet_reports {
+        if Some(packet_report.seq_num()) != expected_seq_num {
+            bail!(
+                "Packet reports must have consecutive sequence numbers: expected {expected_seq_num:?}, got {}",
+                packet_report.seq_num()
+            );
+        }
+        symbols.push(packet_report.status_symbol());
+        expected_seq_num = Some(packet_report.seq_num().wrapping_add(1));
+    }
+
+    let mut chunks: Vec<SomePacketStatusChunk> = Vec::new();
+    let mut remaining = &symbols[..];
+    while let Some(first) = remaining.first() {
+        let run_length = remaining.iter().take_while(|s| *s == first).count();
+        let one_bit_symbols = remaining.len() > 7
+            && !remaining
+                .iter()
+                .take(14)
+                .any(|s| *s == PacketStatusSymbol::ReceivedLargeOrNegativeDelta);
+        let status_vector_capacity = if one_bit_symbols { 14 } else { 7 };
+        let num_symbols = if run_length >= status_vector_capacity {
+            let run_length = run_length.min(MAX_RUN_LENGTH);
+            chunks.push(SomePacketStatusChunk::RunLengthEncodingChunk(
+                RunLengthEncodingChunk {
+                    symbol: *first,
+                    run_length: u13::new(run_length as u16),
+                },
+            ));
+            run_length
+        } else {
+            let num_symbols = status_vector_capacity.min(remaining.len());
+            chunks.push(SomePacketStatusChunk::StatusVectorChunk(StatusVectorChunk(
+                remaining[..num_symbols].to_vec(),
+            )));
+            num_symbols
+        };
+        remaining = &remaining[num_symbols..];
+    }
+
+    Ok(chunks)
+}
+
+impl PrettyPrint for RtcpFbTccPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Transport-wide Congestion Control Feedback", |tree| {
+            self.header.pretty_print(tree)?;
+            self.fb_header.pretty_print(tree)?;
+            tree.field("Base sequence number", self.base_seq_num)?;
+            tree.field("Packet status count", self.packet_status_count)?;
+            tree.field(
+                "Reference time",
+                format_args!(
+                    "{} ({:?})",
+                    u32::from(self.reference_time),
+                    self.reference_time_duration()
+                ),
+            )?;
+            tree.field("Feedback packet count", self.feedback_packet_count)?;
+            tree.field("Packet status chunks", self.chunks.len())?;
+            tree.subtree("Packet reports", |tree| {
+                for packet_report in self.packet_reports() {
+                    let seq_num = format!("Seq {}", packet_report.seq_num());
+                    match packet_report.delta_ticks() {
+                        Some(delta_ticks) => tree.field(
+                            &seq_num,
+                            format_args!("received, delta {delta_ticks} ticks"),
+                        )?,
+                        None => tree.field(&seq_num, "not received")?,
+                    }
+                }
+                Ok(())
+            })
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u24};
+    use std::time::Duration;
+
+    use bit_cursor::{
+        bit_cursor::BitCursor,
+        nsw_types::{u2, u24, u5},
+    };
     use bitvec::{bits, order::Msb0, vec::BitVec};
 
-    use crate::rtcp::rtcp_fb_tcc::{PacketReport, PacketStatusSymbol};
+    use crate::rtcp::{
+        rtcp_fb_header::{read_rtcp_fb_header, RtcpFbHeader},
+        rtcp_fb_packet::RtcpFbTlPacket,
+        rtcp_fb_tcc::{PacketReport, PacketStatusSymbol},
+        rtcp_header::{read_rtcp_header, RtcpHeader},
+    };
+
+    use super::{
+        create_packet_status_chunks, read_rtcp_fb_tcc, read_status_vector_chunk, write_rtcp_fb_tcc,
+        write_rtcp_fb_tcc_data, RtcpFbTccPacket, SomePacketStatusChunk, TccReferenceTimeUnwrapper,
+    };
 
-    use super::{read_rtcp_fb_tcc_data, read_status_vector_chunk};
+    fn empty_header() -> RtcpHeader {
+        RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: 0,
+            length_field: 0,
+        }
+    }
+
+    fn fb_header() -> RtcpFbHeader {
+        RtcpFbHeader {
+            sender_ssrc: 1,
+            media_source_ssrc: 2,
+        }
+    }
 
     #[test]
     fn test_sv_chunk_1_bit_symbols() {
@@ -474,13 +1138,14 @@ mod test {
             0x00, 0x50, 0x04, 0x00, 0x00, 0x00, 0x00, 00
         ];
         let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data_buf));
-        let (packet_reports, reference_time, feedback_packet_count) =
-            read_rtcp_fb_tcc_data(&mut cursor).unwrap();
+        let fb_tcc = read_rtcp_fb_tcc(&mut cursor, empty_header(), fb_header()).unwrap();
 
-        assert_eq!(reference_time, u24::new(1683176));
-        assert_eq!(feedback_packet_count, 69);
+        assert_eq!(fb_tcc.reference_time, u24::new(1683176));
+        assert_eq!(fb_tcc.feedback_packet_count, 69);
+        assert_eq!(fb_tcc.base_seq_num(), 385);
+        assert_eq!(fb_tcc.packet_status_count(), 8);
         assert_eq!(
-            packet_reports,
+            fb_tcc.packet_reports().collect::<Vec<_>>(),
             [
                 PacketReport::ReceivedPacketSmallDelta {
                     seq_num: 385,
@@ -516,6 +1181,267 @@ mod test {
                 },
             ]
         );
-        dbg!(packet_reports);
+    }
+
+    #[test]
+    fn test_write_tcc_fb_data_roundtrip() {
+        #[rustfmt::skip]
+        let data_buf = [
+            0x01, 0x81, 0x00, 0x08, 0x19, 0xae, 0xe8, 0x45,
+            0xd9, 0x55, 0x20, 0x01, 0xa8, 0xff, 0xfc, 0x04,
+            0x00, 0x50, 0x04, 0x00, 0x00, 0x00, 0x00, 00
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data_buf));
+        let fb_tcc = read_rtcp_fb_tcc(&mut cursor, empty_header(), fb_header()).unwrap();
+
+        let mut write_cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 24]));
+        write_rtcp_fb_tcc_data(&mut write_cursor, &fb_tcc).unwrap();
+        assert!(write_cursor.remaining_slice().is_empty());
+        assert_eq!(write_cursor.into_inner().as_raw_slice(), data_buf);
+    }
+
+    #[test]
+    fn test_write_tcc_fb_roundtrip() {
+        let mut packet_reports: Vec<PacketReport> = (65530..=65535)
+            .map(|seq_num| PacketReport::ReceivedPacketSmallDelta {
+                seq_num,
+                delta_ticks: 4,
+            })
+            .collect();
+        packet_reports.extend((0..20).map(|seq_num| PacketReport::UnreceivedPacket { seq_num }));
+        packet_reports.push(PacketReport::ReceivedPacketLargeOrNegativeDelta {
+            seq_num: 20,
+            delta_ticks: -100,
+        });
+        let fb_tcc = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: RtcpFbTccPacket::FMT,
+                packet_type: RtcpFbTlPacket::PT,
+                // fb header (8) + fixed fields (8) + chunks (6) + deltas (8), padded to 32
+                length_field: 8,
+            },
+            fb_header(),
+            &packet_reports,
+            u24::new(42),
+            3,
+        )
+        .unwrap();
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 36]));
+        write_rtcp_fb_tcc(&mut cursor, &fb_tcc).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_tcc.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_tcc = read_rtcp_fb_tcc(&mut read_cursor, header, fb_header).unwrap();
+        assert_eq!(
+            read_fb_tcc.packet_reports().collect::<Vec<_>>(),
+            packet_reports
+        );
+        assert_eq!(read_fb_tcc.reference_time, fb_tcc.reference_time);
+        assert_eq!(
+            read_fb_tcc.feedback_packet_count,
+            fb_tcc.feedback_packet_count
+        );
+    }
+
+    #[test]
+    fn test_sync() {
+        let mut fb_tcc = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header(),
+            &[
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 10,
+                    delta_ticks: 1,
+                },
+                PacketReport::UnreceivedPacket { seq_num: 11 },
+                PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                    seq_num: 12,
+                    delta_ticks: -1,
+                },
+            ],
+            u24::new(1),
+            1,
+        )
+        .unwrap();
+        // fb header (8) + fixed fields (8) + 1 chunk (2) + deltas (3), padded to 32
+        assert_eq!(fb_tcc.payload_length_bytes().unwrap(), 24);
+
+        fb_tcc.sync().unwrap();
+        assert_eq!(fb_tcc.header.length_field, 6);
+        assert_eq!(fb_tcc.header.report_count, RtcpFbTccPacket::FMT);
+        assert_eq!(fb_tcc.header.packet_type, RtcpFbTlPacket::PT);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 28]));
+        write_rtcp_fb_tcc(&mut cursor, &fb_tcc).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+    }
+
+    #[test]
+    fn test_create_packet_status_chunks_long_run() {
+        let packet_reports: Vec<PacketReport> = (0..100)
+            .map(|seq_num| PacketReport::ReceivedPacketSmallDelta {
+                seq_num,
+                delta_ticks: 1,
+            })
+            .collect();
+        let chunks = create_packet_status_chunks(&packet_reports).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert!(matches!(
+            chunks[0],
+            SomePacketStatusChunk::RunLengthEncodingChunk(_)
+        ));
+    }
+
+    #[test]
+    fn test_create_packet_status_chunks_non_consecutive() {
+        let packet_reports = [
+            PacketReport::UnreceivedPacket { seq_num: 1 },
+            PacketReport::UnreceivedPacket { seq_num: 3 },
+        ];
+        assert!(create_packet_status_chunks(&packet_reports).is_err());
+    }
+
+    #[test]
+    fn test_iter_arrivals() {
+        let fb_tcc = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: RtcpFbTccPacket::FMT,
+                packet_type: RtcpFbTlPacket::PT,
+                length_field: 0,
+            },
+            fb_header(),
+            &[
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 10,
+                    delta_ticks: 4,
+                },
+                PacketReport::UnreceivedPacket { seq_num: 11 },
+                PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                    seq_num: 12,
+                    delta_ticks: -2,
+                },
+            ],
+            u24::new(2),
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            fb_tcc.iter_arrivals().collect::<Vec<_>>(),
+            vec![
+                (10, Some(Duration::from_micros(129_000))),
+                (11, None),
+                (12, Some(Duration::from_micros(128_500))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reference_time_unwrapper() {
+        let mut unwrapper = TccReferenceTimeUnwrapper::new();
+        assert_eq!(unwrapper.unwrap(u24::new(16_777_214)), 16_777_214);
+        assert_eq!(unwrapper.unwrap(u24::new(16_777_215)), 16_777_215);
+        assert_eq!(unwrapper.unwrap(u24::new(1)), 16_777_217);
+        // Reordered from before the wrap
+        assert_eq!(unwrapper.unwrap(u24::new(16_777_215)), 16_777_215);
+        assert_eq!(unwrapper.unwrap(u24::new(2)), 16_777_218);
+    }
+
+    #[test]
+    fn test_reference_time_unwrapper_reordered_first() {
+        let mut unwrapper = TccReferenceTimeUnwrapper::new();
+        assert_eq!(unwrapper.unwrap(u24::new(0)), 0);
+        assert_eq!(unwrapper.unwrap(u24::new(16_777_215)), -1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = RtcpFbTccPacket::from_packet_reports(
+            empty_header(),
+            fb_header(),
+            &[
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 10,
+                    delta_ticks: 4,
+                },
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 11,
+                    delta_ticks: 8,
+                },
+                PacketReport::UnreceivedPacket { seq_num: 12 },
+            ],
+            u24::new(1),
+            5,
+        )
+        .unwrap();
+        let second = RtcpFbTccPacket::from_packet_reports(
+            empty_header(),
+            fb_header(),
+            &[
+                // Arrived after the first feedback packet was sent, 64ms after its reference time
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 12,
+                    delta_ticks: 0,
+                },
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 13,
+                    delta_ticks: 1,
+                },
+            ],
+            u24::new(2),
+            6,
+        )
+        .unwrap();
+        assert_eq!(second.num_feedback_packets_missed_since(&first), 0);
+
+        let merged = first.merge(&second).unwrap();
+        assert_eq!(merged.base_seq_num(), 10);
+        assert_eq!(merged.reference_time, u24::new(1));
+        assert_eq!(merged.feedback_packet_count, 6);
+        assert_eq!(merged.header.length_field, 6);
+        assert_eq!(
+            merged.iter_arrivals().collect::<Vec<_>>(),
+            first
+                .iter_arrivals()
+                .take(2)
+                .chain(second.iter_arrivals())
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_merge_missed_feedback() {
+        let packet_reports = [PacketReport::UnreceivedPacket { seq_num: 1 }];
+        let first = RtcpFbTccPacket::from_packet_reports(
+            empty_header(),
+            fb_header(),
+            &packet_reports,
+            u24::new(1),
+            255,
+        )
+        .unwrap();
+        let third = RtcpFbTccPacket::from_packet_reports(
+            empty_header(),
+            fb_header(),
+            &packet_reports,
+            u24::new(1),
+            1,
+        )
+        .unwrap();
+        assert_eq!(third.num_feedback_packets_missed_since(&first), 1);
+        assert!(first.merge(&third).is_err());
     }
 }
@@ -0,0 +1,210 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use bit_cursor::nsw_types::{u2, u24, u5};
+
+use crate::rtp::seq_num::SeqNum;
+
+use super::{
+    rtcp_fb_header::RtcpFbHeader,
+    rtcp_fb_tcc::{PacketReport, RtcpFbTccPacket},
+    rtcp_header::RtcpHeader,
+};
+
+/// Builds TCC feedback from the arrival times of received packets.  If all of the packets can't
+/// be described by a single feedback packet (because a receive delta is too large to be
+/// represented, or there are too many packet statuses), multiple feedback packets are created,
+/// each with its own reference time and an incrementing feedback packet count.
+///
+/// Arrival times can be relative to any epoch, as long as it's the same for all packets.
+#[derive(Debug)]
+pub struct RtcpFbTccBuilder {
+    sender_ssrc: u32,
+    media_source_ssrc: u32,
+    feedback_packet_count: u8,
+    received_packets: Vec<(u16, Duration)>,
+}
+
+impl RtcpFbTccBuilder {
+    /// `feedback_packet_count` is the count to use for the first feedback packet that's built.
+    pub fn new(sender_ssrc: u32, media_source_ssrc: u32, feedback_packet_count: u8) -> Self {
+        Self {
+            sender_ssrc,
+            media_source_ssrc,
+            feedback_packet_count,
+            received_packets: Vec::new(),
+        }
+    }
+
+    /// Record that the packet with the given transport-wide sequence number arrived at the given
+    /// time.  Packets can be added in any order, but all sequence numbers must be within 32768 of
+    /// the first one added.
+    pub fn add_received_packet(&mut self, seq_num: u16, arrival_time: Duration) -> &mut Self {
+        self.received_packets.push((seq_num, arrival_time));
+        self
+    }
+
+    /// The feedback packet count which will be used for the next feedback packet that's built
+    pub fn next_feedback_packet_count(&self) -> u8 {
+        self.feedback_packet_count
+    }
+
+    /// Build the feedback packets, which will be synced, for all of the packets added since the
+    /// last call to build.  Returns an empty vec if no packets were added.
+    pub fn build(&mut self) -> Result<Vec<RtcpFbTccPacket>> {
+        let mut received_packets = std::mem::take(&mut self.received_packets);
+        let Some((first_seq_num, _)) = received_packets.first().copied() else {
+            return Ok(Vec::new());
+        };
+        // Sort by sequence number, taking wraparound into account by ordering relative to the
+        // first sequence number that was added
+        received_packets.sort_by_key(|(seq_num, _)| {
+            SeqNum(*seq_num).signed_distance_from(SeqNum(first_seq_num))
+        });
+        received_packets.dedup_by_key(|(seq_num, _)| *seq_num);
+
+        let mut packets = Vec::new();
+        let mut remaining = &received_packets[..];
+        while !remaining.is_empty() {
+            let (packet, num_consumed) = self
+                .build_packet(remaining)
+                .with_context(|| format!("feedback packet {}", packets.len()))?;
+            packets.push(packet);
+            remaining = &remaining[num_consumed..];
+        }
+
+        Ok(packets)
+    }
+
+    /// Build a single feedback packet from the start of the given (sorted) received packets,
+    /// returning it and how many of the received packets it covers.
+    fn build_packet(
+        &mut self,
+        received_packets: &[(u16, Duration)],
+    ) -> Result<(RtcpFbTccPacket, usize)> {
+        let (base_seq_num, first_arrival_time) = received_packets[0];
+        let reference_time_ticks =
+            first_arrival_time.as_micros() / RtcpFbTccPacket::REFERENCE_TIME_RESOLUTION.as_micros();
+        let reference_time = u24::new((reference_time_ticks % (1 << 24)) as u32);
+        // Deltas are relative to the previous received packet's arrival time as it will be
+        // reconstructed by the receiver (i.e. after being rounded to the delta resolution), so
+        // rounding errors don't accumulate.
+        let mut prev_arrival_time_us =
+            (reference_time_ticks * RtcpFbTccPacket::REFERENCE_TIME_RESOLUTION.as_micros()) as i128;
+        let delta_tick_us = RtcpFbTccPacket::DELTA_TICK_RESOLUTION.as_micros() as i128;
+
+        let mut packet_reports: Vec<PacketReport> = Vec::new();
+        let mut num_consumed = 0;
+        for (seq_num, arrival_time) in received_packets {
+            // Fill in any packets which weren't received before this one
+            let num_missing = SeqNum(*seq_num)
+                .distance_from(SeqNum(base_seq_num))
+                .wrapping_sub(packet_reports.len() as u16) as usize;
+            if packet_reports.len() + num_missing + 1 > u16::MAX as usize {
+                break;
+            }
+            let delta_us = arrival_time.as_micros() as i128 - prev_arrival_time_us;
+            let delta_ticks = (delta_us as f64 / delta_tick_us as f64).round() as i128;
+            let Some(report) = i64::try_from(delta_ticks)
+                .ok()
+                .and_then(|delta_ticks| PacketReport::received(*seq_num, delta_ticks))
+            else {
+                // The delta can't be represented, this packet will have to go in the next
+                // feedback packet
+                break;
+            };
+            for _ in 0..num_missing {
+                let missing_seq_num = base_seq_num.wrapping_add(packet_reports.len() as u16);
+                packet_reports.push(PacketReport::UnreceivedPacket {
+                    seq_num: missing_seq_num,
+                });
+            }
+            packet_reports.push(report);
+            prev_arrival_time_us += delta_ticks * delta_tick_us;
+            num_consumed += 1;
+        }
+        if num_consumed == 0 {
+            bail!("Unable to fit packet {base_seq_num} in a feedback packet");
+        }
+
+        let mut packet = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            RtcpFbHeader {
+                sender_ssrc: self.sender_ssrc,
+                media_source_ssrc: self.media_source_ssrc,
+            },
+            &packet_reports,
+            reference_time,
+            self.feedback_packet_count,
+        )?;
+        packet.sync().context("sync")?;
+        self.feedback_packet_count = self.feedback_packet_count.wrapping_add(1);
+
+        Ok((packet, num_consumed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_single_packet() {
+        let mut builder = RtcpFbTccBuilder::new(1, 2, 5);
+        builder
+            .add_received_packet(10, Duration::from_millis(130))
+            .add_received_packet(13, Duration::from_millis(131))
+            .add_received_packet(12, Duration::from_millis(200));
+        let packets = builder.build().unwrap();
+        assert_eq!(packets.len(), 1);
+        let packet = &packets[0];
+        assert_eq!(packet.feedback_packet_count, 5);
+        // 130ms / 64ms
+        assert_eq!(packet.reference_time, u24::new(2));
+        assert_eq!(
+            packet.packet_reports().collect::<Vec<_>>(),
+            vec![
+                // 130 - 128 = 2ms
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 10,
+                    delta_ticks: 8
+                },
+                PacketReport::UnreceivedPacket { seq_num: 11 },
+                // 200 - 130 = 70ms
+                PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                    seq_num: 12,
+                    delta_ticks: 280
+                },
+                // 131 - 200 = -69ms
+                PacketReport::ReceivedPacketLargeOrNegativeDelta {
+                    seq_num: 13,
+                    delta_ticks: -276
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_splits_on_large_delta() {
+        let mut builder = RtcpFbTccBuilder::new(1, 2, 255);
+        builder
+            .add_received_packet(65535, Duration::from_millis(0))
+            .add_received_packet(0, Duration::from_millis(1))
+            // Too far from the previous packet for a 16 bit delta
+            .add_received_packet(1, Duration::from_secs(10));
+        let packets = builder.build().unwrap();
+        assert_eq!(packets.len(), 2);
+        assert_eq!(packets[0].packet_status_count(), 2);
+        assert_eq!(packets[0].feedback_packet_count, 255);
+        assert_eq!(packets[1].base_seq_num(), 1);
+        assert_eq!(packets[1].feedback_packet_count, 0);
+        assert_eq!(builder.next_feedback_packet_count(), 1);
+        assert!(builder.build().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use super::rtcp_fb_tcc::RtcpFbTccPacket;
+
+#[derive(Debug)]
+struct SentPacket {
+    send_time: Instant,
+    size_bytes: usize,
+}
+
+/// The outcome of a single sent packet, as described by a TCC feedback packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TccPacketResult {
+    pub seq_num: u16,
+    pub send_time: Instant,
+    pub size_bytes: usize,
+    /// The time the packet arrived at the receiver, relative to the receiver's epoch (see
+    /// [`RtcpFbTccPacket::iter_arrivals`]), or None if it was lost.
+    pub receive_time: Option<Duration>,
+}
+
+impl TccPacketResult {
+    pub fn is_lost(&self) -> bool {
+        self.receive_time.is_none()
+    }
+}
+
+/// Records the packets which have been sent with a transport-wide sequence number so they can be
+/// matched up with the TCC feedback which reports on them.  Only the most recent `max_packets`
+/// packets are kept.
+#[derive(Debug)]
+pub struct TccSendHistory {
+    max_packets: usize,
+    packets: HashMap<u16, SentPacket>,
+    /// The sequence numbers in `packets`, in the order they were sent
+    send_order: VecDeque<u16>,
+}
+
+impl TccSendHistory {
+    /// `max_packets` should be well below 65536, so that a sequence number is forgotten before it
+    /// wraps around and is used again.
+    pub fn new(max_packets: usize) -> Self {
+        Self {
+            max_packets,
+            packets: HashMap::new(),
+            send_order: VecDeque::new(),
+        }
+    }
+
+    /// Record that the packet with the given transport-wide sequence number was sent.  If a packet
+    /// with the same sequence number is already in the history, it's replaced.
+    pub fn on_packet_sent(&mut self, seq_num: u16, send_time: Instant, size_bytes: usize) {
+        if self
+            .packets
+            .insert(
+                seq_num,
+                SentPacket {
+                    send_time,
+                    size_bytes,
+                },
+            )
+            .is_some()
+        {
+            self.send_order.retain(|s| *s != seq_num);
+        }
+        self.send_order.push_back(seq_num);
+        while self.send_order.len() > self.max_packets {
+            if let Some(oldest) = self.send_order.pop_front() {
+                self.packets.remove(&oldest);
+            }
+        }
+    }
+
+    /// How many sent packets are currently in the history
+    pub fn len(&self) -> usize {
+        self.packets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packets.is_empty()
+    }
+
+    /// Match the packets the given feedback reports on with the sent packets in the history,
+    /// returning a result for each one in sequence number order.  Packets the feedback reports on
+    /// which aren't in the history are skipped.  Packets stay in the history, so a packet which is
+    /// reported as lost can be reported as received by later feedback.
+    pub fn process_feedback(&self, fb_tcc: &RtcpFbTccPacket) -> Vec<TccPacketResult> {
+        fb_tcc
+            .iter_arrivals()
+            .filter_map(|(seq_num, receive_time)| {
+                self.packets
+                    .get(&seq_num)
+                    .map(|sent_packet| TccPacketResult {
+                        seq_num,
+                        send_time: sent_packet.send_time,
+                        size_bytes: sent_packet.size_bytes,
+                        receive_time,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::nsw_types::{u2, u24, u5};
+
+    use crate::rtcp::{
+        rtcp_fb_header::RtcpFbHeader, rtcp_fb_tcc::PacketReport, rtcp_header::RtcpHeader,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_process_feedback() {
+        let start = Instant::now();
+        let mut history = TccSendHistory::new(100);
+        history.on_packet_sent(65535, start, 1000);
+        history.on_packet_sent(0, start + Duration::from_millis(5), 500);
+
+        let fb_tcc = RtcpFbTccPacket::from_packet_reports(
+            RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            RtcpFbHeader {
+                sender_ssrc: 1,
+                media_source_ssrc: 2,
+            },
+            &[
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 65535,
+                    delta_ticks: 4,
+                },
+                PacketReport::UnreceivedPacket { seq_num: 0 },
+                // Never sent
+                PacketReport::ReceivedPacketSmallDelta {
+                    seq_num: 1,
+                    delta_ticks: 4,
+                },
+            ],
+            u24::new(1),
+            0,
+        )
+        .unwrap();
+
+        let results = history.process_feedback(&fb_tcc);
+        assert_eq!(
+            results,
+            vec![
+                TccPacketResult {
+                    seq_num: 65535,
+                    send_time: start,
+                    size_bytes: 1000,
+                    receive_time: Some(Duration::from_millis(65)),
+                },
+                TccPacketResult {
+                    seq_num: 0,
+                    send_time: start + Duration::from_millis(5),
+                    size_bytes: 500,
+                    receive_time: None,
+                },
+            ]
+        );
+        assert!(results[1].is_lost());
+    }
+
+    #[test]
+    fn test_max_packets() {
+        let start = Instant::now();
+        let mut history = TccSendHistory::new(2);
+        history.on_packet_sent(1, start, 100);
+        history.on_packet_sent(2, start, 100);
+        history.on_packet_sent(1, start, 100);
+        history.on_packet_sent(3, start, 100);
+        assert_eq!(history.len(), 2);
+        assert!(history.packets.contains_key(&1));
+        assert!(!history.packets.contains_key(&2));
+        assert!(history.packets.contains_key(&3));
+    }
+}
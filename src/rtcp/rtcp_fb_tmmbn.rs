@@ -0,0 +1,109 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::nsw_types::u5;
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbTlPacket,
+    rtcp_fb_tmmbr::{read_tmmb_fcis, tmmb_payload_length_bytes, write_tmmb_fci, TmmbFci},
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.2
+///
+/// TMMBN uses the same FCI as TMMBR.  The FCI entries are the current bounding set, and a TMMBN
+/// with no FCI entries indicates an empty bounding set.  As with TMMBR, the "SSRC of media
+/// source" is not used and SHALL be set to 0.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbTmmbnPacket {
+    pub header: RtcpHeader,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = super::rtcp_fb_header::arbitrary_without_media_source)
+    )]
+    pub fb_header: RtcpFbHeader,
+    pub fcis: Vec<TmmbFci>,
+}
+
+impl RtcpFbTmmbnPacket {
+    pub const FMT: u5 = u5::new(4);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        tmmb_payload_length_bytes(&self.fcis)
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbTlPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbTmmbnPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES + self.fcis.len() * TmmbFci::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_tmmbn<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+) -> Result<RtcpFbTmmbnPacket> {
+    if fb_header.media_source_ssrc != 0 {
+        bail!(RtpParseError::invalid_field(
+            "media source SSRC",
+            0,
+            fb_header.media_source_ssrc
+        ));
+    }
+    let fcis = read_tmmb_fcis(buf)?;
+
+    Ok(RtcpFbTmmbnPacket {
+        header,
+        fb_header,
+        fcis,
+    })
+}
+
+pub fn write_rtcp_fb_tmmbn<B: PacketBufferMut>(
+    buf: &mut B,
+    fb_tmmbn: &RtcpFbTmmbnPacket,
+) -> Result<()> {
+    write_rtcp_header(buf, &fb_tmmbn.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_tmmbn.fb_header).context("fb header")?;
+    for (i, fci) in fb_tmmbn.fcis.iter().enumerate() {
+        write_tmmb_fci(buf, fci).with_context(|| format!("fci {i}"))?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpFbTmmbnPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree(
+            "RTCP Temporary Maximum Media Stream Bit Rate Notification",
+            |tree| {
+                self.header.pretty_print(tree)?;
+                self.fb_header.pretty_print(tree)?;
+                for (i, fci) in self.fcis.iter().enumerate() {
+                    tree.subtree(format_args!("FCI {}", i + 1), |tree| fci.pretty_print(tree))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
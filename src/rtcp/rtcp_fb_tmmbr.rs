@@ -0,0 +1,316 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u17, u5, u6, u9},
+};
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_fb_header::{write_rtcp_fb_header, RtcpFbHeader},
+    rtcp_fb_packet::RtcpFbTlPacket,
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.1
+///
+/// From https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.1.2:
+/// Within the common packet header for feedback messages (as defined in
+/// section 6.1 of [RFC4585]), the "SSRC of packet sender" field
+/// indicates the source of the request, and the "SSRC of media source"
+/// is not used and SHALL be set to 0.  The SSRCs of the media senders to
+/// which the TMMBR applies are in the corresponding FCI entries.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpFbTmmbrPacket {
+    pub header: RtcpHeader,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = super::rtcp_fb_header::arbitrary_without_media_source)
+    )]
+    pub fb_header: RtcpFbHeader,
+    pub fcis: Vec<TmmbFci>,
+}
+
+impl RtcpFbTmmbrPacket {
+    pub const FMT: u5 = u5::new(3);
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        tmmb_payload_length_bytes(&self.fcis)
+    }
+
+    /// Update the header of this packet (packet type, fmt and length) to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = RtcpFbTlPacket::PT;
+        self.header.report_count = Self::FMT;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpFbTmmbrPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES + RtcpFbHeader::SIZE_BYTES + self.fcis.len() * TmmbFci::SIZE_BYTES
+    }
+}
+
+pub fn read_rtcp_fb_tmmbr<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    fb_header: RtcpFbHeader,
+) -> Result<RtcpFbTmmbrPacket> {
+    if fb_header.media_source_ssrc != 0 {
+        bail!(RtpParseError::invalid_field(
+            "media source SSRC",
+            0,
+            fb_header.media_source_ssrc
+        ));
+    }
+    let fcis = read_tmmb_fcis(buf)?;
+
+    Ok(RtcpFbTmmbrPacket {
+        header,
+        fb_header,
+        fcis,
+    })
+}
+
+pub fn write_rtcp_fb_tmmbr<B: PacketBufferMut>(
+    buf: &mut B,
+    fb_tmmbr: &RtcpFbTmmbrPacket,
+) -> Result<()> {
+    write_rtcp_header(buf, &fb_tmmbr.header).context("rtcp header")?;
+    write_rtcp_fb_header(buf, &fb_tmmbr.fb_header).context("fb header")?;
+    for (i, fci) in fb_tmmbr.fcis.iter().enumerate() {
+        write_tmmb_fci(buf, fci).with_context(|| format!("fci {i}"))?;
+    }
+
+    Ok(())
+}
+
+/// The FCI used by both TMMBR and TMMBN.
+///
+/// https://datatracker.ietf.org/doc/html/rfc5104#section-4.2.1.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              SSRC                             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | MxTBR Exp |  MxTBR Mantissa                 |Measured Overhead|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The maximum total media bitrate (MxTBR) is mantissa * 2^exp bits per second.  The measured
+/// overhead is the per-packet overhead in bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct TmmbFci {
+    pub ssrc: u32,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u6"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u6))]
+    pub mxtbr_exp: u6,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u17"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u17))]
+    pub mxtbr_mantissa: u17,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u9"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u9))]
+    pub measured_overhead: u9,
+}
+
+impl TmmbFci {
+    pub const SIZE_BYTES: usize = 8;
+
+    /// Create an FCI for the given ssrc, bitrate (in bits per second) and per-packet overhead.
+    /// Precision may be lost if the bitrate doesn't fit in the 17 bit mantissa.
+    pub fn new(ssrc: u32, bitrate_bps: u64, measured_overhead: u9) -> Self {
+        let (mxtbr_exp, mxtbr_mantissa) = bitrate_to_exp_mantissa(bitrate_bps);
+        Self {
+            ssrc,
+            mxtbr_exp,
+            mxtbr_mantissa,
+            measured_overhead,
+        }
+    }
+
+    /// The maximum total media bitrate, in bits per second
+    pub fn bitrate_bps(&self) -> u64 {
+        let mantissa: u32 = self.mxtbr_mantissa.into();
+        let exp: u8 = self.mxtbr_exp.into();
+        (mantissa as u64)
+            .checked_shl(exp as u32)
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Set the maximum total media bitrate, in bits per second.  Precision may be lost if the
+    /// bitrate doesn't fit in the 17 bit mantissa.
+    pub fn set_bitrate_bps(&mut self, bitrate_bps: u64) {
+        (self.mxtbr_exp, self.mxtbr_mantissa) = bitrate_to_exp_mantissa(bitrate_bps);
+    }
+}
+
+impl LengthBytes for TmmbFci {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+/// Convert the given bitrate to the smallest exponent and corresponding mantissa which can
+/// represent it.
+fn bitrate_to_exp_mantissa(bitrate_bps: u64) -> (u6, u17) {
+    let max_mantissa: u32 = u17::MAX.into();
+    let mut exp = 0u8;
+    while (bitrate_bps >> exp) > max_mantissa as u64 {
+        exp += 1;
+    }
+    (u6::new(exp), u17::new((bitrate_bps >> exp) as u32))
+}
+
+pub fn read_tmmb_fci<B: PacketBuffer>(buf: &mut B) -> Result<TmmbFci> {
+    Ok(TmmbFci {
+        ssrc: buf.read_u32::<NetworkOrder>().context("ssrc")?,
+        mxtbr_exp: buf.read_u6().context("mxtbr exp")?,
+        mxtbr_mantissa: buf.read_u17::<NetworkOrder>().context("mxtbr mantissa")?,
+        measured_overhead: buf.read_u9::<NetworkOrder>().context("measured overhead")?,
+    })
+}
+
+pub fn write_tmmb_fci<B: PacketBufferMut>(buf: &mut B, fci: &TmmbFci) -> Result<()> {
+    buf.write_u32::<NetworkOrder>(fci.ssrc).context("ssrc")?;
+    buf.write_u6(fci.mxtbr_exp).context("mxtbr exp")?;
+    buf.write_u17::<NetworkOrder>(fci.mxtbr_mantissa)
+        .context("mxtbr mantissa")?;
+    buf.write_u9::<NetworkOrder>(fci.measured_overhead)
+        .context("measured overhead")?;
+
+    Ok(())
+}
+
+pub(crate) fn read_tmmb_fcis<B: PacketBuffer>(buf: &mut B) -> Result<Vec<TmmbFci>> {
+    let mut fcis = Vec::new();
+    while buf.bytes_remaining() >= TmmbFci::SIZE_BYTES {
+        let fci = read_tmmb_fci(buf).with_context(|| format!("fci {}", fcis.len()))?;
+        fcis.push(fci);
+    }
+
+    Ok(fcis)
+}
+
+pub(crate) fn tmmb_payload_length_bytes(fcis: &[TmmbFci]) -> Result<u16> {
+    let length_bytes = RtcpFbHeader::SIZE_BYTES + fcis.len() * TmmbFci::SIZE_BYTES;
+    length_bytes
+        .try_into()
+        .map_err(|_| anyhow!("TMMB payload length {length_bytes} bytes is too large"))
+}
+
+impl PrettyPrint for RtcpFbTmmbrPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree(
+            "RTCP Temporary Maximum Media Stream Bit Rate Request",
+            |tree| {
+                self.header.pretty_print(tree)?;
+                self.fb_header.pretty_print(tree)?;
+                for (i, fci) in self.fcis.iter().enumerate() {
+                    tree.subtree(format_args!("FCI {}", i + 1), |tree| fci.pretty_print(tree))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+impl PrettyPrint for TmmbFci {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("SSRC", Ssrc(self.ssrc))?;
+        tree.field(
+            "Maximum total media bit rate",
+            format_args!("{} bps", self.bitrate_bps()),
+        )?;
+        tree.field("Measured overhead", u16::from(self.measured_overhead))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::u2};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::{rtcp_fb_header::read_rtcp_fb_header, rtcp_header::read_rtcp_header};
+
+    use super::*;
+
+    #[test]
+    fn test_read_tmmb_fci() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // exp 3, mantissa 125000, overhead 40
+            0x0F, 0xD0, 0x90, 0x28,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let fci = read_tmmb_fci(&mut cursor).unwrap();
+        assert_eq!(fci.ssrc, 1);
+        assert_eq!(fci.mxtbr_exp, u6::new(3));
+        assert_eq!(fci.mxtbr_mantissa, u17::new(125_000));
+        assert_eq!(fci.measured_overhead, u9::new(40));
+        assert_eq!(fci.bitrate_bps(), 1_000_000);
+    }
+
+    #[test]
+    fn test_bitrate_conversion() {
+        let mut fci = TmmbFci::new(1, 100_000, u9::new(0));
+        assert_eq!(fci.mxtbr_exp, u6::new(0));
+        assert_eq!(fci.bitrate_bps(), 100_000);
+
+        fci.set_bitrate_bps(2_000_000);
+        assert_eq!(fci.mxtbr_exp, u6::new(4));
+        assert_eq!(fci.bitrate_bps(), 2_000_000);
+    }
+
+    #[test]
+    fn test_write_rtcp_fb_tmmbr_roundtrip() {
+        let mut fb_tmmbr = RtcpFbTmmbrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            fb_header: RtcpFbHeader {
+                sender_ssrc: 42,
+                media_source_ssrc: 0,
+            },
+            fcis: vec![
+                TmmbFci::new(1, 500_000, u9::new(40)),
+                TmmbFci::new(2, 3_000_000, u9::new(60)),
+            ],
+        };
+        fb_tmmbr.sync().unwrap();
+        assert_eq!(fb_tmmbr.header.length_field, 6);
+        assert_eq!(fb_tmmbr.header.report_count, RtcpFbTmmbrPacket::FMT);
+        assert_eq!(fb_tmmbr.header.packet_type, RtcpFbTlPacket::PT);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 28]));
+        write_rtcp_fb_tmmbr(&mut cursor, &fb_tmmbr).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        assert_eq!(header, fb_tmmbr.header);
+        let fb_header = read_rtcp_fb_header(&mut read_cursor).unwrap();
+        let read_fb_tmmbr = read_rtcp_fb_tmmbr(&mut read_cursor, header, fb_header).unwrap();
+        assert_eq!(read_fb_tmmbr.fcis, fb_tmmbr.fcis);
+    }
+}
@@ -1,11 +1,17 @@
 use std::fmt::{Debug, LowerHex};
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{bail, Context, Result};
 use bit_cursor::{
     bit_read::BitRead, bit_read_exts::BitReadExts, bit_write::BitWrite,
     bit_write_exts::BitWriteExts, byte_order::NetworkOrder, nsw_types::*,
 };
 
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes,
+};
+
 /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.1
 ///  0                   1                   2                   3
 ///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
@@ -20,9 +26,21 @@ use bit_cursor::{
 ///   scanning a compound RTCP packet, while counting 32-bit words
 ///   avoids a validity check for a multiple of 4.)
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpHeader {
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u2"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(value = u2::new(2)))]
     pub version: u2,
+    /// Whether the packet ends with padding.  Parsed packets don't keep their padding, so this is
+    /// cleared (and the padding is removed from the length field) when a packet is parsed; use
+    /// [`write_padded_rtcp_packet`] to write a padded packet.
+    ///
+    /// [`write_padded_rtcp_packet`]: super::rtcp_packet::write_padded_rtcp_packet
+    #[cfg_attr(feature = "arbitrary", arbitrary(value = false))]
     pub has_padding: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u5"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u5))]
     pub report_count: u5,
     pub packet_type: u8,
     pub length_field: u16,
@@ -41,10 +59,31 @@ impl RtcpHeader {
 
     /// The length of this RTCP packet's payload (i.e. excluding the header) in bytes
     pub fn payload_length_bytes(&self) -> Result<u16> {
-        self.length_field
-            .checked_mul(4)
-            .ok_or(anyhow!("Invalid length field"))
+        self.length_field.checked_mul(4).ok_or_else(|| {
+            RtpParseError::invalid_field(
+                "length field",
+                format!("at most {}", u16::MAX / 4),
+                self.length_field,
+            )
+            .into()
+        })
+    }
+}
+
+impl LengthBytes for RtcpHeader {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+/// Convert a number of items (report blocks, SDES chunks, BYE SSRCs) into a value for the report
+/// count field of the header, failing if there are too many to be represented.
+pub(crate) fn report_count_from_len(len: usize) -> Result<u5> {
+    if len > u5::MAX.into() {
+        bail!("Count {len} is too large to fit in the report count field");
     }
+
+    Ok(u5::new(len as u8))
 }
 
 pub fn read_rtcp_header<R: BitRead + Debug + LowerHex>(buf: &mut R) -> Result<RtcpHeader> {
@@ -68,6 +107,23 @@ pub fn write_rtcp_header<W: BitWrite>(buf: &mut W, header: &RtcpHeader) -> Resul
     Ok(())
 }
 
+impl PrettyPrint for RtcpHeader {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("Version", u8::from(self.version))?;
+        tree.field("Padding", self.has_padding)?;
+        tree.field("Count", u8::from(self.report_count))?;
+        tree.field("Packet type", self.packet_type)?;
+        tree.field(
+            "Length",
+            format_args!(
+                "{} ({} bytes)",
+                self.length_field,
+                (self.length_field as usize + 1) * 4
+            ),
+        )
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::unusual_byte_groupings, clippy::bool_assert_comparison)]
 mod tests {
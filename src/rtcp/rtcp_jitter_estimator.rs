@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+/// Calculates the interarrival jitter of a received stream, as reported in the jitter field of
+/// reception report blocks (https://datatracker.ietf.org/doc/html/rfc3550#appendix-A.8).
+///
+/// The jitter is a smoothed estimate of how much the spacing between packets' arrival times
+/// differs from the spacing between their RTP timestamps.  Packets should be fed in the order
+/// they arrive (not sequence number order), and retransmissions shouldn't be fed at all since
+/// their arrival times don't reflect the network jitter.
+#[derive(Debug, Default)]
+pub struct JitterEstimator {
+    /// The arrival time and RTP timestamp of the previous packet
+    last: Option<(Instant, u32)>,
+    /// The jitter estimate, in RTP timestamp units
+    jitter: f64,
+}
+
+impl JitterEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Update the estimate with a packet with the given RTP timestamp which arrived at the given
+    /// time, for a stream with the given clock rate (in Hz).
+    pub fn on_packet(&mut self, rtp_timestamp: u32, arrival_time: Instant, clock_rate: u32) {
+        if let Some((last_arrival_time, last_rtp_timestamp)) = self.last {
+            let arrival_delta_secs = match arrival_time.checked_duration_since(last_arrival_time) {
+                Some(delta) => delta.as_secs_f64(),
+                None => -last_arrival_time.duration_since(arrival_time).as_secs_f64(),
+            };
+            let arrival_delta = arrival_delta_secs * clock_rate as f64;
+            let rtp_delta = rtp_timestamp.wrapping_sub(last_rtp_timestamp) as i32 as f64;
+            // The difference in the packets' relative transit times
+            let d = (arrival_delta - rtp_delta).abs();
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.last = Some((arrival_time, rtp_timestamp));
+    }
+
+    /// The current jitter estimate in RTP timestamp units, as written in report blocks
+    pub fn jitter(&self) -> u32 {
+        self.jitter.round() as u32
+    }
+
+    /// The current jitter estimate as a duration, for a stream with the given clock rate (in Hz)
+    pub fn jitter_duration(&self, clock_rate: u32) -> Duration {
+        Duration::from_secs_f64(self.jitter / clock_rate as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_jitter() {
+        let mut estimator = JitterEstimator::new();
+        let start = Instant::now();
+        for i in 0..10u32 {
+            estimator.on_packet(
+                (u32::MAX - 1000).wrapping_add(i * 960),
+                start + Duration::from_millis(20 * i as u64),
+                48_000,
+            );
+        }
+        assert_eq!(estimator.jitter(), 0);
+    }
+
+    #[test]
+    fn test_jitter() {
+        let mut estimator = JitterEstimator::new();
+        let start = Instant::now();
+        estimator.on_packet(0, start, 90_000);
+        // 30ms after the first packet, but arriving 10ms late: D = 900 timestamp units
+        estimator.on_packet(2700, start + Duration::from_millis(40), 90_000);
+        assert_eq!(estimator.jitter(), 56);
+        // On time again relative to the first packet, which is 10ms early relative to the second
+        estimator.on_packet(5400, start + Duration::from_millis(60), 90_000);
+        assert_eq!(estimator.jitter(), 109);
+        assert_eq!(estimator.jitter_duration(90_000).as_micros(), 1210);
+        // Arriving out of order
+        estimator.on_packet(10800, start + Duration::from_millis(90), 90_000);
+        estimator.on_packet(8100, start + Duration::from_millis(70), 90_000);
+        assert!(estimator.jitter() > 109);
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::{Duration, SystemTime},
+};
+
+use crate::ntp::NtpTimestamp;
+
+use super::rtcp_sr::RtcpSrPacket;
+
+/// How many of each stream's most recent sender reports are used to estimate its clock rate
+const MAX_SENDER_REPORTS: usize = 8;
+/// If the clock rate estimated from a stream's sender reports is further than this (as a
+/// fraction) from its configured clock rate, the sender's RTP timestamps are assumed to have
+/// jumped and the older reports are discarded.
+const MAX_CLOCK_RATE_DEVIATION: f64 = 0.05;
+
+#[derive(Debug, Default)]
+struct StreamClock {
+    clock_rate: Option<u32>,
+    /// The NTP and RTP timestamps of the most recent sender reports, oldest first
+    sender_reports: VecDeque<(NtpTimestamp, u32)>,
+}
+
+impl StreamClock {
+    /// The clock rate measured across the stored sender reports
+    fn measured_clock_rate(&self) -> Option<f64> {
+        let (first_ntp, _) = self.sender_reports.front()?;
+        let (last_ntp, _) = self.sender_reports.back()?;
+        let elapsed = last_ntp
+            .to_duration()
+            .checked_sub(first_ntp.to_duration())?;
+        if elapsed.is_zero() {
+            return None;
+        }
+        // Consecutive reports are unwrapped relative to each other, so the total can exceed the
+        // 32 bit range
+        let rtp_elapsed: i64 = self
+            .sender_reports
+            .iter()
+            .zip(self.sender_reports.iter().skip(1))
+            .map(|((_, prev), (_, next))| next.wrapping_sub(*prev) as i32 as i64)
+            .sum();
+        if rtp_elapsed <= 0 {
+            return None;
+        }
+
+        Some(rtp_elapsed as f64 / elapsed.as_secs_f64())
+    }
+
+    fn clock_rate(&self) -> Option<f64> {
+        match (self.measured_clock_rate(), self.clock_rate) {
+            (Some(measured), _) => Some(measured),
+            (None, Some(clock_rate)) => Some(clock_rate as f64),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Maps the RTP timestamps of received streams to the sender's wallclock (NTP) time, using the
+/// NTP/RTP timestamp pairs in their sender reports.  This is what's needed to synchronize
+/// streams from the same sender (e.g. lip sync) or to measure end-to-end latency.
+///
+/// A stream's clock rate is measured from its recent sender reports, so drift between the
+/// sender's media and NTP clocks is accounted for.  Until there are enough reports to measure
+/// it, the configured clock rate (see [`MediaClockMapper::set_clock_rate`]) is used.
+#[derive(Debug, Default)]
+pub struct MediaClockMapper {
+    streams: BTreeMap<u32, StreamClock>,
+}
+
+impl MediaClockMapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the nominal clock rate (in Hz) of the given stream, as negotiated in its SDP
+    pub fn set_clock_rate(&mut self, ssrc: u32, clock_rate: u32) {
+        self.streams.entry(ssrc).or_default().clock_rate = Some(clock_rate);
+    }
+
+    /// Record the NTP/RTP timestamp pair from a sender report for the given stream.  Reports
+    /// which aren't newer than the last one are ignored.
+    pub fn on_sender_report(&mut self, ssrc: u32, ntp_timestamp: NtpTimestamp, rtp_timestamp: u32) {
+        let stream = self.streams.entry(ssrc).or_default();
+        if stream
+            .sender_reports
+            .back()
+            .is_some_and(|(last_ntp, _)| ntp_timestamp <= *last_ntp)
+        {
+            return;
+        }
+        if stream.sender_reports.len() == MAX_SENDER_REPORTS {
+            stream.sender_reports.pop_front();
+        }
+        stream
+            .sender_reports
+            .push_back((ntp_timestamp, rtp_timestamp));
+        if let (Some(measured), Some(clock_rate)) =
+            (stream.measured_clock_rate(), stream.clock_rate)
+        {
+            if (measured / clock_rate as f64 - 1.0).abs() > MAX_CLOCK_RATE_DEVIATION {
+                stream
+                    .sender_reports
+                    .drain(..stream.sender_reports.len() - 1);
+            }
+        }
+    }
+
+    /// Record the sender info of the given sender report
+    pub fn on_sr_packet(&mut self, sr: &RtcpSrPacket) {
+        self.on_sender_report(
+            sr.sender_ssrc,
+            sr.sender_info.ntp_timestamp(),
+            sr.sender_info.rtp_timestamp,
+        );
+    }
+
+    /// Stop tracking the given stream
+    pub fn remove_stream(&mut self, ssrc: u32) {
+        self.streams.remove(&ssrc);
+    }
+
+    /// The clock rate (in Hz) of the given stream: measured from its sender reports if possible,
+    /// otherwise its configured clock rate
+    pub fn clock_rate(&self, ssrc: u32) -> Option<f64> {
+        self.streams.get(&ssrc)?.clock_rate()
+    }
+
+    /// The sender's NTP time corresponding to the given RTP timestamp of the given stream, or None
+    /// if the stream hasn't had a sender report or its clock rate isn't known yet.  RTP timestamps
+    /// are interpreted as the closest value (before or after) to the last sender report's.
+    pub fn rtp_to_ntp(&self, ssrc: u32, rtp_timestamp: u32) -> Option<NtpTimestamp> {
+        let stream = self.streams.get(&ssrc)?;
+        let clock_rate = stream.clock_rate()?;
+        let (sr_ntp, sr_rtp) = stream.sender_reports.back()?;
+        let rtp_offset = rtp_timestamp.wrapping_sub(*sr_rtp) as i32 as f64;
+        // Only the offset is computed as a float, since NTP times in nanoseconds are too large to
+        // be represented precisely
+        let offset_nanos = (rtp_offset * 1e9 / clock_rate).round() as i128;
+        let nanos = u64::try_from(sr_ntp.to_duration().as_nanos() as i128 + offset_nanos).ok()?;
+
+        Some(NtpTimestamp::from_duration(Duration::from_nanos(nanos)))
+    }
+
+    /// Like [`MediaClockMapper::rtp_to_ntp`], but as a wall clock time
+    pub fn rtp_to_system_time(&self, ssrc: u32, rtp_timestamp: u32) -> Option<SystemTime> {
+        self.rtp_to_ntp(ssrc, rtp_timestamp)
+            .map(|ntp_timestamp| ntp_timestamp.to_system_time())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ntp_millis(millis: u64) -> NtpTimestamp {
+        NtpTimestamp::from_duration(Duration::from_millis(millis))
+    }
+
+    #[test]
+    fn test_configured_clock_rate() {
+        let mut mapper = MediaClockMapper::new();
+        assert!(mapper.rtp_to_ntp(1, 0).is_none());
+        mapper.set_clock_rate(1, 90_000);
+        mapper.on_sender_report(1, ntp_millis(10_000), 4_294_000_000);
+        assert_eq!(mapper.clock_rate(1), Some(90_000.0));
+        assert_eq!(
+            mapper.rtp_to_ntp(1, 4_294_090_000).unwrap(),
+            ntp_millis(11_000)
+        );
+        assert_eq!(
+            mapper.rtp_to_ntp(1, 4_293_955_000).unwrap(),
+            ntp_millis(9_500)
+        );
+        // Across the RTP timestamp wrap
+        let wrapped = 4_294_000_000u32.wrapping_add(1_800_000);
+        assert_eq!(mapper.rtp_to_ntp(1, wrapped).unwrap(), ntp_millis(30_000));
+    }
+
+    #[test]
+    fn test_drift() {
+        let mut mapper = MediaClockMapper::new();
+        mapper.set_clock_rate(1, 48_000);
+        // The sender's media clock runs 0.1% fast
+        mapper.on_sender_report(1, ntp_millis(0), 1000);
+        mapper.on_sender_report(1, ntp_millis(10_000), 1000 + 480_480);
+        assert!((mapper.clock_rate(1).unwrap() - 48_048.0).abs() < 0.01);
+        let ntp = mapper.rtp_to_ntp(1, 1000 + 480_480 + 48_048).unwrap();
+        assert!(
+            ntp.to_duration().abs_diff(Duration::from_millis(11_000)) < Duration::from_micros(1)
+        );
+
+        // An old report is ignored
+        mapper.on_sender_report(1, ntp_millis(5_000), 0);
+        assert!((mapper.clock_rate(1).unwrap() - 48_048.0).abs() < 0.01);
+
+        // The RTP timestamps jump, so the measurement starts over from the latest report
+        mapper.on_sender_report(1, ntp_millis(20_000), 5_000_000);
+        assert_eq!(mapper.clock_rate(1), Some(48_000.0));
+        assert_eq!(mapper.rtp_to_ntp(1, 5_048_000).unwrap(), ntp_millis(21_000));
+    }
+
+    #[test]
+    fn test_unconfigured_clock_rate() {
+        let mut mapper = MediaClockMapper::new();
+        mapper.on_sender_report(2, ntp_millis(1_000), 0);
+        assert!(mapper.rtp_to_ntp(2, 100).is_none());
+        mapper.on_sender_report(2, ntp_millis(2_000), 8_000);
+        assert_eq!(mapper.clock_rate(2), Some(8_000.0));
+        assert_eq!(mapper.rtp_to_ntp(2, 12_000).unwrap(), ntp_millis(2_500));
+        mapper.remove_stream(2);
+        assert!(mapper.rtp_to_ntp(2, 12_000).is_none());
+    }
+}
@@ -1,34 +1,54 @@
-use std::fmt::LowerHex;
+use std::{any::Any, fmt::LowerHex, sync::Arc};
 
 use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    nsw_types::{u2, u5},
+};
 
 use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, HexBytes, PrettyPrint},
     rtcp::{
-        rtcp_bye::read_rtcp_bye,
-        rtcp_fb_nack::read_rtcp_fb_nack,
-        rtcp_fb_tcc::{read_rtcp_fb_tcc, RtcpFbTccPacket},
-        rtcp_header::read_rtcp_header,
+        rtcp_bye::{read_rtcp_bye, write_rtcp_bye},
+        rtcp_fb_nack::{read_rtcp_fb_nack, write_rtcp_fb_nack},
+        rtcp_fb_tcc::{read_rtcp_fb_tcc, write_rtcp_fb_tcc, RtcpFbTccPacket},
+        rtcp_header::{read_rtcp_header, write_rtcp_header},
     },
-    PacketBuffer,
+    LengthBytes, PacketBuffer, PacketBufferMut,
 };
 
 use super::{
+    rtcp_app::{read_rtcp_app, write_rtcp_app, RtcpAppPacket},
     rtcp_bye::RtcpByePacket,
-    rtcp_fb_fir::{read_rtcp_fb_fir, RtcpFbFirPacket},
+    rtcp_fb_ccfb::{read_rtcp_fb_ccfb, write_rtcp_fb_ccfb, RtcpFbCcfbPacket},
+    rtcp_fb_fir::{read_rtcp_fb_fir, write_rtcp_fb_fir, RtcpFbFirPacket},
     rtcp_fb_header::read_rtcp_fb_header,
+    rtcp_fb_lrr::{read_rtcp_fb_lrr, write_rtcp_fb_lrr, RtcpFbLrrPacket},
     rtcp_fb_nack::RtcpFbNackPacket,
     rtcp_fb_packet::{RtcpFbPsPacket, RtcpFbTlPacket},
-    rtcp_fb_pli::{read_rtcp_fb_pli, RtcpFbPliPacket},
+    rtcp_fb_pli::{read_rtcp_fb_pli, write_rtcp_fb_pli, RtcpFbPliPacket},
+    rtcp_fb_remb::{read_rtcp_fb_remb, write_rtcp_fb_remb, RtcpFbRembPacket},
+    rtcp_fb_rpsi::{read_rtcp_fb_rpsi, write_rtcp_fb_rpsi, RtcpFbRpsiPacket},
+    rtcp_fb_sli::{read_rtcp_fb_sli, write_rtcp_fb_sli, RtcpFbSliPacket},
+    rtcp_fb_tmmbn::{read_rtcp_fb_tmmbn, write_rtcp_fb_tmmbn, RtcpFbTmmbnPacket},
+    rtcp_fb_tmmbr::{read_rtcp_fb_tmmbr, write_rtcp_fb_tmmbr, RtcpFbTmmbrPacket},
     rtcp_header::RtcpHeader,
-    rtcp_rr::{read_rtcp_rr, RtcpRrPacket},
-    rtcp_sdes::{read_rtcp_sdes, RtcpSdesPacket},
-    rtcp_sr::{read_rtcp_sr, RtcpSrPacket},
+    rtcp_parser_registry::RtcpParserRegistry,
+    rtcp_rr::{read_rtcp_rr, write_rtcp_rr, RtcpRrPacket},
+    rtcp_sdes::{read_rtcp_sdes, write_rtcp_sdes, RtcpSdesPacket},
+    rtcp_sr::{read_rtcp_sr, write_rtcp_sr, RtcpSrPacket},
+    rtcp_xr::{read_rtcp_xr, write_rtcp_xr, RtcpXrPacket},
 };
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SomeRtcpPacket {
     CompoundRtcpPacket(Vec<SomeRtcpPacket>),
     RtcpByePacket(RtcpByePacket),
+    RtcpAppPacket(RtcpAppPacket),
     RtcpSrPacket(RtcpSrPacket),
     RtcpRrPacket(RtcpRrPacket),
     RtcpSdesPacket(RtcpSdesPacket),
@@ -36,47 +56,445 @@ pub enum SomeRtcpPacket {
     RtcpFbFirPacket(RtcpFbFirPacket),
     RtcpFbTccPacket(RtcpFbTccPacket),
     RtcpFbPliPacket(RtcpFbPliPacket),
+    RtcpFbRembPacket(RtcpFbRembPacket),
+    RtcpFbSliPacket(RtcpFbSliPacket),
+    RtcpFbRpsiPacket(RtcpFbRpsiPacket),
+    RtcpFbLrrPacket(RtcpFbLrrPacket),
+    RtcpFbTmmbrPacket(RtcpFbTmmbrPacket),
+    RtcpFbTmmbnPacket(RtcpFbTmmbnPacket),
+    RtcpFbCcfbPacket(RtcpFbCcfbPacket),
+    RtcpXrPacket(RtcpXrPacket),
+    /// A packet parsed by a parser from an [`RtcpParserRegistry`].  The packet can be downcast to
+    /// the type the custom parser returned.  Custom packets can't be serialized with serde.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    CustomRtcpPacket {
+        header: RtcpHeader,
+        packet: Box<dyn Any + Send + Sync>,
+    },
     UnknownRtcpPacket {
         header: RtcpHeader,
         payload: Vec<u8>,
     },
 }
 
+impl SomeRtcpPacket {
+    /// The header of this packet, or None if this is a compound packet
+    pub fn header(&self) -> Option<&RtcpHeader> {
+        match self {
+            SomeRtcpPacket::CompoundRtcpPacket(_) => None,
+            SomeRtcpPacket::RtcpByePacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpAppPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpSrPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpRrPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpSdesPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbNackPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbFirPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbTccPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbPliPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbRembPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbSliPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbRpsiPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbLrrPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpFbCcfbPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::RtcpXrPacket(packet) => Some(&packet.header),
+            SomeRtcpPacket::CustomRtcpPacket { header, .. } => Some(header),
+            SomeRtcpPacket::UnknownRtcpPacket { header, .. } => Some(header),
+        }
+    }
+
+    /// The header of this packet, or None if this is a compound packet
+    pub fn header_mut(&mut self) -> Option<&mut RtcpHeader> {
+        match self {
+            SomeRtcpPacket::CompoundRtcpPacket(_) => None,
+            SomeRtcpPacket::RtcpByePacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpAppPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpSrPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpRrPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpSdesPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbNackPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbFirPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbTccPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbPliPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbRembPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbSliPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbRpsiPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbLrrPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpFbCcfbPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::RtcpXrPacket(packet) => Some(&mut packet.header),
+            SomeRtcpPacket::CustomRtcpPacket { header, .. } => Some(header),
+            SomeRtcpPacket::UnknownRtcpPacket { header, .. } => Some(header),
+        }
+    }
+
+    /// Update the header of this packet to match its contents.  For a compound packet, every
+    /// sub-packet is synced.
+    pub fn sync(&mut self) -> Result<()> {
+        match self {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+                for (i, packet) in packets.iter_mut().enumerate() {
+                    packet
+                        .sync()
+                        .with_context(|| format!("sub packet {}", i + 1))?;
+                }
+                Ok(())
+            }
+            SomeRtcpPacket::RtcpByePacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpAppPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpSrPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpRrPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpSdesPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbNackPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbFirPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbTccPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbPliPacket(packet) => {
+                packet.sync();
+                Ok(())
+            }
+            SomeRtcpPacket::RtcpFbRembPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbSliPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbRpsiPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbLrrPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpFbCcfbPacket(packet) => packet.sync(),
+            SomeRtcpPacket::RtcpXrPacket(packet) => packet.sync(),
+            // We don't know anything about the contents of a custom packet, so its header is
+            // left as-is
+            SomeRtcpPacket::CustomRtcpPacket { .. } => Ok(()),
+            SomeRtcpPacket::UnknownRtcpPacket { header, payload } => {
+                if !payload.len().is_multiple_of(4) {
+                    bail!(
+                        "RTCP payload must be a multiple of 4 bytes, was {} bytes",
+                        payload.len()
+                    );
+                }
+                header.length_field = (payload.len() / 4).try_into().map_err(|_| {
+                    anyhow!("RTCP payload length {} bytes is too large", payload.len())
+                })?;
+                header.has_padding = false;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl LengthBytes for SomeRtcpPacket {
+    fn length_bytes(&self) -> usize {
+        match self {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+                packets.iter().map(|packet| packet.length_bytes()).sum()
+            }
+            SomeRtcpPacket::RtcpByePacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpAppPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpSrPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpRrPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpSdesPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbNackPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbFirPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbTccPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbPliPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbRembPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbSliPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbRpsiPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbLrrPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpFbCcfbPacket(packet) => packet.length_bytes(),
+            SomeRtcpPacket::RtcpXrPacket(packet) => packet.length_bytes(),
+            // Custom packets can't be written, this is the length of the packet that was parsed
+            SomeRtcpPacket::CustomRtcpPacket { header, .. } => {
+                RtcpHeader::SIZE_BYTES + header.length_field as usize * 4
+            }
+            SomeRtcpPacket::UnknownRtcpPacket { payload, .. } => {
+                RtcpHeader::SIZE_BYTES + payload.len()
+            }
+        }
+    }
+}
+
+/// Generates any type of packet except custom ones.  Compound packets are made up of 1-4
+/// non-compound packets.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for SomeRtcpPacket {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.ratio(1, 4)? {
+            let packets = (0..u.int_in_range(1..=4)?)
+                .map(|_| arbitrary_single_rtcp_packet(u))
+                .collect::<arbitrary::Result<_>>()?;
+            Ok(SomeRtcpPacket::CompoundRtcpPacket(packets))
+        } else {
+            arbitrary_single_rtcp_packet(u)
+        }
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+fn arbitrary_single_rtcp_packet(
+    u: &mut arbitrary::Unstructured<'_>,
+) -> arbitrary::Result<SomeRtcpPacket> {
+    Ok(match u.int_in_range(0..=17)? {
+        0 => SomeRtcpPacket::RtcpByePacket(u.arbitrary()?),
+        1 => SomeRtcpPacket::RtcpAppPacket(u.arbitrary()?),
+        2 => SomeRtcpPacket::RtcpSrPacket(u.arbitrary()?),
+        3 => SomeRtcpPacket::RtcpRrPacket(u.arbitrary()?),
+        4 => SomeRtcpPacket::RtcpSdesPacket(u.arbitrary()?),
+        5 => SomeRtcpPacket::RtcpFbNackPacket(u.arbitrary()?),
+        6 => SomeRtcpPacket::RtcpFbFirPacket(u.arbitrary()?),
+        7 => SomeRtcpPacket::RtcpFbTccPacket(u.arbitrary()?),
+        8 => SomeRtcpPacket::RtcpFbPliPacket(u.arbitrary()?),
+        9 => SomeRtcpPacket::RtcpFbRembPacket(u.arbitrary()?),
+        10 => SomeRtcpPacket::RtcpFbSliPacket(u.arbitrary()?),
+        11 => SomeRtcpPacket::RtcpFbRpsiPacket(u.arbitrary()?),
+        12 => SomeRtcpPacket::RtcpFbLrrPacket(u.arbitrary()?),
+        13 => SomeRtcpPacket::RtcpFbTmmbrPacket(u.arbitrary()?),
+        14 => SomeRtcpPacket::RtcpFbTmmbnPacket(u.arbitrary()?),
+        15 => SomeRtcpPacket::RtcpFbCcfbPacket(u.arbitrary()?),
+        16 => SomeRtcpPacket::RtcpXrPacket(u.arbitrary()?),
+        // A packet type this crate doesn't parse, so it's read back as unknown
+        _ => SomeRtcpPacket::UnknownRtcpPacket {
+            header: RtcpHeader {
+                packet_type: u.int_in_range(RtcpXrPacket::PT + 1..=223)?,
+                ..u.arbitrary()?
+            },
+            payload: crate::arbitrary_helpers::words(u)?,
+        },
+    })
+}
+
+/// Options which affect how RTCP packets are parsed
+#[derive(Debug, Clone)]
+pub struct RtcpParseContext {
+    /// Whether reduced-size RTCP (https://datatracker.ietf.org/doc/html/rfc5506) has been
+    /// negotiated (i.e. via a=rtcp-rsize).  When it has, packets which don't follow the compound
+    /// packet rules (e.g. a single feedback packet on its own) are accepted.  When it hasn't,
+    /// every RTCP packet must be a compound packet which starts with an SR or RR.
+    pub reduced_size: bool,
+    /// Custom parsers which are consulted before the built-in ones
+    pub parser_registry: Option<Arc<RtcpParserRegistry>>,
+    /// Whether packets with a version other than 2, inconsistent lengths or non-zero reserved
+    /// bits are rejected, or accepted with a warning
+    pub mode: ParseMode,
+}
+
+impl Default for RtcpParseContext {
+    /// The default context accepts reduced-size packets, which matches the behavior of
+    /// [`parse_rtcp_packet`].
+    fn default() -> Self {
+        Self {
+            reduced_size: true,
+            parser_registry: None,
+            mode: ParseMode::default(),
+        }
+    }
+}
+
 pub fn parse_rtcp_packet<B: PacketBuffer + LowerHex>(buf: &mut B) -> Result<SomeRtcpPacket> {
-    let mut packets: Vec<SomeRtcpPacket> = Vec::new();
+    parse_rtcp_packet_with_context(buf, &RtcpParseContext::default()).map(|(packet, _)| packet)
+}
 
-    let mut sub_packet_num = 1;
+/// Like [`parse_rtcp_packet`], but using the given context.  Any warnings for violations which
+/// were accepted in [`ParseMode::Lenient`] are returned along with the packet.
+pub fn parse_rtcp_packet_with_context<B: PacketBuffer + LowerHex>(
+    buf: &mut B,
+    context: &RtcpParseContext,
+) -> Result<(SomeRtcpPacket, ParseWarnings)> {
     // println!("parsing packet, buf: {buf:x}");
-    while buf.bytes_remaining() >= RtcpHeader::SIZE_BYTES {
-        let packet = parse_single_rtcp_packet(buf)
-            .with_context(|| format!("sub packet {sub_packet_num}"))?;
-        packets.push(packet);
-        sub_packet_num += 1;
+    let mut iter = RtcpPacketIter::new_with_context(buf, context.clone());
+    let mut packets = iter.by_ref().collect::<Result<Vec<SomeRtcpPacket>>>()?;
+
+    let packet = match packets.len() {
+        0 => bail!(RtpParseError::Truncated {
+            needed_bytes: Some(RtcpHeader::SIZE_BYTES),
+            available_bytes: None,
+        }),
+        1 => packets.remove(0),
+        _ => SomeRtcpPacket::CompoundRtcpPacket(packets),
+    };
+
+    Ok((packet, iter.into_warnings()))
+}
+
+/// Lazily parses the packets in a (possibly compound) RTCP packet one at a time.  Iteration stops
+/// once there isn't enough data left for another RTCP header or after the first error.  The
+/// context's rules for the whole compound packet are applied as well: the first packet must be an
+/// SR or RR unless reduced-size RTCP is in use, and leftover bytes which are too short to be
+/// another packet are a violation.
+pub struct RtcpPacketIter<'a, B> {
+    buf: &'a mut B,
+    context: RtcpParseContext,
+    warnings: ParseWarnings,
+    sub_packet_num: usize,
+    done: bool,
+}
+
+impl<'a, B: PacketBuffer> RtcpPacketIter<'a, B> {
+    pub fn new(buf: &'a mut B) -> Self {
+        Self::new_with_context(buf, RtcpParseContext::default())
     }
 
-    match packets.len() {
-        0 => Err(anyhow!("No valid packets found")),
-        1 => Ok(packets.remove(0)),
-        _ => Ok(SomeRtcpPacket::CompoundRtcpPacket(packets)),
+    pub fn new_with_context(buf: &'a mut B, context: RtcpParseContext) -> Self {
+        Self {
+            buf,
+            context,
+            warnings: ParseWarnings::new(),
+            sub_packet_num: 1,
+            done: false,
+        }
+    }
+
+    /// The warnings for violations which were accepted in [`ParseMode::Lenient`] while parsing
+    /// the packets returned so far
+    pub fn warnings(&self) -> &ParseWarnings {
+        &self.warnings
+    }
+
+    pub fn into_warnings(self) -> ParseWarnings {
+        self.warnings
+    }
+}
+
+impl<B: PacketBuffer> Iterator for RtcpPacketIter<'_, B> {
+    type Item = Result<SomeRtcpPacket>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        if self.buf.bytes_remaining() < RtcpHeader::SIZE_BYTES {
+            self.done = true;
+            let trailing_bytes = self.buf.bytes_remaining();
+            if trailing_bytes > 0 {
+                if let Err(e) = self.context.mode.on_violation(
+                    &mut self.warnings,
+                    RtpParseError::invalid_field(
+                        "compound packet length",
+                        "a whole number of RTCP packets",
+                        format!("{trailing_bytes} trailing bytes"),
+                    ),
+                ) {
+                    return Some(Err(e));
+                }
+            }
+            return None;
+        }
+        let sub_packet_num = self.sub_packet_num;
+        let result = read_single_rtcp_packet(self.buf, &self.context, &mut self.warnings)
+            .and_then(|packet| {
+                if sub_packet_num == 1
+                    && !self.context.reduced_size
+                    && !matches!(
+                        packet,
+                        SomeRtcpPacket::RtcpSrPacket(_) | SomeRtcpPacket::RtcpRrPacket(_)
+                    )
+                {
+                    bail!(RtpParseError::invalid_field(
+                        "first packet type",
+                        "SR or RR, since reduced-size RTCP isn't in use",
+                        packet.header().map_or(0, |header| header.packet_type)
+                    ));
+                }
+                Ok(packet)
+            })
+            .with_context(|| format!("sub packet {sub_packet_num}"));
+        self.done = result.is_err();
+        self.sub_packet_num += 1;
+
+        Some(result)
     }
 }
 
 pub fn parse_single_rtcp_packet<B: PacketBuffer>(buf: &mut B) -> Result<SomeRtcpPacket> {
+    parse_single_rtcp_packet_with_context(buf, &RtcpParseContext::default())
+        .map(|(packet, _)| packet)
+}
+
+/// Like [`parse_single_rtcp_packet`], but using the given context.  Any warnings for violations
+/// which were accepted in [`ParseMode::Lenient`] are returned along with the packet.
+pub fn parse_single_rtcp_packet_with_context<B: PacketBuffer>(
+    buf: &mut B,
+    context: &RtcpParseContext,
+) -> Result<(SomeRtcpPacket, ParseWarnings)> {
+    let mut warnings = ParseWarnings::new();
+    let packet = read_single_rtcp_packet(buf, context, &mut warnings)?;
+
+    Ok((packet, warnings))
+}
+
+fn read_single_rtcp_packet<B: PacketBuffer>(
+    buf: &mut B,
+    context: &RtcpParseContext,
+    warnings: &mut ParseWarnings,
+) -> Result<SomeRtcpPacket> {
     // println!("Parsing single rtcp packet: {buf:x}");
-    let header = read_rtcp_header(buf).context("rtcp header")?;
+    let mut header = read_rtcp_header(buf).context("rtcp header")?;
+    if header.version != u2::new(2) {
+        context.mode.on_violation(
+            warnings,
+            RtpParseError::invalid_field("RTCP version", 2, u8::from(header.version)),
+        )?;
+    }
+    // XR packets have no count, so those bits are reserved
+    if header.packet_type == RtcpXrPacket::PT && header.report_count != u5::new(0) {
+        context.mode.on_violation(
+            warnings,
+            RtpParseError::invalid_field("XR reserved bits", 0, u8::from(header.report_count)),
+        )?;
+    }
     let payload_length = header
         .payload_length_bytes()
         .context("header length field")? as usize;
     if payload_length > buf.bytes_remaining() {
-        bail!("Invalid RTCP packet, length {payload_length} bytes but buf has only {} bytes remaining", buf.bytes_remaining());
+        bail!(RtpParseError::truncated(
+            payload_length,
+            buf.bytes_remaining()
+        ));
     }
     let payload_length_bits = payload_length * 8;
-    let mut payload_buffer = buf.sub_buffer(0..(payload_length * 8));
+    // Any padding is stripped from the buffer given to the packet-specific readers
+    let mut padding_length = if header.has_padding {
+        read_padding_length(buf, payload_length).context("padding length")?
+    } else {
+        0
+    };
+    if !padding_length.is_multiple_of(4) {
+        context.mode.on_violation(
+            warnings,
+            RtpParseError::invalid_field("padding length", "a multiple of 4", padding_length),
+        )?;
+        // Only whole words of padding are stripped, so the length field can still describe
+        // what's left
+        padding_length -= padding_length % 4;
+    }
+    // The packet doesn't keep its padding, so its header is updated to match: otherwise writing it
+    // back out without syncing it would produce a malformed packet
+    header.has_padding = false;
+    header.length_field -= (padding_length / 4) as u16;
+    let data_length_bits = (payload_length - padding_length) * 8;
+
+    if let Some(parser) = context
+        .parser_registry
+        .as_ref()
+        .and_then(|registry| registry.get(&header))
+    {
+        let mut payload = vec![0u8; payload_length];
+        buf.read_exact(&mut payload).context("payload")?;
+        payload.truncate(payload_length - padding_length);
+        let packet = parser(&header, &payload).context("custom parser")?;
+        return Ok(SomeRtcpPacket::CustomRtcpPacket { header, packet });
+    }
+    let mut payload_buffer = buf.sub_buffer(0..data_length_bits);
 
     let result = match header.packet_type {
         RtcpByePacket::PT => Ok(SomeRtcpPacket::RtcpByePacket(
             read_rtcp_bye(&mut payload_buffer, header).context("rtcp bye")?,
         )),
+        RtcpAppPacket::PT => Ok(SomeRtcpPacket::RtcpAppPacket(
+            read_rtcp_app(&mut payload_buffer, header).context("rtcp app")?,
+        )),
         RtcpSrPacket::PT => Ok(SomeRtcpPacket::RtcpSrPacket(
             read_rtcp_sr(&mut payload_buffer, header).context("rtcp sr")?,
         )),
@@ -86,17 +504,58 @@ pub fn parse_single_rtcp_packet<B: PacketBuffer>(buf: &mut B) -> Result<SomeRtcp
         RtcpSdesPacket::PT => Ok(SomeRtcpPacket::RtcpSdesPacket(
             read_rtcp_sdes(&mut payload_buffer, header).context("rtcp sdes")?,
         )),
+        RtcpXrPacket::PT => Ok(SomeRtcpPacket::RtcpXrPacket(
+            read_rtcp_xr(&mut payload_buffer, header, context.mode, warnings).context("rtcp xr")?,
+        )),
         RtcpFbPsPacket::PT | RtcpFbTlPacket::PT => {
             let fb_header = read_rtcp_fb_header(&mut payload_buffer).context("fb header")?;
             match (header.packet_type, header.report_count) {
                 (RtcpFbPsPacket::PT, RtcpFbFirPacket::FMT) => Ok(SomeRtcpPacket::RtcpFbFirPacket(
-                    read_rtcp_fb_fir(&mut payload_buffer, header, fb_header)
-                        .context("rtcp fb fir")?,
+                    read_rtcp_fb_fir(
+                        &mut payload_buffer,
+                        header,
+                        fb_header,
+                        context.mode,
+                        warnings,
+                    )
+                    .context("rtcp fb fir")?,
                 )),
                 (RtcpFbPsPacket::PT, RtcpFbPliPacket::FMT) => Ok(SomeRtcpPacket::RtcpFbPliPacket(
                     read_rtcp_fb_pli(&mut payload_buffer, header, fb_header)
                         .context("rtcp fb pli")?,
                 )),
+                (RtcpFbPsPacket::PT, RtcpFbSliPacket::FMT) => Ok(SomeRtcpPacket::RtcpFbSliPacket(
+                    read_rtcp_fb_sli(&mut payload_buffer, header, fb_header)
+                        .context("rtcp fb sli")?,
+                )),
+                (RtcpFbPsPacket::PT, RtcpFbRpsiPacket::FMT) => {
+                    Ok(SomeRtcpPacket::RtcpFbRpsiPacket(
+                        read_rtcp_fb_rpsi(
+                            &mut payload_buffer,
+                            header,
+                            fb_header,
+                            context.mode,
+                            warnings,
+                        )
+                        .context("rtcp fb rpsi")?,
+                    ))
+                }
+                (RtcpFbPsPacket::PT, RtcpFbLrrPacket::FMT) => Ok(SomeRtcpPacket::RtcpFbLrrPacket(
+                    read_rtcp_fb_lrr(
+                        &mut payload_buffer,
+                        header,
+                        fb_header,
+                        context.mode,
+                        warnings,
+                    )
+                    .context("rtcp fb lrr")?,
+                )),
+                (RtcpFbPsPacket::PT, RtcpFbRembPacket::FMT) => {
+                    Ok(SomeRtcpPacket::RtcpFbRembPacket(
+                        read_rtcp_fb_remb(&mut payload_buffer, header, fb_header)
+                            .context("rtcp fb remb")?,
+                    ))
+                }
                 (RtcpFbTlPacket::PT, RtcpFbTccPacket::FMT) => Ok(SomeRtcpPacket::RtcpFbTccPacket(
                     read_rtcp_fb_tcc(&mut payload_buffer, header, fb_header)
                         .context("rtcp fb tcc")?,
@@ -107,14 +566,826 @@ pub fn parse_single_rtcp_packet<B: PacketBuffer>(buf: &mut B) -> Result<SomeRtcp
                             .context("rtcp fb nack")?,
                     ))
                 }
-                (pt, fmt) => bail!("Unsuppsorted RTCP FB packet, pt {pt} fmt {fmt}"),
+                (RtcpFbTlPacket::PT, RtcpFbTmmbrPacket::FMT) => {
+                    Ok(SomeRtcpPacket::RtcpFbTmmbrPacket(
+                        read_rtcp_fb_tmmbr(&mut payload_buffer, header, fb_header)
+                            .context("rtcp fb tmmbr")?,
+                    ))
+                }
+                (RtcpFbTlPacket::PT, RtcpFbTmmbnPacket::FMT) => {
+                    Ok(SomeRtcpPacket::RtcpFbTmmbnPacket(
+                        read_rtcp_fb_tmmbn(&mut payload_buffer, header, fb_header)
+                            .context("rtcp fb tmmbn")?,
+                    ))
+                }
+                (RtcpFbTlPacket::PT, RtcpFbCcfbPacket::FMT) => {
+                    Ok(SomeRtcpPacket::RtcpFbCcfbPacket(
+                        read_rtcp_fb_ccfb(
+                            &mut payload_buffer,
+                            header,
+                            fb_header,
+                            context.mode,
+                            warnings,
+                        )
+                        .context("rtcp fb ccfb")?,
+                    ))
+                }
+                _ => read_unknown_rtcp_packet(&mut buf.sub_buffer(0..data_length_bits), header)
+                    .context("rtcp fb unknown"),
             }
         }
-        pt => bail!("Unsupported packet type {pt}"),
+        _ => read_unknown_rtcp_packet(&mut buf.sub_buffer(0..data_length_bits), header)
+            .context("rtcp unknown"),
     };
+    // Unknown packets are read from their own buffer and always consume the whole payload
+    let unread_bytes = payload_buffer.bytes_remaining();
     drop(payload_buffer);
+    if unread_bytes > 0 && !matches!(result, Ok(SomeRtcpPacket::UnknownRtcpPacket { .. })) {
+        let data_length = payload_length - padding_length;
+        context.mode.on_violation(
+            warnings,
+            RtpParseError::invalid_field(
+                "packet length",
+                format!("{} bytes", data_length - unread_bytes),
+                format!("{data_length} bytes"),
+            ),
+        )?;
+    }
     if result.is_ok() {
         buf.seek(std::io::SeekFrom::Current(payload_length_bits as i64))?;
     }
     result
 }
+
+/// When the padding bit is set, the last octet of the packet contains the number of padding
+/// octets (including itself) which should be ignored.
+fn read_padding_length<B: PacketBuffer>(buf: &B, payload_length: usize) -> Result<usize> {
+    if payload_length == 0 {
+        bail!(RtpParseError::truncated(1, 0));
+    }
+    let mut last_octet = buf.sub_buffer((payload_length - 1) * 8..payload_length * 8);
+    let padding_length = last_octet.read_u8().context("padding octet")? as usize;
+    if padding_length == 0 || padding_length > payload_length {
+        bail!(RtpParseError::invalid_field(
+            "padding length",
+            format!("1-{payload_length} bytes"),
+            padding_length
+        ));
+    }
+
+    Ok(padding_length)
+}
+
+fn read_unknown_rtcp_packet<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+) -> Result<SomeRtcpPacket> {
+    let mut payload = vec![0u8; buf.bytes_remaining()];
+    std::io::Read::read_exact(buf, &mut payload).context("payload")?;
+
+    Ok(SomeRtcpPacket::UnknownRtcpPacket { header, payload })
+}
+
+/// Sync the given packet (and, for a compound packet, every sub-packet) and then write it to the
+/// buffer.  Use this unless the headers need to be written exactly as they are.
+pub fn write_synced_rtcp_packet<B: PacketBufferMut>(
+    buf: &mut B,
+    packet: &mut SomeRtcpPacket,
+) -> Result<()> {
+    packet.sync().context("sync")?;
+    write_some_rtcp_packet(buf, packet)
+}
+
+/// Write the given packet to the buffer.  For a compound packet, every sub-packet is written in
+/// order.  Note that the headers are written as they are, without syncing the packet: if its
+/// contents have changed, [`SomeRtcpPacket::sync`] must be called first (or use
+/// [`write_synced_rtcp_packet`]), otherwise stale length and count fields are written.
+pub fn write_some_rtcp_packet<B: PacketBufferMut>(
+    buf: &mut B,
+    packet: &SomeRtcpPacket,
+) -> Result<()> {
+    match packet {
+        SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+            for (i, packet) in packets.iter().enumerate() {
+                write_some_rtcp_packet(buf, packet)
+                    .with_context(|| format!("sub packet {}", i + 1))?;
+            }
+            Ok(())
+        }
+        SomeRtcpPacket::RtcpByePacket(packet) => write_rtcp_bye(buf, packet).context("rtcp bye"),
+        SomeRtcpPacket::RtcpAppPacket(packet) => write_rtcp_app(buf, packet).context("rtcp app"),
+        SomeRtcpPacket::RtcpSrPacket(packet) => write_rtcp_sr(buf, packet).context("rtcp sr"),
+        SomeRtcpPacket::RtcpRrPacket(packet) => write_rtcp_rr(buf, packet).context("rtcp rr"),
+        SomeRtcpPacket::RtcpSdesPacket(packet) => write_rtcp_sdes(buf, packet).context("rtcp sdes"),
+        SomeRtcpPacket::RtcpFbNackPacket(packet) => {
+            write_rtcp_fb_nack(buf, packet).context("rtcp fb nack")
+        }
+        SomeRtcpPacket::RtcpFbFirPacket(packet) => {
+            write_rtcp_fb_fir(buf, packet).context("rtcp fb fir")
+        }
+        SomeRtcpPacket::RtcpFbTccPacket(packet) => {
+            write_rtcp_fb_tcc(buf, packet).context("rtcp fb tcc")
+        }
+        SomeRtcpPacket::RtcpFbPliPacket(packet) => {
+            write_rtcp_fb_pli(buf, packet).context("rtcp fb pli")
+        }
+        SomeRtcpPacket::RtcpFbRembPacket(packet) => {
+            write_rtcp_fb_remb(buf, packet).context("rtcp fb remb")
+        }
+        SomeRtcpPacket::RtcpFbSliPacket(packet) => {
+            write_rtcp_fb_sli(buf, packet).context("rtcp fb sli")
+        }
+        SomeRtcpPacket::RtcpFbRpsiPacket(packet) => {
+            write_rtcp_fb_rpsi(buf, packet).context("rtcp fb rpsi")
+        }
+        SomeRtcpPacket::RtcpFbLrrPacket(packet) => {
+            write_rtcp_fb_lrr(buf, packet).context("rtcp fb lrr")
+        }
+        SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => {
+            write_rtcp_fb_tmmbr(buf, packet).context("rtcp fb tmmbr")
+        }
+        SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => {
+            write_rtcp_fb_tmmbn(buf, packet).context("rtcp fb tmmbn")
+        }
+        SomeRtcpPacket::RtcpFbCcfbPacket(packet) => {
+            write_rtcp_fb_ccfb(buf, packet).context("rtcp fb ccfb")
+        }
+        SomeRtcpPacket::RtcpXrPacket(packet) => write_rtcp_xr(buf, packet).context("rtcp xr"),
+        SomeRtcpPacket::CustomRtcpPacket { .. } => {
+            bail!("Writing custom RTCP packets isn't supported")
+        }
+        SomeRtcpPacket::UnknownRtcpPacket { header, payload } => {
+            write_rtcp_header(buf, header).context("rtcp header")?;
+            buf.write_all(payload).context("payload")?;
+            Ok(())
+        }
+    }
+}
+
+/// Sync and write the given (non-compound) packet followed by `padding_length` octets of padding.
+/// The packet's header is updated to set the padding bit and to include the padding in its
+/// length.  Per
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1, only the last packet in a compound
+/// packet should be padded.  Since the packet itself is always a multiple of 32 bits, the padding
+/// must be as well.
+pub fn write_padded_rtcp_packet<B: PacketBufferMut>(
+    buf: &mut B,
+    packet: &mut SomeRtcpPacket,
+    padding_length: u8,
+) -> Result<()> {
+    if padding_length == 0 || !padding_length.is_multiple_of(4) {
+        bail!("Padding length must be a non-zero multiple of 4, was {padding_length}");
+    }
+    if packet.header().is_none() {
+        bail!("Compound packets can't be padded directly");
+    }
+    packet.sync().context("sync")?;
+    let header = packet
+        .header_mut()
+        .expect("non-compound packet has a header");
+    header.has_padding = true;
+    header.length_field = header
+        .length_field
+        .checked_add(padding_length as u16 / 4)
+        .ok_or(anyhow!("Padded packet length is too large"))?;
+    write_some_rtcp_packet(buf, packet).context("packet")?;
+    // The last octet of the padding contains the number of padding octets, including itself
+    for _ in 0..padding_length - 1 {
+        buf.write_u8(0).context("padding")?;
+    }
+    buf.write_u8(padding_length).context("padding length")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for SomeRtcpPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        match self {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+                tree.subtree("Compound RTCP packet", |tree| {
+                    for packet in packets {
+                        packet.pretty_print(tree)?;
+                    }
+                    Ok(())
+                })
+            }
+            SomeRtcpPacket::RtcpByePacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpAppPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpSrPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpRrPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpSdesPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbNackPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbFirPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbTccPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbPliPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbRembPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbSliPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbRpsiPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbLrrPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbTmmbrPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbTmmbnPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpFbCcfbPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::RtcpXrPacket(packet) => packet.pretty_print(tree),
+            SomeRtcpPacket::CustomRtcpPacket { header, .. } => {
+                tree.subtree("Custom RTCP packet", |tree| header.pretty_print(tree))
+            }
+            SomeRtcpPacket::UnknownRtcpPacket { header, payload } => {
+                tree.subtree("Unknown RTCP packet", |tree| {
+                    header.pretty_print(tree)?;
+                    tree.field("Payload", HexBytes(payload))
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{
+        bit_cursor::BitCursor,
+        nsw_types::{u2, u5},
+    };
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::{
+        rtcp_fb_header::RtcpFbHeader,
+        rtcp_sdes::{SdesChunk, SdesItem},
+    };
+
+    use super::*;
+
+    fn empty_header() -> RtcpHeader {
+        RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: 0,
+            length_field: 0,
+        }
+    }
+
+    #[rustfmt::skip]
+    const PLI: [u8; 12] = [
+        // v=2, fmt 1, pt 206, length 2
+        0x81, 0xCE, 0x00, 0x02,
+        // sender ssrc
+        0x00, 0x00, 0x00, 0x01,
+        // media source ssrc
+        0x00, 0x00, 0x00, 0x02,
+    ];
+
+    #[rustfmt::skip]
+    const RR: [u8; 8] = [
+        // v=2, rc 0, pt 201, length 1
+        0x80, 0xC9, 0x00, 0x01,
+        // sender ssrc
+        0x00, 0x00, 0x00, 0x01,
+    ];
+
+    #[test]
+    fn test_reduced_size_single_fb_packet() {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&PLI));
+        let (packet, warnings) =
+            parse_rtcp_packet_with_context(&mut cursor, &RtcpParseContext::default()).unwrap();
+        assert!(matches!(packet, SomeRtcpPacket::RtcpFbPliPacket(_)));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_non_reduced_size_single_fb_packet() {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&PLI));
+        let result = parse_rtcp_packet_with_context(
+            &mut cursor,
+            &RtcpParseContext {
+                reduced_size: false,
+                ..Default::default()
+            },
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        // Version 1, followed by 2 trailing bytes
+        let mut data = PLI.to_vec();
+        data[0] = 0x41;
+        data.extend([0, 0]);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let (packet, warnings) =
+            parse_rtcp_packet_with_context(&mut cursor, &RtcpParseContext::default()).unwrap();
+        assert!(matches!(packet, SomeRtcpPacket::RtcpFbPliPacket(_)));
+        assert_eq!(warnings.len(), 2);
+        assert_eq!(
+            warnings[0],
+            RtpParseError::invalid_field("RTCP version", 2, 1)
+        );
+
+        let context = RtcpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let err = parse_rtcp_packet_with_context(&mut cursor, &context).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::invalid_field("RTCP version", 2, 1))
+        );
+
+        // Trailing bytes after a valid packet
+        let mut data = PLI.to_vec();
+        data.extend([0, 0]);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        assert!(parse_rtcp_packet_with_context(&mut cursor, &context).is_err());
+
+        // A length field which covers more than the packet's contents
+        let mut data = PLI.to_vec();
+        data[3] = 3;
+        data.extend([0xDE, 0xAD, 0xBE, 0xEF]);
+        let length_violation = RtpParseError::invalid_field("packet length", "8 bytes", "12 bytes");
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let err = parse_rtcp_packet_with_context(&mut cursor, &context).unwrap_err();
+        assert_eq!(RtpParseError::find(&err), Some(length_violation.clone()));
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let (packet, warnings) =
+            parse_rtcp_packet_with_context(&mut cursor, &RtcpParseContext::default()).unwrap();
+        assert!(matches!(packet, SomeRtcpPacket::RtcpFbPliPacket(_)));
+        assert_eq!(warnings, vec![length_violation]);
+    }
+
+    #[test]
+    fn test_non_reduced_size_compound_packet() {
+        let mut data = RR.to_vec();
+        data.extend(PLI);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let (packet, _) = parse_rtcp_packet_with_context(
+            &mut cursor,
+            &RtcpParseContext {
+                reduced_size: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        match packet {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => assert_eq!(packets.len(), 2),
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_write_compound_packet() {
+        let mut packet = SomeRtcpPacket::CompoundRtcpPacket(vec![
+            SomeRtcpPacket::RtcpRrPacket(RtcpRrPacket {
+                header: empty_header(),
+                sender_ssrc: 1,
+                report_blocks: Vec::new(),
+                profile_extensions: Vec::new(),
+            }),
+            SomeRtcpPacket::RtcpSdesPacket(RtcpSdesPacket {
+                header: empty_header(),
+                chunks: vec![SdesChunk {
+                    ssrc: 1,
+                    sdes_items: vec![SdesItem::Cname("cname".to_owned())],
+                }],
+            }),
+            SomeRtcpPacket::RtcpFbPliPacket(RtcpFbPliPacket {
+                header: empty_header(),
+                fb_header: RtcpFbHeader {
+                    sender_ssrc: 1,
+                    media_source_ssrc: 2,
+                },
+            }),
+            SomeRtcpPacket::RtcpByePacket(RtcpByePacket {
+                header: empty_header(),
+                ssrcs: vec![1],
+                reason: Some("later".to_owned()),
+            }),
+        ]);
+        packet.sync().unwrap();
+
+        // RR: 8, SDES: 4 + 12, PLI: 12, BYE: 4 + 4 + 8
+        assert_eq!(packet.length_bytes(), 52);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 52]));
+        write_some_rtcp_packet(&mut cursor, &packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut cursor = BitCursor::new(cursor.into_inner());
+        let (packet, _) = parse_rtcp_packet_with_context(
+            &mut cursor,
+            &RtcpParseContext {
+                reduced_size: false,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let SomeRtcpPacket::CompoundRtcpPacket(packets) = packet else {
+            panic!("Expected compound packet");
+        };
+        assert_eq!(packets.len(), 4);
+        match &packets[1] {
+            SomeRtcpPacket::RtcpSdesPacket(sdes) => {
+                assert_eq!(sdes.chunks.len(), 1);
+                assert!(
+                    matches!(&sdes.chunks[0].sdes_items[..], [SdesItem::Cname(v)] if v == "cname")
+                );
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+        match &packets[2] {
+            SomeRtcpPacket::RtcpFbPliPacket(pli) => {
+                assert_eq!(pli.fb_header.media_source_ssrc, 2)
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+        match &packets[3] {
+            SomeRtcpPacket::RtcpByePacket(bye) => {
+                assert_eq!(bye.ssrcs, vec![1]);
+                assert_eq!(bye.reason.as_deref(), Some("later"));
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_packet_iter() {
+        let mut data = RR.to_vec();
+        data.extend(PLI);
+        // An RR whose length field is larger than the remaining data
+        data.extend([0x80, 0xC9, 0x00, 0x05]);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let mut iter = RtcpPacketIter::new(&mut cursor);
+        assert!(matches!(
+            iter.next(),
+            Some(Ok(SomeRtcpPacket::RtcpRrPacket(_)))
+        ));
+        assert!(matches!(
+            iter.next(),
+            Some(Ok(SomeRtcpPacket::RtcpFbPliPacket(_)))
+        ));
+        assert!(matches!(iter.next(), Some(Err(_))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn test_packet_iter_with_context() {
+        // The first packet must be an SR or RR without reduced-size RTCP
+        let mut data = PLI.to_vec();
+        data.extend(RR);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let mut iter = RtcpPacketIter::new_with_context(
+            &mut cursor,
+            RtcpParseContext {
+                reduced_size: false,
+                ..Default::default()
+            },
+        );
+        let err = iter.next().unwrap().unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::invalid_field(
+                "first packet type",
+                "SR or RR, since reduced-size RTCP isn't in use",
+                206
+            ))
+        );
+        assert!(iter.next().is_none());
+
+        // Trailing bytes which are too short for another packet
+        let mut data = PLI.to_vec();
+        data.extend([0, 0]);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let mut iter = RtcpPacketIter::new_with_context(
+            &mut cursor,
+            RtcpParseContext {
+                mode: ParseMode::Strict,
+                ..Default::default()
+            },
+        );
+        assert!(matches!(
+            iter.next(),
+            Some(Ok(SomeRtcpPacket::RtcpFbPliPacket(_)))
+        ));
+        assert!(matches!(iter.next(), Some(Err(_))));
+        assert!(iter.next().is_none());
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let mut iter = RtcpPacketIter::new(&mut cursor);
+        assert_eq!(iter.by_ref().count(), 1);
+        assert_eq!(iter.warnings().len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_packet_type() {
+        #[rustfmt::skip]
+        let data = vec![
+            // v=2, pt 210, length 1
+            0x80, 0xD2, 0x00, 0x01,
+            0xDE, 0xAD, 0xBE, 0xEF,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        match parse_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::UnknownRtcpPacket { header, payload } => {
+                assert_eq!(header.packet_type, 210);
+                assert_eq!(payload, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parser_registry() {
+        #[derive(Debug, PartialEq)]
+        struct MyPli {
+            media_source_ssrc: u32,
+        }
+
+        let mut registry = RtcpParserRegistry::new();
+        registry.register(
+            RtcpFbPsPacket::PT,
+            Some(RtcpFbPliPacket::FMT),
+            |_, payload| {
+                Ok(Box::new(MyPli {
+                    media_source_ssrc: u32::from_be_bytes(payload[4..8].try_into()?),
+                }))
+            },
+        );
+        let context = RtcpParseContext {
+            parser_registry: Some(Arc::new(registry)),
+            ..Default::default()
+        };
+
+        let mut data = PLI.to_vec();
+        data.extend(RR);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let (SomeRtcpPacket::CompoundRtcpPacket(packets), _) =
+            parse_rtcp_packet_with_context(&mut cursor, &context).unwrap()
+        else {
+            panic!("Expected compound packet");
+        };
+        match &packets[0] {
+            SomeRtcpPacket::CustomRtcpPacket { packet, .. } => assert_eq!(
+                packet.downcast_ref::<MyPli>(),
+                Some(&MyPli {
+                    media_source_ssrc: 2
+                })
+            ),
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+        assert!(matches!(packets[1], SomeRtcpPacket::RtcpRrPacket(_)));
+    }
+
+    #[test]
+    fn test_write_synced_packet() {
+        // The headers are stale: neither has the right count or length
+        let mut packet = SomeRtcpPacket::CompoundRtcpPacket(vec![
+            SomeRtcpPacket::RtcpByePacket(RtcpByePacket {
+                header: empty_header(),
+                ssrcs: vec![1, 2],
+                reason: None,
+            }),
+            SomeRtcpPacket::RtcpByePacket(RtcpByePacket {
+                header: empty_header(),
+                ssrcs: vec![3],
+                reason: None,
+            }),
+        ]);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_synced_rtcp_packet(&mut cursor, &mut packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut cursor = BitCursor::new(cursor.into_inner());
+        match parse_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::CompoundRtcpPacket(packets) => {
+                assert_eq!(packets.len(), 2);
+                match &packets[1] {
+                    SomeRtcpPacket::RtcpByePacket(bye) => assert_eq!(bye.ssrcs, vec![3]),
+                    p => panic!("Unexpected packet: {p:?}"),
+                }
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_padded_packet() {
+        #[rustfmt::skip]
+        let data = [
+            // v=2, p=1, fmt 1, pt 206, length 3
+            0xA1, 0xCE, 0x00, 0x03,
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // media source ssrc
+            0x00, 0x00, 0x00, 0x02,
+            // padding
+            0x00, 0x00, 0x00, 0x04,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        match parse_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::RtcpFbPliPacket(pli) => {
+                assert!(!pli.header.has_padding);
+                assert_eq!(pli.header.length_field, 2);
+                assert_eq!(pli.fb_header.media_source_ssrc, 2);
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_padded_packet_roundtrip() {
+        #[rustfmt::skip]
+        let data = [
+            0xA1, 0xCE, 0x00, 0x03,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x04,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        let mut packet = parse_rtcp_packet(&mut cursor).unwrap();
+        packet.sync().unwrap();
+        assert!(!packet.header().unwrap().has_padding);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 12]));
+        write_some_rtcp_packet(&mut cursor, &packet).unwrap();
+        let written = cursor.into_inner().into_vec();
+        // The padding bit is cleared and the length no longer includes the padding
+        assert_eq!(written[..4], [0x81, 0xCE, 0x00, 0x02]);
+        assert_eq!(written[4..], data[4..12]);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(written));
+        match parse_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::RtcpFbPliPacket(pli) => {
+                assert!(!pli.header.has_padding);
+                assert_eq!(pli.fb_header.sender_ssrc, 1);
+                assert_eq!(pli.fb_header.media_source_ssrc, 2);
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    #[test]
+    fn test_padded_packet_write_without_sync() {
+        #[rustfmt::skip]
+        let data = [
+            // v=2, p=1, rc 0, pt 201, length 2
+            0xA0, 0xC9, 0x00, 0x02,
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // padding
+            0x00, 0x00, 0x00, 0x04,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        let packet = parse_rtcp_packet(&mut cursor).unwrap();
+        assert_eq!(packet.length_bytes(), 8);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 8]));
+        write_some_rtcp_packet(&mut cursor, &packet).unwrap();
+        assert_eq!(
+            cursor.into_inner().into_vec(),
+            vec![0x80, 0xC9, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01]
+        );
+
+        #[rustfmt::skip]
+        let data = [
+            // v=2, p=1, pt 210, length 3
+            0xA0, 0xD2, 0x00, 0x03,
+            0x01, 0x02, 0x03, 0x04,
+            // padding
+            0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x08,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        let packet = parse_rtcp_packet(&mut cursor).unwrap();
+        assert!(
+            matches!(&packet, SomeRtcpPacket::UnknownRtcpPacket { payload, .. } if payload == &[1, 2, 3, 4])
+        );
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 8]));
+        write_some_rtcp_packet(&mut cursor, &packet).unwrap();
+        assert_eq!(
+            cursor.into_inner().into_vec(),
+            vec![0x80, 0xD2, 0x00, 0x01, 0x01, 0x02, 0x03, 0x04]
+        );
+    }
+
+    #[test]
+    fn test_unaligned_padding_length() {
+        #[rustfmt::skip]
+        let data = [
+            // v=2, p=1, pt 210, length 2
+            0xA0, 0xD2, 0x00, 0x02,
+            0x01, 0x02, 0x03, 0x04,
+            0x05, 0x06, 0x00, 0x02,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        let (packet, warnings) =
+            parse_rtcp_packet_with_context(&mut cursor, &RtcpParseContext::default()).unwrap();
+        assert_eq!(
+            warnings,
+            vec![RtpParseError::invalid_field(
+                "padding length",
+                "a multiple of 4",
+                2
+            )]
+        );
+        // None of the padding is stripped
+        assert!(
+            matches!(&packet, SomeRtcpPacket::UnknownRtcpPacket { header, payload } if header.length_field == 2 && payload.len() == 8)
+        );
+
+        let context = RtcpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        assert!(parse_rtcp_packet_with_context(&mut cursor, &context).is_err());
+    }
+
+    #[test]
+    fn test_invalid_padding_length() {
+        #[rustfmt::skip]
+        let data = [
+            // v=2, p=1, fmt 1, pt 206, length 2
+            0xA1, 0xCE, 0x00, 0x02,
+            0x00, 0x00, 0x00, 0x01,
+            // padding length is larger than the payload
+            0x00, 0x00, 0x00, 0x10,
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&data));
+        assert!(parse_rtcp_packet(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_write_padded_packet() {
+        let mut packet = SomeRtcpPacket::RtcpByePacket(RtcpByePacket {
+            header: empty_header(),
+            ssrcs: vec![1, 2],
+            reason: None,
+        });
+        packet.sync().unwrap();
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 20]));
+        write_padded_rtcp_packet(&mut cursor, &mut packet, 8).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut cursor = BitCursor::new(cursor.into_inner());
+        match parse_rtcp_packet(&mut cursor).unwrap() {
+            SomeRtcpPacket::RtcpByePacket(bye) => {
+                assert!(!bye.header.has_padding);
+                assert_eq!(bye.ssrcs, vec![1, 2]);
+                assert!(bye.reason.is_none());
+            }
+            p => panic!("Unexpected packet: {p:?}"),
+        }
+    }
+
+    /// Generated packets should survive being synced, written and parsed back (in strict mode),
+    /// and be written back out the same way.
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_round_trip() {
+        use arbitrary::{Arbitrary, Unstructured};
+
+        let context = RtcpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let write = |packet: &SomeRtcpPacket| {
+            let mut cursor =
+                BitCursor::new(BitVec::<u8, Msb0>::repeat(false, packet.length_bytes() * 8));
+            write_some_rtcp_packet(&mut cursor, packet).unwrap();
+            cursor.into_inner().into_vec()
+        };
+        // A simple xorshift generator, so the inputs are the same on every run
+        let mut state = 0x2545F4914F6CDD1Du64;
+        let mut failures = Vec::new();
+        for _ in 0..2000 {
+            let input = (0..1024)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<_>>();
+            let Ok(mut packet) = SomeRtcpPacket::arbitrary(&mut Unstructured::new(&input)) else {
+                continue;
+            };
+            if let Err(e) = packet.sync() {
+                failures.push(format!("sync {packet:?}: {e:#}"));
+                continue;
+            }
+            let data = write(&packet);
+            let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+            match parse_rtcp_packet_with_context(&mut cursor, &context) {
+                Ok((read_packet, warnings)) => {
+                    if !warnings.is_empty() || write(&read_packet) != data {
+                        failures.push(format!("mismatch {packet:?} -> {read_packet:?}"));
+                    }
+                }
+                Err(e) => failures.push(format!("parse {packet:?}: {e:#}")),
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "{} packets didn't round trip, the first was:\n{}",
+            failures.len(),
+            failures[0]
+        );
+    }
+}
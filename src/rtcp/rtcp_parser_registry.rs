@@ -0,0 +1,55 @@
+use std::{any::Any, collections::HashMap, fmt::Debug};
+
+use anyhow::Result;
+use bit_cursor::nsw_types::u5;
+
+use super::rtcp_header::RtcpHeader;
+
+/// A parser for an application-specific RTCP packet.  It's given the packet's header and its
+/// payload (everything after the header) and returns whatever type it parses the packet into.
+pub type RtcpCustomParser =
+    Box<dyn Fn(&RtcpHeader, &[u8]) -> Result<Box<dyn Any + Send + Sync>> + Send + Sync>;
+
+/// Custom parsers for RTCP packets (e.g. proprietary APP or feedback formats) keyed by packet
+/// type and, optionally, fmt.  A registry can be given to the parser via
+/// [`RtcpParseContext`](super::rtcp_packet::RtcpParseContext), in which case it's consulted before
+/// any of the built-in parsers.  Packets parsed by a custom parser are returned as
+/// [`SomeRtcpPacket::CustomRtcpPacket`](super::rtcp_packet::SomeRtcpPacket::CustomRtcpPacket).
+#[derive(Default)]
+pub struct RtcpParserRegistry {
+    parsers: HashMap<(u8, Option<u8>), RtcpCustomParser>,
+}
+
+impl RtcpParserRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a parser for packets with the given packet type.  If `fmt` is given, the parser
+    /// only handles packets whose fmt (report count) field matches; otherwise it handles every
+    /// packet with that type.  A parser registered for a specific fmt takes priority over one
+    /// registered for the whole packet type.  Registering for a key which already has a parser
+    /// replaces it.
+    pub fn register<F>(&mut self, packet_type: u8, fmt: Option<u5>, parser: F)
+    where
+        F: Fn(&RtcpHeader, &[u8]) -> Result<Box<dyn Any + Send + Sync>> + Send + Sync + 'static,
+    {
+        self.parsers
+            .insert((packet_type, fmt.map(u8::from)), Box::new(parser));
+    }
+
+    /// Find the parser, if any, which should handle the packet with the given header.
+    pub fn get(&self, header: &RtcpHeader) -> Option<&RtcpCustomParser> {
+        self.parsers
+            .get(&(header.packet_type, Some(header.report_count.into())))
+            .or_else(|| self.parsers.get(&(header.packet_type, None)))
+    }
+}
+
+impl Debug for RtcpParserRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RtcpParserRegistry")
+            .field("parsers", &self.parsers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
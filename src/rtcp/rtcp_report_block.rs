@@ -4,6 +4,11 @@ use bit_cursor::{
     bit_write_exts::BitWriteExts, byte_order::NetworkOrder, nsw_types::*,
 };
 
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes,
+};
+
 /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1
 ///         0                   1                   2                   3
 ///         0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
@@ -41,9 +46,13 @@ use bit_cursor::{
 ///        |                  profile-specific extensions                  |
 ///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpReportBlock {
     pub ssrc: u32,
     pub fraction_lost: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u24"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u24))]
     pub cumulative_lost: u24,
     pub extended_highest_seq_num: u32,
     pub interarrival_jitter: u32,
@@ -51,6 +60,16 @@ pub struct RtcpReportBlock {
     pub delay_since_last_sr: u32,
 }
 
+impl RtcpReportBlock {
+    pub const SIZE_BYTES: usize = 24;
+}
+
+impl LengthBytes for RtcpReportBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
 pub fn read_rtcp_report_block<R: BitRead>(buf: &mut R) -> Result<RtcpReportBlock> {
     Ok(RtcpReportBlock {
         ssrc: buf.read_u32::<NetworkOrder>().context("ssrc")?,
@@ -90,3 +109,18 @@ pub fn write_rtcp_report_block<W: BitWrite>(
 
     Ok(())
 }
+
+impl PrettyPrint for RtcpReportBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("SSRC", Ssrc(self.ssrc))?;
+        tree.field("Fraction lost", format_args!("{}/256", self.fraction_lost))?;
+        tree.field("Cumulative lost", u32::from(self.cumulative_lost))?;
+        tree.field(
+            "Extended highest sequence number",
+            self.extended_highest_seq_num,
+        )?;
+        tree.field("Interarrival jitter", self.interarrival_jitter)?;
+        tree.field("Last SR timestamp", self.last_sr_timestamp)?;
+        tree.field("Delay since last SR", self.delay_since_last_sr)
+    }
+}
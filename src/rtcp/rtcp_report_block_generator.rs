@@ -0,0 +1,287 @@
+use std::{collections::BTreeMap, time::Instant};
+
+use anyhow::{Context, Result};
+use bit_cursor::nsw_types::{u2, u24, u5};
+
+use crate::{
+    ntp::{duration_to_compact_ntp, NtpTimestamp},
+    rtp::{rtp_packet::RtpPacket, seq_num::SeqNumUnwrapper},
+};
+
+use super::{
+    rtcp_header::RtcpHeader, rtcp_jitter_estimator::JitterEstimator,
+    rtcp_report_block::RtcpReportBlock, rtcp_rr::RtcpRrPacket, rtcp_sr::RtcpSrPacket,
+};
+
+/// The most report blocks which fit in a single RR (the report count field is 5 bits)
+const MAX_REPORT_BLOCKS_PER_PACKET: usize = 31;
+/// The range of the signed 24 bit cumulative lost field
+const MIN_CUMULATIVE_LOST: i64 = -0x80_0000;
+const MAX_CUMULATIVE_LOST: i64 = 0x7F_FFFF;
+
+/// The receive statistics of a single stream
+/// (https://datatracker.ietf.org/doc/html/rfc3550#appendix-A.3)
+#[derive(Debug, Default)]
+struct ReceiveStats {
+    seq_num_unwrapper: SeqNumUnwrapper,
+    base_seq_num: u64,
+    num_received: u64,
+    jitter_estimator: JitterEstimator,
+    /// The number of packets expected and received as of the last report
+    expected_prior: u64,
+    received_prior: u64,
+    /// The compact NTP timestamp of the last SR from this stream's sender and when it arrived
+    last_sr: Option<(u32, Instant)>,
+}
+
+impl ReceiveStats {
+    fn on_packet(
+        &mut self,
+        seq_num: u16,
+        rtp_timestamp: u32,
+        arrival_time: Instant,
+        clock_rate: u32,
+    ) {
+        let extended_seq_num = self.seq_num_unwrapper.unwrap(seq_num);
+        if self.num_received == 0 {
+            self.base_seq_num = extended_seq_num;
+        }
+        self.base_seq_num = self.base_seq_num.min(extended_seq_num);
+        self.num_received += 1;
+        self.jitter_estimator
+            .on_packet(rtp_timestamp, arrival_time, clock_rate);
+    }
+
+    fn report_block(&mut self, ssrc: u32, now: Instant) -> Option<RtcpReportBlock> {
+        let highest = self.seq_num_unwrapper.highest()?;
+        let expected = highest - self.base_seq_num + 1;
+        let cumulative_lost = (expected as i64 - self.num_received as i64)
+            .clamp(MIN_CUMULATIVE_LOST, MAX_CUMULATIVE_LOST);
+
+        let expected_interval = expected - self.expected_prior;
+        let received_interval = self.num_received - self.received_prior;
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        self.expected_prior = expected;
+        self.received_prior = self.num_received;
+        let fraction_lost = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64) as u8
+        };
+
+        let (last_sr_timestamp, delay_since_last_sr) = match self.last_sr {
+            Some((last_sr_timestamp, arrival_time)) => (
+                last_sr_timestamp,
+                duration_to_compact_ntp(now.saturating_duration_since(arrival_time)),
+            ),
+            None => (0, 0),
+        };
+
+        Some(RtcpReportBlock {
+            ssrc,
+            fraction_lost,
+            // The field is a 24 bit two's complement value
+            cumulative_lost: u24::new(cumulative_lost as u32 & 0xFF_FFFF),
+            extended_highest_seq_num: highest as u32,
+            interarrival_jitter: self.jitter_estimator.jitter(),
+            last_sr_timestamp,
+            delay_since_last_sr,
+        })
+    }
+}
+
+/// Tracks the receive statistics of any number of streams and generates the reception report
+/// blocks describing them (https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1): the
+/// fraction of packets lost since the previous report, the cumulative number lost, the extended
+/// highest sequence number, the interarrival jitter and the timing of the last SR.
+#[derive(Debug)]
+pub struct ReportBlockGenerator {
+    sender_ssrc: u32,
+    streams: BTreeMap<u32, ReceiveStats>,
+}
+
+impl ReportBlockGenerator {
+    pub fn new(sender_ssrc: u32) -> Self {
+        Self {
+            sender_ssrc,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    /// Record a received packet from the given stream, which has the given clock rate (in Hz).
+    /// Retransmissions should not be recorded.
+    pub fn on_packet_received(
+        &mut self,
+        ssrc: u32,
+        seq_num: u16,
+        rtp_timestamp: u32,
+        arrival_time: Instant,
+        clock_rate: u32,
+    ) {
+        self.streams.entry(ssrc).or_default().on_packet(
+            seq_num,
+            rtp_timestamp,
+            arrival_time,
+            clock_rate,
+        );
+    }
+
+    /// Like [`ReportBlockGenerator::on_packet_received`], taking the fields from the packet
+    pub fn on_rtp_packet(&mut self, packet: &RtpPacket, arrival_time: Instant, clock_rate: u32) {
+        self.on_packet_received(
+            packet.ssrc(),
+            packet.seq_num(),
+            packet.timestamp(),
+            arrival_time,
+            clock_rate,
+        );
+    }
+
+    /// Record an SR from the sender of the given stream which arrived at the given time, so the
+    /// next report block for it includes the LSR and DLSR fields.
+    pub fn on_sender_report(
+        &mut self,
+        ssrc: u32,
+        ntp_timestamp: NtpTimestamp,
+        arrival_time: Instant,
+    ) {
+        if let Some(stream) = self.streams.get_mut(&ssrc) {
+            stream.last_sr = Some((ntp_timestamp.compact(), arrival_time));
+        }
+    }
+
+    /// Like [`ReportBlockGenerator::on_sender_report`], taking the fields from the packet
+    pub fn on_sr_packet(&mut self, sr: &RtcpSrPacket, arrival_time: Instant) {
+        self.on_sender_report(sr.sender_ssrc, sr.sender_info.ntp_timestamp(), arrival_time);
+    }
+
+    /// Stop reporting on the given stream
+    pub fn remove_stream(&mut self, ssrc: u32) {
+        self.streams.remove(&ssrc);
+    }
+
+    /// Generate a report block for every stream which has received packets.  The fraction lost
+    /// in each is relative to the previous call.
+    pub fn report_blocks(&mut self, now: Instant) -> Vec<RtcpReportBlock> {
+        self.streams
+            .iter_mut()
+            .filter_map(|(ssrc, stream)| stream.report_block(*ssrc, now))
+            .collect()
+    }
+
+    /// Generate synced RRs containing the report blocks for every stream (see
+    /// [`ReportBlockGenerator::report_blocks`]), split across multiple packets if there are more
+    /// than fit in one.  If there are no streams to report on, a single RR with no report blocks
+    /// is returned, since every compound RTCP packet must start with an SR or RR.
+    pub fn build_rr_packets(&mut self, now: Instant) -> Result<Vec<RtcpRrPacket>> {
+        let mut report_blocks = self.report_blocks(now).into_iter();
+        let mut rrs = Vec::new();
+        loop {
+            let report_blocks = report_blocks
+                .by_ref()
+                .take(MAX_REPORT_BLOCKS_PER_PACKET)
+                .collect::<Vec<_>>();
+            if report_blocks.is_empty() && !rrs.is_empty() {
+                break;
+            }
+            let mut rr = RtcpRrPacket {
+                header: RtcpHeader {
+                    version: u2::new(2),
+                    has_padding: false,
+                    report_count: u5::new(0),
+                    packet_type: RtcpRrPacket::PT,
+                    length_field: 0,
+                },
+                sender_ssrc: self.sender_ssrc,
+                report_blocks,
+                profile_extensions: Vec::new(),
+            };
+            rr.sync().with_context(|| format!("rr {}", rrs.len()))?;
+            rrs.push(rr);
+        }
+
+        Ok(rrs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_report_blocks() {
+        let mut generator = ReportBlockGenerator::new(1);
+        let start = Instant::now();
+        // Sequence numbers 65534-5, with 1 and 2 lost and 3 arriving before 0
+        for (i, seq_num) in [65534u16, 65535, 3, 0, 4, 5].into_iter().enumerate() {
+            generator.on_packet_received(
+                10,
+                seq_num,
+                seq_num as u32 * 960,
+                start + Duration::from_millis(20 * i as u64),
+                48_000,
+            );
+        }
+        generator.on_sender_report(
+            10,
+            NtpTimestamp::from_parts(0x1234_5678, 0x9ABC_DEF0),
+            start,
+        );
+
+        let now = start + Duration::from_millis(1500);
+        let report_blocks = generator.report_blocks(now);
+        assert_eq!(report_blocks.len(), 1);
+        let report_block = &report_blocks[0];
+        assert_eq!(report_block.ssrc, 10);
+        assert_eq!(report_block.extended_highest_seq_num, 0x1_0005);
+        assert_eq!(report_block.cumulative_lost, u24::new(2));
+        // 2 of 8
+        assert_eq!(report_block.fraction_lost, 64);
+        assert_eq!(report_block.last_sr_timestamp, 0x5678_9ABC);
+        assert_eq!(report_block.delay_since_last_sr, 0x0001_8000);
+
+        // The next report only covers the packets since this one
+        for seq_num in 6..10 {
+            generator.on_packet_received(10, seq_num, 0, now, 48_000);
+        }
+        let report_block = &generator.report_blocks(now)[0];
+        assert_eq!(report_block.fraction_lost, 0);
+        assert_eq!(report_block.cumulative_lost, u24::new(2));
+    }
+
+    #[test]
+    fn test_duplicates_give_negative_loss() {
+        let mut generator = ReportBlockGenerator::new(1);
+        let now = Instant::now();
+        for seq_num in [1, 2, 2, 3] {
+            generator.on_packet_received(10, seq_num, 0, now, 90_000);
+        }
+        let report_block = &generator.report_blocks(now)[0];
+        assert_eq!(report_block.cumulative_lost, u24::new(0xFF_FFFF));
+        assert_eq!(report_block.fraction_lost, 0);
+    }
+
+    #[test]
+    fn test_build_rr_packets() {
+        let mut generator = ReportBlockGenerator::new(1);
+        let now = Instant::now();
+        let rrs = generator.build_rr_packets(now).unwrap();
+        assert_eq!(rrs.len(), 1);
+        assert!(rrs[0].report_blocks.is_empty());
+
+        for ssrc in 0..40 {
+            generator.on_packet_received(ssrc, 0, 0, now, 90_000);
+        }
+        let rrs = generator.build_rr_packets(now).unwrap();
+        assert_eq!(rrs.len(), 2);
+        assert_eq!(rrs[0].report_blocks.len(), 31);
+        assert_eq!(rrs[0].header.report_count, u5::new(31));
+        assert_eq!(rrs[1].report_blocks.len(), 9);
+        assert_eq!(rrs[1].sender_ssrc, 1);
+
+        generator.remove_stream(0);
+        assert_eq!(generator.report_blocks(now).len(), 39);
+    }
+}
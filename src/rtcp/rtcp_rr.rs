@@ -1,13 +1,17 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
-    bit_read::BitRead, bit_read_exts::BitReadExts, bit_write::BitWrite,
-    bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    bit_read_exts::BitReadExts, bit_write::BitWrite, bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
 };
 
-use crate::rtcp::rtcp_report_block::read_rtcp_report_block;
+use crate::{
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
+    rtcp::rtcp_report_block::read_rtcp_report_block,
+    LengthBytes, PacketBuffer,
+};
 
 use super::{
-    rtcp_header::{write_rtcp_header, RtcpHeader},
+    rtcp_header::{report_count_from_len, write_rtcp_header, RtcpHeader},
     rtcp_report_block::{write_rtcp_report_block, RtcpReportBlock},
 };
 
@@ -39,27 +43,72 @@ use super::{
 ///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 ///
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpRrPacket {
     pub header: RtcpHeader,
     pub sender_ssrc: u32,
     pub report_blocks: Vec<RtcpReportBlock>,
+    /// Profile-specific extension data following the report blocks.  Empty if there is none.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::words))]
+    pub profile_extensions: Vec<u8>,
 }
 
 impl RtcpRrPacket {
     pub const PT: u8 = 201;
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("RR payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, report count and length) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        if !self.profile_extensions.len().is_multiple_of(4) {
+            bail!(
+                "RR profile extensions must be a multiple of 4 bytes, was {} bytes",
+                self.profile_extensions.len()
+            );
+        }
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = Self::PT;
+        self.header.report_count =
+            report_count_from_len(self.report_blocks.len()).context("report count")?;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
 }
 
-pub fn read_rtcp_rr<R: BitRead>(buf: &mut R, header: RtcpHeader) -> Result<RtcpRrPacket> {
+impl LengthBytes for RtcpRrPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + 4
+            + self.report_blocks.len() * RtcpReportBlock::SIZE_BYTES
+            + self.profile_extensions.len()
+    }
+}
+
+pub fn read_rtcp_rr<B: PacketBuffer>(buf: &mut B, header: RtcpHeader) -> Result<RtcpRrPacket> {
     let sender_ssrc = buf.read_u32::<NetworkOrder>().context("sender ssrc")?;
     let report_blocks = (0u32..header.report_count.into())
         .map(|i| read_rtcp_report_block(buf).with_context(|| format!("report block {i}")))
         .collect::<Result<Vec<RtcpReportBlock>>>()
         .context("report blocks")?;
+    let mut profile_extensions = vec![0u8; buf.bytes_remaining()];
+    buf.read_exact(&mut profile_extensions)
+        .context("profile extensions")?;
 
     Ok(RtcpRrPacket {
         header,
         sender_ssrc,
         report_blocks,
+        profile_extensions,
     })
 }
 
@@ -76,5 +125,84 @@ pub fn write_rtcp_rr<W: BitWrite>(buf: &mut W, packet: &RtcpRrPacket) -> Result<
         })
         .collect::<Result<Vec<()>>>()
         .context("report blocks")?;
+    buf.write_all(&packet.profile_extensions)
+        .context("profile extensions")?;
+
     Ok(())
 }
+
+impl PrettyPrint for RtcpRrPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Receiver Report", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Sender SSRC", Ssrc(self.sender_ssrc))?;
+            for (i, report_block) in self.report_blocks.iter().enumerate() {
+                tree.subtree(format_args!("Report block {}", i + 1), |tree| {
+                    report_block.pretty_print(tree)
+                })?;
+            }
+            if !self.profile_extensions.is_empty() {
+                tree.field(
+                    "Profile-specific extensions",
+                    HexBytes(&self.profile_extensions),
+                )?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{bit_cursor::BitCursor, nsw_types::*};
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_header::read_rtcp_header;
+
+    use super::*;
+
+    #[test]
+    fn test_profile_extensions_round_trip() {
+        let mut rr = RtcpRrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            sender_ssrc: 42,
+            report_blocks: Vec::new(),
+            profile_extensions: vec![0xDE, 0xAD, 0xBE, 0xEF],
+        };
+        rr.sync().unwrap();
+        assert_eq!(rr.header.length_field, 2);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 12]));
+        write_rtcp_rr(&mut cursor, &rr).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut cursor).unwrap();
+        let rr = read_rtcp_rr(&mut cursor, header).unwrap();
+        assert_eq!(rr.sender_ssrc, 42);
+        assert_eq!(rr.profile_extensions, vec![0xDE, 0xAD, 0xBE, 0xEF]);
+    }
+
+    #[test]
+    fn test_sync_unaligned_profile_extensions() {
+        let mut rr = RtcpRrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            sender_ssrc: 42,
+            report_blocks: Vec::new(),
+            profile_extensions: vec![0xDE, 0xAD],
+        };
+        assert!(rr.sync().is_err());
+    }
+}
@@ -0,0 +1,303 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, Instant},
+};
+
+/// The fraction of the session bandwidth allocated to RTCP
+const RTCP_BANDWIDTH_FRACTION: f64 = 0.05;
+/// The fraction of the RTCP bandwidth allocated to senders, when they're a minority
+const SENDER_BANDWIDTH_FRACTION: f64 = 0.25;
+const RECEIVER_BANDWIDTH_FRACTION: f64 = 1.0 - SENDER_BANDWIDTH_FRACTION;
+/// The default minimum interval between reports
+const DEFAULT_MIN_INTERVAL: Duration = Duration::from_secs(5);
+/// Compensates for timer reconsideration converging to a value below the intended average
+/// (e - 3/2)
+const COMPENSATION: f64 = std::f64::consts::E - 1.5;
+/// The assumed size (in bytes, including UDP/IP headers) of the first compound packet, before
+/// any have been sent or received
+const INITIAL_AVG_RTCP_SIZE: f64 = 100.0;
+
+/// Schedules the transmission of compound RTCP packets according to the rules in
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.2 and
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.3: RTCP gets 5% of the session
+/// bandwidth (a quarter of which is reserved for senders when they're a minority), reports are
+/// at least a minimum interval apart (halved before the first one), intervals are randomized and
+/// the timer is reconsidered when it expires and when members leave.
+///
+/// The application tells the scheduler about the session (its members and senders, RTCP packets
+/// sent and received, whether it's sending RTP) and asks it, once
+/// [`RtcpScheduler::next_transmission_time`] has been reached, whether it should send a report.
+#[derive(Debug)]
+pub struct RtcpScheduler {
+    /// The RTCP bandwidth, in bytes per second
+    rtcp_bandwidth: f64,
+    min_interval: Duration,
+    /// The number of members (including ourselves) and senders
+    members: usize,
+    senders: usize,
+    /// The number of members when the next transmission time was last computed
+    pmembers: usize,
+    /// Whether RTP was sent since the previous report, and between the 2nd previous report and
+    /// the previous report
+    sent_since_previous_report: bool,
+    sent_before_previous_report: bool,
+    /// The average size of compound packets sent and received, in bytes
+    avg_rtcp_size: f64,
+    /// Whether no report has been sent yet
+    initial: bool,
+    /// The time the last report was sent
+    tp: Instant,
+    /// The next scheduled transmission time
+    tn: Instant,
+    rng_state: u64,
+}
+
+impl RtcpScheduler {
+    /// Create a scheduler for a session with the given bandwidth (in bits per second, e.g. from
+    /// the SDP `b=AS` line) which was joined at the given time
+    pub fn new(session_bandwidth_bps: u32, now: Instant) -> Self {
+        let seed = RandomState::new().build_hasher().finish();
+        let mut scheduler = Self {
+            rtcp_bandwidth: session_bandwidth_bps as f64 * RTCP_BANDWIDTH_FRACTION / 8.0,
+            min_interval: DEFAULT_MIN_INTERVAL,
+            members: 1,
+            senders: 0,
+            pmembers: 1,
+            sent_since_previous_report: false,
+            sent_before_previous_report: false,
+            avg_rtcp_size: INITIAL_AVG_RTCP_SIZE,
+            initial: true,
+            tp: now,
+            tn: now,
+            rng_state: seed | 1,
+        };
+        scheduler.tn = now + scheduler.calculate_interval();
+
+        scheduler
+    }
+
+    /// Use the given minimum interval between reports instead of the default 5 seconds (e.g. the
+    /// reduced minimum of 360 divided by the session bandwidth in kbps).  The first report is
+    /// rescheduled.
+    pub fn with_min_interval(mut self, min_interval: Duration) -> Self {
+        self.min_interval = min_interval;
+        self.tn = self.tp + self.calculate_interval();
+        self
+    }
+
+    /// Seed the randomization of intervals, so they're reproducible.  The first report is
+    /// rescheduled.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.rng_state = seed | 1;
+        self.tn = self.tp + self.calculate_interval();
+        self
+    }
+
+    /// The time at which the scheduler should next be asked whether to send a report
+    pub fn next_transmission_time(&self) -> Instant {
+        self.tn
+    }
+
+    pub fn members(&self) -> usize {
+        self.members
+    }
+
+    pub fn senders(&self) -> usize {
+        self.senders
+    }
+
+    /// Whether we count as a sender, i.e. have sent RTP since the 2nd previous report
+    pub fn we_sent(&self) -> bool {
+        self.sent_since_previous_report || self.sent_before_previous_report
+    }
+
+    /// Update the number of session members (including ourselves) and senders.  If the number of
+    /// members dropped, e.g. due to BYEs or timeouts, the next transmission is brought forward
+    /// ("reverse reconsideration", https://datatracker.ietf.org/doc/html/rfc3550#section-6.3.4).
+    pub fn set_members(&mut self, members: usize, senders: usize, now: Instant) {
+        self.members = members.max(1);
+        self.senders = senders.min(self.members);
+        if self.members < self.pmembers {
+            let ratio = self.members as f64 / self.pmembers as f64;
+            self.tn = now + self.tn.saturating_duration_since(now).mul_f64(ratio);
+            self.tp = now
+                .checked_sub(now.saturating_duration_since(self.tp).mul_f64(ratio))
+                .unwrap_or(self.tp);
+            self.pmembers = self.members;
+        }
+    }
+
+    /// Record that we sent an RTP packet
+    pub fn on_rtp_sent(&mut self) {
+        self.sent_since_previous_report = true;
+    }
+
+    /// Record a received compound RTCP packet of the given size in bytes (including UDP/IP
+    /// headers)
+    pub fn on_rtcp_received(&mut self, size_bytes: usize) {
+        self.update_avg_rtcp_size(size_bytes);
+    }
+
+    /// Whether a report should be sent now.  If the next transmission time has been reached,
+    /// the interval is recalculated with the current session state ("timer reconsideration",
+    /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.3.6): if it's still due, true is
+    /// returned and [`RtcpScheduler::on_rtcp_sent`] should be called once it's sent, otherwise
+    /// the next transmission time is pushed back.
+    pub fn should_send(&mut self, now: Instant) -> bool {
+        if now < self.tn {
+            return false;
+        }
+        let next = self.tp + self.calculate_interval();
+        if next <= now {
+            true
+        } else {
+            self.tn = next;
+            self.pmembers = self.members;
+            false
+        }
+    }
+
+    /// Record that we sent a compound RTCP packet of the given size in bytes (including UDP/IP
+    /// headers) and schedule the next one
+    pub fn on_rtcp_sent(&mut self, size_bytes: usize, now: Instant) {
+        self.update_avg_rtcp_size(size_bytes);
+        self.sent_before_previous_report = self.sent_since_previous_report;
+        self.sent_since_previous_report = false;
+        self.initial = false;
+        self.tp = now;
+        self.tn = now + self.calculate_interval();
+        self.pmembers = self.members;
+    }
+
+    fn update_avg_rtcp_size(&mut self, size_bytes: usize) {
+        self.avg_rtcp_size += (size_bytes as f64 - self.avg_rtcp_size) / 16.0;
+    }
+
+    /// The randomized interval until the next report, given the current session state
+    /// (https://datatracker.ietf.org/doc/html/rfc3550#appendix-A.7)
+    fn calculate_interval(&mut self) -> Duration {
+        let min_interval = if self.initial {
+            self.min_interval / 2
+        } else {
+            self.min_interval
+        };
+        let mut rtcp_bandwidth = self.rtcp_bandwidth;
+        let mut n = self.members;
+        if self.senders as f64 <= self.members as f64 * SENDER_BANDWIDTH_FRACTION {
+            if self.we_sent() {
+                rtcp_bandwidth *= SENDER_BANDWIDTH_FRACTION;
+                n = self.senders;
+            } else {
+                rtcp_bandwidth *= RECEIVER_BANDWIDTH_FRACTION;
+                n -= self.senders;
+            }
+        }
+        let deterministic = if rtcp_bandwidth > 0.0 {
+            (self.avg_rtcp_size * n as f64 / rtcp_bandwidth).max(min_interval.as_secs_f64())
+        } else {
+            min_interval.as_secs_f64()
+        };
+
+        Duration::from_secs_f64(deterministic * (self.random() + 0.5) / COMPENSATION)
+    }
+
+    /// A pseudorandom number in [0, 1) (xorshift64*)
+    fn random(&mut self) -> f64 {
+        self.rng_state ^= self.rng_state >> 12;
+        self.rng_state ^= self.rng_state << 25;
+        self.rng_state ^= self.rng_state >> 27;
+        let value = self.rng_state.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether the given time is within the randomization range of the given interval after
+    /// the given start
+    fn in_range(time: Instant, start: Instant, interval_secs: f64) -> bool {
+        let elapsed = time.duration_since(start).as_secs_f64();
+        elapsed >= interval_secs * 0.5 / COMPENSATION
+            && elapsed < interval_secs * 1.5 / COMPENSATION
+    }
+
+    /// Wait for the scheduler to say a report is due, send one and return the time it was sent
+    fn send_next_report(scheduler: &mut RtcpScheduler) -> Instant {
+        loop {
+            let now = scheduler.next_transmission_time();
+            if scheduler.should_send(now) {
+                scheduler.on_rtcp_sent(100, now);
+                return now;
+            }
+        }
+    }
+
+    #[test]
+    fn test_initial_and_min_interval() {
+        let start = Instant::now();
+        let mut scheduler = RtcpScheduler::new(1_000_000, start).with_seed(42);
+        // Before the first report the minimum is halved
+        assert!(in_range(scheduler.next_transmission_time(), start, 2.5));
+        assert!(!scheduler.should_send(start));
+
+        let now = send_next_report(&mut scheduler);
+        assert!(in_range(scheduler.next_transmission_time(), now, 5.0));
+    }
+
+    #[test]
+    fn test_bandwidth_share() {
+        let start = Instant::now();
+        // 64kbps gives RTCP 400 bytes/s, 300 of which are for the receivers
+        let mut scheduler = RtcpScheduler::new(64_000, start)
+            .with_seed(7)
+            .with_min_interval(Duration::ZERO);
+        scheduler.set_members(1000, 10, start);
+        let now = send_next_report(&mut scheduler);
+        // 990 receivers * 100 bytes / 300 bytes/s
+        assert!(in_range(scheduler.next_transmission_time(), now, 330.0));
+
+        // As a sender, we share 100 bytes/s with the other senders
+        scheduler.on_rtp_sent();
+        assert!(scheduler.we_sent());
+        let now = send_next_report(&mut scheduler);
+        assert!(in_range(scheduler.next_transmission_time(), now, 10.0));
+        // We stop counting as a sender after the 2nd report without sending RTP
+        assert!(scheduler.we_sent());
+        send_next_report(&mut scheduler);
+        assert!(!scheduler.we_sent());
+    }
+
+    #[test]
+    fn test_timer_reconsideration() {
+        let start = Instant::now();
+        let mut scheduler = RtcpScheduler::new(64_000, start).with_seed(1);
+        let first = scheduler.next_transmission_time();
+        // Many members join before the timer expires, so the report is pushed back
+        scheduler.set_members(1000, 0, start);
+        assert!(!scheduler.should_send(first));
+        let next = scheduler.next_transmission_time();
+        assert!(next > first);
+        assert!(in_range(next, start, 1000.0 * 100.0 / 300.0));
+    }
+
+    #[test]
+    fn test_reverse_reconsideration() {
+        let start = Instant::now();
+        let mut scheduler = RtcpScheduler::new(64_000, start).with_seed(3);
+        scheduler.set_members(100, 0, start);
+        let now = send_next_report(&mut scheduler);
+        let tn = scheduler.next_transmission_time();
+
+        // Half the members leave, so the remaining wait is halved
+        scheduler.set_members(50, 0, now);
+        let expected = now + tn.duration_since(now) / 2;
+        let actual = scheduler.next_transmission_time();
+        let error = actual
+            .saturating_duration_since(expected)
+            .max(expected.saturating_duration_since(actual));
+        assert!(error < Duration::from_millis(1));
+    }
+}
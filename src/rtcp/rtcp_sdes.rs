@@ -1,13 +1,16 @@
 use std::str::from_utf8;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use bit_cursor::{
     bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
 };
 
-use crate::{util::consume_padding, PacketBuffer, PacketBufferMut};
+use crate::{
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
 
-use super::rtcp_header::{write_rtcp_header, RtcpHeader};
+use super::rtcp_header::{report_count_from_len, write_rtcp_header, RtcpHeader};
 
 /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.5
 ///         0                   1                   2                   3
@@ -36,6 +39,8 @@ use super::rtcp_header::{write_rtcp_header, RtcpHeader};
 ///     the P bit in the RTCP header.  A chunk with zero items (four null
 ///     octets) is valid but useless.
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpSdesPacket {
     pub header: RtcpHeader,
     pub chunks: Vec<SdesChunk>,
@@ -43,6 +48,38 @@ pub struct RtcpSdesPacket {
 
 impl RtcpSdesPacket {
     pub const PT: u8 = 202;
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("SDES payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, source count and length) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = Self::PT;
+        self.header.report_count =
+            report_count_from_len(self.chunks.len()).context("source count")?;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpSdesPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + self
+                .chunks
+                .iter()
+                .map(|chunk| chunk.length_bytes())
+                .sum::<usize>()
+    }
 }
 
 pub fn read_rtcp_sdes<B: PacketBuffer>(buf: &mut B, header: RtcpHeader) -> Result<RtcpSdesPacket> {
@@ -74,12 +111,45 @@ pub fn write_rtcp_sdes<B: PacketBufferMut>(buf: &mut B, rtcp_sdes: &RtcpSdesPack
 /// |      ID       |     length    | value                       ...
 /// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum SdesItem {
     Empty,
     Cname(String),
     Unknown { item_type: u8, data: Vec<u8> },
 }
 
+/// Generates CNAME and unknown items with up to 255 bytes of data.  The empty item only appears
+/// at the end of a chunk, so it isn't generated.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for SdesItem {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.arbitrary()? {
+            let mut cname: String = u.arbitrary()?;
+            while cname.len() > u8::MAX as usize {
+                cname.pop();
+            }
+            Ok(SdesItem::Cname(cname))
+        } else {
+            let data_len = u.int_in_range(0..=u8::MAX as usize)?;
+            Ok(SdesItem::Unknown {
+                item_type: u.int_in_range(2..=u8::MAX)?,
+                data: u.bytes(data_len)?.to_vec(),
+            })
+        }
+    }
+}
+
+impl LengthBytes for SdesItem {
+    /// The length of this item when written, including its id and length fields
+    fn length_bytes(&self) -> usize {
+        match self {
+            SdesItem::Empty => 1,
+            SdesItem::Cname(value) => 2 + value.len(),
+            SdesItem::Unknown { data, .. } => 2 + data.len(),
+        }
+    }
+}
+
 pub fn read_sdes_item<R: PacketBuffer>(buf: &mut R) -> Result<SdesItem> {
     let id = buf.read_u8().context("id")?;
     if id == 0 {
@@ -110,6 +180,7 @@ pub fn write_sdes_item<W: PacketBufferMut>(buf: &mut W, sdes_item: &SdesItem) ->
         }
         SdesItem::Unknown { item_type, data } => {
             buf.write_u8(*item_type).context("id")?;
+            buf.write_u8(data.len() as u8).context("length")?;
             buf.write(data).context("value")?;
         }
     }
@@ -118,11 +189,32 @@ pub fn write_sdes_item<W: PacketBufferMut>(buf: &mut W, sdes_item: &SdesItem) ->
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct SdesChunk {
     pub ssrc: u32,
     pub sdes_items: Vec<SdesItem>,
 }
 
+impl SdesChunk {
+    fn unpadded_length_bytes(&self) -> usize {
+        let items_length_bytes = self
+            .sdes_items
+            .iter()
+            .map(|item| item.length_bytes())
+            .sum::<usize>();
+        4 + items_length_bytes + SdesItem::Empty.length_bytes()
+    }
+}
+
+impl LengthBytes for SdesChunk {
+    /// The length of this chunk when written: the SSRC, its items and the terminating null item,
+    /// padded to a 32-bit boundary.
+    fn length_bytes(&self) -> usize {
+        self.unpadded_length_bytes().next_multiple_of(4)
+    }
+}
+
 pub fn read_sdes_chunk<R: PacketBuffer>(buf: &mut R) -> Result<SdesChunk> {
     let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
     let mut sdes_items: Vec<SdesItem> = Vec::new();
@@ -134,9 +226,15 @@ pub fn read_sdes_chunk<R: PacketBuffer>(buf: &mut R) -> Result<SdesChunk> {
         sdes_items.push(sdes_item);
     }
 
-    consume_padding(buf);
+    let sdes_chunk = SdesChunk { ssrc, sdes_items };
+    // Chunks always start on a 32-bit boundary, so the padding is relative to the start of the
+    // chunk.  It's read exactly rather than skipped as zeros, since the next chunk's SSRC may start
+    // with a zero byte.
+    let mut padding = [0u8; 3];
+    let padding_bytes = sdes_chunk.length_bytes() - sdes_chunk.unpadded_length_bytes();
+    std::io::Read::read(buf, &mut padding[..padding_bytes]).context("padding")?;
 
-    Ok(SdesChunk { ssrc, sdes_items })
+    Ok(sdes_chunk)
 }
 
 pub fn write_sdes_chunk<W: PacketBufferMut>(buf: &mut W, sdes_chunk: &SdesChunk) -> Result<()> {
@@ -154,13 +252,44 @@ pub fn write_sdes_chunk<W: PacketBufferMut>(buf: &mut W, sdes_chunk: &SdesChunk)
 
     write_sdes_item(buf, &SdesItem::Empty).context("empty item")?;
 
-    // TODO: I'm wondering about doing the padding here: what if the buffer we're given is a slice
-    // which doesn't have the proper context of the overall alignment? Also, we'll need some trait
-    // to be able to even add padding here to some alignment.
+    // Chunks always start on a 32-bit boundary, so we can pad relative to the start of the chunk
+    let padding_bytes = sdes_chunk.length_bytes() - sdes_chunk.unpadded_length_bytes();
+    buf.write_all(&vec![0u8; padding_bytes])
+        .context("padding")?;
 
     Ok(())
 }
 
+impl PrettyPrint for RtcpSdesPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Source Description", |tree| {
+            self.header.pretty_print(tree)?;
+            for (i, chunk) in self.chunks.iter().enumerate() {
+                tree.subtree(format_args!("Chunk {}", i + 1), |tree| {
+                    chunk.pretty_print(tree)
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl PrettyPrint for SdesChunk {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("SSRC", Ssrc(self.ssrc))?;
+        for item in &self.sdes_items {
+            match item {
+                SdesItem::Empty => tree.field("Item", "end")?,
+                SdesItem::Cname(cname) => tree.field("CNAME", cname)?,
+                SdesItem::Unknown { item_type, data } => {
+                    tree.field("Item", format_args!("type {item_type}: {}", HexBytes(data)))?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bit_cursor::{
@@ -229,6 +358,54 @@ mod tests {
         assert_eq!(chunk.ssrc, 2828806853);
     }
 
+    #[test]
+    fn test_write_sdes_chunk_padding() {
+        let chunk = SdesChunk {
+            ssrc: 42,
+            sdes_items: vec![SdesItem::Cname("abc".to_owned())],
+        };
+        // ssrc (4) + cname item (2 + 3) + null item (1), padded to 12
+        assert_eq!(chunk.length_bytes(), 12);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0xFF; 12]));
+        write_sdes_chunk(&mut cursor, &chunk).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(&data[9..], &[0, 0, 0]);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let read_chunk = read_sdes_chunk(&mut cursor).unwrap();
+        assert_eq!(read_chunk.ssrc, 42);
+        assert!(matches!(&read_chunk.sdes_items[..], [SdesItem::Cname(v)] if v == "abc"));
+    }
+
+    #[test]
+    fn test_read_sdes_multiple_chunks() {
+        let chunks = vec![
+            SdesChunk {
+                ssrc: 42,
+                sdes_items: vec![SdesItem::Cname("abc".to_owned())],
+            },
+            // The padding of the first chunk mustn't swallow the leading zero byte of this ssrc
+            SdesChunk {
+                ssrc: 0x00001234,
+                sdes_items: vec![SdesItem::Cname("defg".to_owned())],
+            },
+        ];
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 24]));
+        for chunk in &chunks {
+            write_sdes_chunk(&mut cursor, chunk).unwrap();
+        }
+        let data = cursor.into_inner().into_vec();
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let first = read_sdes_chunk(&mut cursor).unwrap();
+        assert_eq!(first.ssrc, 42);
+        let second = read_sdes_chunk(&mut cursor).unwrap();
+        assert_eq!(second.ssrc, 0x00001234);
+        assert!(matches!(&second.sdes_items[..], [SdesItem::Cname(v)] if v == "defg"));
+        assert!(cursor.remaining_slice().is_empty());
+    }
+
     // TODO:
     // parse_sdes_chunk success | failure in chunk | failure in item
     // parse_sdes_chunks
@@ -4,6 +4,12 @@ use bit_cursor::{
     bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
 };
 
+use crate::{
+    ntp::NtpTimestamp,
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes,
+};
+
 /// https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1
 ///        +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 /// sender |              NTP timestamp, most significant word             |
@@ -17,6 +23,8 @@ use bit_cursor::{
 ///        |                      sender's octet count                     |
 ///        +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpSenderInfo {
     pub ntp_timestamp_msw: u32,
     pub ntp_timestamp_lsw: u32,
@@ -25,6 +33,31 @@ pub struct RtcpSenderInfo {
     pub sender_octet_count: u32,
 }
 
+impl RtcpSenderInfo {
+    pub const SIZE_BYTES: usize = 20;
+
+    pub fn ntp_timestamp(&self) -> NtpTimestamp {
+        NtpTimestamp::from_parts(self.ntp_timestamp_msw, self.ntp_timestamp_lsw)
+    }
+
+    pub fn set_ntp_timestamp(&mut self, ntp_timestamp: NtpTimestamp) {
+        self.ntp_timestamp_msw = ntp_timestamp.msw();
+        self.ntp_timestamp_lsw = ntp_timestamp.lsw();
+    }
+
+    /// The middle 32 bits of the NTP timestamp, which receivers echo back in the LSR field of
+    /// their reception reports
+    pub fn compact_ntp_timestamp(&self) -> u32 {
+        self.ntp_timestamp().compact()
+    }
+}
+
+impl LengthBytes for RtcpSenderInfo {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
 pub fn read_rtcp_sender_info<R: BitRead>(buf: &mut R) -> Result<RtcpSenderInfo> {
     Ok(RtcpSenderInfo {
         ntp_timestamp_msw: buf
@@ -60,3 +93,12 @@ pub fn write_rtcp_sender_info<W: BitWrite>(
 
     Ok(())
 }
+
+impl PrettyPrint for RtcpSenderInfo {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("NTP timestamp", self.ntp_timestamp())?;
+        tree.field("RTP timestamp", self.rtp_timestamp)?;
+        tree.field("Sender's packet count", self.sender_packet_count)?;
+        tree.field("Sender's octet count", self.sender_octet_count)
+    }
+}
@@ -1,18 +1,19 @@
 use std::fmt::Debug;
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
 use bit_cursor::{
     bit_read_exts::BitReadExts, bit_write::BitWrite, bit_write_exts::BitWriteExts,
     byte_order::NetworkOrder,
 };
 
 use crate::{
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
     rtcp::{
-        rtcp_header::write_rtcp_header,
+        rtcp_header::{report_count_from_len, write_rtcp_header},
         rtcp_report_block::{read_rtcp_report_block, write_rtcp_report_block},
         rtcp_sender_info::{read_rtcp_sender_info, write_rtcp_sender_info},
     },
-    PacketBuffer,
+    LengthBytes, PacketBuffer,
 };
 
 use super::{
@@ -56,15 +57,57 @@ use super::{
 ///        |                  profile-specific extensions                  |
 ///        +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct RtcpSrPacket {
     pub header: RtcpHeader,
     pub sender_ssrc: u32,
     pub sender_info: RtcpSenderInfo,
     pub report_blocks: Vec<RtcpReportBlock>,
+    /// Profile-specific extension data following the report blocks.  Empty if there is none.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::words))]
+    pub profile_extensions: Vec<u8>,
 }
 
 impl RtcpSrPacket {
     pub const PT: u8 = 200;
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> Result<u16> {
+        let length_bytes = self.length_bytes() - RtcpHeader::SIZE_BYTES;
+        length_bytes
+            .try_into()
+            .map_err(|_| anyhow!("SR payload length {length_bytes} bytes is too large"))
+    }
+
+    /// Update the header of this packet (packet type, report count and length) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        if !self.profile_extensions.len().is_multiple_of(4) {
+            bail!(
+                "SR profile extensions must be a multiple of 4 bytes, was {} bytes",
+                self.profile_extensions.len()
+            );
+        }
+        let payload_length_bytes = self.payload_length_bytes().context("payload length")?;
+        self.header.packet_type = Self::PT;
+        self.header.report_count =
+            report_count_from_len(self.report_blocks.len()).context("report count")?;
+        self.header.length_field = payload_length_bytes / 4;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpSrPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES
+            + 4
+            + RtcpSenderInfo::SIZE_BYTES
+            + self.report_blocks.len() * RtcpReportBlock::SIZE_BYTES
+            + self.profile_extensions.len()
+    }
 }
 
 pub fn read_rtcp_sr<B: PacketBuffer>(buf: &mut B, header: RtcpHeader) -> Result<RtcpSrPacket> {
@@ -74,12 +117,16 @@ pub fn read_rtcp_sr<B: PacketBuffer>(buf: &mut B, header: RtcpHeader) -> Result<
         .map(|i| read_rtcp_report_block(buf).with_context(|| format!("report block {i}")))
         .collect::<Result<Vec<RtcpReportBlock>>>()
         .context("report blocks")?;
+    let mut profile_extensions = vec![0u8; buf.bytes_remaining()];
+    buf.read_exact(&mut profile_extensions)
+        .context("profile extensions")?;
 
     Ok(RtcpSrPacket {
         header,
         sender_ssrc,
         sender_info,
         report_blocks,
+        profile_extensions,
     })
 }
 
@@ -97,5 +144,30 @@ pub fn write_rtcp_sr<W: BitWrite>(buf: &mut W, packet: &RtcpSrPacket) -> Result<
         })
         .collect::<Result<Vec<()>>>()
         .context("report blocks")?;
-    todo!()
+    buf.write_all(&packet.profile_extensions)
+        .context("profile extensions")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpSrPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Sender Report", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Sender SSRC", Ssrc(self.sender_ssrc))?;
+            self.sender_info.pretty_print(tree)?;
+            for (i, report_block) in self.report_blocks.iter().enumerate() {
+                tree.subtree(format_args!("Report block {}", i + 1), |tree| {
+                    report_block.pretty_print(tree)
+                })?;
+            }
+            if !self.profile_extensions.is_empty() {
+                tree.field(
+                    "Profile-specific extensions",
+                    HexBytes(&self.profile_extensions),
+                )?;
+            }
+            Ok(())
+        })
+    }
 }
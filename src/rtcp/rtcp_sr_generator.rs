@@ -0,0 +1,174 @@
+use std::time::SystemTime;
+
+use anyhow::{Context, Result};
+use bit_cursor::nsw_types::{u2, u5};
+
+use crate::{ntp::NtpTimestamp, rtp::rtp_packet::RtpPacket};
+
+use super::{
+    rtcp_header::RtcpHeader, rtcp_report_block::RtcpReportBlock, rtcp_sender_info::RtcpSenderInfo,
+    rtcp_sr::RtcpSrPacket,
+};
+
+/// Generates SRs for a stream being sent.  It counts the packets and payload octets sent, and
+/// keeps a reference point between the stream's RTP clock and the NTP (wall) clock, so the RTP
+/// timestamp in each SR corresponds to the NTP timestamp of when it's generated, as required by
+/// https://datatracker.ietf.org/doc/html/rfc3550#section-6.4.1.
+#[derive(Debug)]
+pub struct SrGenerator {
+    sender_ssrc: u32,
+    clock_rate: u32,
+    /// An RTP timestamp and the NTP time it corresponds to
+    rtp_clock_reference: Option<(u32, NtpTimestamp)>,
+    packet_count: u32,
+    octet_count: u32,
+}
+
+impl SrGenerator {
+    /// Create a generator for the stream with the given SSRC and RTP clock rate (in Hz)
+    pub fn new(sender_ssrc: u32, clock_rate: u32) -> Self {
+        Self {
+            sender_ssrc,
+            clock_rate,
+            rtp_clock_reference: None,
+            packet_count: 0,
+            octet_count: 0,
+        }
+    }
+
+    /// Set the NTP time which the given RTP timestamp corresponds to, e.g. the capture time of
+    /// the frame with that timestamp
+    pub fn set_rtp_clock_reference(&mut self, rtp_timestamp: u32, ntp_timestamp: NtpTimestamp) {
+        self.rtp_clock_reference = Some((rtp_timestamp, ntp_timestamp));
+    }
+
+    /// Count a sent packet with a payload of the given size.  The octet count in SRs only
+    /// includes payloads, not headers or padding.
+    pub fn on_packet_sent(&mut self, payload_length_bytes: usize) {
+        self.packet_count = self.packet_count.wrapping_add(1);
+        self.octet_count = self.octet_count.wrapping_add(payload_length_bytes as u32);
+    }
+
+    /// Count the given sent packet, and use its RTP timestamp and the given capture time as the
+    /// RTP clock reference
+    pub fn on_rtp_packet_sent(&mut self, packet: &RtpPacket, capture_time: SystemTime) {
+        self.on_packet_sent(packet.payload().len());
+        self.set_rtp_clock_reference(
+            packet.timestamp(),
+            NtpTimestamp::from_system_time(capture_time),
+        );
+    }
+
+    pub fn packet_count(&self) -> u32 {
+        self.packet_count
+    }
+
+    pub fn octet_count(&self) -> u32 {
+        self.octet_count
+    }
+
+    /// The RTP timestamp corresponding to the given NTP time, or None if there's no RTP clock
+    /// reference yet
+    pub fn rtp_timestamp_at(&self, ntp_timestamp: NtpTimestamp) -> Option<u32> {
+        let (reference_rtp_timestamp, reference_ntp_timestamp) = self.rtp_clock_reference?;
+        let elapsed_nanos = ntp_timestamp.to_duration().as_nanos() as i128
+            - reference_ntp_timestamp.to_duration().as_nanos() as i128;
+        let elapsed_ticks = elapsed_nanos * self.clock_rate as i128 / 1_000_000_000;
+
+        Some(reference_rtp_timestamp.wrapping_add(elapsed_ticks as u32))
+    }
+
+    /// Build a synced SR generated at the given NTP time, with the given report blocks (e.g. from
+    /// a [`ReportBlockGenerator`](super::rtcp_report_block_generator::ReportBlockGenerator)).  If
+    /// there's no RTP clock reference yet, the RTP timestamp is 0.
+    pub fn build(
+        &self,
+        now: NtpTimestamp,
+        report_blocks: Vec<RtcpReportBlock>,
+    ) -> Result<RtcpSrPacket> {
+        let mut sr = RtcpSrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: RtcpSrPacket::PT,
+                length_field: 0,
+            },
+            sender_ssrc: self.sender_ssrc,
+            sender_info: RtcpSenderInfo {
+                ntp_timestamp_msw: now.msw(),
+                ntp_timestamp_lsw: now.lsw(),
+                rtp_timestamp: self.rtp_timestamp_at(now).unwrap_or(0),
+                sender_packet_count: self.packet_count,
+                sender_octet_count: self.octet_count,
+            },
+            report_blocks,
+            profile_extensions: Vec::new(),
+        };
+        sr.sync().context("sr")?;
+
+        Ok(sr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use bit_cursor::nsw_types::u24;
+
+    use crate::rtp::rtp_packet::RtpPacketBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_build() {
+        let mut generator = SrGenerator::new(1234, 90_000);
+        let capture_time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        for i in 0..3 {
+            let packet = RtpPacketBuilder::new()
+                .timestamp(u32::MAX - 89_999)
+                .payload(&[0; 100])
+                .padding(if i == 0 { 4 } else { 0 })
+                .build()
+                .unwrap();
+            generator.on_rtp_packet_sent(&packet, capture_time);
+        }
+
+        // 2 seconds after the capture time, so the RTP timestamp has wrapped
+        let now = NtpTimestamp::from_system_time(capture_time + Duration::from_secs(2));
+        let sr = generator
+            .build(
+                now,
+                vec![RtcpReportBlock {
+                    ssrc: 5,
+                    fraction_lost: 0,
+                    cumulative_lost: u24::new(0),
+                    extended_highest_seq_num: 0,
+                    interarrival_jitter: 0,
+                    last_sr_timestamp: 0,
+                    delay_since_last_sr: 0,
+                }],
+            )
+            .unwrap();
+        assert_eq!(sr.sender_ssrc, 1234);
+        assert_eq!(sr.sender_info.ntp_timestamp(), now);
+        assert_eq!(sr.sender_info.rtp_timestamp, 90_000);
+        assert_eq!(sr.sender_info.sender_packet_count, 3);
+        assert_eq!(sr.sender_info.sender_octet_count, 300);
+        assert_eq!(sr.header.report_count, u5::new(1));
+        assert_eq!(sr.header.length_field, 12);
+
+        // Before the reference
+        let before = NtpTimestamp::from_system_time(capture_time - Duration::from_millis(500));
+        assert_eq!(generator.rtp_timestamp_at(before), Some(u32::MAX - 134_999));
+    }
+
+    #[test]
+    fn test_no_clock_reference() {
+        let generator = SrGenerator::new(1, 48_000);
+        let sr = generator.build(NtpTimestamp(1 << 32), Vec::new()).unwrap();
+        assert_eq!(sr.sender_info.rtp_timestamp, 0);
+        assert_eq!(sr.sender_info.sender_packet_count, 0);
+    }
+}
@@ -0,0 +1,664 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    nsw_types::u5,
+};
+
+use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_header::{write_rtcp_header, RtcpHeader},
+    rtcp_xr_burst_gap_loss::{
+        read_burst_gap_loss_block, write_burst_gap_loss_block, BurstGapLossBlock,
+    },
+    rtcp_xr_delay_metrics::{
+        read_delay_metrics_block, write_delay_metrics_block, DelayMetricsBlock,
+    },
+    rtcp_xr_dlrr::{read_dlrr_block, write_dlrr_block, DlrrBlock},
+    rtcp_xr_duplicate_rle::{
+        read_duplicate_rle_block, write_duplicate_rle_block, DuplicateRleBlock,
+    },
+    rtcp_xr_loss_rle::{read_loss_rle_block, write_loss_rle_block, LossRleBlock},
+    rtcp_xr_measurement_info::{
+        read_measurement_info_block, write_measurement_info_block, MeasurementInfoBlock,
+    },
+    rtcp_xr_packet_receipt_times::{
+        read_packet_receipt_times_block, write_packet_receipt_times_block, PacketReceiptTimesBlock,
+    },
+    rtcp_xr_rrt::{
+        read_receiver_reference_time_block, write_receiver_reference_time_block,
+        ReceiverReferenceTimeBlock,
+    },
+    rtcp_xr_statistics_summary::{
+        read_statistics_summary_block, write_statistics_summary_block, StatisticsSummaryBlock,
+    },
+    rtcp_xr_voip_metrics::{read_voip_metrics_block, write_voip_metrics_block, VoipMetricsBlock},
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-2
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |V=2|P|reserved |   PT=XR=207   |             length            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                              SSRC                             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :                         report blocks                         :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RtcpXrPacket {
+    pub header: RtcpHeader,
+    pub sender_ssrc: u32,
+    pub blocks: Vec<SomeXrBlock>,
+}
+
+impl RtcpXrPacket {
+    pub const PT: u8 = 207;
+
+    /// The length of this packet's payload (i.e. excluding the RTCP header) in bytes
+    pub fn payload_length_bytes(&self) -> usize {
+        4 + self
+            .blocks
+            .iter()
+            .map(|block| block.length_bytes())
+            .sum::<usize>()
+    }
+
+    /// Update the header of this packet (and the headers of all of its blocks) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        for (i, block) in self.blocks.iter_mut().enumerate() {
+            block.sync().with_context(|| format!("block {i}"))?;
+        }
+        let payload_length_bytes = self.payload_length_bytes();
+        self.header.packet_type = Self::PT;
+        // XR packets have no count, so those bits are reserved
+        self.header.report_count = u5::new(0);
+        self.header.length_field = (payload_length_bytes / 4)
+            .try_into()
+            .map_err(|_| anyhow!("XR payload length {payload_length_bytes} bytes is too large"))?;
+        self.header.has_padding = false;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for RtcpXrPacket {
+    fn length_bytes(&self) -> usize {
+        RtcpHeader::SIZE_BYTES + self.payload_length_bytes()
+    }
+}
+
+pub fn read_rtcp_xr<B: PacketBuffer>(
+    buf: &mut B,
+    header: RtcpHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtcpXrPacket> {
+    let sender_ssrc = buf.read_u32::<NetworkOrder>().context("sender ssrc")?;
+    let mut blocks = Vec::new();
+    let mut block_num = 1;
+    while buf.bytes_remaining() >= XrBlockHeader::SIZE_BYTES {
+        let block = read_some_xr_block(buf, mode, warnings)
+            .with_context(|| format!("block {block_num}"))?;
+        blocks.push(block);
+        block_num += 1;
+    }
+
+    Ok(RtcpXrPacket {
+        header,
+        sender_ssrc,
+        blocks,
+    })
+}
+
+pub fn write_rtcp_xr<B: PacketBufferMut>(buf: &mut B, rtcp_xr: &RtcpXrPacket) -> Result<()> {
+    write_rtcp_header(buf, &rtcp_xr.header).context("rtcp header")?;
+    buf.write_u32::<NetworkOrder>(rtcp_xr.sender_ssrc)
+        .context("sender ssrc")?;
+    for (i, block) in rtcp_xr.blocks.iter().enumerate() {
+        write_some_xr_block(buf, block).with_context(|| format!("block {i}"))?;
+    }
+
+    Ok(())
+}
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-3
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      BT       | type-specific |         block length          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :             type-specific block contents                      :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// block length: 16 bits
+///   The length of this report block, including the header, in 32-
+///   bit words minus one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct XrBlockHeader {
+    pub block_type: u8,
+    pub type_specific: u8,
+    pub block_length: u16,
+}
+
+impl XrBlockHeader {
+    pub const SIZE_BYTES: usize = 4;
+
+    /// The length of this block's contents (i.e. excluding the block header) in bytes
+    pub fn content_length_bytes(&self) -> usize {
+        self.block_length as usize * 4
+    }
+}
+
+impl LengthBytes for XrBlockHeader {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_xr_block_header<B: PacketBuffer>(buf: &mut B) -> Result<XrBlockHeader> {
+    Ok(XrBlockHeader {
+        block_type: buf.read_u8().context("block type")?,
+        type_specific: buf.read_u8().context("type specific")?,
+        block_length: buf.read_u16::<NetworkOrder>().context("block length")?,
+    })
+}
+
+pub fn write_xr_block_header<B: PacketBufferMut>(
+    buf: &mut B,
+    block_header: &XrBlockHeader,
+) -> Result<()> {
+    buf.write_u8(block_header.block_type)
+        .context("block type")?;
+    buf.write_u8(block_header.type_specific)
+        .context("type specific")?;
+    buf.write_u16::<NetworkOrder>(block_header.block_length)
+        .context("block length")?;
+
+    Ok(())
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum SomeXrBlock {
+    LossRleBlock(LossRleBlock),
+    DuplicateRleBlock(DuplicateRleBlock),
+    PacketReceiptTimesBlock(PacketReceiptTimesBlock),
+    ReceiverReferenceTimeBlock(ReceiverReferenceTimeBlock),
+    DlrrBlock(DlrrBlock),
+    StatisticsSummaryBlock(StatisticsSummaryBlock),
+    VoipMetricsBlock(VoipMetricsBlock),
+    MeasurementInfoBlock(MeasurementInfoBlock),
+    DelayMetricsBlock(DelayMetricsBlock),
+    BurstGapLossBlock(BurstGapLossBlock),
+    UnknownXrBlock {
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_unknown_xr_block_header))]
+        header: XrBlockHeader,
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::words))]
+        data: Vec<u8>,
+    },
+}
+
+/// A header with a block type that isn't one of the known ones, so the block is read back as
+/// unknown
+#[cfg(feature = "arbitrary")]
+fn arbitrary_unknown_xr_block_header(
+    u: &mut arbitrary::Unstructured,
+) -> arbitrary::Result<XrBlockHeader> {
+    Ok(XrBlockHeader {
+        block_type: u.int_in_range(BurstGapLossBlock::BT + 1..=u8::MAX)?,
+        type_specific: u.arbitrary()?,
+        block_length: 0,
+    })
+}
+
+impl SomeXrBlock {
+    /// Update the header of this block to match its contents.
+    pub fn sync(&mut self) -> Result<()> {
+        match self {
+            SomeXrBlock::LossRleBlock(b) => b.sync()?,
+            SomeXrBlock::DuplicateRleBlock(b) => b.sync()?,
+            SomeXrBlock::PacketReceiptTimesBlock(b) => b.sync()?,
+            SomeXrBlock::ReceiverReferenceTimeBlock(b) => b.sync(),
+            SomeXrBlock::DlrrBlock(b) => b.sync()?,
+            SomeXrBlock::StatisticsSummaryBlock(b) => b.sync(),
+            SomeXrBlock::VoipMetricsBlock(b) => b.sync(),
+            SomeXrBlock::MeasurementInfoBlock(b) => b.sync(),
+            SomeXrBlock::DelayMetricsBlock(b) => b.sync(),
+            SomeXrBlock::BurstGapLossBlock(b) => b.sync(),
+            SomeXrBlock::UnknownXrBlock { header, data } => {
+                if data.len() % 4 != 0 {
+                    bail!(
+                        "XR block data must be a multiple of 4 bytes, was {} bytes",
+                        data.len()
+                    );
+                }
+                header.block_length = (data.len() / 4)
+                    .try_into()
+                    .map_err(|_| anyhow!("XR block length {} bytes is too large", data.len()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for SomeXrBlock {
+    /// The length of this block, including its header, in bytes
+    fn length_bytes(&self) -> usize {
+        match self {
+            SomeXrBlock::LossRleBlock(b) => b.length_bytes(),
+            SomeXrBlock::DuplicateRleBlock(b) => b.length_bytes(),
+            SomeXrBlock::PacketReceiptTimesBlock(b) => b.length_bytes(),
+            SomeXrBlock::ReceiverReferenceTimeBlock(_) => ReceiverReferenceTimeBlock::SIZE_BYTES,
+            SomeXrBlock::DlrrBlock(b) => b.length_bytes(),
+            SomeXrBlock::StatisticsSummaryBlock(_) => StatisticsSummaryBlock::SIZE_BYTES,
+            SomeXrBlock::VoipMetricsBlock(_) => VoipMetricsBlock::SIZE_BYTES,
+            SomeXrBlock::MeasurementInfoBlock(_) => MeasurementInfoBlock::SIZE_BYTES,
+            SomeXrBlock::DelayMetricsBlock(_) => DelayMetricsBlock::SIZE_BYTES,
+            SomeXrBlock::BurstGapLossBlock(_) => BurstGapLossBlock::SIZE_BYTES,
+            SomeXrBlock::UnknownXrBlock { data, .. } => XrBlockHeader::SIZE_BYTES + data.len(),
+        }
+    }
+}
+
+pub fn read_some_xr_block<B: PacketBuffer>(
+    buf: &mut B,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<SomeXrBlock> {
+    let block_header = read_xr_block_header(buf).context("block header")?;
+    let content_length = block_header.content_length_bytes();
+    if content_length > buf.bytes_remaining() {
+        bail!(RtpParseError::truncated(
+            content_length,
+            buf.bytes_remaining()
+        ));
+    }
+    let content_length_bits = content_length * 8;
+    let mut block_buffer = buf.sub_buffer(0..content_length_bits);
+
+    let block = match block_header.block_type {
+        LossRleBlock::BT => SomeXrBlock::LossRleBlock(
+            read_loss_rle_block(&mut block_buffer, block_header).context("loss rle block")?,
+        ),
+        DuplicateRleBlock::BT => SomeXrBlock::DuplicateRleBlock(
+            read_duplicate_rle_block(&mut block_buffer, block_header)
+                .context("duplicate rle block")?,
+        ),
+        PacketReceiptTimesBlock::BT => SomeXrBlock::PacketReceiptTimesBlock(
+            read_packet_receipt_times_block(&mut block_buffer, block_header)
+                .context("packet receipt times block")?,
+        ),
+        ReceiverReferenceTimeBlock::BT => SomeXrBlock::ReceiverReferenceTimeBlock(
+            read_receiver_reference_time_block(&mut block_buffer, block_header)
+                .context("receiver reference time block")?,
+        ),
+        DlrrBlock::BT => SomeXrBlock::DlrrBlock(
+            read_dlrr_block(&mut block_buffer, block_header).context("dlrr block")?,
+        ),
+        StatisticsSummaryBlock::BT => SomeXrBlock::StatisticsSummaryBlock(
+            read_statistics_summary_block(&mut block_buffer, block_header)
+                .context("statistics summary block")?,
+        ),
+        VoipMetricsBlock::BT => SomeXrBlock::VoipMetricsBlock(
+            read_voip_metrics_block(&mut block_buffer, block_header, mode, warnings)
+                .context("voip metrics block")?,
+        ),
+        MeasurementInfoBlock::BT => SomeXrBlock::MeasurementInfoBlock(
+            read_measurement_info_block(&mut block_buffer, block_header, mode, warnings)
+                .context("measurement info block")?,
+        ),
+        DelayMetricsBlock::BT => SomeXrBlock::DelayMetricsBlock(
+            read_delay_metrics_block(&mut block_buffer, block_header)
+                .context("delay metrics block")?,
+        ),
+        BurstGapLossBlock::BT => SomeXrBlock::BurstGapLossBlock(
+            read_burst_gap_loss_block(&mut block_buffer, block_header)
+                .context("burst gap loss block")?,
+        ),
+        _ => {
+            let mut data = vec![0u8; content_length];
+            std::io::Read::read_exact(&mut block_buffer, &mut data)
+                .context("unknown block data")?;
+            SomeXrBlock::UnknownXrBlock {
+                header: block_header,
+                data,
+            }
+        }
+    };
+    let unread_bytes = block_buffer.bytes_remaining();
+    drop(block_buffer);
+    if unread_bytes > 0 {
+        mode.on_violation(
+            warnings,
+            RtpParseError::invalid_field(
+                "XR block length",
+                format!("{} bytes", content_length - unread_bytes),
+                format!("{content_length} bytes"),
+            ),
+        )?;
+    }
+    buf.seek(std::io::SeekFrom::Current(content_length_bits as i64))?;
+
+    Ok(block)
+}
+
+pub fn write_some_xr_block<B: PacketBufferMut>(buf: &mut B, block: &SomeXrBlock) -> Result<()> {
+    match block {
+        SomeXrBlock::LossRleBlock(b) => {
+            write_loss_rle_block(buf, b).context("loss rle block")?;
+        }
+        SomeXrBlock::DuplicateRleBlock(b) => {
+            write_duplicate_rle_block(buf, b).context("duplicate rle block")?;
+        }
+        SomeXrBlock::PacketReceiptTimesBlock(b) => {
+            write_packet_receipt_times_block(buf, b).context("packet receipt times block")?;
+        }
+        SomeXrBlock::ReceiverReferenceTimeBlock(b) => {
+            write_receiver_reference_time_block(buf, b).context("receiver reference time block")?;
+        }
+        SomeXrBlock::DlrrBlock(b) => {
+            write_dlrr_block(buf, b).context("dlrr block")?;
+        }
+        SomeXrBlock::StatisticsSummaryBlock(b) => {
+            write_statistics_summary_block(buf, b).context("statistics summary block")?;
+        }
+        SomeXrBlock::VoipMetricsBlock(b) => {
+            write_voip_metrics_block(buf, b).context("voip metrics block")?;
+        }
+        SomeXrBlock::MeasurementInfoBlock(b) => {
+            write_measurement_info_block(buf, b).context("measurement info block")?;
+        }
+        SomeXrBlock::DelayMetricsBlock(b) => {
+            write_delay_metrics_block(buf, b).context("delay metrics block")?;
+        }
+        SomeXrBlock::BurstGapLossBlock(b) => {
+            write_burst_gap_loss_block(buf, b).context("burst gap loss block")?;
+        }
+        SomeXrBlock::UnknownXrBlock { header, data } => {
+            write_xr_block_header(buf, header).context("block header")?;
+            buf.write_all(data).context("unknown block data")?;
+        }
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtcpXrPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTCP Extended Report", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Sender SSRC", Ssrc(self.sender_ssrc))?;
+            for block in &self.blocks {
+                block.pretty_print(tree)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl PrettyPrint for XrBlockHeader {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.field("Block type", self.block_type)?;
+        tree.field("Type-specific", format_args!("{:#04x}", self.type_specific))?;
+        tree.field(
+            "Block length",
+            format_args!(
+                "{} ({} bytes)",
+                self.block_length,
+                (self.block_length as usize + 1) * 4
+            ),
+        )
+    }
+}
+
+impl PrettyPrint for SomeXrBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        match self {
+            SomeXrBlock::LossRleBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::DuplicateRleBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::PacketReceiptTimesBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::ReceiverReferenceTimeBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::DlrrBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::StatisticsSummaryBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::VoipMetricsBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::MeasurementInfoBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::DelayMetricsBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::BurstGapLossBlock(block) => block.pretty_print(tree),
+            SomeXrBlock::UnknownXrBlock { header, data } => tree.subtree("Unknown block", |tree| {
+                header.pretty_print(tree)?;
+                tree.field("Data", HexBytes(data))
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::{
+        bit_cursor::BitCursor,
+        nsw_types::{u2, u5},
+    };
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_header::read_rtcp_header;
+
+    use super::*;
+
+    #[test]
+    fn test_read_unknown_block() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x2a,
+            // bt 42, type specific 1, length 1
+            0x2a, 0x01, 0x00, 0x01,
+            0xDE, 0xAD, 0xBE, 0xEF,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: RtcpXrPacket::PT,
+            length_field: 3,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        let rtcp_xr = read_rtcp_xr(
+            &mut cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(rtcp_xr.sender_ssrc, 42);
+        assert_eq!(rtcp_xr.blocks.len(), 1);
+        match &rtcp_xr.blocks[0] {
+            SomeXrBlock::UnknownXrBlock { header, data } => {
+                assert_eq!(header.block_type, 42);
+                assert_eq!(header.type_specific, 1);
+                assert_eq!(data, &[0xDE, 0xAD, 0xBE, 0xEF]);
+            }
+            b => panic!("Unexpected block type: {b:?}"),
+        }
+    }
+
+    #[test]
+    fn test_read_rrt_and_dlrr_blocks() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x2a,
+            // bt 4, reserved, length 2
+            0x04, 0x00, 0x00, 0x02,
+            // ntp msw
+            0x00, 0x00, 0x00, 0x01,
+            // ntp lsw
+            0x00, 0x00, 0x00, 0x02,
+            // bt 5, reserved, length 3
+            0x05, 0x00, 0x00, 0x03,
+            // ssrc
+            0x00, 0x00, 0x00, 0x03,
+            // last rr
+            0x00, 0x00, 0x00, 0x04,
+            // dlrr
+            0x00, 0x00, 0x00, 0x05,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: RtcpXrPacket::PT,
+            length_field: 8,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        let rtcp_xr = read_rtcp_xr(
+            &mut cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(rtcp_xr.blocks.len(), 2);
+        match &rtcp_xr.blocks[0] {
+            SomeXrBlock::ReceiverReferenceTimeBlock(rrt) => {
+                assert_eq!(rrt.ntp_timestamp_msw, 1);
+                assert_eq!(rrt.ntp_timestamp_lsw, 2);
+            }
+            b => panic!("Unexpected block type: {b:?}"),
+        }
+        match &rtcp_xr.blocks[1] {
+            SomeXrBlock::DlrrBlock(dlrr) => {
+                assert_eq!(dlrr.sub_blocks.len(), 1);
+                assert_eq!(dlrr.sub_blocks[0].ssrc, 3);
+            }
+            b => panic!("Unexpected block type: {b:?}"),
+        }
+    }
+
+    #[test]
+    fn test_read_duplicate_rle_block() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x2a,
+            // bt 2, thinning 2, length 3
+            0x02, 0x02, 0x00, 0x03,
+            // ssrc of source
+            0x00, 0x00, 0x00, 0x01,
+            // begin seq 0, end seq 12
+            0x00, 0x00, 0x00, 0x0C,
+            // bit vector: 010000000000000
+            0xA0, 0x00,
+            // terminating null
+            0x00, 0x00,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: RtcpXrPacket::PT,
+            length_field: 5,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        let rtcp_xr = read_rtcp_xr(
+            &mut cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        match &rtcp_xr.blocks[0] {
+            SomeXrBlock::DuplicateRleBlock(b) => {
+                let statuses: Vec<(u16, bool)> = b.iter_duplicate_status().collect();
+                assert_eq!(statuses, vec![(0, false), (4, true), (8, false)]);
+            }
+            b => panic!("Unexpected block type: {b:?}"),
+        }
+    }
+
+    #[test]
+    fn test_read_block_length_too_long() {
+        #[rustfmt::skip]
+        let payload = vec![
+            // sender ssrc
+            0x00, 0x00, 0x00, 0x2a,
+            // bt 42, type specific 1, length 2
+            0x2a, 0x01, 0x00, 0x02,
+            0xDE, 0xAD, 0xBE, 0xEF,
+        ];
+        let header = RtcpHeader {
+            version: u2::new(2),
+            has_padding: false,
+            report_count: u5::new(0),
+            packet_type: RtcpXrPacket::PT,
+            length_field: 3,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(payload));
+        assert!(read_rtcp_xr(
+            &mut cursor,
+            header,
+            ParseMode::Lenient,
+            &mut ParseWarnings::new()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_write_unknown_block_roundtrip() {
+        let mut rtcp_xr = RtcpXrPacket {
+            header: RtcpHeader {
+                version: u2::new(2),
+                has_padding: false,
+                report_count: u5::new(0),
+                packet_type: 0,
+                length_field: 0,
+            },
+            sender_ssrc: 42,
+            blocks: vec![SomeXrBlock::UnknownXrBlock {
+                header: XrBlockHeader {
+                    block_type: 42,
+                    type_specific: 1,
+                    block_length: 0,
+                },
+                data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+            }],
+        };
+        rtcp_xr.sync().unwrap();
+        assert_eq!(rtcp_xr.header.length_field, 4);
+        assert_eq!(rtcp_xr.header.packet_type, RtcpXrPacket::PT);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0u8; 20]));
+        write_rtcp_xr(&mut cursor, &rtcp_xr).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_rtcp_header(&mut read_cursor).unwrap();
+        let read_rtcp_xr = read_rtcp_xr(
+            &mut read_cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(read_rtcp_xr.sender_ssrc, 42);
+        match &read_rtcp_xr.blocks[0] {
+            SomeXrBlock::UnknownXrBlock { header, data } => {
+                assert_eq!(header.block_length, 2);
+                assert_eq!(data, &[1, 2, 3, 4, 5, 6, 7, 8]);
+            }
+            b => panic!("Unexpected block type: {b:?}"),
+        }
+    }
+}
@@ -0,0 +1,309 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    nsw_types::*,
+};
+
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_xr::{write_xr_block_header, XrBlockHeader},
+    rtcp_xr_delay_metrics::IntervalMetric,
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc7003#section-3
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=22     | I |   resv.   |      block length = 5         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of Source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   Threshold   |         Sum of Burst Durations (ms)           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             Packets Lost in Bursts            |  Total...     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | ...Packets Expected in Bursts |   Number of Bursts    | Sum...|
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |      ...of Squares of Burst Durations (ms^2)                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// Threshold: 8 bits
+///   The Gmin threshold: the number of successive packets that must not be lost prior to and
+///   following a lost packet in order for that lost packet to be regarded as part of a gap.
+///
+/// Sum of Squares of Burst Durations: 36 bits
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct BurstGapLossBlock {
+    pub header: XrBlockHeader,
+    pub interval_metric: IntervalMetric,
+    pub ssrc: u32,
+    pub threshold: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u24"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u24))]
+    pub sum_of_burst_durations: u24,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u24"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u24))]
+    pub packets_lost_in_bursts: u24,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u24"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u24))]
+    pub total_packets_expected_in_bursts: u24,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u12"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u12))]
+    pub number_of_bursts: u12,
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = |u: &mut arbitrary::Unstructured| {
+            u.int_in_range(0..=BurstGapLossBlock::MAX_SUM_OF_SQUARES)
+        })
+    )]
+    pub sum_of_squares_of_burst_durations: u64,
+}
+
+impl BurstGapLossBlock {
+    pub const BT: u8 = 22;
+    pub const SIZE_BYTES: usize = 24;
+    pub const MAX_SUM_OF_SQUARES: u64 = (1 << 36) - 1;
+
+    /// The fraction of packets expected in bursts which were lost, or None if no packets were
+    /// expected in bursts.
+    pub fn burst_loss_rate(&self) -> Option<f32> {
+        let expected: u32 = self.total_packets_expected_in_bursts.into();
+        if expected == 0 {
+            return None;
+        }
+        Some(u32::from(self.packets_lost_in_bursts) as f32 / expected as f32)
+    }
+
+    /// The mean burst duration in milliseconds, or None if there were no bursts.
+    pub fn mean_burst_duration_ms(&self) -> Option<f32> {
+        let number_of_bursts: u16 = self.number_of_bursts.into();
+        if number_of_bursts == 0 {
+            return None;
+        }
+        Some(u32::from(self.sum_of_burst_durations) as f32 / number_of_bursts as f32)
+    }
+
+    /// The value of the type-specific byte in the block header for the current interval metric.
+    /// The reserved bits are always 0.
+    pub fn type_specific(&self) -> u8 {
+        self.interval_metric.type_specific()
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.type_specific = self.type_specific();
+        self.header.block_length = 5;
+    }
+}
+
+impl LengthBytes for BurstGapLossBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_burst_gap_loss_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<BurstGapLossBlock> {
+    let interval_metric =
+        IntervalMetric::from_type_specific(header.type_specific).context("interval metric")?;
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let threshold = buf.read_u8().context("threshold")?;
+    let sum_of_burst_durations = buf
+        .read_u24::<NetworkOrder>()
+        .context("sum of burst durations")?;
+    let packets_lost_in_bursts = buf
+        .read_u24::<NetworkOrder>()
+        .context("packets lost in bursts")?;
+    let total_packets_expected_in_bursts = buf
+        .read_u24::<NetworkOrder>()
+        .context("total packets expected in bursts")?;
+    let number_of_bursts = buf.read_u12::<NetworkOrder>().context("number of bursts")?;
+    let sum_of_squares_msb: u8 = buf
+        .read_u4()
+        .context("sum of squares of burst durations")?
+        .into();
+    let sum_of_squares_lsb = buf
+        .read_u32::<NetworkOrder>()
+        .context("sum of squares of burst durations")?;
+
+    Ok(BurstGapLossBlock {
+        header,
+        interval_metric,
+        ssrc,
+        threshold,
+        sum_of_burst_durations,
+        packets_lost_in_bursts,
+        total_packets_expected_in_bursts,
+        number_of_bursts,
+        sum_of_squares_of_burst_durations: ((sum_of_squares_msb as u64) << 32)
+            | sum_of_squares_lsb as u64,
+    })
+}
+
+pub fn write_burst_gap_loss_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &BurstGapLossBlock,
+) -> Result<()> {
+    if block.sum_of_squares_of_burst_durations > BurstGapLossBlock::MAX_SUM_OF_SQUARES {
+        bail!(
+            "Sum of squares of burst durations {} doesn't fit in 36 bits",
+            block.sum_of_squares_of_burst_durations
+        );
+    }
+    let header = XrBlockHeader {
+        type_specific: block.type_specific(),
+        ..block.header.clone()
+    };
+    write_xr_block_header(buf, &header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u8(block.threshold).context("threshold")?;
+    buf.write_u24::<NetworkOrder>(block.sum_of_burst_durations)
+        .context("sum of burst durations")?;
+    buf.write_u24::<NetworkOrder>(block.packets_lost_in_bursts)
+        .context("packets lost in bursts")?;
+    buf.write_u24::<NetworkOrder>(block.total_packets_expected_in_bursts)
+        .context("total packets expected in bursts")?;
+    buf.write_u12::<NetworkOrder>(block.number_of_bursts)
+        .context("number of bursts")?;
+    buf.write_u4(u4::new(
+        (block.sum_of_squares_of_burst_durations >> 32) as u8,
+    ))
+    .context("sum of squares of burst durations")?;
+    buf.write_u32::<NetworkOrder>(block.sum_of_squares_of_burst_durations as u32)
+        .context("sum of squares of burst durations")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for BurstGapLossBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Burst/gap loss metrics report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field(
+                "Interval metric",
+                format_args!("{:?}", self.interval_metric),
+            )?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Threshold", self.threshold)?;
+            tree.field(
+                "Sum of burst durations",
+                format_args!("{} ms", u32::from(self.sum_of_burst_durations)),
+            )?;
+            tree.field(
+                "Packets lost in bursts",
+                u32::from(self.packets_lost_in_bursts),
+            )?;
+            tree.field(
+                "Total packets expected in bursts",
+                u32::from(self.total_packets_expected_in_bursts),
+            )?;
+            tree.field("Number of bursts", u16::from(self.number_of_bursts))?;
+            tree.field(
+                "Sum of squares of burst durations",
+                self.sum_of_squares_of_burst_durations,
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    fn create_block() -> BurstGapLossBlock {
+        BurstGapLossBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            interval_metric: IntervalMetric::Cumulative,
+            ssrc: 42,
+            threshold: 16,
+            sum_of_burst_durations: u24::new(300),
+            packets_lost_in_bursts: u24::new(10),
+            total_packets_expected_in_bursts: u24::new(40),
+            number_of_bursts: u12::new(3),
+            sum_of_squares_of_burst_durations: 0x9_0000_0001,
+        }
+    }
+
+    #[test]
+    fn test_read_burst_gap_loss_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // threshold 16, sum of burst durations 300
+            0x10, 0x00, 0x01, 0x2C,
+            // packets lost in bursts 10, total packets expected in bursts 40 (first byte)
+            0x00, 0x00, 0x0A, 0x00,
+            // total packets expected in bursts (cont), number of bursts 3, sum of squares (first 4 bits)
+            0x00, 0x28, 0x00, 0x30,
+            // sum of squares (cont)
+            0x00, 0x00, 0x75, 0x30,
+        ];
+        let header = XrBlockHeader {
+            block_type: BurstGapLossBlock::BT,
+            type_specific: 0b0100_0000,
+            block_length: 5,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_burst_gap_loss_block(&mut cursor, header).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(block.interval_metric, IntervalMetric::Sampled);
+        assert_eq!(block.threshold, 16);
+        assert_eq!(block.sum_of_burst_durations, u24::new(300));
+        assert_eq!(block.packets_lost_in_bursts, u24::new(10));
+        assert_eq!(block.total_packets_expected_in_bursts, u24::new(40));
+        assert_eq!(block.number_of_bursts, u12::new(3));
+        assert_eq!(block.sum_of_squares_of_burst_durations, 30_000);
+        assert_eq!(block.burst_loss_rate(), Some(0.25));
+        assert_eq!(block.mean_burst_duration_ms(), Some(100.0));
+    }
+
+    #[test]
+    fn test_write_burst_gap_loss_block_roundtrip() {
+        let mut block = create_block();
+        block.sync();
+        assert_eq!(block.header.type_specific, 0b1100_0000);
+        assert_eq!(block.header.block_length, 5);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            BurstGapLossBlock::SIZE_BYTES
+        ]));
+        write_burst_gap_loss_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_burst_gap_loss_block(&mut read_cursor, header).unwrap();
+        assert_eq!(read_block, block);
+    }
+
+    #[test]
+    fn test_write_sum_of_squares_too_large() {
+        let mut block = create_block();
+        block.sum_of_squares_of_burst_durations = 1 << 36;
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            BurstGapLossBlock::SIZE_BYTES
+        ]));
+        assert!(write_burst_gap_loss_block(&mut cursor, &block).is_err());
+    }
+}
@@ -0,0 +1,299 @@
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+};
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc6843#section-3.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |    BT=16      | I |   resv.   |      Block Length = 6         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                           SSRC of Source                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            Mean Network Round-Trip Delay                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             Min Network Round-Trip Delay                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             Max Network Round-Trip Delay                      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               End System Delay - Seconds (bit 0-31)           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |               End System Delay - Fraction (bit 0-31)          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The network round trip delays are expressed in the "short" NTP format (16 bits of seconds and
+/// 16 bits of fraction) and the end system delay in the 64 bit NTP format.  A value with all bits
+/// set means the measurement is unavailable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DelayMetricsBlock {
+    pub header: XrBlockHeader,
+    pub interval_metric: IntervalMetric,
+    pub ssrc: u32,
+    pub mean_network_rtt: u32,
+    pub min_network_rtt: u32,
+    pub max_network_rtt: u32,
+    pub end_system_delay_seconds: u32,
+    pub end_system_delay_fraction: u32,
+}
+
+impl DelayMetricsBlock {
+    pub const BT: u8 = 16;
+    pub const SIZE_BYTES: usize = 28;
+    pub const UNAVAILABLE: u32 = u32::MAX;
+
+    /// The mean network round trip delay, or None if the measurement is unavailable
+    pub fn mean_network_rtt(&self) -> Option<Duration> {
+        ntp_short_to_duration(self.mean_network_rtt)
+    }
+
+    /// The min network round trip delay, or None if the measurement is unavailable
+    pub fn min_network_rtt(&self) -> Option<Duration> {
+        ntp_short_to_duration(self.min_network_rtt)
+    }
+
+    /// The max network round trip delay, or None if the measurement is unavailable
+    pub fn max_network_rtt(&self) -> Option<Duration> {
+        ntp_short_to_duration(self.max_network_rtt)
+    }
+
+    /// The end system delay, or None if the measurement is unavailable
+    pub fn end_system_delay(&self) -> Option<Duration> {
+        if self.end_system_delay_seconds == Self::UNAVAILABLE
+            && self.end_system_delay_fraction == Self::UNAVAILABLE
+        {
+            return None;
+        }
+        Some(
+            Duration::from_secs(self.end_system_delay_seconds as u64)
+                + Duration::from_nanos(
+                    (self.end_system_delay_fraction as u64 * 1_000_000_000) >> 32,
+                ),
+        )
+    }
+
+    /// The value of the type-specific byte in the block header for the current interval metric.
+    /// The reserved bits are always 0.
+    pub fn type_specific(&self) -> u8 {
+        self.interval_metric.type_specific()
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.type_specific = self.type_specific();
+        self.header.block_length = 6;
+    }
+}
+
+impl LengthBytes for DelayMetricsBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+/// Convert a duration to the "short" NTP format, clamping it to the largest value which isn't
+/// used to signal that the measurement is unavailable.
+pub fn duration_to_ntp_short(duration: Duration) -> u32 {
+    let value = (duration.as_nanos() << 16) / 1_000_000_000;
+    value.min(DelayMetricsBlock::UNAVAILABLE as u128 - 1) as u32
+}
+
+/// Convert a value in the "short" NTP format to a duration, or None if the value signals that the
+/// measurement is unavailable.
+pub fn ntp_short_to_duration(value: u32) -> Option<Duration> {
+    if value == DelayMetricsBlock::UNAVAILABLE {
+        return None;
+    }
+    Some(Duration::from_nanos((value as u64 * 1_000_000_000) >> 16))
+}
+
+/// The value of the interval metric flag (I), which describes the reporting interval the metrics
+/// in a block apply to.
+/// https://datatracker.ietf.org/doc/html/rfc6843#section-3.2
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum IntervalMetric {
+    Sampled = 1,
+    Interval = 2,
+    Cumulative = 3,
+}
+
+impl IntervalMetric {
+    const MASK: u8 = 0b1100_0000;
+    const SHIFT: u8 = 6;
+
+    /// Parse the interval metric flag from the type-specific byte of an XR block header
+    pub fn from_type_specific(type_specific: u8) -> Result<Self> {
+        ((type_specific & Self::MASK) >> Self::SHIFT).try_into()
+    }
+
+    /// The value of the type-specific byte of an XR block header with only this flag set
+    pub fn type_specific(&self) -> u8 {
+        (*self as u8) << Self::SHIFT
+    }
+}
+
+impl TryFrom<u8> for IntervalMetric {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> std::prelude::v1::Result<Self, Self::Error> {
+        match value {
+            1 => Ok(IntervalMetric::Sampled),
+            2 => Ok(IntervalMetric::Interval),
+            3 => Ok(IntervalMetric::Cumulative),
+            v => bail!(RtpParseError::invalid_field("interval metric", "1-3", v)),
+        }
+    }
+}
+
+pub fn read_delay_metrics_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<DelayMetricsBlock> {
+    let interval_metric =
+        IntervalMetric::from_type_specific(header.type_specific).context("interval metric")?;
+
+    Ok(DelayMetricsBlock {
+        header,
+        interval_metric,
+        ssrc: buf.read_u32::<NetworkOrder>().context("ssrc")?,
+        mean_network_rtt: buf.read_u32::<NetworkOrder>().context("mean network rtt")?,
+        min_network_rtt: buf.read_u32::<NetworkOrder>().context("min network rtt")?,
+        max_network_rtt: buf.read_u32::<NetworkOrder>().context("max network rtt")?,
+        end_system_delay_seconds: buf
+            .read_u32::<NetworkOrder>()
+            .context("end system delay seconds")?,
+        end_system_delay_fraction: buf
+            .read_u32::<NetworkOrder>()
+            .context("end system delay fraction")?,
+    })
+}
+
+pub fn write_delay_metrics_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &DelayMetricsBlock,
+) -> Result<()> {
+    let header = XrBlockHeader {
+        type_specific: block.type_specific(),
+        ..block.header.clone()
+    };
+    write_xr_block_header(buf, &header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u32::<NetworkOrder>(block.mean_network_rtt)
+        .context("mean network rtt")?;
+    buf.write_u32::<NetworkOrder>(block.min_network_rtt)
+        .context("min network rtt")?;
+    buf.write_u32::<NetworkOrder>(block.max_network_rtt)
+        .context("max network rtt")?;
+    buf.write_u32::<NetworkOrder>(block.end_system_delay_seconds)
+        .context("end system delay seconds")?;
+    buf.write_u32::<NetworkOrder>(block.end_system_delay_fraction)
+        .context("end system delay fraction")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for DelayMetricsBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Delay metrics report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field(
+                "Interval metric",
+                format_args!("{:?}", self.interval_metric),
+            )?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field(
+                "Mean network RTT",
+                format_args!("{:?}", self.mean_network_rtt()),
+            )?;
+            tree.field(
+                "Min network RTT",
+                format_args!("{:?}", self.min_network_rtt()),
+            )?;
+            tree.field(
+                "Max network RTT",
+                format_args!("{:?}", self.max_network_rtt()),
+            )?;
+            tree.field(
+                "End system delay",
+                format_args!("{:?}", self.end_system_delay()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_read_delay_metrics_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // mean rtt: 0.5s
+            0x00, 0x00, 0x80, 0x00,
+            // min rtt: 0.25s
+            0x00, 0x00, 0x40, 0x00,
+            // max rtt: unavailable
+            0xFF, 0xFF, 0xFF, 0xFF,
+            // end system delay: 1.5s
+            0x00, 0x00, 0x00, 0x01,
+            0x80, 0x00, 0x00, 0x00,
+        ];
+        let header = XrBlockHeader {
+            block_type: DelayMetricsBlock::BT,
+            type_specific: 0b1000_0000,
+            block_length: 6,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_delay_metrics_block(&mut cursor, header).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(block.interval_metric, IntervalMetric::Interval);
+        assert_eq!(block.mean_network_rtt(), Some(Duration::from_millis(500)));
+        assert_eq!(block.min_network_rtt(), Some(Duration::from_millis(250)));
+        assert_eq!(block.max_network_rtt(), None);
+        assert_eq!(block.end_system_delay(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn test_read_delay_metrics_block_reserved_interval_metric() {
+        let header = XrBlockHeader {
+            block_type: DelayMetricsBlock::BT,
+            type_specific: 0,
+            block_length: 6,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 24]));
+        assert!(read_delay_metrics_block(&mut cursor, header).is_err());
+    }
+
+    #[test]
+    fn test_duration_to_ntp_short() {
+        assert_eq!(
+            duration_to_ntp_short(Duration::from_millis(1500)),
+            0x0001_8000
+        );
+        assert_eq!(
+            duration_to_ntp_short(Duration::from_secs(100_000)),
+            DelayMetricsBlock::UNAVAILABLE - 1
+        );
+    }
+}
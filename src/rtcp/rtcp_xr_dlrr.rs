@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+};
+
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.5
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=5      |   reserved    |         block length          |
+/// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+/// |                 SSRC_1 (SSRC of first receiver)               | sub-
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ block
+/// |                         last RR (LRR)                         |   1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                   delay since last RR (DLRR)                  |
+/// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+/// |                 SSRC_2 (SSRC of second receiver)              | sub-
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ block
+/// :                               ...                             :   2
+/// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DlrrBlock {
+    pub header: XrBlockHeader,
+    pub sub_blocks: Vec<DlrrSubBlock>,
+}
+
+impl DlrrBlock {
+    pub const BT: u8 = 5;
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) -> Result<()> {
+        self.header.block_type = Self::BT;
+        self.header.block_length = (self.sub_blocks.len() * 3)
+            .try_into()
+            .map_err(|_| anyhow!("Too many DLRR sub blocks: {}", self.sub_blocks.len()))?;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for DlrrBlock {
+    /// The length of this block, including its header, in bytes
+    fn length_bytes(&self) -> usize {
+        XrBlockHeader::SIZE_BYTES + self.sub_blocks.len() * DlrrSubBlock::SIZE_BYTES
+    }
+}
+
+pub fn read_dlrr_block<B: PacketBuffer>(buf: &mut B, header: XrBlockHeader) -> Result<DlrrBlock> {
+    let mut sub_blocks = Vec::new();
+    let mut sub_block_num = 1;
+    while buf.bytes_remaining() >= DlrrSubBlock::SIZE_BYTES {
+        let sub_block =
+            read_dlrr_sub_block(buf).with_context(|| format!("sub block {sub_block_num}"))?;
+        sub_blocks.push(sub_block);
+        sub_block_num += 1;
+    }
+
+    Ok(DlrrBlock { header, sub_blocks })
+}
+
+pub fn write_dlrr_block<B: PacketBufferMut>(buf: &mut B, block: &DlrrBlock) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    for (i, sub_block) in block.sub_blocks.iter().enumerate() {
+        write_dlrr_sub_block(buf, sub_block).with_context(|| format!("sub block {i}"))?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DlrrSubBlock {
+    pub ssrc: u32,
+    pub last_rr: u32,
+    pub delay_since_last_rr: u32,
+}
+
+impl DlrrSubBlock {
+    pub const SIZE_BYTES: usize = 12;
+}
+
+impl LengthBytes for DlrrSubBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_dlrr_sub_block<B: PacketBuffer>(buf: &mut B) -> Result<DlrrSubBlock> {
+    Ok(DlrrSubBlock {
+        ssrc: buf.read_u32::<NetworkOrder>().context("ssrc")?,
+        last_rr: buf.read_u32::<NetworkOrder>().context("last rr")?,
+        delay_since_last_rr: buf
+            .read_u32::<NetworkOrder>()
+            .context("delay since last rr")?,
+    })
+}
+
+pub fn write_dlrr_sub_block<B: PacketBufferMut>(
+    buf: &mut B,
+    sub_block: &DlrrSubBlock,
+) -> Result<()> {
+    buf.write_u32::<NetworkOrder>(sub_block.ssrc)
+        .context("ssrc")?;
+    buf.write_u32::<NetworkOrder>(sub_block.last_rr)
+        .context("last rr")?;
+    buf.write_u32::<NetworkOrder>(sub_block.delay_since_last_rr)
+        .context("delay since last rr")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for DlrrBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("DLRR report block", |tree| {
+            self.header.pretty_print(tree)?;
+            for (i, sub_block) in self.sub_blocks.iter().enumerate() {
+                tree.subtree(format_args!("Sub-block {}", i + 1), |tree| {
+                    tree.field("SSRC", Ssrc(sub_block.ssrc))?;
+                    tree.field("Last RR", sub_block.last_rr)?;
+                    tree.field("Delay since last RR", sub_block.delay_since_last_rr)
+                })?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_read_dlrr_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // last rr
+            0x00, 0x00, 0x00, 0x02,
+            // dlrr
+            0x00, 0x00, 0x00, 0x03,
+        ];
+        let header = XrBlockHeader {
+            block_type: DlrrBlock::BT,
+            type_specific: 0,
+            block_length: 3,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_dlrr_block(&mut cursor, header).unwrap();
+        assert_eq!(
+            block.sub_blocks,
+            vec![DlrrSubBlock {
+                ssrc: 1,
+                last_rr: 2,
+                delay_since_last_rr: 3,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_write_dlrr_block_roundtrip() {
+        let mut block = DlrrBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            sub_blocks: vec![
+                DlrrSubBlock {
+                    ssrc: 1,
+                    last_rr: 2,
+                    delay_since_last_rr: 3,
+                },
+                DlrrSubBlock {
+                    ssrc: 4,
+                    last_rr: 5,
+                    delay_since_last_rr: 6,
+                },
+            ],
+        };
+        block.sync().unwrap();
+        assert_eq!(block.header.block_type, DlrrBlock::BT);
+        assert_eq!(block.header.block_length, 6);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 28]));
+        write_dlrr_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        assert_eq!(header, block.header);
+        let read_block = read_dlrr_block(&mut read_cursor, header).unwrap();
+        assert_eq!(read_block.sub_blocks, block.sub_blocks);
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use bit_cursor::nsw_types::u4;
+
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::{
+    rtcp_xr::{write_xr_block_header, XrBlockHeader},
+    rtcp_xr_loss_rle::{
+        iter_rle_statuses, read_rle_block_data, rle_block_length_bytes, rle_block_length_field,
+        rle_thinning, write_rle_block_data, RleChunk,
+    },
+};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.2
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=2      | rsvd. |   T   |         block length          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |             end_seq           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          chunk 1              |             chunk 2           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :                              ...                              :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          chunk n-1            |             chunk n           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The layout is identical to the loss RLE block, but in the chunks of a duplicate RLE block a 1
+/// means the packet was duplicated and a 0 means it was not.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct DuplicateRleBlock {
+    pub header: XrBlockHeader,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub chunks: Vec<RleChunk>,
+}
+
+impl DuplicateRleBlock {
+    pub const BT: u8 = 2;
+
+    /// The amount of thinning performed on the sequence number space, taken from the
+    /// type-specific byte of the block header.
+    pub fn thinning(&self) -> u4 {
+        rle_thinning(&self.header)
+    }
+
+    /// Returns an iterator of (sequence number, duplicated) for each sequence number covered by
+    /// this block, taking thinning into account.
+    pub fn iter_duplicate_status(&self) -> impl Iterator<Item = (u16, bool)> + '_ {
+        iter_rle_statuses(self.thinning(), self.begin_seq, self.end_seq, &self.chunks)
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) -> Result<()> {
+        self.header.block_type = Self::BT;
+        self.header.block_length = rle_block_length_field(&self.chunks)?;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for DuplicateRleBlock {
+    /// The length of this block, including its header, in bytes
+    fn length_bytes(&self) -> usize {
+        rle_block_length_bytes(&self.chunks)
+    }
+}
+
+pub fn read_duplicate_rle_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<DuplicateRleBlock> {
+    let (ssrc, begin_seq, end_seq, chunks) = read_rle_block_data(buf)?;
+
+    Ok(DuplicateRleBlock {
+        header,
+        ssrc,
+        begin_seq,
+        end_seq,
+        chunks,
+    })
+}
+
+pub fn write_duplicate_rle_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &DuplicateRleBlock,
+) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    write_rle_block_data(
+        buf,
+        block.ssrc,
+        block.begin_seq,
+        block.end_seq,
+        &block.chunks,
+    )
+}
+
+impl PrettyPrint for DuplicateRleBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Duplicate RLE report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Thinning", u8::from(self.thinning()))?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Begin sequence number", self.begin_seq)?;
+            tree.field("End sequence number", self.end_seq)?;
+            for chunk in &self.chunks {
+                tree.field("Chunk", chunk)?;
+            }
+            Ok(())
+        })
+    }
+}
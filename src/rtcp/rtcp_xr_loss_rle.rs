@@ -0,0 +1,509 @@
+use std::fmt::Display;
+
+use anyhow::{anyhow, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{
+        num_traits::{ConstOne, ConstZero},
+        *,
+    },
+};
+
+use crate::{
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=1      | rsvd. |   T   |         block length          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |             end_seq           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          chunk 1              |             chunk 2           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :                              ...                              :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          chunk n-1            |             chunk n           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// thinning (T): 4 bits
+///   The amount of thinning performed on the sequence number space.
+///   Only those packets with sequence numbers 0 mod 2^T are reported on
+///   by this block.
+///
+/// begin_seq: 16 bits
+///   The first sequence number that this block reports on.
+///
+/// end_seq: 16 bits
+///   The last sequence number that this block reports on plus one.
+///
+/// In the chunks of a loss RLE block a 1 means the packet was received and a 0 means it was lost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct LossRleBlock {
+    pub header: XrBlockHeader,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub chunks: Vec<RleChunk>,
+}
+
+impl LossRleBlock {
+    pub const BT: u8 = 1;
+
+    /// The amount of thinning performed on the sequence number space, taken from the
+    /// type-specific byte of the block header.
+    pub fn thinning(&self) -> u4 {
+        rle_thinning(&self.header)
+    }
+
+    /// Returns an iterator of (sequence number, received) for each sequence number covered by
+    /// this block, taking thinning into account.
+    pub fn iter_loss_status(&self) -> impl Iterator<Item = (u16, bool)> + '_ {
+        iter_rle_statuses(self.thinning(), self.begin_seq, self.end_seq, &self.chunks)
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) -> Result<()> {
+        self.header.block_type = Self::BT;
+        self.header.block_length = rle_block_length_field(&self.chunks)?;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for LossRleBlock {
+    /// The length of this block, including its header, in bytes
+    fn length_bytes(&self) -> usize {
+        rle_block_length_bytes(&self.chunks)
+    }
+}
+
+pub fn read_loss_rle_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<LossRleBlock> {
+    let (ssrc, begin_seq, end_seq, chunks) = read_rle_block_data(buf)?;
+
+    Ok(LossRleBlock {
+        header,
+        ssrc,
+        begin_seq,
+        end_seq,
+        chunks,
+    })
+}
+
+pub fn write_loss_rle_block<B: PacketBufferMut>(buf: &mut B, block: &LossRleBlock) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    write_rle_block_data(
+        buf,
+        block.ssrc,
+        block.begin_seq,
+        block.end_seq,
+        &block.chunks,
+    )
+}
+
+/// A chunk in a loss or duplicate RLE block.
+///
+/// Run length chunk:
+/// ```text
+///  0                   1
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |C|R|        run length         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// Bit vector chunk:
+/// ```text
+///  0                   1
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |C|        bit vector           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// ```
+///
+/// A run length chunk of zeros with a length of 0 is the terminating null chunk, which is used
+/// to pad the block to a 32 bit boundary.
+///
+/// Unlike the TCC chunks, RLE chunks have no notion of a delta size: each sequence number is
+/// represented by a single bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RleChunk {
+    RunLength {
+        run_type: bool,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u14"))]
+        run_length: u14,
+    },
+    BitVector(#[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u15"))] u15),
+    TerminatingNull,
+}
+
+/// Generates run length and bit vector chunks.  The terminating null chunk (and the run length
+/// chunk of zeros with a length of 0, which is written the same way) only appears as padding, so
+/// it isn't generated.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RleChunk {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        if u.arbitrary()? {
+            let max = u16::from(u14::MAX);
+            Ok(RleChunk::RunLength {
+                run_type: u.arbitrary()?,
+                run_length: u14::new(u.int_in_range(1..=max)?),
+            })
+        } else {
+            Ok(RleChunk::BitVector(crate::arbitrary_helpers::u15(u)?))
+        }
+    }
+}
+
+impl RleChunk {
+    pub const SIZE_BYTES: usize = 2;
+    pub const BIT_VECTOR_LENGTH: usize = 15;
+    pub const MAX_RUN_LENGTH: usize = 16383;
+
+    /// The bits represented by this chunk, in sequence number order
+    pub fn bits(&self) -> Vec<bool> {
+        match self {
+            RleChunk::RunLength {
+                run_type,
+                run_length,
+            } => vec![*run_type; u16::from(*run_length) as usize],
+            RleChunk::BitVector(bit_vector) => {
+                let bit_vector = u16::from(*bit_vector);
+                (0..Self::BIT_VECTOR_LENGTH)
+                    .map(|i| (bit_vector >> (Self::BIT_VECTOR_LENGTH - 1 - i)) & 0x1 == 1)
+                    .collect()
+            }
+            RleChunk::TerminatingNull => Vec::new(),
+        }
+    }
+}
+
+impl LengthBytes for RleChunk {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_rle_chunk<B: PacketBuffer>(buf: &mut B) -> Result<RleChunk> {
+    let chunk_type = buf.read_u1().context("chunk type")?;
+    match chunk_type {
+        u1::ZERO => {
+            let run_type = buf.read_bool().context("run type")?;
+            let run_length = buf.read_u14::<NetworkOrder>().context("run length")?;
+            if !run_type && run_length == u14::ZERO {
+                Ok(RleChunk::TerminatingNull)
+            } else {
+                Ok(RleChunk::RunLength {
+                    run_type,
+                    run_length,
+                })
+            }
+        }
+        u1::ONE => Ok(RleChunk::BitVector(
+            buf.read_u15::<NetworkOrder>().context("bit vector")?,
+        )),
+        _ => unreachable!("u1 can only be 0 or 1"),
+    }
+}
+
+pub fn write_rle_chunk<B: PacketBufferMut>(buf: &mut B, chunk: &RleChunk) -> Result<()> {
+    match chunk {
+        RleChunk::RunLength {
+            run_type,
+            run_length,
+        } => {
+            buf.write_u1(u1::ZERO).context("chunk type")?;
+            buf.write_bool(*run_type).context("run type")?;
+            buf.write_u14::<NetworkOrder>(*run_length)
+                .context("run length")?;
+        }
+        RleChunk::BitVector(bit_vector) => {
+            buf.write_u1(u1::ONE).context("chunk type")?;
+            buf.write_u15::<NetworkOrder>(*bit_vector)
+                .context("bit vector")?;
+        }
+        RleChunk::TerminatingNull => {
+            buf.write_u16::<NetworkOrder>(0)
+                .context("terminating null")?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Encode the given bits (one per reported sequence number, in order) as RLE chunks.  Runs which
+/// are at least as long as a bit vector are encoded as run length chunks, everything else is
+/// encoded as bit vectors.
+pub fn create_rle_chunks(bits: &[bool]) -> Vec<RleChunk> {
+    let mut chunks = Vec::new();
+    let mut remaining = bits;
+    while let Some(first) = remaining.first() {
+        let run_length = remaining.iter().take_while(|b| *b == first).count();
+        let num_bits = if run_length >= RleChunk::BIT_VECTOR_LENGTH {
+            let run_length = run_length.min(RleChunk::MAX_RUN_LENGTH);
+            chunks.push(RleChunk::RunLength {
+                run_type: *first,
+                run_length: u14::new(run_length as u16),
+            });
+            run_length
+        } else {
+            let num_bits = RleChunk::BIT_VECTOR_LENGTH.min(remaining.len());
+            let bit_vector = remaining[..num_bits]
+                .iter()
+                .enumerate()
+                .filter(|(_, bit)| **bit)
+                .fold(0u16, |bit_vector, (i, _)| {
+                    bit_vector | 1 << (RleChunk::BIT_VECTOR_LENGTH - 1 - i)
+                });
+            chunks.push(RleChunk::BitVector(u15::new(bit_vector)));
+            num_bits
+        };
+        remaining = &remaining[num_bits..];
+    }
+
+    chunks
+}
+
+pub(crate) fn rle_thinning(header: &XrBlockHeader) -> u4 {
+    u4::new(header.type_specific & 0x0F)
+}
+
+/// The length in bytes of an RLE block with the given chunks.  A terminating null chunk is added
+/// when writing if there is an odd number of chunks, so that is accounted for here as well.
+pub(crate) fn rle_block_length_bytes(chunks: &[RleChunk]) -> usize {
+    // Block header + ssrc + begin/end seq
+    12 + chunks.len().next_multiple_of(2) * RleChunk::SIZE_BYTES
+}
+
+pub(crate) fn rle_block_length_field(chunks: &[RleChunk]) -> Result<u16> {
+    let length_bytes = rle_block_length_bytes(chunks);
+    (length_bytes / 4 - 1)
+        .try_into()
+        .map_err(|_| anyhow!("RLE block length {length_bytes} bytes is too large"))
+}
+
+pub(crate) fn iter_rle_statuses(
+    thinning: u4,
+    begin_seq: u16,
+    end_seq: u16,
+    chunks: &[RleChunk],
+) -> impl Iterator<Item = (u16, bool)> + '_ {
+    let step = 1u32 << u32::from(thinning);
+    let num_seq_nums = end_seq.wrapping_sub(begin_seq) as u32;
+    let num_reported = num_seq_nums.div_ceil(step) as usize;
+    chunks
+        .iter()
+        .flat_map(|chunk| chunk.bits())
+        .take(num_reported)
+        .enumerate()
+        .map(move |(i, bit)| (begin_seq.wrapping_add((i as u32 * step) as u16), bit))
+}
+
+pub(crate) fn read_rle_block_data<B: PacketBuffer>(
+    buf: &mut B,
+) -> Result<(u32, u16, u16, Vec<RleChunk>)> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let begin_seq = buf.read_u16::<NetworkOrder>().context("begin seq")?;
+    let end_seq = buf.read_u16::<NetworkOrder>().context("end seq")?;
+    let mut chunks = Vec::new();
+    while buf.bytes_remaining() >= RleChunk::SIZE_BYTES {
+        let chunk = read_rle_chunk(buf).with_context(|| format!("chunk {}", chunks.len()))?;
+        chunks.push(chunk);
+    }
+
+    Ok((ssrc, begin_seq, end_seq, chunks))
+}
+
+pub(crate) fn write_rle_block_data<B: PacketBufferMut>(
+    buf: &mut B,
+    ssrc: u32,
+    begin_seq: u16,
+    end_seq: u16,
+    chunks: &[RleChunk],
+) -> Result<()> {
+    buf.write_u32::<NetworkOrder>(ssrc).context("ssrc")?;
+    buf.write_u16::<NetworkOrder>(begin_seq)
+        .context("begin seq")?;
+    buf.write_u16::<NetworkOrder>(end_seq).context("end seq")?;
+    for (i, chunk) in chunks.iter().enumerate() {
+        write_rle_chunk(buf, chunk).with_context(|| format!("chunk {i}"))?;
+    }
+    if !chunks.len().is_multiple_of(2) {
+        write_rle_chunk(buf, &RleChunk::TerminatingNull).context("terminating null chunk")?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for LossRleBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Loss RLE report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Thinning", u8::from(self.thinning()))?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Begin sequence number", self.begin_seq)?;
+            tree.field("End sequence number", self.end_seq)?;
+            for chunk in &self.chunks {
+                tree.field("Chunk", chunk)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl Display for RleChunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RleChunk::RunLength {
+                run_type,
+                run_length,
+            } => write!(f, "run of {} {}s", u16::from(*run_length), *run_type as u8),
+            RleChunk::BitVector(bit_vector) => {
+                write!(f, "bit vector {:015b}", u16::from(*bit_vector))
+            }
+            RleChunk::TerminatingNull => write!(f, "terminating null"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_read_loss_rle_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // begin seq 10, end seq 30
+            0x00, 0x0A, 0x00, 0x1E,
+            // bit vector: 101010101010101
+            0xD5, 0x55,
+            // run of 5 received
+            0x40, 0x05,
+        ];
+        let header = XrBlockHeader {
+            block_type: LossRleBlock::BT,
+            type_specific: 0,
+            block_length: 3,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_loss_rle_block(&mut cursor, header).unwrap();
+        assert_eq!(block.ssrc, 1);
+        assert_eq!(
+            block.chunks,
+            vec![
+                RleChunk::BitVector(u15::new(0x5555)),
+                RleChunk::RunLength {
+                    run_type: true,
+                    run_length: u14::new(5)
+                }
+            ]
+        );
+        let statuses: Vec<(u16, bool)> = block.iter_loss_status().collect();
+        assert_eq!(statuses.len(), 20);
+        assert_eq!(statuses[0], (10, true));
+        assert_eq!(statuses[1], (11, false));
+        assert_eq!(statuses[14], (24, true));
+        assert_eq!(statuses[19], (29, true));
+    }
+
+    #[test]
+    fn test_iter_loss_status_thinning() {
+        let block = LossRleBlock {
+            header: XrBlockHeader {
+                block_type: LossRleBlock::BT,
+                // T = 1
+                type_specific: 1,
+                block_length: 3,
+            },
+            ssrc: 1,
+            begin_seq: 65530,
+            end_seq: 4,
+            chunks: create_rle_chunks(&[true, false, true, false, true]),
+        };
+        let statuses: Vec<(u16, bool)> = block.iter_loss_status().collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (65530, true),
+                (65532, false),
+                (65534, true),
+                (0, false),
+                (2, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_create_rle_chunks() {
+        let mut bits = vec![false; 20];
+        bits.extend([true, false, true]);
+        let chunks = create_rle_chunks(&bits);
+        assert_eq!(
+            chunks,
+            vec![
+                RleChunk::RunLength {
+                    run_type: false,
+                    run_length: u14::new(20)
+                },
+                RleChunk::BitVector(u15::new(0b101 << 12)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_write_loss_rle_block_roundtrip() {
+        let mut block = LossRleBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            ssrc: 42,
+            begin_seq: 100,
+            end_seq: 120,
+            chunks: vec![RleChunk::RunLength {
+                run_type: true,
+                run_length: u14::new(20),
+            }],
+        };
+        block.sync().unwrap();
+        assert_eq!(block.header.block_length, 3);
+        assert_eq!(block.length_bytes(), 16);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 16]));
+        write_loss_rle_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_loss_rle_block(&mut read_cursor, header).unwrap();
+        assert_eq!(
+            read_block.chunks,
+            vec![block.chunks[0], RleChunk::TerminatingNull]
+        );
+        assert!(read_block.iter_loss_status().all(|(_, received)| received));
+    }
+}
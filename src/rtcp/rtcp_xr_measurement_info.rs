@@ -0,0 +1,257 @@
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+};
+
+use crate::{
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc6776#section-4.1
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=14     |    Reserved   |      block length = 7         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                     SSRC of stream source                     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |            Reserved           |    first sequence number      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           extended first sequence number of interval          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                 extended last sequence number                 |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |              Measurement Duration (Interval)                  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Measurement Duration (Cumulative) - Seconds         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |           Measurement Duration (Cumulative) - Fraction        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// Measurement Duration (Interval): 32 bits
+///   The duration, expressed in units of 1/65536 seconds, of the reporting interval applicable to
+///   Interval reports which use this measurement information block.
+///
+/// Measurement Duration (Cumulative): 64 bits
+///   The duration of the reporting interval applicable to Cumulative reports which use this
+///   measurement information block, in the 64 bit NTP timestamp format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct MeasurementInfoBlock {
+    pub header: XrBlockHeader,
+    pub ssrc: u32,
+    pub first_seq: u16,
+    pub extended_interval_first_seq: u32,
+    pub extended_last_seq: u32,
+    pub interval_duration: u32,
+    pub cumulative_duration_seconds: u32,
+    pub cumulative_duration_fraction: u32,
+}
+
+impl MeasurementInfoBlock {
+    pub const BT: u8 = 14;
+    pub const SIZE_BYTES: usize = 32;
+
+    /// The interval measurement duration as a [`Duration`]
+    pub fn interval_duration(&self) -> Duration {
+        Duration::from_nanos((self.interval_duration as u64 * 1_000_000_000) >> 16)
+    }
+
+    /// Set the interval measurement duration field from the given [`Duration`].  Durations which
+    /// are too long to be represented are clamped to the max value.
+    pub fn set_interval_duration(&mut self, duration: Duration) {
+        let units = (duration.as_nanos() << 16) / 1_000_000_000;
+        self.interval_duration = units.min(u32::MAX as u128) as u32;
+    }
+
+    /// The cumulative measurement duration as a [`Duration`]
+    pub fn cumulative_duration(&self) -> Duration {
+        Duration::from_secs(self.cumulative_duration_seconds as u64)
+            + Duration::from_nanos((self.cumulative_duration_fraction as u64 * 1_000_000_000) >> 32)
+    }
+
+    /// Set the cumulative measurement duration fields from the given [`Duration`].  Durations
+    /// which are too long to be represented are clamped to the max value.
+    pub fn set_cumulative_duration(&mut self, duration: Duration) {
+        if duration.as_secs() > u32::MAX as u64 {
+            self.cumulative_duration_seconds = u32::MAX;
+            self.cumulative_duration_fraction = u32::MAX;
+        } else {
+            self.cumulative_duration_seconds = duration.as_secs() as u32;
+            self.cumulative_duration_fraction =
+                (((duration.subsec_nanos() as u64) << 32) / 1_000_000_000) as u32;
+        }
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.block_length = 7;
+    }
+}
+
+impl LengthBytes for MeasurementInfoBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_measurement_info_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<MeasurementInfoBlock> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let reserved = buf.read_u16::<NetworkOrder>().context("reserved")?;
+    mode.check_reserved(warnings, "measurement info reserved bits", reserved.into())?;
+
+    Ok(MeasurementInfoBlock {
+        header,
+        ssrc,
+        first_seq: buf.read_u16::<NetworkOrder>().context("first seq")?,
+        extended_interval_first_seq: buf
+            .read_u32::<NetworkOrder>()
+            .context("extended interval first seq")?,
+        extended_last_seq: buf
+            .read_u32::<NetworkOrder>()
+            .context("extended last seq")?,
+        interval_duration: buf
+            .read_u32::<NetworkOrder>()
+            .context("interval duration")?,
+        cumulative_duration_seconds: buf
+            .read_u32::<NetworkOrder>()
+            .context("cumulative duration seconds")?,
+        cumulative_duration_fraction: buf
+            .read_u32::<NetworkOrder>()
+            .context("cumulative duration fraction")?,
+    })
+}
+
+pub fn write_measurement_info_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &MeasurementInfoBlock,
+) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u16::<NetworkOrder>(0).context("reserved")?;
+    buf.write_u16::<NetworkOrder>(block.first_seq)
+        .context("first seq")?;
+    buf.write_u32::<NetworkOrder>(block.extended_interval_first_seq)
+        .context("extended interval first seq")?;
+    buf.write_u32::<NetworkOrder>(block.extended_last_seq)
+        .context("extended last seq")?;
+    buf.write_u32::<NetworkOrder>(block.interval_duration)
+        .context("interval duration")?;
+    buf.write_u32::<NetworkOrder>(block.cumulative_duration_seconds)
+        .context("cumulative duration seconds")?;
+    buf.write_u32::<NetworkOrder>(block.cumulative_duration_fraction)
+        .context("cumulative duration fraction")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for MeasurementInfoBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Measurement information report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("First sequence number", self.first_seq)?;
+            tree.field(
+                "Extended interval first sequence number",
+                self.extended_interval_first_seq,
+            )?;
+            tree.field("Extended last sequence number", self.extended_last_seq)?;
+            tree.field(
+                "Interval duration",
+                format_args!("{:?}", self.interval_duration()),
+            )?;
+            tree.field(
+                "Cumulative duration",
+                format_args!("{:?}", self.cumulative_duration()),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_durations() {
+        let mut block = MeasurementInfoBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            ssrc: 0,
+            first_seq: 0,
+            extended_interval_first_seq: 0,
+            extended_last_seq: 0,
+            interval_duration: 0,
+            cumulative_duration_seconds: 0,
+            cumulative_duration_fraction: 0,
+        };
+        block.set_interval_duration(Duration::from_millis(2500));
+        assert_eq!(block.interval_duration, 0x0002_8000);
+        assert_eq!(block.interval_duration(), Duration::from_millis(2500));
+
+        block.set_cumulative_duration(Duration::from_millis(10_500));
+        assert_eq!(block.cumulative_duration_seconds, 10);
+        assert_eq!(block.cumulative_duration_fraction, 0x8000_0000);
+        assert_eq!(block.cumulative_duration(), Duration::from_millis(10_500));
+    }
+
+    #[test]
+    fn test_write_measurement_info_block_roundtrip() {
+        let mut block = MeasurementInfoBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            ssrc: 42,
+            first_seq: 1000,
+            extended_interval_first_seq: 70_000,
+            extended_last_seq: 71_000,
+            interval_duration: 0x0005_0000,
+            cumulative_duration_seconds: 60,
+            cumulative_duration_fraction: 0,
+        };
+        block.sync();
+        assert_eq!(block.header.block_type, MeasurementInfoBlock::BT);
+        assert_eq!(block.header.block_length, 7);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            MeasurementInfoBlock::SIZE_BYTES
+        ]));
+        write_measurement_info_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_measurement_info_block(
+            &mut read_cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(read_block, block);
+    }
+}
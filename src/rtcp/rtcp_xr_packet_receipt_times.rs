@@ -0,0 +1,274 @@
+use anyhow::{anyhow, bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    nsw_types::u4,
+};
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.3
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=3      | rsvd. |   T   |         block length          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |             end_seq           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |       Receipt time of packet begin_seq                        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |       Receipt time of packet (begin_seq + 1) mod 65536        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// :                              ...                              :
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |       Receipt time of packet (end_seq - 1) mod 65536          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// Receipt time of packet: 32 bits
+///   The receipt time of the packet with the sequence number, expressed in the same units as the
+///   RTP timestamp of the packet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketReceiptTimesBlock {
+    pub header: XrBlockHeader,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub receipt_times: Vec<u32>,
+}
+
+/// Generates a sequence range that covers the receipt times, given the thinning.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PacketReceiptTimesBlock {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let header: XrBlockHeader = u.arbitrary()?;
+        let step = 1usize << (header.type_specific & 0x0F);
+        let mut receipt_times: Vec<u32> = u.arbitrary()?;
+        receipt_times.truncate(u16::MAX as usize / step);
+        let begin_seq: u16 = u.arbitrary()?;
+        let end_seq = begin_seq.wrapping_add((receipt_times.len() * step) as u16);
+        Ok(PacketReceiptTimesBlock {
+            header,
+            ssrc: u.arbitrary()?,
+            begin_seq,
+            end_seq,
+            receipt_times,
+        })
+    }
+}
+
+impl PacketReceiptTimesBlock {
+    pub const BT: u8 = 3;
+
+    /// The amount of thinning performed on the sequence number space, taken from the
+    /// type-specific byte of the block header.
+    pub fn thinning(&self) -> u4 {
+        u4::new(self.header.type_specific & 0x0F)
+    }
+
+    /// Returns an iterator of (sequence number, receipt time) for each receipt time in this
+    /// block, taking thinning into account.
+    pub fn iter_receipt_times(&self) -> impl Iterator<Item = (u16, u32)> + '_ {
+        let step = 1u32 << u32::from(self.thinning());
+        self.receipt_times
+            .iter()
+            .enumerate()
+            .map(move |(i, time)| (self.begin_seq.wrapping_add((i as u32 * step) as u16), *time))
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) -> Result<()> {
+        self.header.block_type = Self::BT;
+        self.header.block_length = (self.receipt_times.len() + 2).try_into().map_err(|_| {
+            anyhow!(
+                "Too many packet receipt times: {}",
+                self.receipt_times.len()
+            )
+        })?;
+
+        Ok(())
+    }
+}
+
+impl LengthBytes for PacketReceiptTimesBlock {
+    /// The length of this block, including its header, in bytes
+    fn length_bytes(&self) -> usize {
+        // Block header + ssrc + begin/end seq
+        12 + self.receipt_times.len() * 4
+    }
+}
+
+pub fn read_packet_receipt_times_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<PacketReceiptTimesBlock> {
+    if header.block_length < 2 {
+        bail!(RtpParseError::invalid_field(
+            "block length",
+            "at least 2",
+            header.block_length
+        ));
+    }
+    let num_receipt_times = header.block_length as usize - 2;
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let begin_seq = buf.read_u16::<NetworkOrder>().context("begin seq")?;
+    let end_seq = buf.read_u16::<NetworkOrder>().context("end seq")?;
+    let step = 1u32 << (header.type_specific & 0x0F);
+    let num_seq_nums = (end_seq.wrapping_sub(begin_seq) as u32).div_ceil(step) as usize;
+    if num_receipt_times > num_seq_nums {
+        bail!(RtpParseError::invalid_field(
+            "receipt time count",
+            format!("at most {num_seq_nums}, for sequence range {begin_seq}-{end_seq}"),
+            num_receipt_times
+        ));
+    }
+    if num_receipt_times * 4 > buf.bytes_remaining() {
+        bail!(RtpParseError::truncated(
+            num_receipt_times * 4,
+            buf.bytes_remaining()
+        ));
+    }
+    let receipt_times = (0..num_receipt_times)
+        .map(|i| {
+            buf.read_u32::<NetworkOrder>()
+                .with_context(|| format!("receipt time {i}"))
+        })
+        .collect::<Result<Vec<u32>>>()?;
+
+    Ok(PacketReceiptTimesBlock {
+        header,
+        ssrc,
+        begin_seq,
+        end_seq,
+        receipt_times,
+    })
+}
+
+pub fn write_packet_receipt_times_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &PacketReceiptTimesBlock,
+) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u16::<NetworkOrder>(block.begin_seq)
+        .context("begin seq")?;
+    buf.write_u16::<NetworkOrder>(block.end_seq)
+        .context("end seq")?;
+    for (i, receipt_time) in block.receipt_times.iter().enumerate() {
+        buf.write_u32::<NetworkOrder>(*receipt_time)
+            .with_context(|| format!("receipt time {i}"))?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for PacketReceiptTimesBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Packet receipt times report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("Thinning", u8::from(self.thinning()))?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Begin sequence number", self.begin_seq)?;
+            tree.field("End sequence number", self.end_seq)?;
+            for (seq_num, receipt_time) in self.iter_receipt_times() {
+                tree.field(&format!("Seq {seq_num}"), receipt_time)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_read_packet_receipt_times_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // begin seq 65535, end seq 1
+            0xFF, 0xFF, 0x00, 0x01,
+            // receipt times
+            0x00, 0x00, 0x00, 0x0A,
+            0x00, 0x00, 0x00, 0x14,
+        ];
+        let header = XrBlockHeader {
+            block_type: PacketReceiptTimesBlock::BT,
+            type_specific: 0,
+            block_length: 4,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_packet_receipt_times_block(&mut cursor, header).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(
+            block.iter_receipt_times().collect::<Vec<(u16, u32)>>(),
+            vec![(65535, 10), (0, 20)]
+        );
+    }
+
+    #[test]
+    fn test_read_packet_receipt_times_block_too_many_times() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // begin seq 0, end seq 1
+            0x00, 0x00, 0x00, 0x01,
+            // receipt times
+            0x00, 0x00, 0x00, 0x0A,
+            0x00, 0x00, 0x00, 0x14,
+        ];
+        let header = XrBlockHeader {
+            block_type: PacketReceiptTimesBlock::BT,
+            type_specific: 0,
+            block_length: 4,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        assert!(read_packet_receipt_times_block(&mut cursor, header).is_err());
+    }
+
+    #[test]
+    fn test_write_packet_receipt_times_block_roundtrip() {
+        let mut block = PacketReceiptTimesBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 1,
+                block_length: 0,
+            },
+            ssrc: 42,
+            begin_seq: 10,
+            end_seq: 16,
+            receipt_times: vec![100, 200, 300],
+        };
+        block.sync().unwrap();
+        assert_eq!(block.header.block_length, 5);
+
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; block.length_bytes()]));
+        write_packet_receipt_times_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_packet_receipt_times_block(&mut read_cursor, header).unwrap();
+        assert_eq!(read_block, block);
+        assert_eq!(
+            read_block.iter_receipt_times().collect::<Vec<(u16, u32)>>(),
+            vec![(10, 100), (12, 200), (14, 300)]
+        );
+    }
+}
@@ -0,0 +1,90 @@
+use anyhow::{Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+};
+
+use crate::{
+    ntp::NtpTimestamp,
+    pretty::{FieldTree, PrettyPrint},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.4
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=4      |   reserved    |       block length = 2        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |              NTP timestamp, most significant word             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |             NTP timestamp, least significant word             |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// This block extends RTCP's timestamp reporting so that non-senders may also send timestamps.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct ReceiverReferenceTimeBlock {
+    pub header: XrBlockHeader,
+    pub ntp_timestamp_msw: u32,
+    pub ntp_timestamp_lsw: u32,
+}
+
+impl ReceiverReferenceTimeBlock {
+    pub const BT: u8 = 4;
+    pub const SIZE_BYTES: usize = 12;
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.block_length = 2;
+    }
+}
+
+impl LengthBytes for ReceiverReferenceTimeBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+pub fn read_receiver_reference_time_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<ReceiverReferenceTimeBlock> {
+    Ok(ReceiverReferenceTimeBlock {
+        header,
+        ntp_timestamp_msw: buf
+            .read_u32::<NetworkOrder>()
+            .context("ntp timestamp msw")?,
+        ntp_timestamp_lsw: buf
+            .read_u32::<NetworkOrder>()
+            .context("ntp timestamp lsw")?,
+    })
+}
+
+pub fn write_receiver_reference_time_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &ReceiverReferenceTimeBlock,
+) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ntp_timestamp_msw)
+        .context("ntp timestamp msw")?;
+    buf.write_u32::<NetworkOrder>(block.ntp_timestamp_lsw)
+        .context("ntp timestamp lsw")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for ReceiverReferenceTimeBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Receiver reference time report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field(
+                "NTP timestamp",
+                NtpTimestamp::from_parts(self.ntp_timestamp_msw, self.ntp_timestamp_lsw),
+            )
+        })
+    }
+}
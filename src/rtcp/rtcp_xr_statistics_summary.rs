@@ -0,0 +1,382 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+};
+
+use crate::{
+    error::RtpParseError,
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.6
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=6      |L|D|J|ToH|rsvd.|       block length = 9        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          begin_seq            |             end_seq           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        lost_packets                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        dup_packets                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         min_jitter                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         max_jitter                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         mean_jitter                           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                         dev_jitter                            |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | min_ttl_or_hl | max_ttl_or_hl |mean_ttl_or_hl | dev_ttl_or_hl |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The L, D, J and ToH flags indicate which of the loss, duplicate, jitter and TTL/hop limit
+/// fields contain valid values.  When a flag is not set the contents of the corresponding fields
+/// are undefined.  The flags are modeled as fields here: the type-specific byte of the block
+/// header is derived from them when writing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct StatisticsSummaryBlock {
+    pub header: XrBlockHeader,
+    pub loss_report: bool,
+    pub duplicate_report: bool,
+    pub jitter: bool,
+    pub ttl_or_hop_limit: TtlOrHopLimit,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub lost_packets: u32,
+    pub dup_packets: u32,
+    pub min_jitter: u32,
+    pub max_jitter: u32,
+    pub mean_jitter: u32,
+    pub dev_jitter: u32,
+    pub min_ttl_or_hl: u8,
+    pub max_ttl_or_hl: u8,
+    pub mean_ttl_or_hl: u8,
+    pub dev_ttl_or_hl: u8,
+}
+
+impl StatisticsSummaryBlock {
+    pub const BT: u8 = 6;
+    pub const SIZE_BYTES: usize = 40;
+
+    const LOSS_REPORT_MASK: u8 = 0b1000_0000;
+    const DUPLICATE_REPORT_MASK: u8 = 0b0100_0000;
+    const JITTER_MASK: u8 = 0b0010_0000;
+    const TTL_OR_HOP_LIMIT_MASK: u8 = 0b0001_1000;
+    const TTL_OR_HOP_LIMIT_SHIFT: u8 = 3;
+
+    /// The value of the type-specific byte in the block header for the current flags.  The
+    /// reserved bits are always 0.
+    pub fn type_specific(&self) -> u8 {
+        let mut type_specific = (self.ttl_or_hop_limit as u8) << Self::TTL_OR_HOP_LIMIT_SHIFT;
+        if self.loss_report {
+            type_specific |= Self::LOSS_REPORT_MASK;
+        }
+        if self.duplicate_report {
+            type_specific |= Self::DUPLICATE_REPORT_MASK;
+        }
+        if self.jitter {
+            type_specific |= Self::JITTER_MASK;
+        }
+        type_specific
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.type_specific = self.type_specific();
+        self.header.block_length = 9;
+    }
+}
+
+impl LengthBytes for StatisticsSummaryBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+/// The value of the ToH field, which describes the contents of the TTL/hop limit fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub enum TtlOrHopLimit {
+    NoData = 0,
+    Ipv4Ttl = 1,
+    Ipv6HopLimit = 2,
+}
+
+impl TryFrom<u8> for TtlOrHopLimit {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> std::prelude::v1::Result<Self, Self::Error> {
+        match value {
+            0 => Ok(TtlOrHopLimit::NoData),
+            1 => Ok(TtlOrHopLimit::Ipv4Ttl),
+            2 => Ok(TtlOrHopLimit::Ipv6HopLimit),
+            v => bail!(RtpParseError::invalid_field("ToH", "0-2", v)),
+        }
+    }
+}
+
+pub fn read_statistics_summary_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+) -> Result<StatisticsSummaryBlock> {
+    let loss_report = header.type_specific & StatisticsSummaryBlock::LOSS_REPORT_MASK != 0;
+    let duplicate_report =
+        header.type_specific & StatisticsSummaryBlock::DUPLICATE_REPORT_MASK != 0;
+    let jitter = header.type_specific & StatisticsSummaryBlock::JITTER_MASK != 0;
+    let ttl_or_hop_limit = ((header.type_specific & StatisticsSummaryBlock::TTL_OR_HOP_LIMIT_MASK)
+        >> StatisticsSummaryBlock::TTL_OR_HOP_LIMIT_SHIFT)
+        .try_into()
+        .context("ttl or hop limit")?;
+
+    Ok(StatisticsSummaryBlock {
+        header,
+        loss_report,
+        duplicate_report,
+        jitter,
+        ttl_or_hop_limit,
+        ssrc: buf.read_u32::<NetworkOrder>().context("ssrc")?,
+        begin_seq: buf.read_u16::<NetworkOrder>().context("begin seq")?,
+        end_seq: buf.read_u16::<NetworkOrder>().context("end seq")?,
+        lost_packets: buf.read_u32::<NetworkOrder>().context("lost packets")?,
+        dup_packets: buf.read_u32::<NetworkOrder>().context("dup packets")?,
+        min_jitter: buf.read_u32::<NetworkOrder>().context("min jitter")?,
+        max_jitter: buf.read_u32::<NetworkOrder>().context("max jitter")?,
+        mean_jitter: buf.read_u32::<NetworkOrder>().context("mean jitter")?,
+        dev_jitter: buf.read_u32::<NetworkOrder>().context("dev jitter")?,
+        min_ttl_or_hl: buf.read_u8().context("min ttl or hl")?,
+        max_ttl_or_hl: buf.read_u8().context("max ttl or hl")?,
+        mean_ttl_or_hl: buf.read_u8().context("mean ttl or hl")?,
+        dev_ttl_or_hl: buf.read_u8().context("dev ttl or hl")?,
+    })
+}
+
+pub fn write_statistics_summary_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &StatisticsSummaryBlock,
+) -> Result<()> {
+    let header = XrBlockHeader {
+        type_specific: block.type_specific(),
+        ..block.header.clone()
+    };
+    write_xr_block_header(buf, &header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u16::<NetworkOrder>(block.begin_seq)
+        .context("begin seq")?;
+    buf.write_u16::<NetworkOrder>(block.end_seq)
+        .context("end seq")?;
+    buf.write_u32::<NetworkOrder>(block.lost_packets)
+        .context("lost packets")?;
+    buf.write_u32::<NetworkOrder>(block.dup_packets)
+        .context("dup packets")?;
+    buf.write_u32::<NetworkOrder>(block.min_jitter)
+        .context("min jitter")?;
+    buf.write_u32::<NetworkOrder>(block.max_jitter)
+        .context("max jitter")?;
+    buf.write_u32::<NetworkOrder>(block.mean_jitter)
+        .context("mean jitter")?;
+    buf.write_u32::<NetworkOrder>(block.dev_jitter)
+        .context("dev jitter")?;
+    buf.write_u8(block.min_ttl_or_hl).context("min ttl or hl")?;
+    buf.write_u8(block.max_ttl_or_hl).context("max ttl or hl")?;
+    buf.write_u8(block.mean_ttl_or_hl)
+        .context("mean ttl or hl")?;
+    buf.write_u8(block.dev_ttl_or_hl).context("dev ttl or hl")?;
+
+    Ok(())
+}
+
+/// Builds a [`StatisticsSummaryBlock`], setting the flag for each group of fields that is
+/// provided.  Fields which aren't provided are left as 0.
+pub struct StatisticsSummaryBlockBuilder {
+    block: StatisticsSummaryBlock,
+}
+
+impl StatisticsSummaryBlockBuilder {
+    pub fn new(ssrc: u32, begin_seq: u16, end_seq: u16) -> Self {
+        Self {
+            block: StatisticsSummaryBlock {
+                header: XrBlockHeader {
+                    block_type: StatisticsSummaryBlock::BT,
+                    type_specific: 0,
+                    block_length: 0,
+                },
+                loss_report: false,
+                duplicate_report: false,
+                jitter: false,
+                ttl_or_hop_limit: TtlOrHopLimit::NoData,
+                ssrc,
+                begin_seq,
+                end_seq,
+                lost_packets: 0,
+                dup_packets: 0,
+                min_jitter: 0,
+                max_jitter: 0,
+                mean_jitter: 0,
+                dev_jitter: 0,
+                min_ttl_or_hl: 0,
+                max_ttl_or_hl: 0,
+                mean_ttl_or_hl: 0,
+                dev_ttl_or_hl: 0,
+            },
+        }
+    }
+
+    pub fn lost_packets(mut self, lost_packets: u32) -> Self {
+        self.block.loss_report = true;
+        self.block.lost_packets = lost_packets;
+        self
+    }
+
+    pub fn dup_packets(mut self, dup_packets: u32) -> Self {
+        self.block.duplicate_report = true;
+        self.block.dup_packets = dup_packets;
+        self
+    }
+
+    pub fn jitter(mut self, min: u32, max: u32, mean: u32, dev: u32) -> Self {
+        self.block.jitter = true;
+        self.block.min_jitter = min;
+        self.block.max_jitter = max;
+        self.block.mean_jitter = mean;
+        self.block.dev_jitter = dev;
+        self
+    }
+
+    pub fn ttl_or_hop_limit(
+        mut self,
+        ttl_or_hop_limit: TtlOrHopLimit,
+        min: u8,
+        max: u8,
+        mean: u8,
+        dev: u8,
+    ) -> Self {
+        self.block.ttl_or_hop_limit = ttl_or_hop_limit;
+        self.block.min_ttl_or_hl = min;
+        self.block.max_ttl_or_hl = max;
+        self.block.mean_ttl_or_hl = mean;
+        self.block.dev_ttl_or_hl = dev;
+        self
+    }
+
+    pub fn build(mut self) -> StatisticsSummaryBlock {
+        self.block.sync();
+        self.block
+    }
+}
+
+impl PrettyPrint for StatisticsSummaryBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("Statistics summary report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Begin sequence number", self.begin_seq)?;
+            tree.field("End sequence number", self.end_seq)?;
+            if self.loss_report {
+                tree.field("Lost packets", self.lost_packets)?;
+            }
+            if self.duplicate_report {
+                tree.field("Duplicate packets", self.dup_packets)?;
+            }
+            if self.jitter {
+                tree.field("Min jitter", self.min_jitter)?;
+                tree.field("Max jitter", self.max_jitter)?;
+                tree.field("Mean jitter", self.mean_jitter)?;
+                tree.field("Dev jitter", self.dev_jitter)?;
+            }
+            if self.ttl_or_hop_limit != TtlOrHopLimit::NoData {
+                tree.field(
+                    "TTL or hop limit",
+                    format_args!("{:?}", self.ttl_or_hop_limit),
+                )?;
+                tree.field("Min TTL or hop limit", self.min_ttl_or_hl)?;
+                tree.field("Max TTL or hop limit", self.max_ttl_or_hl)?;
+                tree.field("Mean TTL or hop limit", self.mean_ttl_or_hl)?;
+                tree.field("Dev TTL or hop limit", self.dev_ttl_or_hl)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_builder() {
+        let block = StatisticsSummaryBlockBuilder::new(1, 10, 20)
+            .lost_packets(3)
+            .jitter(1, 2, 3, 4)
+            .build();
+        assert!(block.loss_report);
+        assert!(!block.duplicate_report);
+        assert!(block.jitter);
+        assert_eq!(block.ttl_or_hop_limit, TtlOrHopLimit::NoData);
+        assert_eq!(block.header.block_type, StatisticsSummaryBlock::BT);
+        assert_eq!(block.header.type_specific, 0b1010_0000);
+        assert_eq!(block.header.block_length, 9);
+    }
+
+    #[test]
+    fn test_write_reserved_bits_zeroed() {
+        let mut block = StatisticsSummaryBlockBuilder::new(1, 10, 20)
+            .dup_packets(2)
+            .ttl_or_hop_limit(TtlOrHopLimit::Ipv6HopLimit, 1, 2, 3, 4)
+            .build();
+        // Garbage in the reserved bits of the header shouldn't make it onto the wire
+        block.header.type_specific = 0xFF;
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 40]));
+        write_statistics_summary_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(data[1], 0b0101_0000);
+        assert_eq!(data[1] & 0b0000_0111, 0);
+    }
+
+    #[test]
+    fn test_write_roundtrip() {
+        let block = StatisticsSummaryBlockBuilder::new(1, 10, 20)
+            .lost_packets(3)
+            .dup_packets(4)
+            .jitter(5, 6, 7, 8)
+            .ttl_or_hop_limit(TtlOrHopLimit::Ipv4Ttl, 9, 10, 11, 12)
+            .build();
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 40]));
+        write_statistics_summary_block(&mut cursor, &block).unwrap();
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_statistics_summary_block(&mut read_cursor, header).unwrap();
+        assert_eq!(read_block, block);
+    }
+
+    #[test]
+    fn test_read_reserved_toh() {
+        let header = XrBlockHeader {
+            block_type: StatisticsSummaryBlock::BT,
+            type_specific: 0b0001_1000,
+            block_length: 9,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 36]));
+        assert!(read_statistics_summary_block(&mut cursor, header).is_err());
+    }
+}
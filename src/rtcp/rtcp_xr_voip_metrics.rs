@@ -0,0 +1,370 @@
+use anyhow::{Context, Result};
+use bit_cursor::{
+    bit_read_exts::BitReadExts, bit_write_exts::BitWriteExts, byte_order::NetworkOrder,
+    nsw_types::*,
+};
+
+use crate::{
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, PrettyPrint, Ssrc},
+    LengthBytes, PacketBuffer, PacketBufferMut,
+};
+
+use super::rtcp_xr::{write_xr_block_header, XrBlockHeader};
+
+/// https://datatracker.ietf.org/doc/html/rfc3611#section-4.7
+///  0                   1                   2                   3
+///  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     BT=7      |   reserved    |       block length = 8        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |                        SSRC of source                         |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   loss rate   | discard rate  | burst density |  gap density  |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |       burst duration          |         gap duration          |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |     round trip delay          |       end system delay        |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// | signal level  |  noise level  |     RERL      |     Gmin      |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   R factor    | ext. R factor |    MOS-LQ     |    MOS-CQ     |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |   RX config   |   reserved    |          JB nominal           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+/// |          JB maximum           |          JB abs max           |
+/// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+///
+/// The RX config byte is made up of the packet loss concealment (PLC, 2 bits), jitter buffer
+/// adaptive (JBA, 2 bits) and jitter buffer rate (JB rate, 4 bits) fields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct VoipMetricsBlock {
+    pub header: XrBlockHeader,
+    pub ssrc: u32,
+    pub loss_rate: u8,
+    pub discard_rate: u8,
+    pub burst_density: u8,
+    pub gap_density: u8,
+    pub burst_duration: u16,
+    pub gap_duration: u16,
+    pub round_trip_delay: u16,
+    pub end_system_delay: u16,
+    /// Signal level in dBm
+    pub signal_level: i8,
+    /// Noise level in dBm
+    pub noise_level: i8,
+    pub residual_echo_return_loss: u8,
+    pub gmin: u8,
+    pub r_factor: u8,
+    pub ext_r_factor: u8,
+    /// MOS-LQ, multiplied by 10
+    pub mos_lq: u8,
+    /// MOS-CQ, multiplied by 10
+    pub mos_cq: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u2"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u2))]
+    pub packet_loss_concealment: u2,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u2"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u2))]
+    pub jitter_buffer_adaptive: u2,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serde_nsw::u4"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::u4))]
+    pub jitter_buffer_rate: u4,
+    pub jitter_buffer_nominal: u16,
+    pub jitter_buffer_maximum: u16,
+    pub jitter_buffer_abs_maximum: u16,
+}
+
+impl VoipMetricsBlock {
+    pub const BT: u8 = 7;
+    pub const SIZE_BYTES: usize = 36;
+    /// The value used by the R factor and MOS fields to signal that the value is unavailable
+    pub const UNAVAILABLE: u8 = 127;
+
+    /// The MOS-LQ score, or None if it is unavailable
+    pub fn mos_lq_score(&self) -> Option<f32> {
+        mos_score(self.mos_lq)
+    }
+
+    /// The MOS-CQ score, or None if it is unavailable
+    pub fn mos_cq_score(&self) -> Option<f32> {
+        mos_score(self.mos_cq)
+    }
+
+    /// Update the block header to match the contents of this block.
+    pub fn sync(&mut self) {
+        self.header.block_type = Self::BT;
+        self.header.block_length = 8;
+    }
+}
+
+impl LengthBytes for VoipMetricsBlock {
+    fn length_bytes(&self) -> usize {
+        Self::SIZE_BYTES
+    }
+}
+
+fn mos_score(value: u8) -> Option<f32> {
+    match value {
+        VoipMetricsBlock::UNAVAILABLE => None,
+        v => Some(v as f32 / 10.0),
+    }
+}
+
+pub fn read_voip_metrics_block<B: PacketBuffer>(
+    buf: &mut B,
+    header: XrBlockHeader,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<VoipMetricsBlock> {
+    let ssrc = buf.read_u32::<NetworkOrder>().context("ssrc")?;
+    let loss_rate = buf.read_u8().context("loss rate")?;
+    let discard_rate = buf.read_u8().context("discard rate")?;
+    let burst_density = buf.read_u8().context("burst density")?;
+    let gap_density = buf.read_u8().context("gap density")?;
+    let burst_duration = buf.read_u16::<NetworkOrder>().context("burst duration")?;
+    let gap_duration = buf.read_u16::<NetworkOrder>().context("gap duration")?;
+    let round_trip_delay = buf.read_u16::<NetworkOrder>().context("round trip delay")?;
+    let end_system_delay = buf.read_u16::<NetworkOrder>().context("end system delay")?;
+    let signal_level = buf.read_u8().context("signal level")? as i8;
+    let noise_level = buf.read_u8().context("noise level")? as i8;
+    let residual_echo_return_loss = buf.read_u8().context("rerl")?;
+    let gmin = buf.read_u8().context("gmin")?;
+    let r_factor = buf.read_u8().context("r factor")?;
+    let ext_r_factor = buf.read_u8().context("ext r factor")?;
+    let mos_lq = buf.read_u8().context("mos lq")?;
+    let mos_cq = buf.read_u8().context("mos cq")?;
+    let packet_loss_concealment = buf.read_u2().context("plc")?;
+    let jitter_buffer_adaptive = buf.read_u2().context("jba")?;
+    let jitter_buffer_rate = buf.read_u4().context("jb rate")?;
+    let reserved = buf.read_u8().context("reserved")?;
+    mode.check_reserved(warnings, "VoIP metrics reserved bits", reserved.into())?;
+    let jitter_buffer_nominal = buf.read_u16::<NetworkOrder>().context("jb nominal")?;
+    let jitter_buffer_maximum = buf.read_u16::<NetworkOrder>().context("jb maximum")?;
+    let jitter_buffer_abs_maximum = buf.read_u16::<NetworkOrder>().context("jb abs max")?;
+
+    Ok(VoipMetricsBlock {
+        header,
+        ssrc,
+        loss_rate,
+        discard_rate,
+        burst_density,
+        gap_density,
+        burst_duration,
+        gap_duration,
+        round_trip_delay,
+        end_system_delay,
+        signal_level,
+        noise_level,
+        residual_echo_return_loss,
+        gmin,
+        r_factor,
+        ext_r_factor,
+        mos_lq,
+        mos_cq,
+        packet_loss_concealment,
+        jitter_buffer_adaptive,
+        jitter_buffer_rate,
+        jitter_buffer_nominal,
+        jitter_buffer_maximum,
+        jitter_buffer_abs_maximum,
+    })
+}
+
+pub fn write_voip_metrics_block<B: PacketBufferMut>(
+    buf: &mut B,
+    block: &VoipMetricsBlock,
+) -> Result<()> {
+    write_xr_block_header(buf, &block.header).context("block header")?;
+    buf.write_u32::<NetworkOrder>(block.ssrc).context("ssrc")?;
+    buf.write_u8(block.loss_rate).context("loss rate")?;
+    buf.write_u8(block.discard_rate).context("discard rate")?;
+    buf.write_u8(block.burst_density).context("burst density")?;
+    buf.write_u8(block.gap_density).context("gap density")?;
+    buf.write_u16::<NetworkOrder>(block.burst_duration)
+        .context("burst duration")?;
+    buf.write_u16::<NetworkOrder>(block.gap_duration)
+        .context("gap duration")?;
+    buf.write_u16::<NetworkOrder>(block.round_trip_delay)
+        .context("round trip delay")?;
+    buf.write_u16::<NetworkOrder>(block.end_system_delay)
+        .context("end system delay")?;
+    buf.write_u8(block.signal_level as u8)
+        .context("signal level")?;
+    buf.write_u8(block.noise_level as u8)
+        .context("noise level")?;
+    buf.write_u8(block.residual_echo_return_loss)
+        .context("rerl")?;
+    buf.write_u8(block.gmin).context("gmin")?;
+    buf.write_u8(block.r_factor).context("r factor")?;
+    buf.write_u8(block.ext_r_factor).context("ext r factor")?;
+    buf.write_u8(block.mos_lq).context("mos lq")?;
+    buf.write_u8(block.mos_cq).context("mos cq")?;
+    buf.write_u2(block.packet_loss_concealment).context("plc")?;
+    buf.write_u2(block.jitter_buffer_adaptive).context("jba")?;
+    buf.write_u4(block.jitter_buffer_rate).context("jb rate")?;
+    buf.write_u8(0).context("reserved")?;
+    buf.write_u16::<NetworkOrder>(block.jitter_buffer_nominal)
+        .context("jb nominal")?;
+    buf.write_u16::<NetworkOrder>(block.jitter_buffer_maximum)
+        .context("jb maximum")?;
+    buf.write_u16::<NetworkOrder>(block.jitter_buffer_abs_maximum)
+        .context("jb abs max")?;
+
+    Ok(())
+}
+
+impl PrettyPrint for VoipMetricsBlock {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("VoIP metrics report block", |tree| {
+            self.header.pretty_print(tree)?;
+            tree.field("SSRC", Ssrc(self.ssrc))?;
+            tree.field("Loss rate", format_args!("{}/256", self.loss_rate))?;
+            tree.field("Discard rate", format_args!("{}/256", self.discard_rate))?;
+            tree.field("Burst density", format_args!("{}/256", self.burst_density))?;
+            tree.field("Gap density", format_args!("{}/256", self.gap_density))?;
+            tree.field("Burst duration", format_args!("{} ms", self.burst_duration))?;
+            tree.field("Gap duration", format_args!("{} ms", self.gap_duration))?;
+            tree.field(
+                "Round trip delay",
+                format_args!("{} ms", self.round_trip_delay),
+            )?;
+            tree.field(
+                "End system delay",
+                format_args!("{} ms", self.end_system_delay),
+            )?;
+            tree.field("Signal level", format_args!("{} dBm", self.signal_level))?;
+            tree.field("Noise level", format_args!("{} dBm", self.noise_level))?;
+            tree.field("RERL", self.residual_echo_return_loss)?;
+            tree.field("Gmin", self.gmin)?;
+            tree.field("R factor", self.r_factor)?;
+            tree.field("External R factor", self.ext_r_factor)?;
+            tree.field("MOS-LQ", self.mos_lq)?;
+            tree.field("MOS-CQ", self.mos_cq)?;
+            tree.field("PLC", u8::from(self.packet_loss_concealment))?;
+            tree.field("JBA", u8::from(self.jitter_buffer_adaptive))?;
+            tree.field("JB rate", u8::from(self.jitter_buffer_rate))?;
+            tree.field(
+                "JB nominal",
+                format_args!("{} ms", self.jitter_buffer_nominal),
+            )?;
+            tree.field(
+                "JB maximum",
+                format_args!("{} ms", self.jitter_buffer_maximum),
+            )?;
+            tree.field(
+                "JB abs max",
+                format_args!("{} ms", self.jitter_buffer_abs_maximum),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unusual_byte_groupings)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtcp::rtcp_xr::read_xr_block_header;
+
+    use super::*;
+
+    #[test]
+    fn test_read_voip_metrics_block() {
+        #[rustfmt::skip]
+        let data = vec![
+            // ssrc
+            0x00, 0x00, 0x00, 0x01,
+            // loss rate, discard rate, burst density, gap density
+            0x01, 0x02, 0x03, 0x04,
+            // burst duration, gap duration
+            0x00, 0x05, 0x00, 0x06,
+            // round trip delay, end system delay
+            0x00, 0x07, 0x00, 0x08,
+            // signal level (-20), noise level (-60), rerl, gmin
+            0xEC, 0xC4, 0x09, 0x10,
+            // r factor, ext r factor, mos lq (4.1), mos cq (unavailable)
+            0x5A, 0x7F, 0x29, 0x7F,
+            // rx config (plc 3, jba 2, jb rate 5), reserved, jb nominal
+            0b11_10_0101, 0x00, 0x00, 0x14,
+            // jb maximum, jb abs max
+            0x00, 0x28, 0x00, 0x50,
+        ];
+        let header = XrBlockHeader {
+            block_type: VoipMetricsBlock::BT,
+            type_specific: 0,
+            block_length: 8,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data));
+        let block = read_voip_metrics_block(
+            &mut cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+        assert_eq!(block.ssrc, 1);
+        assert_eq!(block.signal_level, -20);
+        assert_eq!(block.noise_level, -60);
+        assert_eq!(block.mos_lq_score(), Some(4.1));
+        assert_eq!(block.mos_cq_score(), None);
+        assert_eq!(block.packet_loss_concealment, u2::new(3));
+        assert_eq!(block.jitter_buffer_adaptive, u2::new(2));
+        assert_eq!(block.jitter_buffer_rate, u4::new(5));
+        assert_eq!(block.jitter_buffer_abs_maximum, 80);
+    }
+
+    #[test]
+    fn test_write_voip_metrics_block_roundtrip() {
+        let mut block = VoipMetricsBlock {
+            header: XrBlockHeader {
+                block_type: 0,
+                type_specific: 0,
+                block_length: 0,
+            },
+            ssrc: 42,
+            loss_rate: 1,
+            discard_rate: 2,
+            burst_density: 3,
+            gap_density: 4,
+            burst_duration: 5,
+            gap_duration: 6,
+            round_trip_delay: 7,
+            end_system_delay: 8,
+            signal_level: -10,
+            noise_level: -70,
+            residual_echo_return_loss: 9,
+            gmin: 16,
+            r_factor: 90,
+            ext_r_factor: VoipMetricsBlock::UNAVAILABLE,
+            mos_lq: 40,
+            mos_cq: 41,
+            packet_loss_concealment: u2::new(1),
+            jitter_buffer_adaptive: u2::new(3),
+            jitter_buffer_rate: u4::new(15),
+            jitter_buffer_nominal: 20,
+            jitter_buffer_maximum: 40,
+            jitter_buffer_abs_maximum: 80,
+        };
+        block.sync();
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; 36]));
+        write_voip_metrics_block(&mut cursor, &block).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let mut read_cursor = BitCursor::new(cursor.into_inner());
+        let header = read_xr_block_header(&mut read_cursor).unwrap();
+        let read_block = read_voip_metrics_block(
+            &mut read_cursor,
+            header,
+            ParseMode::Strict,
+            &mut ParseWarnings::new(),
+        )
+        .unwrap();
+        assert_eq!(read_block, block);
+    }
+}
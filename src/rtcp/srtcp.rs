@@ -0,0 +1,218 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_cursor::BitCursor, bit_write_exts::BitWriteExts, byte_order::NetworkOrder};
+use bitvec::{order::Msb0, slice::BitSlice};
+use bytes::Bytes;
+
+use crate::{error::RtpParseError, parse_mode::ParseWarnings, LengthBytes, PacketBufferMut};
+
+use super::rtcp_packet::{
+    parse_rtcp_packet_with_context, write_some_rtcp_packet, RtcpParseContext, SomeRtcpPacket,
+};
+
+// https://datatracker.ietf.org/doc/html/rfc3711#section-3.4
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+<+
+//  |V=2|P|    RC   |   PT=SR or RR   |             length          | |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |                         SSRC of sender                        | |
+//  +>+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                          sender info                          ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                         report block 1                        ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                         report block 2                        ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                              ...                              ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | |V=2|P|    SC   |  PT=SDES=202  |             length            | |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | |                          SSRC/CSRC 1                          | |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                           SDES items                          ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | ~                              ...                              ~ |
+//  +>+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | |E|                         SRTCP index                         | |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+<+
+//  | ~                     SRTCP MKI (OPTIONAL)                      ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | :                     authentication tag                        : |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |                                                                   |
+//  +-- Encrypted Portion                    Authenticated Portion -----+
+//
+// E: whether the packet is encrypted
+// SRTCP index: a 31 bit counter of the SRTCP packets sent with this SSRC
+
+const E_AND_INDEX_SIZE_BYTES: usize = 4;
+const ENCRYPTED_MASK: u32 = 0x8000_0000;
+const INDEX_MASK: u32 = 0x7FFF_FFFF;
+
+/// The fields SRTCP adds to the end of a (compound) RTCP packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SrtcpTrailer {
+    pub encrypted: bool,
+    /// 31 bits
+    pub index: u32,
+    /// The MKI (if the session uses one) and authentication tag
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::bytes))]
+    pub auth_tag: Bytes,
+}
+
+impl SrtcpTrailer {
+    /// A trailer with the given E bit and index and a zeroed authentication tag of the given
+    /// length, reserving space for an SRTP implementation to fill in when protecting the packet.
+    pub fn new(encrypted: bool, index: u32, auth_tag_length_bytes: usize) -> Self {
+        Self {
+            encrypted,
+            index,
+            auth_tag: Bytes::from(vec![0; auth_tag_length_bytes]),
+        }
+    }
+}
+
+impl LengthBytes for SrtcpTrailer {
+    fn length_bytes(&self) -> usize {
+        E_AND_INDEX_SIZE_BYTES + self.auth_tag.len()
+    }
+}
+
+/// Read the SRTCP trailer off the end of the given SRTCP packet, returning it along with the
+/// length of the RTCP packet that precedes it
+pub fn read_srtcp_trailer(
+    data: &[u8],
+    auth_tag_length_bytes: usize,
+) -> Result<(SrtcpTrailer, usize)> {
+    let Some(rtcp_length_bytes) = data
+        .len()
+        .checked_sub(E_AND_INDEX_SIZE_BYTES + auth_tag_length_bytes)
+    else {
+        bail!(RtpParseError::truncated(
+            E_AND_INDEX_SIZE_BYTES + auth_tag_length_bytes,
+            data.len()
+        ));
+    };
+    let auth_tag_start = rtcp_length_bytes + E_AND_INDEX_SIZE_BYTES;
+    let e_and_index = u32::from_be_bytes(data[rtcp_length_bytes..auth_tag_start].try_into()?);
+    let trailer = SrtcpTrailer {
+        encrypted: e_and_index & ENCRYPTED_MASK != 0,
+        index: e_and_index & INDEX_MASK,
+        auth_tag: Bytes::copy_from_slice(&data[auth_tag_start..]),
+    };
+
+    Ok((trailer, rtcp_length_bytes))
+}
+
+pub fn write_srtcp_trailer<B: PacketBufferMut>(buf: &mut B, trailer: &SrtcpTrailer) -> Result<()> {
+    if trailer.index > INDEX_MASK {
+        bail!("SRTCP index must fit in 31 bits, got {}", trailer.index);
+    }
+    let mut e_and_index = trailer.index;
+    if trailer.encrypted {
+        e_and_index |= ENCRYPTED_MASK;
+    }
+    buf.write_u32::<NetworkOrder>(e_and_index)
+        .context("e and srtcp index")?;
+    std::io::Write::write_all(buf, &trailer.auth_tag).context("auth tag")?;
+
+    Ok(())
+}
+
+/// A (possibly compound) RTCP packet along with its SRTCP trailer
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct SrtcpPacket {
+    pub packet: SomeRtcpPacket,
+    pub trailer: SrtcpTrailer,
+}
+
+impl LengthBytes for SrtcpPacket {
+    fn length_bytes(&self) -> usize {
+        self.packet.length_bytes() + self.trailer.length_bytes()
+    }
+}
+
+/// Parse an SRTCP packet whose authentication tag (and MKI, if used) is `auth_tag_length_bytes`
+/// long.  The RTCP packet is parsed from everything before the trailer, so it should already have
+/// been decrypted.
+pub fn parse_srtcp_packet(data: &[u8], auth_tag_length_bytes: usize) -> Result<SrtcpPacket> {
+    parse_srtcp_packet_with_context(data, auth_tag_length_bytes, &RtcpParseContext::default())
+        .map(|(packet, _)| packet)
+}
+
+/// Like [`parse_srtcp_packet`], but the RTCP packet is parsed using the given context.  Any
+/// warnings for violations which were accepted in lenient mode are returned along with the packet.
+pub fn parse_srtcp_packet_with_context(
+    data: &[u8],
+    auth_tag_length_bytes: usize,
+    context: &RtcpParseContext,
+) -> Result<(SrtcpPacket, ParseWarnings)> {
+    let (trailer, rtcp_length_bytes) =
+        read_srtcp_trailer(data, auth_tag_length_bytes).context("srtcp trailer")?;
+    let mut cursor = BitCursor::new(BitSlice::<u8, Msb0>::from_slice(&data[..rtcp_length_bytes]));
+    let (packet, warnings) =
+        parse_rtcp_packet_with_context(&mut cursor, context).context("rtcp packet")?;
+
+    Ok((SrtcpPacket { packet, trailer }, warnings))
+}
+
+/// Write the given packet followed by its trailer.  Note that this doesn't sync the packet:
+/// [`SomeRtcpPacket::sync`] should be called first if the packet's contents have been changed.
+pub fn write_srtcp_packet<B: PacketBufferMut>(buf: &mut B, packet: &SrtcpPacket) -> Result<()> {
+    write_some_rtcp_packet(buf, &packet.packet).context("rtcp packet")?;
+    write_srtcp_trailer(buf, &packet.trailer).context("srtcp trailer")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bitvec::vec::BitVec;
+
+    use super::*;
+
+    #[rustfmt::skip]
+    const SRTCP_PLI: [u8; 22] = [
+        // v=2, fmt 1, pt 206, length 2
+        0x81, 0xCE, 0x00, 0x02,
+        // sender ssrc
+        0x00, 0x00, 0x00, 0x01,
+        // media source ssrc
+        0x00, 0x00, 0x00, 0x02,
+        // E=1, index 0x1234
+        0x80, 0x00, 0x12, 0x34,
+        // auth tag
+        0xA0, 0xA1, 0xA2, 0xA3, 0xA4, 0xA5,
+    ];
+
+    #[test]
+    fn test_roundtrip() {
+        let packet = parse_srtcp_packet(&SRTCP_PLI, 6).unwrap();
+        assert!(matches!(packet.packet, SomeRtcpPacket::RtcpFbPliPacket(_)));
+        assert!(packet.trailer.encrypted);
+        assert_eq!(packet.trailer.index, 0x1234);
+        assert_eq!(packet.trailer.auth_tag.as_ref(), &SRTCP_PLI[16..]);
+        assert_eq!(packet.length_bytes(), SRTCP_PLI.len());
+
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; packet.length_bytes()]));
+        write_srtcp_packet(&mut cursor, &packet).unwrap();
+        assert_eq!(cursor.into_inner().into_vec(), SRTCP_PLI);
+    }
+
+    #[test]
+    fn test_trailer_errors() {
+        assert!(parse_srtcp_packet(&SRTCP_PLI[..8], 6).is_err());
+
+        let trailer = SrtcpTrailer::new(false, 0x8000_0000, 4);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            trailer.length_bytes()
+        ]));
+        assert!(write_srtcp_trailer(&mut cursor, &trailer).is_err());
+    }
+}
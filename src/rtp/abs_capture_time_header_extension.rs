@@ -0,0 +1,133 @@
+use std::time::SystemTime;
+
+use anyhow::{bail, Result};
+
+use crate::{error::RtpParseError, ntp::NtpTimestamp};
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=7 |     absolute capture timestamp (bit 0-23)     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |             absolute capture timestamp (bit 24-55)            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ... (56-63)  |
+// +-+-+-+-+-+-+-+-+
+//
+// With the optional estimated capture clock offset, len=15 and the timestamp is followed by:
+//
+// |           estimated capture clock offset (bit 0-63)           |
+//
+// The absolute capture timestamp is a 64 bit NTP timestamp (32.32 fixed point seconds since
+// 1900) of when the first frame in the packet was captured, according to the capturing system's
+// clock.  The estimated capture clock offset is a signed 32.32 fixed point number of seconds: the
+// estimated offset between the capturing system's clock and the sender's.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsCaptureTime {
+    /// 32.32 fixed point NTP timestamp
+    pub absolute_capture_timestamp: u64,
+    /// Signed 32.32 fixed point number of seconds
+    pub estimated_capture_clock_offset: Option<i64>,
+}
+
+impl AbsCaptureTime {
+    /// Create an abs-capture-time from a wall clock capture time, without an estimated clock
+    /// offset.
+    pub fn from_system_time(capture_time: SystemTime) -> Self {
+        AbsCaptureTime {
+            absolute_capture_timestamp: NtpTimestamp::from_system_time(capture_time).into(),
+            estimated_capture_clock_offset: None,
+        }
+    }
+
+    /// The capture time as a wall clock time.  Times before the Unix epoch are clamped to it.
+    pub fn capture_time(&self) -> SystemTime {
+        NtpTimestamp(self.absolute_capture_timestamp).to_system_time()
+    }
+
+    /// The estimated capture clock offset in microseconds
+    pub fn estimated_capture_clock_offset_micros(&self) -> Option<i64> {
+        self.estimated_capture_clock_offset
+            .map(|offset| ((offset as i128 * 1_000_000) >> 32) as i64)
+    }
+}
+
+impl RtpHeaderExtensionValue for AbsCaptureTime {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-capture-time";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() != 8 && data.len() != 16 {
+            bail!(RtpParseError::invalid_field(
+                "abs capture time length",
+                "8 or 16 bytes",
+                format!("{} bytes", data.len())
+            ));
+        }
+        let absolute_capture_timestamp = u64::from_be_bytes(data[..8].try_into().unwrap());
+        let estimated_capture_clock_offset = data
+            .get(8..)
+            .filter(|offset| !offset.is_empty())
+            .map(|offset| i64::from_be_bytes(offset.try_into().unwrap()));
+
+        Ok(AbsCaptureTime {
+            absolute_capture_timestamp,
+            estimated_capture_clock_offset,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = self.absolute_capture_timestamp.to_be_bytes().to_vec();
+        if let Some(offset) = self.estimated_capture_clock_offset {
+            data.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use crate::ntp::NTP_UNIX_EPOCH_OFFSET_SECS;
+
+    use super::*;
+
+    #[test]
+    fn test_system_time_conversion() {
+        let capture_time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let abs_capture_time = AbsCaptureTime::from_system_time(capture_time);
+        assert_eq!(
+            abs_capture_time.absolute_capture_timestamp,
+            ((1_700_000_000 + NTP_UNIX_EPOCH_OFFSET_SECS) << 32) | 0x8000_0000
+        );
+        assert_eq!(abs_capture_time.capture_time(), capture_time);
+    }
+
+    #[test]
+    fn test_parse_with_offset() {
+        let abs_capture_time = AbsCaptureTime {
+            absolute_capture_timestamp: 1 << 32,
+            // -0.5 seconds
+            estimated_capture_clock_offset: Some(-(1 << 31)),
+        };
+        let data = abs_capture_time.encode();
+        assert_eq!(data.len(), 16);
+        let parsed = AbsCaptureTime::parse(&data).unwrap();
+        assert_eq!(parsed, abs_capture_time);
+        assert_eq!(
+            parsed.estimated_capture_clock_offset_micros(),
+            Some(-500_000)
+        );
+        assert_eq!(
+            AbsCaptureTime::parse(&data[..8])
+                .unwrap()
+                .estimated_capture_clock_offset,
+            None
+        );
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Result};
+use bit_cursor::nsw_types::u24;
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=2 |              absolute send time               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The absolute send time is a 6.18 fixed point number of seconds: the 24 bits in the middle of
+// the NTP timestamp of when the packet was sent.  It wraps around every 64 seconds.
+
+const TICKS_PER_SECOND: u64 = 1 << 18;
+const RANGE_TICKS: i64 = 1 << 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AbsSendTime(pub u24);
+
+impl AbsSendTime {
+    /// Create an abs-send-time from a duration since any epoch.  Only the duration modulo 64
+    /// seconds is kept.
+    pub fn from_duration(duration: Duration) -> Self {
+        let ticks = duration.as_secs() * TICKS_PER_SECOND
+            + (duration.subsec_nanos() as u64 * TICKS_PER_SECOND) / 1_000_000_000;
+        AbsSendTime(u24::new((ticks % RANGE_TICKS as u64) as u32))
+    }
+
+    /// Create an abs-send-time from a wall clock time.  The NTP epoch is a multiple of 64
+    /// seconds before the Unix epoch, so the time since the Unix epoch gives the same value.
+    pub fn from_system_time(time: SystemTime) -> Self {
+        Self::from_duration(time.duration_since(UNIX_EPOCH).unwrap_or_default())
+    }
+
+    /// The send time, within the 64 second range of the extension.
+    pub fn to_duration(&self) -> Duration {
+        let ticks = u32::from(self.0) as u64;
+        Duration::from_nanos(ticks * 1_000_000_000 / TICKS_PER_SECOND)
+    }
+
+    /// The time in microseconds between `previous` and this send time, assuming they're less
+    /// than 32 seconds apart.  Negative if this send time is before `previous`.
+    pub fn delta_micros(&self, previous: &AbsSendTime) -> i64 {
+        let mut diff = u32::from(self.0) as i64 - u32::from(previous.0) as i64;
+        if diff >= RANGE_TICKS / 2 {
+            diff -= RANGE_TICKS;
+        } else if diff < -RANGE_TICKS / 2 {
+            diff += RANGE_TICKS;
+        }
+        diff * 1_000_000 / TICKS_PER_SECOND as i64
+    }
+}
+
+impl RtpHeaderExtensionValue for AbsSendTime {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let [b0, b1, b2] = data else {
+            bail!(RtpParseError::invalid_field(
+                "abs-send-time length",
+                "3 bytes",
+                format!("{} bytes", data.len())
+            ));
+        };
+        Ok(AbsSendTime(u24::new(u32::from_be_bytes([
+            0, *b0, *b1, *b2,
+        ]))))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        u32::from(self.0).to_be_bytes()[1..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_duration_conversion() {
+        let abs_send_time = AbsSendTime::from_duration(Duration::from_millis(64_500));
+        // Wrapped at 64 seconds
+        assert_eq!(abs_send_time.0, u24::new(TICKS_PER_SECOND as u32 / 2));
+        assert_eq!(abs_send_time.to_duration(), Duration::from_millis(500));
+        assert_eq!(abs_send_time.encode(), vec![0x02, 0x00, 0x00]);
+        assert_eq!(
+            AbsSendTime::parse(&abs_send_time.encode()).unwrap(),
+            abs_send_time
+        );
+        assert!(AbsSendTime::parse(&[0x00, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_delta_wraparound() {
+        let before_wrap = AbsSendTime::from_duration(Duration::from_millis(63_750));
+        let after_wrap = AbsSendTime::from_duration(Duration::from_millis(64_250));
+        assert_eq!(after_wrap.delta_micros(&before_wrap), 500_000);
+        assert_eq!(before_wrap.delta_micros(&after_wrap), -500_000);
+    }
+}
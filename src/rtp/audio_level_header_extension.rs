@@ -8,19 +8,78 @@
 // |  ID   | len=0 |V| level       |
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 
-use super::header_extensions::SomeHeaderExtension;
+use anyhow::{bail, Result};
+use bit_cursor::nsw_types::u7;
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::{RtpHeaderExtensionValue, SomeHeaderExtension};
 
 const AUDIO_LEVEL_MASK: u8 = 0x7F;
 const VAD_MASK: u8 = 0x80;
 
+/// The audio level of a packet, in -dBov (so 0 is the loudest and 127 is silence), and whether
+/// the sender's voice activity detection thinks it contains speech.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AudioLevel {
+    pub vad: bool,
+    pub level: u7,
+}
+
+impl AudioLevel {
+    pub fn is_muted(&self) -> bool {
+        self.level == u7::new(127)
+    }
+}
+
+impl RtpHeaderExtensionValue for AudioLevel {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:ssrc-audio-level";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let Some(value) = data.first() else {
+            bail!(RtpParseError::truncated(1, 0));
+        };
+        Ok(AudioLevel {
+            vad: value & VAD_MASK != 0,
+            level: u7::new(value & AUDIO_LEVEL_MASK),
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let vad = if self.vad { VAD_MASK } else { 0 };
+        vec![vad | u8::from(self.level)]
+    }
+}
+
+#[deprecated(note = "use AudioLevel via HeaderExtensions::get_typed")]
 pub fn get_audio_level(ext: &SomeHeaderExtension) -> u8 {
     ext.data()[0] & AUDIO_LEVEL_MASK
 }
 
+#[deprecated(note = "use AudioLevel via HeaderExtensions::get_typed")]
 pub fn is_muted(ext: &SomeHeaderExtension) -> bool {
-    get_audio_level(ext) == 127
+    ext.data()[0] & AUDIO_LEVEL_MASK == 127
 }
 
+#[deprecated(note = "use AudioLevel via HeaderExtensions::get_typed")]
 pub fn get_vad(ext: &SomeHeaderExtension) -> bool {
     ext.data()[0] & VAD_MASK != 0
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audio_level_roundtrip() {
+        let audio_level = AudioLevel::parse(&[0xFF]).unwrap();
+        assert!(audio_level.vad);
+        assert!(audio_level.is_muted());
+        let audio_level = AudioLevel {
+            vad: false,
+            level: u7::new(30),
+        };
+        assert_eq!(audio_level.encode(), vec![30]);
+        assert_eq!(AudioLevel::parse(&[30]).unwrap(), audio_level);
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/color-space
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   primaries   |   transfer    |    matrix     |range+chr.sit. |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  mastering_metadata.primary_r.x and .y                        |  (HDR metadata, optional)
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  mastering_metadata.primary_g.x and .y                        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  mastering_metadata.primary_b.x and .y                        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  mastering_metadata.white.x and .y                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  luminance_max                |  luminance_min                |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  max_content_light_level      | max_frame_average_light_level |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The primaries, transfer and matrix values are the code points from ITU-T H.273.  The last byte
+// of the short form holds the range (bits 4-5), horizontal chroma siting (bits 2-3) and vertical
+// chroma siting (bits 0-1).
+
+const SIZE_BYTES_WITHOUT_HDR_METADATA: usize = 4;
+const SIZE_BYTES_WITH_HDR_METADATA: usize = 28;
+
+/// A CIE 1931 xy chromaticity coordinate, in units of 0.00002
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Chromaticity {
+    pub x: u16,
+    pub y: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HdrMetadata {
+    pub primary_r: Chromaticity,
+    pub primary_g: Chromaticity,
+    pub primary_b: Chromaticity,
+    pub white_point: Chromaticity,
+    /// In units of 1 nit
+    pub luminance_max: u16,
+    /// In units of 0.0001 nit
+    pub luminance_min: u16,
+    /// In units of 1 nit
+    pub max_content_light_level: u16,
+    /// In units of 1 nit
+    pub max_frame_average_light_level: u16,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ColorSpace {
+    pub primaries: u8,
+    pub transfer: u8,
+    pub matrix: u8,
+    /// 2 bits
+    pub range: u8,
+    /// 2 bits
+    pub chroma_siting_horizontal: u8,
+    /// 2 bits
+    pub chroma_siting_vertical: u8,
+    /// When present the long (28 byte) form of the extension is written, otherwise the short (4
+    /// byte) form is.
+    pub hdr_metadata: Option<HdrMetadata>,
+}
+
+impl RtpHeaderExtensionValue for ColorSpace {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/color-space";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        if data.len() != SIZE_BYTES_WITHOUT_HDR_METADATA
+            && data.len() != SIZE_BYTES_WITH_HDR_METADATA
+        {
+            bail!(RtpParseError::invalid_field(
+                "color space length",
+                format!(
+                    "{SIZE_BYTES_WITHOUT_HDR_METADATA} or {SIZE_BYTES_WITH_HDR_METADATA} bytes"
+                ),
+                format!("{} bytes", data.len())
+            ));
+        }
+        let hdr_metadata = if data.len() == SIZE_BYTES_WITH_HDR_METADATA {
+            let values = data[4..]
+                .chunks_exact(2)
+                .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+                .collect::<Vec<u16>>();
+            Some(HdrMetadata {
+                primary_r: Chromaticity {
+                    x: values[0],
+                    y: values[1],
+                },
+                primary_g: Chromaticity {
+                    x: values[2],
+                    y: values[3],
+                },
+                primary_b: Chromaticity {
+                    x: values[4],
+                    y: values[5],
+                },
+                white_point: Chromaticity {
+                    x: values[6],
+                    y: values[7],
+                },
+                luminance_max: values[8],
+                luminance_min: values[9],
+                max_content_light_level: values[10],
+                max_frame_average_light_level: values[11],
+            })
+        } else {
+            None
+        };
+
+        Ok(ColorSpace {
+            primaries: data[0],
+            transfer: data[1],
+            matrix: data[2],
+            range: (data[3] >> 4) & 0b11,
+            chroma_siting_horizontal: (data[3] >> 2) & 0b11,
+            chroma_siting_vertical: data[3] & 0b11,
+            hdr_metadata,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = vec![
+            self.primaries,
+            self.transfer,
+            self.matrix,
+            (self.range & 0b11) << 4
+                | (self.chroma_siting_horizontal & 0b11) << 2
+                | (self.chroma_siting_vertical & 0b11),
+        ];
+        if let Some(hdr_metadata) = &self.hdr_metadata {
+            for value in [
+                hdr_metadata.primary_r.x,
+                hdr_metadata.primary_r.y,
+                hdr_metadata.primary_g.x,
+                hdr_metadata.primary_g.y,
+                hdr_metadata.primary_b.x,
+                hdr_metadata.primary_b.y,
+                hdr_metadata.white_point.x,
+                hdr_metadata.white_point.y,
+                hdr_metadata.luminance_max,
+                hdr_metadata.luminance_min,
+                hdr_metadata.max_content_light_level,
+                hdr_metadata.max_frame_average_light_level,
+            ] {
+                data.extend_from_slice(&value.to_be_bytes());
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_short_and_long_forms() {
+        let mut color_space = ColorSpace {
+            primaries: 1,
+            transfer: 1,
+            matrix: 1,
+            range: 1,
+            chroma_siting_horizontal: 2,
+            chroma_siting_vertical: 1,
+            hdr_metadata: None,
+        };
+        assert_eq!(color_space.encode(), vec![0x01, 0x01, 0x01, 0x19]);
+        assert_eq!(
+            ColorSpace::parse(&color_space.encode()).unwrap(),
+            color_space
+        );
+
+        color_space.hdr_metadata = Some(HdrMetadata {
+            luminance_max: 1000,
+            max_content_light_level: 500,
+            ..Default::default()
+        });
+        let data = color_space.encode();
+        assert_eq!(data.len(), 28);
+        assert_eq!(data[20..22], [0x03, 0xE8]);
+        assert_eq!(ColorSpace::parse(&data).unwrap(), color_space);
+        assert!(ColorSpace::parse(&data[..8]).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// https://datatracker.ietf.org/doc/html/rfc6465#section-3
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=2 |0|   level 1   |0|   level 2   |0|   level 3   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Each level is the audio level, in -dBov, of the corresponding CSRC in the packet's CSRC list.
+
+const LEVEL_MASK: u8 = 0x7F;
+
+/// The audio levels of each of the contributing sources in a packet sent by a mixer, in the same
+/// order as the packet's csrcs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsrcAudioLevels(pub Vec<u8>);
+
+impl CsrcAudioLevels {
+    /// Check that there's a level for each csrc in a packet with the given number of csrcs.  This
+    /// should be done before a packet with this extension is synced and written: the extension's
+    /// id is negotiated, so the packet can't check it itself.
+    pub fn validate(&self, csrc_count: usize) -> Result<()> {
+        if self.0.len() != csrc_count {
+            bail!(
+                "Packet has {csrc_count} csrcs but {} csrc audio levels",
+                self.0.len()
+            );
+        }
+        if let Some(level) = self.0.iter().find(|level| **level > LEVEL_MASK) {
+            bail!("Invalid csrc audio level {level}, must be 0-127");
+        }
+
+        Ok(())
+    }
+}
+
+impl RtpHeaderExtensionValue for CsrcAudioLevels {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:csrc-audio-level";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        // The csrc count is at most 15
+        if !(1..=15).contains(&data.len()) {
+            bail!(RtpParseError::invalid_field(
+                "csrc audio levels length",
+                "1-15 bytes",
+                format!("{} bytes", data.len())
+            ));
+        }
+        // The top bit is reserved and must be ignored
+        Ok(CsrcAudioLevels(
+            data.iter().map(|level| level & LEVEL_MASK).collect(),
+        ))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.iter().map(|level| level & LEVEL_MASK).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtp::rtp_packet::RtpPacketBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_validate_against_packet() {
+        let mut packet = RtpPacketBuilder::new().csrc(1).csrc(2).build().unwrap();
+        let levels = CsrcAudioLevels(vec![10, 127]);
+        levels.validate(packet.csrcs().count()).unwrap();
+        packet
+            .header_extensions_mut()
+            .set_typed(1, &levels)
+            .unwrap();
+        assert_eq!(
+            packet
+                .header_extensions()
+                .get_typed::<CsrcAudioLevels>(1)
+                .unwrap(),
+            Some(levels)
+        );
+
+        packet.set_csrcs(&[1]);
+        let levels = packet
+            .header_extensions()
+            .get_typed::<CsrcAudioLevels>(1)
+            .unwrap()
+            .unwrap();
+        assert!(levels.validate(packet.csrcs().count()).is_err());
+        assert!(CsrcAudioLevels(vec![128]).validate(1).is_err());
+    }
+}
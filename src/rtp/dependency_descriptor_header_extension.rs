@@ -0,0 +1,686 @@
+use anyhow::{bail, Context, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension
+//
+// The dependency descriptor is a bitstream (not byte aligned) made up of:
+//
+// mandatory_descriptor_fields:
+//   start_of_frame                              f(1)
+//   end_of_frame                                f(1)
+//   frame_dependency_template_id                f(6)
+//   frame_number                                f(16)
+// extended_descriptor_fields (only present if the descriptor is longer than 3 bytes):
+//   template_dependency_structure_present_flag  f(1)
+//   active_decode_targets_present_flag          f(1)
+//   custom_dtis_flag                            f(1)
+//   custom_fdiffs_flag                          f(1)
+//   custom_chains_flag                          f(1)
+//   template_dependency_structure               (if present)
+//   active_decode_targets_bitmask               f(DtCnt) (if present)
+// frame_dependency_definition:
+//   frame_dtis                                  (if custom_dtis_flag)
+//   frame_fdiffs                                (if custom_fdiffs_flag)
+//   frame_chains                                (if custom_chains_flag)
+// zero_padding
+//
+// The number of decode targets (DtCnt) and chains needed to read the extended fields come from
+// the latest template dependency structure, which is only sent in some packets (typically the
+// first packet of a key frame), so it has to be remembered by the receiver.
+
+/// How important a frame is to a decode target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeTargetIndication {
+    /// The frame isn't part of the decode target
+    NotPresent,
+    /// The frame is part of the decode target, but no other frames depend on it
+    Discardable,
+    /// Decoding of the decode target can start (or be switched to) at this frame
+    Switch,
+    /// The frame is part of the decode target and is needed to decode it
+    Required,
+}
+
+impl DecodeTargetIndication {
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0 => DecodeTargetIndication::NotPresent,
+            1 => DecodeTargetIndication::Discardable,
+            2 => DecodeTargetIndication::Switch,
+            _ => DecodeTargetIndication::Required,
+        }
+    }
+
+    fn bits(&self) -> u32 {
+        match self {
+            DecodeTargetIndication::NotPresent => 0,
+            DecodeTargetIndication::Discardable => 1,
+            DecodeTargetIndication::Switch => 2,
+            DecodeTargetIndication::Required => 3,
+        }
+    }
+}
+
+/// The dependencies of a frame: either one of the templates in a [`FrameDependencyStructure`]
+/// or, once resolved, the dependencies of a particular frame.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDependencyTemplate {
+    pub spatial_id: u8,
+    pub temporal_id: u8,
+    /// One per decode target
+    pub decode_target_indications: Vec<DecodeTargetIndication>,
+    /// The differences between this frame's number and the numbers of the frames it references.
+    /// In a template these are 1-16.
+    pub frame_diffs: Vec<u16>,
+    /// One per chain: the difference between this frame's number and the number of the previous
+    /// frame in the chain.  In a template these are 0-15.
+    pub chain_diffs: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderResolution {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// The template dependency structure, which describes the decode targets, chains and frame
+/// templates for the frames which follow it.
+///
+/// The templates must be ordered by spatial id and then temporal id, and each template must have
+/// the same spatial and temporal ids as the previous one, the next temporal id, or the next
+/// spatial id with a temporal id of 0.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FrameDependencyStructure {
+    /// 6 bits
+    pub template_id_offset: u8,
+    /// 1-32
+    pub decode_target_count: u8,
+    /// 0 to `decode_target_count`
+    pub chain_count: u8,
+    /// For each decode target, the chain which protects it.  Empty if there are no chains.
+    pub decode_target_protected_by_chain: Vec<u8>,
+    pub templates: Vec<FrameDependencyTemplate>,
+    /// If present, one per spatial layer
+    pub resolutions: Vec<RenderResolution>,
+}
+
+/// The active decode targets, as one bit per decode target (bit 0 is decode target 0)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActiveDecodeTargets {
+    pub bitmask: u32,
+    pub decode_target_count: u8,
+}
+
+/// A dependency descriptor as it appears in a packet.  Frames usually just refer to one of the
+/// templates in the latest [`FrameDependencyStructure`] (possibly with custom overrides), use
+/// [`DependencyDescriptor::frame_dependencies`] to resolve them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DependencyDescriptor {
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    /// 6 bits
+    pub template_id: u8,
+    pub frame_number: u16,
+    pub structure: Option<FrameDependencyStructure>,
+    /// If not present and a structure is attached, all decode targets are active
+    pub active_decode_targets: Option<ActiveDecodeTargets>,
+    pub custom_decode_target_indications: Option<Vec<DecodeTargetIndication>>,
+    pub custom_frame_diffs: Option<Vec<u16>>,
+    pub custom_chain_diffs: Option<Vec<u8>>,
+}
+
+impl DependencyDescriptor {
+    /// Parse a dependency descriptor using the latest structure that was received, which is
+    /// needed when the descriptor has extended fields but doesn't contain its own structure.
+    pub fn parse_with_structure(
+        data: &[u8],
+        latest_structure: Option<&FrameDependencyStructure>,
+    ) -> Result<Self> {
+        if data.len() < 3 {
+            bail!(RtpParseError::truncated(3, data.len()));
+        }
+        let mut reader = BitReader::new(data);
+        let mut descriptor = DependencyDescriptor {
+            start_of_frame: reader.read_bool()?,
+            end_of_frame: reader.read_bool()?,
+            template_id: reader.read(6)? as u8,
+            frame_number: reader.read(16)? as u16,
+            ..Default::default()
+        };
+        if data.len() == 3 {
+            return Ok(descriptor);
+        }
+
+        let structure_present = reader.read_bool()?;
+        let active_decode_targets_present = reader.read_bool()?;
+        let custom_dtis = reader.read_bool()?;
+        let custom_fdiffs = reader.read_bool()?;
+        let custom_chains = reader.read_bool()?;
+        if structure_present {
+            descriptor.structure = Some(read_structure(&mut reader).context("structure")?);
+        }
+        let structure = descriptor.structure.as_ref().or(latest_structure);
+        let needs_structure = active_decode_targets_present || custom_dtis || custom_chains;
+        let (decode_target_count, chain_count) = match structure {
+            Some(structure) => (structure.decode_target_count, structure.chain_count),
+            None if needs_structure => {
+                bail!(RtpParseError::invalid_field(
+                    "template dependency structure",
+                    "included or previously received",
+                    "none"
+                ))
+            }
+            None => (0, 0),
+        };
+        if active_decode_targets_present {
+            descriptor.active_decode_targets = Some(ActiveDecodeTargets {
+                bitmask: reader.read(decode_target_count as u32)?,
+                decode_target_count,
+            });
+        }
+        if custom_dtis {
+            descriptor.custom_decode_target_indications = Some(
+                (0..decode_target_count)
+                    .map(|_| Ok(DecodeTargetIndication::from_bits(reader.read(2)?)))
+                    .collect::<Result<_>>()
+                    .context("custom decode target indications")?,
+            );
+        }
+        if custom_fdiffs {
+            let mut frame_diffs = Vec::new();
+            loop {
+                let size = reader.read(2)?;
+                if size == 0 {
+                    break;
+                }
+                frame_diffs.push(reader.read(4 * size)? as u16 + 1);
+            }
+            descriptor.custom_frame_diffs = Some(frame_diffs);
+        }
+        if custom_chains {
+            descriptor.custom_chain_diffs = Some(
+                (0..chain_count)
+                    .map(|_| Ok(reader.read(8)? as u8))
+                    .collect::<Result<_>>()
+                    .context("custom chain diffs")?,
+            );
+        }
+
+        Ok(descriptor)
+    }
+
+    /// Resolve the dependencies of this frame using the given structure (either the one attached
+    /// to this descriptor or the latest one received).
+    pub fn frame_dependencies(
+        &self,
+        structure: &FrameDependencyStructure,
+    ) -> Result<FrameDependencyTemplate> {
+        let template_index =
+            (self.template_id as usize + 64 - structure.template_id_offset as usize) % 64;
+        let Some(template) = structure.templates.get(template_index) else {
+            bail!(
+                "Template id {} is not in the structure (offset {}, {} templates)",
+                self.template_id,
+                structure.template_id_offset,
+                structure.templates.len()
+            );
+        };
+        let mut frame_dependencies = template.clone();
+        if let Some(dtis) = &self.custom_decode_target_indications {
+            frame_dependencies.decode_target_indications = dtis.clone();
+        }
+        if let Some(frame_diffs) = &self.custom_frame_diffs {
+            frame_dependencies.frame_diffs = frame_diffs.clone();
+        }
+        if let Some(chain_diffs) = &self.custom_chain_diffs {
+            frame_dependencies.chain_diffs = chain_diffs.clone();
+        }
+
+        Ok(frame_dependencies)
+    }
+
+    /// The render resolution of this frame, if the structure includes resolutions
+    pub fn resolution(&self, structure: &FrameDependencyStructure) -> Option<RenderResolution> {
+        let frame_dependencies = self.frame_dependencies(structure).ok()?;
+        structure
+            .resolutions
+            .get(frame_dependencies.spatial_id as usize)
+            .copied()
+    }
+
+    fn has_extended_fields(&self) -> bool {
+        self.structure.is_some()
+            || self.active_decode_targets.is_some()
+            || self.custom_decode_target_indications.is_some()
+            || self.custom_frame_diffs.is_some()
+            || self.custom_chain_diffs.is_some()
+    }
+}
+
+impl RtpHeaderExtensionValue for DependencyDescriptor {
+    const URI: &'static str =
+        "https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension";
+
+    /// Parse a dependency descriptor which either has no extended fields or carries its own
+    /// structure; otherwise use [`DependencyDescriptor::parse_with_structure`].
+    fn parse(data: &[u8]) -> Result<Self> {
+        Self::parse_with_structure(data, None)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        writer.write_bool(self.start_of_frame);
+        writer.write_bool(self.end_of_frame);
+        writer.write(self.template_id as u32, 6);
+        writer.write(self.frame_number as u32, 16);
+        if !self.has_extended_fields() {
+            return writer.finish();
+        }
+
+        writer.write_bool(self.structure.is_some());
+        writer.write_bool(self.active_decode_targets.is_some());
+        writer.write_bool(self.custom_decode_target_indications.is_some());
+        writer.write_bool(self.custom_frame_diffs.is_some());
+        writer.write_bool(self.custom_chain_diffs.is_some());
+        if let Some(structure) = &self.structure {
+            write_structure(&mut writer, structure);
+        }
+        if let Some(active_decode_targets) = &self.active_decode_targets {
+            writer.write(
+                active_decode_targets.bitmask,
+                active_decode_targets.decode_target_count as u32,
+            );
+        }
+        for dti in self.custom_decode_target_indications.iter().flatten() {
+            writer.write(dti.bits(), 2);
+        }
+        if let Some(frame_diffs) = &self.custom_frame_diffs {
+            for frame_diff in frame_diffs {
+                let value = frame_diff.saturating_sub(1) as u32;
+                let size = match value {
+                    0..=0xF => 1,
+                    0x10..=0xFF => 2,
+                    _ => 3,
+                };
+                writer.write(size, 2);
+                writer.write(value, 4 * size);
+            }
+            writer.write(0, 2);
+        }
+        for chain_diff in self.custom_chain_diffs.iter().flatten() {
+            writer.write(*chain_diff as u32, 8);
+        }
+
+        writer.finish()
+    }
+}
+
+fn read_structure(reader: &mut BitReader) -> Result<FrameDependencyStructure> {
+    let template_id_offset = reader.read(6)? as u8;
+    let decode_target_count = reader.read(5)? as u8 + 1;
+
+    // Template layers
+    let mut templates = Vec::new();
+    let (mut spatial_id, mut temporal_id) = (0, 0);
+    loop {
+        templates.push(FrameDependencyTemplate {
+            spatial_id,
+            temporal_id,
+            ..Default::default()
+        });
+        match reader.read(2)? {
+            0 => {}
+            1 => temporal_id += 1,
+            2 => {
+                temporal_id = 0;
+                spatial_id += 1;
+            }
+            _ => break,
+        }
+    }
+    for template in templates.iter_mut() {
+        for _ in 0..decode_target_count {
+            template
+                .decode_target_indications
+                .push(DecodeTargetIndication::from_bits(reader.read(2)?));
+        }
+    }
+    for template in templates.iter_mut() {
+        while reader.read_bool()? {
+            template.frame_diffs.push(reader.read(4)? as u16 + 1);
+        }
+    }
+
+    // Template chains
+    let chain_count = reader.read_non_symmetric(decode_target_count as u32 + 1)? as u8;
+    let mut decode_target_protected_by_chain = Vec::new();
+    if chain_count > 0 {
+        for _ in 0..decode_target_count {
+            decode_target_protected_by_chain
+                .push(reader.read_non_symmetric(chain_count as u32)? as u8);
+        }
+        for template in templates.iter_mut() {
+            for _ in 0..chain_count {
+                template.chain_diffs.push(reader.read(4)? as u8);
+            }
+        }
+    }
+
+    let mut resolutions = Vec::new();
+    if reader.read_bool()? {
+        for _ in 0..=spatial_id {
+            resolutions.push(RenderResolution {
+                width: (reader.read(16)? + 1) as u16,
+                height: (reader.read(16)? + 1) as u16,
+            });
+        }
+    }
+
+    Ok(FrameDependencyStructure {
+        template_id_offset,
+        decode_target_count,
+        chain_count,
+        decode_target_protected_by_chain,
+        templates,
+        resolutions,
+    })
+}
+
+fn write_structure(writer: &mut BitWriter, structure: &FrameDependencyStructure) {
+    writer.write(structure.template_id_offset as u32, 6);
+    writer.write(structure.decode_target_count.saturating_sub(1) as u32, 5);
+
+    for (i, template) in structure.templates.iter().enumerate() {
+        let next_layer_idc = match structure.templates.get(i + 1) {
+            None => 3,
+            Some(next) if next.spatial_id > template.spatial_id => 2,
+            Some(next) if next.temporal_id > template.temporal_id => 1,
+            Some(_) => 0,
+        };
+        writer.write(next_layer_idc, 2);
+    }
+    for template in &structure.templates {
+        for dti in &template.decode_target_indications {
+            writer.write(dti.bits(), 2);
+        }
+    }
+    for template in &structure.templates {
+        for frame_diff in &template.frame_diffs {
+            writer.write_bool(true);
+            writer.write(frame_diff.saturating_sub(1) as u32, 4);
+        }
+        writer.write_bool(false);
+    }
+
+    writer.write_non_symmetric(
+        structure.chain_count as u32,
+        structure.decode_target_count as u32 + 1,
+    );
+    if structure.chain_count > 0 {
+        for chain in &structure.decode_target_protected_by_chain {
+            writer.write_non_symmetric(*chain as u32, structure.chain_count as u32);
+        }
+        for template in &structure.templates {
+            for chain_diff in &template.chain_diffs {
+                writer.write(*chain_diff as u32, 4);
+            }
+        }
+    }
+
+    writer.write_bool(!structure.resolutions.is_empty());
+    for resolution in &structure.resolutions {
+        writer.write(resolution.width.saturating_sub(1) as u32, 16);
+        writer.write(resolution.height.saturating_sub(1) as u32, 16);
+    }
+}
+
+/// Reads MSB-first bit fields of up to 32 bits from a byte slice
+struct BitReader<'a> {
+    data: &'a [u8],
+    bit_position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            bit_position: 0,
+        }
+    }
+
+    fn read(&mut self, num_bits: u32) -> Result<u32> {
+        if self.bit_position + num_bits as usize > self.data.len() * 8 {
+            bail!(RtpParseError::truncated(
+                (self.bit_position + num_bits as usize).div_ceil(8),
+                self.data.len()
+            ));
+        }
+        let mut value = 0;
+        for _ in 0..num_bits {
+            let byte = self.data[self.bit_position / 8];
+            let bit = (byte >> (7 - self.bit_position % 8)) & 1;
+            value = (value << 1) | bit as u32;
+            self.bit_position += 1;
+        }
+
+        Ok(value)
+    }
+
+    fn read_bool(&mut self) -> Result<bool> {
+        Ok(self.read(1)? == 1)
+    }
+
+    /// Read a value in the range 0 to n - 1 using the non-symmetric unsigned encoding, ns(n)
+    fn read_non_symmetric(&mut self, n: u32) -> Result<u32> {
+        let width = u32::BITS - n.leading_zeros();
+        let num_short_values = (1 << width) - n;
+        let value = self.read(width - 1)?;
+        if value < num_short_values {
+            return Ok(value);
+        }
+        let extra_bit = self.read(1)?;
+
+        Ok((value << 1) - num_short_values + extra_bit)
+    }
+}
+
+/// Writes MSB-first bit fields, zero padding the last byte
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    num_bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, num_bits: u32) {
+        for i in (0..num_bits).rev() {
+            if self.num_bits == self.data.len() * 8 {
+                self.data.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            let last = self.data.len() - 1;
+            self.data[last] |= bit << (7 - self.num_bits % 8);
+            self.num_bits += 1;
+        }
+    }
+
+    fn write_bool(&mut self, value: bool) {
+        self.write(value as u32, 1);
+    }
+
+    /// Write a value in the range 0 to n - 1 using the non-symmetric unsigned encoding, ns(n)
+    fn write_non_symmetric(&mut self, value: u32, n: u32) {
+        let width = u32::BITS - n.leading_zeros();
+        let num_short_values = (1 << width) - n;
+        if value < num_short_values {
+            self.write(value, width - 1);
+        } else {
+            let value = value + num_short_values;
+            self.write(value >> 1, width - 1);
+            self.write(value & 1, 1);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two spatial layers with two temporal layers each (L2T2), protected by one chain per
+    /// spatial layer
+    fn structure() -> FrameDependencyStructure {
+        use DecodeTargetIndication::*;
+        let template = |spatial_id,
+                        temporal_id,
+                        dtis: [DecodeTargetIndication; 4],
+                        diffs: &[u16],
+                        chains: [u8; 2]| {
+            FrameDependencyTemplate {
+                spatial_id,
+                temporal_id,
+                decode_target_indications: dtis.to_vec(),
+                frame_diffs: diffs.to_vec(),
+                chain_diffs: chains.to_vec(),
+            }
+        };
+        FrameDependencyStructure {
+            template_id_offset: 60,
+            decode_target_count: 4,
+            chain_count: 2,
+            decode_target_protected_by_chain: vec![0, 0, 1, 1],
+            templates: vec![
+                template(0, 0, [Switch, Switch, Switch, Switch], &[], [0, 0]),
+                template(0, 0, [Switch, Switch, Switch, Switch], &[4], [4, 3]),
+                template(
+                    0,
+                    1,
+                    [NotPresent, Discardable, NotPresent, Discardable],
+                    &[2],
+                    [2, 1],
+                ),
+                template(1, 0, [NotPresent, NotPresent, Switch, Switch], &[1], [1, 1]),
+                template(
+                    1,
+                    0,
+                    [NotPresent, NotPresent, Required, Required],
+                    &[4, 1],
+                    [1, 4],
+                ),
+                template(
+                    1,
+                    1,
+                    [NotPresent, NotPresent, NotPresent, Discardable],
+                    &[2, 1],
+                    [3, 2],
+                ),
+            ],
+            resolutions: vec![
+                RenderResolution {
+                    width: 640,
+                    height: 360,
+                },
+                RenderResolution {
+                    width: 1280,
+                    height: 720,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_non_symmetric_roundtrip() {
+        for n in 1..10 {
+            let mut writer = BitWriter::default();
+            for value in 0..n {
+                writer.write_non_symmetric(value, n);
+            }
+            let data = writer.finish();
+            let mut reader = BitReader::new(&data);
+            for value in 0..n {
+                assert_eq!(reader.read_non_symmetric(n).unwrap(), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_mandatory_fields_only() {
+        let descriptor = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: false,
+            template_id: 2,
+            frame_number: 0x1234,
+            ..Default::default()
+        };
+        let data = descriptor.encode();
+        assert_eq!(data, vec![0x82, 0x12, 0x34]);
+        assert_eq!(DependencyDescriptor::parse(&data).unwrap(), descriptor);
+    }
+
+    #[test]
+    fn test_structure_roundtrip() {
+        let descriptor = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            template_id: 60,
+            frame_number: 1,
+            structure: Some(structure()),
+            active_decode_targets: Some(ActiveDecodeTargets {
+                bitmask: 0b0011,
+                decode_target_count: 4,
+            }),
+            ..Default::default()
+        };
+        let parsed = DependencyDescriptor::parse(&descriptor.encode()).unwrap();
+        assert_eq!(parsed, descriptor);
+        let frame_dependencies = parsed.frame_dependencies(&structure()).unwrap();
+        assert_eq!(frame_dependencies.spatial_id, 0);
+        assert_eq!(
+            parsed.resolution(&structure()),
+            Some(RenderResolution {
+                width: 640,
+                height: 360
+            })
+        );
+    }
+
+    #[test]
+    fn test_custom_fields_use_latest_structure() {
+        let descriptor = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            // Template index 4
+            template_id: 0,
+            frame_number: 100,
+            custom_frame_diffs: Some(vec![1, 20, 300]),
+            custom_chain_diffs: Some(vec![5, 200]),
+            ..Default::default()
+        };
+        let data = descriptor.encode();
+        assert!(DependencyDescriptor::parse(&data).is_err());
+
+        let structure = structure();
+        let parsed = DependencyDescriptor::parse_with_structure(&data, Some(&structure)).unwrap();
+        assert_eq!(parsed, descriptor);
+        let frame_dependencies = parsed.frame_dependencies(&structure).unwrap();
+        assert_eq!(
+            (
+                frame_dependencies.spatial_id,
+                frame_dependencies.temporal_id
+            ),
+            (1, 0)
+        );
+        assert_eq!(frame_dependencies.frame_diffs, vec![1, 20, 300]);
+        assert_eq!(frame_dependencies.chain_diffs, vec![5, 200]);
+        assert_eq!(
+            frame_dependencies.decode_target_indications,
+            structure.templates[4].decode_target_indications
+        );
+    }
+}
@@ -0,0 +1,344 @@
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+
+use super::header_extensions::{HeaderExtensionConfig, RtpHeaderExtensionValue};
+
+// https://datatracker.ietf.org/doc/html/rfc8285#section-8
+//
+// a=extmap:<value>["/"<direction>] <URI> <extensionattributes>
+//
+// e.g.
+//   a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level vad=on
+//   a=extmap:2/sendonly http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time
+//   a=extmap-allow-mixed
+//
+// Extensions which are encrypted (https://datatracker.ietf.org/doc/html/rfc6904#section-4) are
+// signaled with the encrypt URI followed by the URI of the extension being encrypted:
+//   a=extmap:3 urn:ietf:params:rtp-hdrext:encrypt urn:ietf:params:rtp-hdrext:toffset
+
+const EXTMAP_PREFIX: &str = "a=extmap:";
+const EXTMAP_ALLOW_MIXED: &str = "a=extmap-allow-mixed";
+const ENCRYPT_URI: &str = "urn:ietf:params:rtp-hdrext:encrypt";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtmapDirection {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive,
+}
+
+impl FromStr for ExtmapDirection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sendrecv" => Ok(ExtmapDirection::SendRecv),
+            "sendonly" => Ok(ExtmapDirection::SendOnly),
+            "recvonly" => Ok(ExtmapDirection::RecvOnly),
+            "inactive" => Ok(ExtmapDirection::Inactive),
+            _ => bail!("Invalid extmap direction: {s}"),
+        }
+    }
+}
+
+impl Display for ExtmapDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let direction = match self {
+            ExtmapDirection::SendRecv => "sendrecv",
+            ExtmapDirection::SendOnly => "sendonly",
+            ExtmapDirection::RecvOnly => "recvonly",
+            ExtmapDirection::Inactive => "inactive",
+        };
+        write!(f, "{direction}")
+    }
+}
+
+/// A single negotiated header extension (an a=extmap line)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extmap {
+    pub id: u8,
+    /// If not given, the direction is that of the media section
+    pub direction: Option<ExtmapDirection>,
+    pub uri: String,
+    /// Whether the extension is encrypted
+    pub encrypted: bool,
+    pub attributes: Option<String>,
+}
+
+impl Display for Extmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{EXTMAP_PREFIX}{}", self.id)?;
+        if let Some(direction) = self.direction {
+            write!(f, "/{direction}")?;
+        }
+        if self.encrypted {
+            write!(f, " {ENCRYPT_URI}")?;
+        }
+        write!(f, " {}", self.uri)?;
+        if let Some(attributes) = &self.attributes {
+            write!(f, " {attributes}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parse an a=extmap line (with or without the "a=" prefix)
+pub fn parse_extmap_line(line: &str) -> Result<Extmap> {
+    let line = line.trim();
+    let Some(value) = line
+        .strip_prefix(EXTMAP_PREFIX)
+        .or_else(|| line.strip_prefix(&EXTMAP_PREFIX[2..]))
+    else {
+        bail!("Not an extmap line: {line}");
+    };
+    let (id_and_direction, rest) = value
+        .split_once(' ')
+        .with_context(|| format!("extmap line is missing a uri: {line}"))?;
+    let (id, direction) = match id_and_direction.split_once('/') {
+        Some((id, direction)) => (id, Some(direction.parse()?)),
+        None => (id_and_direction, None),
+    };
+    let id: u8 = id
+        .parse()
+        .with_context(|| format!("Invalid extmap id: {id}"))?;
+    if id == 0 {
+        bail!("Extmap id must be 1-255, got 0");
+    }
+    let mut parts = rest.trim().splitn(2, ' ');
+    let mut uri = parts.next().unwrap_or_default();
+    let mut attributes = parts.next();
+    let encrypted = uri == ENCRYPT_URI;
+    if encrypted {
+        let Some(encrypted_uri_and_attributes) = attributes else {
+            bail!("Encrypted extmap is missing the extension's uri: {line}");
+        };
+        let mut parts = encrypted_uri_and_attributes.splitn(2, ' ');
+        uri = parts.next().unwrap_or_default();
+        attributes = parts.next();
+    }
+
+    Ok(Extmap {
+        id,
+        direction,
+        uri: uri.to_owned(),
+        encrypted,
+        attributes: attributes
+            .map(str::trim)
+            .filter(|attributes| !attributes.is_empty())
+            .map(str::to_owned),
+    })
+}
+
+/// The header extensions negotiated in signaling, mapping between their URIs and the local ids
+/// used in packets.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtensionIdMap {
+    extmaps: Vec<Extmap>,
+    allow_mixed: bool,
+}
+
+impl ExtensionIdMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Build a map from the a=extmap and a=extmap-allow-mixed lines of an SDP (or a single media
+    /// section of one).  Other lines are ignored.
+    pub fn from_sdp(sdp: &str) -> Result<Self> {
+        let mut map = ExtensionIdMap::new();
+        for line in sdp.lines().map(str::trim) {
+            if line == EXTMAP_ALLOW_MIXED {
+                map.allow_mixed = true;
+            } else if line.starts_with(EXTMAP_PREFIX) {
+                let extmap = parse_extmap_line(line)?;
+                map.insert(extmap)?;
+            }
+        }
+
+        Ok(map)
+    }
+
+    /// Add the given extmap.  It's an error for its id or uri to already be mapped.
+    pub fn insert(&mut self, extmap: Extmap) -> Result<()> {
+        if let Some(existing) = self.get(extmap.id) {
+            bail!(
+                "Extmap id {} is already used for {}",
+                extmap.id,
+                existing.uri
+            );
+        }
+        if let Some(existing) = self.id(&extmap.uri) {
+            bail!("Extmap {} is already mapped to id {existing}", extmap.uri);
+        }
+        self.extmaps.push(extmap);
+
+        Ok(())
+    }
+
+    pub fn get(&self, id: u8) -> Option<&Extmap> {
+        self.extmaps.iter().find(|extmap| extmap.id == id)
+    }
+
+    /// The local id of the extension with the given uri
+    pub fn id(&self, uri: &str) -> Option<u8> {
+        self.extmaps
+            .iter()
+            .find(|extmap| extmap.uri == uri)
+            .map(|extmap| extmap.id)
+    }
+
+    /// The uri of the extension with the given local id
+    pub fn uri(&self, id: u8) -> Option<&str> {
+        self.get(id).map(|extmap| extmap.uri.as_str())
+    }
+
+    /// The local id of the typed extension `T`
+    pub fn id_of<T: RtpHeaderExtensionValue>(&self) -> Option<u8> {
+        self.id(T::URI)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Extmap> {
+        self.extmaps.iter()
+    }
+
+    pub fn allow_mixed(&self) -> bool {
+        self.allow_mixed
+    }
+
+    pub fn set_allow_mixed(&mut self, allow_mixed: bool) {
+        self.allow_mixed = allow_mixed;
+    }
+
+    /// The config to read and write header extensions with, according to what was negotiated
+    pub fn header_extension_config(&self) -> HeaderExtensionConfig {
+        HeaderExtensionConfig {
+            allow_mixed: self.allow_mixed,
+            encrypted_ids: self
+                .extmaps
+                .iter()
+                .filter(|extmap| extmap.encrypted)
+                .map(|extmap| extmap.id)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::nsw_types::u24;
+
+    use crate::rtp::{
+        abs_send_time_header_extension::AbsSendTime,
+        audio_level_header_extension::AudioLevel,
+        header_extensions::{HeaderExtensions, SomeHeaderExtension},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_extmap_line() {
+        let extmap = parse_extmap_line(
+            "a=extmap:1/sendonly urn:ietf:params:rtp-hdrext:ssrc-audio-level vad=on",
+        )
+        .unwrap();
+        assert_eq!(
+            extmap,
+            Extmap {
+                id: 1,
+                direction: Some(ExtmapDirection::SendOnly),
+                uri: AudioLevel::URI.to_owned(),
+                encrypted: false,
+                attributes: Some("vad=on".to_owned()),
+            }
+        );
+        assert_eq!(
+            extmap.to_string(),
+            "a=extmap:1/sendonly urn:ietf:params:rtp-hdrext:ssrc-audio-level vad=on"
+        );
+
+        let extmap = parse_extmap_line(
+            "extmap:3 urn:ietf:params:rtp-hdrext:encrypt urn:ietf:params:rtp-hdrext:toffset",
+        )
+        .unwrap();
+        assert!(extmap.encrypted);
+        assert_eq!(extmap.uri, "urn:ietf:params:rtp-hdrext:toffset");
+
+        assert!(parse_extmap_line("a=extmap:0 urn:foo").is_err());
+        assert!(parse_extmap_line("a=extmap:1/sideways urn:foo").is_err());
+        assert!(parse_extmap_line("a=extmap:1").is_err());
+    }
+
+    #[test]
+    fn test_from_sdp() {
+        let sdp = "m=audio 9 UDP/TLS/RTP/SAVPF 111\r\n\
+            a=extmap-allow-mixed\r\n\
+            a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+            a=extmap:2 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\r\n\
+            a=extmap:3 urn:ietf:params:rtp-hdrext:encrypt urn:ietf:params:rtp-hdrext:toffset\r\n\
+            a=rtpmap:111 opus/48000/2\r\n";
+        let map = ExtensionIdMap::from_sdp(sdp).unwrap();
+        assert!(map.allow_mixed());
+        assert_eq!(map.id_of::<AudioLevel>(), Some(1));
+        assert_eq!(map.uri(2), Some(AbsSendTime::URI));
+        let config = map.header_extension_config();
+        assert!(config.allow_mixed);
+        assert!(config.is_encrypted(3));
+        assert!(!config.is_encrypted(1));
+
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions
+            .set_mapped(&map, &AbsSendTime(u24::new(12345)))
+            .unwrap();
+        assert!(header_extensions.get(2).is_some());
+        assert_eq!(
+            header_extensions.get_mapped::<AbsSendTime>(&map).unwrap(),
+            Some(AbsSendTime(u24::new(12345)))
+        );
+        assert_eq!(
+            header_extensions.get_mapped::<AudioLevel>(&map).unwrap(),
+            None
+        );
+
+        assert!(ExtensionIdMap::from_sdp("a=extmap:1 urn:a\na=extmap:1 urn:b").is_err());
+    }
+
+    #[test]
+    fn test_remap_ids() {
+        let from = ExtensionIdMap::from_sdp(
+            "a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\n\
+            a=extmap:2 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time\n\
+            a=extmap:3 urn:ietf:params:rtp-hdrext:toffset",
+        )
+        .unwrap();
+        let to = ExtensionIdMap::from_sdp(
+            "a=extmap:2 urn:ietf:params:rtp-hdrext:ssrc-audio-level\n\
+            a=extmap:20 http://www.webrtc.org/experiments/rtp-hdrext/abs-send-time",
+        )
+        .unwrap();
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions
+            .set_mapped(&from, &AbsSendTime(u24::new(1)))
+            .unwrap();
+        header_extensions.insert(SomeHeaderExtension::new(1, &[0x85]).unwrap());
+        header_extensions.insert(SomeHeaderExtension::new(3, &[0, 0, 1]).unwrap());
+        // Not negotiated at all
+        header_extensions.insert(SomeHeaderExtension::new(9, &[0]).unwrap());
+
+        header_extensions.remap_ids(&from, &to).unwrap();
+        assert_eq!(
+            header_extensions
+                .iter()
+                .map(|ext| ext.id())
+                .collect::<Vec<_>>(),
+            vec![20, 2]
+        );
+        assert_eq!(
+            header_extensions.get_mapped::<AbsSendTime>(&to).unwrap(),
+            Some(AbsSendTime(u24::new(1)))
+        );
+        assert_eq!(header_extensions.get(2).unwrap().data().as_ref(), [0x85]);
+    }
+}
@@ -0,0 +1,460 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_write::BitWrite,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u4, u7},
+};
+use bytes::Bytes;
+
+use crate::{error::RtpParseError, LengthBytes};
+
+use super::rtp_header::RtpHeader;
+
+// https://datatracker.ietf.org/doc/html/draft-ietf-payload-flexible-fec-scheme-03#section-4.2
+// The flexible mask form of the FlexFEC header (R=0, F=0), as used by WebRTC:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |R|F|P|X|  CC   |M| PT recovery |        length recovery        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          TS recovery                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   SSRCCount   |                    reserved                   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                             SSRC_i                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |           SN base_i           |k|          Mask [0-14]        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                   Mask [15-45] (optional)                   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                                                             |
+// +-+                   Mask [46-108] (optional)                  |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                     ... next SSRC_i ...                       |
+//
+// The SSRC, SN base and mask are repeated for each protected stream.  The k bit is set in the last
+// part of each mask.  Mask bit i is set if the packet with sequence number SN base + i is
+// protected.  The recovery fields (and the repair payload which follows the header) are the XOR
+// of the corresponding fields of the protected packets, where the length is that of everything
+// after the packets' 12 byte fixed header.
+
+const BASE_HEADER_SIZE_BYTES: usize = 12;
+const RETRANSMISSION_MASK: u8 = 0x80;
+const FIXED_MASK_MASK: u8 = 0x40;
+const PADDING_RECOVERY_MASK: u8 = 0x20;
+const EXTENSION_RECOVERY_MASK: u8 = 0x10;
+const MARKER_RECOVERY_MASK: u8 = 0x80;
+/// The number of packets a single mask can cover
+const MAX_MASK_BITS: u16 = 109;
+
+/// The packets from one stream which are protected by a FlexFEC packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlexfecProtectedStream {
+    pub ssrc: u32,
+    pub seq_num_base: u16,
+    /// Bit i (from the least significant bit) is set if the packet with sequence number
+    /// `seq_num_base + i` is protected.  Only the low 109 bits are used.
+    pub mask: u128,
+}
+
+impl FlexfecProtectedStream {
+    /// The sequence numbers of the protected packets from this stream
+    pub fn protected_seq_nums(&self) -> impl Iterator<Item = u16> + '_ {
+        (0..MAX_MASK_BITS)
+            .filter(|i| self.mask & (1 << i) != 0)
+            .map(|i| self.seq_num_base.wrapping_add(i))
+    }
+
+    fn mask_length_bytes(&self) -> usize {
+        if self.mask < 1 << 15 {
+            2
+        } else if self.mask < 1 << 46 {
+            6
+        } else {
+            14
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FlexfecPayload {
+    pub padding_recovery: bool,
+    pub extension_recovery: bool,
+    pub csrc_count_recovery: u4,
+    pub marker_recovery: bool,
+    pub payload_type_recovery: u7,
+    pub length_recovery: u16,
+    pub timestamp_recovery: u32,
+    pub protected_streams: Vec<FlexfecProtectedStream>,
+    pub repair_payload: Bytes,
+}
+
+impl FlexfecPayload {
+    /// Whether this FEC packet protects the packet with the given ssrc and sequence number
+    pub fn protects(&self, ssrc: u32, seq_num: u16) -> bool {
+        self.protected_streams
+            .iter()
+            .filter(|stream| stream.ssrc == ssrc)
+            .any(|stream| {
+                let i = seq_num.wrapping_sub(stream.seq_num_base);
+                i < MAX_MASK_BITS && stream.mask & (1 << i) != 0
+            })
+    }
+
+    fn header_length_bytes(&self) -> usize {
+        BASE_HEADER_SIZE_BYTES
+            + self
+                .protected_streams
+                .iter()
+                .map(|stream| 6 + stream.mask_length_bytes())
+                .sum::<usize>()
+    }
+}
+
+impl LengthBytes for FlexfecPayload {
+    fn length_bytes(&self) -> usize {
+        self.header_length_bytes() + self.repair_payload.len()
+    }
+}
+
+/// Read a FlexFEC payload.  Only the flexible mask form (R=0, F=0) is supported.  The repair
+/// payload is a slice of `payload` rather than a copy.
+pub fn read_flexfec_payload(payload: Bytes) -> Result<FlexfecPayload> {
+    if payload.len() < BASE_HEADER_SIZE_BYTES {
+        bail!(RtpParseError::truncated(
+            BASE_HEADER_SIZE_BYTES,
+            payload.len()
+        ));
+    }
+    if payload[0] & RETRANSMISSION_MASK != 0 {
+        bail!(RtpParseError::invalid_field(
+            "FlexFEC R bit",
+            "0, since retransmissions aren't supported",
+            1
+        ));
+    }
+    if payload[0] & FIXED_MASK_MASK != 0 {
+        bail!(RtpParseError::invalid_field(
+            "FlexFEC F bit",
+            "0, since fixed offset masks aren't supported",
+            1
+        ));
+    }
+    let ssrc_count = payload[8] as usize;
+    let mut offset = BASE_HEADER_SIZE_BYTES;
+    let mut protected_streams = Vec::with_capacity(ssrc_count);
+    for i in 0..ssrc_count {
+        let Some(stream_header) = payload.get(offset..offset + 8) else {
+            return Err(RtpParseError::truncated(offset + 8, payload.len()))
+                .with_context(|| format!("protected stream {i}"));
+        };
+        let ssrc = u32::from_be_bytes(stream_header[..4].try_into().unwrap());
+        let seq_num_base = u16::from_be_bytes([stream_header[4], stream_header[5]]);
+        let chunk = u16::from_be_bytes([stream_header[6], stream_header[7]]);
+        let mut mask = reverse_bits((chunk & 0x7FFF) as u128, 15);
+        offset += 8;
+        if chunk & 0x8000 == 0 {
+            let Some(chunk) = payload.get(offset..offset + 4) else {
+                return Err(RtpParseError::truncated(offset + 4, payload.len()))
+                    .with_context(|| format!("protected stream {i} mask"));
+            };
+            let chunk = u32::from_be_bytes(chunk.try_into().unwrap());
+            mask |= reverse_bits((chunk & 0x7FFF_FFFF) as u128, 31) << 15;
+            offset += 4;
+            if chunk & 0x8000_0000 == 0 {
+                let Some(chunk) = payload.get(offset..offset + 8) else {
+                    return Err(RtpParseError::truncated(offset + 8, payload.len()))
+                        .with_context(|| format!("protected stream {i} mask"));
+                };
+                let chunk = u64::from_be_bytes(chunk.try_into().unwrap());
+                if chunk & 0x8000_0000_0000_0000 == 0 {
+                    return Err(RtpParseError::invalid_field("mask final k bit", 1, 0))
+                        .with_context(|| format!("protected stream {i} mask"));
+                }
+                mask |= reverse_bits((chunk & 0x7FFF_FFFF_FFFF_FFFF) as u128, 63) << 46;
+                offset += 8;
+            }
+        }
+        protected_streams.push(FlexfecProtectedStream {
+            ssrc,
+            seq_num_base,
+            mask,
+        });
+    }
+
+    Ok(FlexfecPayload {
+        padding_recovery: payload[0] & PADDING_RECOVERY_MASK != 0,
+        extension_recovery: payload[0] & EXTENSION_RECOVERY_MASK != 0,
+        csrc_count_recovery: u4::new(payload[0] & 0xF),
+        marker_recovery: payload[1] & MARKER_RECOVERY_MASK != 0,
+        payload_type_recovery: u7::new(payload[1] & 0x7F),
+        length_recovery: u16::from_be_bytes([payload[2], payload[3]]),
+        timestamp_recovery: u32::from_be_bytes(payload[4..8].try_into().unwrap()),
+        protected_streams,
+        repair_payload: payload.slice(offset..),
+    })
+}
+
+pub fn write_flexfec_payload<W: BitWrite>(
+    buf: &mut W,
+    flexfec_payload: &FlexfecPayload,
+) -> Result<()> {
+    let Ok(ssrc_count) = u8::try_from(flexfec_payload.protected_streams.len()) else {
+        bail!(
+            "FlexFEC can protect at most 255 streams, got {}",
+            flexfec_payload.protected_streams.len()
+        );
+    };
+    let mut first = u8::from(flexfec_payload.csrc_count_recovery);
+    if flexfec_payload.padding_recovery {
+        first |= PADDING_RECOVERY_MASK;
+    }
+    if flexfec_payload.extension_recovery {
+        first |= EXTENSION_RECOVERY_MASK;
+    }
+    let mut second = u8::from(flexfec_payload.payload_type_recovery);
+    if flexfec_payload.marker_recovery {
+        second |= MARKER_RECOVERY_MASK;
+    }
+    buf.write_u8(first).context("flags")?;
+    buf.write_u8(second)
+        .context("marker and payload type recovery")?;
+    buf.write_u16::<NetworkOrder>(flexfec_payload.length_recovery)
+        .context("length recovery")?;
+    buf.write_u32::<NetworkOrder>(flexfec_payload.timestamp_recovery)
+        .context("timestamp recovery")?;
+    buf.write_u32::<NetworkOrder>((ssrc_count as u32) << 24)
+        .context("ssrc count")?;
+
+    for (i, stream) in flexfec_payload.protected_streams.iter().enumerate() {
+        if stream.mask >> MAX_MASK_BITS != 0 {
+            bail!("FlexFEC protected stream {i} mask is longer than {MAX_MASK_BITS} bits");
+        }
+        buf.write_u32::<NetworkOrder>(stream.ssrc)
+            .with_context(|| format!("protected stream {i} ssrc"))?;
+        buf.write_u16::<NetworkOrder>(stream.seq_num_base)
+            .with_context(|| format!("protected stream {i} seq num base"))?;
+        // Each chunk of the mask is written with its k bit set if it's the last one
+        let mask_length_bytes = stream.mask_length_bytes();
+        let last_chunk = |length_bytes: usize| (mask_length_bytes == length_bytes) as u128;
+        let chunk = (last_chunk(2) << 15) | reverse_bits(stream.mask & 0x7FFF, 15);
+        buf.write_u16::<NetworkOrder>(chunk as u16)
+            .with_context(|| format!("protected stream {i} mask"))?;
+        if mask_length_bytes > 2 {
+            let chunk = (last_chunk(6) << 31) | reverse_bits((stream.mask >> 15) & 0x7FFF_FFFF, 31);
+            buf.write_u32::<NetworkOrder>(chunk as u32)
+                .with_context(|| format!("protected stream {i} mask"))?;
+        }
+        if mask_length_bytes > 6 {
+            let chunk = (1 << 63) | reverse_bits(stream.mask >> 46, 63);
+            std::io::Write::write_all(buf, &(chunk as u64).to_be_bytes())
+                .with_context(|| format!("protected stream {i} mask"))?;
+        }
+    }
+    std::io::Write::write_all(buf, &flexfec_payload.repair_payload).context("repair payload")?;
+
+    Ok(())
+}
+
+/// Reverse the order of the low `num_bits` bits of `value`, since the first bit of each mask
+/// chunk on the wire is for the lowest sequence number.
+fn reverse_bits(value: u128, num_bits: u32) -> u128 {
+    value.reverse_bits() >> (128 - num_bits)
+}
+
+/// Generates a FlexFEC payload protecting a group of media packets
+#[derive(Debug, Default)]
+pub struct FlexfecPayloadBuilder {
+    packets: Vec<Bytes>,
+}
+
+impl FlexfecPayloadBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Protect the given serialized media packet.  Packets from each stream should be added in
+    /// sequence number order, and must all be within 109 sequence numbers of the first one.
+    pub fn protect(mut self, packet: Bytes) -> Self {
+        self.packets.push(packet);
+        self
+    }
+
+    pub fn build(self) -> Result<FlexfecPayload> {
+        let mut fixed_header = [0u8; 8];
+        let mut length_recovery = 0u16;
+        let mut repair_payload = Vec::new();
+        let mut protected_streams: Vec<FlexfecProtectedStream> = Vec::new();
+        for (i, packet) in self.packets.iter().enumerate() {
+            if packet.len() < BASE_HEADER_SIZE_BYTES {
+                bail!(
+                    "Protected packet {i} is too short for an RTP header: {} bytes",
+                    packet.len()
+                );
+            }
+            for (recovery, byte) in fixed_header.iter_mut().zip(packet.iter()) {
+                *recovery ^= byte;
+            }
+            let protected = &packet[BASE_HEADER_SIZE_BYTES..];
+            let Ok(length) = u16::try_from(protected.len()) else {
+                bail!("Protected packet {i} is too long: {} bytes", packet.len());
+            };
+            length_recovery ^= length;
+            if repair_payload.len() < protected.len() {
+                repair_payload.resize(protected.len(), 0);
+            }
+            for (repair, byte) in repair_payload.iter_mut().zip(protected) {
+                *repair ^= byte;
+            }
+
+            let ssrc = RtpHeader::ssrc(packet);
+            let seq_num = RtpHeader::seq_num(packet);
+            match protected_streams
+                .iter_mut()
+                .find(|stream| stream.ssrc == ssrc)
+            {
+                Some(stream) => {
+                    let offset = seq_num.wrapping_sub(stream.seq_num_base);
+                    if offset >= MAX_MASK_BITS {
+                        bail!(
+                            "Protected packet {i} (seq num {seq_num}) is more than {MAX_MASK_BITS} \
+                            packets after the first packet from ssrc {ssrc} ({})",
+                            stream.seq_num_base
+                        );
+                    }
+                    stream.mask |= 1 << offset;
+                }
+                None => protected_streams.push(FlexfecProtectedStream {
+                    ssrc,
+                    seq_num_base: seq_num,
+                    mask: 1,
+                }),
+            }
+        }
+
+        Ok(FlexfecPayload {
+            padding_recovery: fixed_header[0] & PADDING_RECOVERY_MASK != 0,
+            extension_recovery: fixed_header[0] & EXTENSION_RECOVERY_MASK != 0,
+            csrc_count_recovery: u4::new(fixed_header[0] & 0xF),
+            marker_recovery: fixed_header[1] & MARKER_RECOVERY_MASK != 0,
+            payload_type_recovery: u7::new(fixed_header[1] & 0x7F),
+            length_recovery,
+            timestamp_recovery: u32::from_be_bytes(fixed_header[4..8].try_into().unwrap()),
+            protected_streams,
+            repair_payload: Bytes::from(repair_payload),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtp::rtp_packet::{write_rtp_packet, RtpPacketBuilder};
+
+    use super::*;
+
+    fn serialize(seq_num: u16, payload: &[u8]) -> Bytes {
+        let packet = RtpPacketBuilder::new()
+            .payload_type(u7::new(96))
+            .seq_num(seq_num)
+            .timestamp(seq_num as u32 * 90)
+            .ssrc(1234)
+            .payload(payload)
+            .build()
+            .unwrap();
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; packet.length_bytes()]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        Bytes::from(cursor.into_inner().into_vec())
+    }
+
+    fn write(flexfec_payload: &FlexfecPayload) -> Bytes {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            flexfec_payload
+                .length_bytes()
+        ]));
+        write_flexfec_payload(&mut cursor, flexfec_payload).unwrap();
+        Bytes::from(cursor.into_inner().into_vec())
+    }
+
+    #[test]
+    fn test_mask_lengths() {
+        for (mask, length_bytes) in [(0b101, 2), (1 << 20, 6), (1 << 100 | 1, 14)] {
+            let flexfec_payload = FlexfecPayload {
+                padding_recovery: false,
+                extension_recovery: false,
+                csrc_count_recovery: u4::new(0),
+                marker_recovery: false,
+                payload_type_recovery: u7::new(0),
+                length_recovery: 0,
+                timestamp_recovery: 0,
+                protected_streams: vec![FlexfecProtectedStream {
+                    ssrc: 1,
+                    seq_num_base: 65500,
+                    mask,
+                }],
+                repair_payload: Bytes::from_static(&[1, 2]),
+            };
+            let data = write(&flexfec_payload);
+            assert_eq!(data.len(), 12 + 6 + length_bytes + 2);
+            assert_eq!(read_flexfec_payload(data).unwrap(), flexfec_payload);
+        }
+        // The first mask bit (after k) is for the seq num base
+        let data = write(&FlexfecPayload {
+            protected_streams: vec![FlexfecProtectedStream {
+                ssrc: 1,
+                seq_num_base: 0,
+                mask: 1,
+            }],
+            ..read_flexfec_payload(Bytes::from_static(&[0; 12])).unwrap()
+        });
+        assert_eq!(&data[18..20], [0xC0, 0x00]);
+    }
+
+    #[test]
+    fn test_build_and_recover() {
+        let packets = [
+            serialize(65535, &[1, 2, 3]),
+            serialize(0, &[4, 5]),
+            serialize(2, &[6, 7, 8, 9]),
+        ];
+        let flexfec_payload = packets
+            .iter()
+            .cloned()
+            .fold(FlexfecPayloadBuilder::new(), |builder, packet| {
+                builder.protect(packet)
+            })
+            .build()
+            .unwrap();
+        let stream = &flexfec_payload.protected_streams[0];
+        assert_eq!(stream.ssrc, 1234);
+        assert_eq!(
+            stream.protected_seq_nums().collect::<Vec<_>>(),
+            vec![65535, 0, 2]
+        );
+        assert!(flexfec_payload.protects(1234, 0));
+        assert!(!flexfec_payload.protects(1234, 1));
+        let parsed = read_flexfec_payload(write(&flexfec_payload)).unwrap();
+        assert_eq!(parsed, flexfec_payload);
+
+        // Recover the second packet from the others
+        let mut recovered = parsed.repair_payload.to_vec();
+        let mut length = parsed.length_recovery;
+        let mut timestamp = parsed.timestamp_recovery;
+        for packet in [&packets[0], &packets[2]] {
+            for (byte, other) in recovered.iter_mut().zip(&packet[12..]) {
+                *byte ^= other;
+            }
+            length ^= (packet.len() - 12) as u16;
+            timestamp ^= RtpHeader::timestamp(packet);
+        }
+        assert_eq!(length, 2);
+        assert_eq!(timestamp, 0);
+        assert_eq!(&recovered[..length as usize], &packets[1][12..]);
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// https://datatracker.ietf.org/doc/html/draft-ietf-avtext-framemarking-16#section-3
+// Non-scalable streams:
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   |  L=0  |S|E|I|D|0 0 0 0|
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Scalable streams (TL0PICIDX may be omitted, in which case L=1):
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   |  L=2  |S|E|I|D|B| TID |      LID      |   TL0PICIDX   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// S: start of frame
+// E: end of frame
+// I: independent frame
+// D: discardable frame
+// B: base layer sync
+// TID: temporal layer id
+// LID: layer id
+// TL0PICIDX: temporal layer 0 picture index
+
+const START_OF_FRAME_MASK: u8 = 0x80;
+const END_OF_FRAME_MASK: u8 = 0x40;
+const INDEPENDENT_MASK: u8 = 0x20;
+const DISCARDABLE_MASK: u8 = 0x10;
+const BASE_LAYER_SYNC_MASK: u8 = 0x08;
+const TEMPORAL_ID_MASK: u8 = 0x07;
+
+/// The layer information for a frame from a scalable stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameMarkingScalability {
+    pub base_layer_sync: bool,
+    /// 3 bits
+    pub temporal_id: u8,
+    pub layer_id: u8,
+    pub tl0_pic_idx: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameMarking {
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    pub independent: bool,
+    pub discardable: bool,
+    /// Present for scalable streams, in which case the longer form of the extension is written
+    pub scalability: Option<FrameMarkingScalability>,
+}
+
+impl RtpHeaderExtensionValue for FrameMarking {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:framemarking";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let (flags, scalability) = match *data {
+            [flags] => (flags, None),
+            [flags, layer_id] => (
+                flags,
+                Some(FrameMarkingScalability {
+                    base_layer_sync: flags & BASE_LAYER_SYNC_MASK != 0,
+                    temporal_id: flags & TEMPORAL_ID_MASK,
+                    layer_id,
+                    tl0_pic_idx: None,
+                }),
+            ),
+            [flags, layer_id, tl0_pic_idx] => (
+                flags,
+                Some(FrameMarkingScalability {
+                    base_layer_sync: flags & BASE_LAYER_SYNC_MASK != 0,
+                    temporal_id: flags & TEMPORAL_ID_MASK,
+                    layer_id,
+                    tl0_pic_idx: Some(tl0_pic_idx),
+                }),
+            ),
+            _ => bail!(RtpParseError::invalid_field(
+                "frame marking length",
+                "1-3 bytes",
+                format!("{} bytes", data.len())
+            )),
+        };
+
+        Ok(FrameMarking {
+            start_of_frame: flags & START_OF_FRAME_MASK != 0,
+            end_of_frame: flags & END_OF_FRAME_MASK != 0,
+            independent: flags & INDEPENDENT_MASK != 0,
+            discardable: flags & DISCARDABLE_MASK != 0,
+            scalability,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut flags = 0;
+        for (set, mask) in [
+            (self.start_of_frame, START_OF_FRAME_MASK),
+            (self.end_of_frame, END_OF_FRAME_MASK),
+            (self.independent, INDEPENDENT_MASK),
+            (self.discardable, DISCARDABLE_MASK),
+        ] {
+            if set {
+                flags |= mask;
+            }
+        }
+        let Some(scalability) = &self.scalability else {
+            return vec![flags];
+        };
+        if scalability.base_layer_sync {
+            flags |= BASE_LAYER_SYNC_MASK;
+        }
+        flags |= scalability.temporal_id & TEMPORAL_ID_MASK;
+        let mut data = vec![flags, scalability.layer_id];
+        if let Some(tl0_pic_idx) = scalability.tl0_pic_idx {
+            data.push(tl0_pic_idx);
+        }
+
+        data
+    }
+}
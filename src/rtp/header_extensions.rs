@@ -1,6 +1,13 @@
-use std::{collections::HashMap, io::Cursor};
+use std::{collections::BTreeSet, io::Cursor};
 
-use bytes::{Buf, Bytes};
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_cursor::BitCursor, bit_write_exts::BitWriteExts, byte_order::NetworkOrder};
+use bitvec::{order::Msb0, vec::BitVec};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use crate::{error::RtpParseError, LengthBytes, PacketBufferMut};
+
+use super::extmap::ExtensionIdMap;
 
 //  https://datatracker.ietf.org/doc/html/rfc3550#section-5.3.1
 //  0                   1                   2                   3
@@ -51,12 +58,35 @@ use bytes::{Buf, Bytes};
 //    16 bytes.  (This permits carriage of 16-byte values, which is a
 //    common length of labels and identifiers, while losing the possibility
 //    of zero-length values, which would often be padded anyway.)
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Bytes", into = "Bytes")
+)]
 pub struct OneByteHeaderExtension(Bytes);
 
 impl OneByteHeaderExtension {
     pub const TYPE: u16 = 0xBEDE;
 
+    /// Create an extension element with the given id (1-14) and data (1-16 bytes).
+    pub fn new(id: u8, data: &[u8]) -> Result<Self> {
+        if !(1..=14).contains(&id) {
+            bail!("One byte header extension id must be 1-14, got {id}");
+        }
+        if !(1..=16).contains(&data.len()) {
+            bail!(
+                "One byte header extension data must be 1-16 bytes, got {}",
+                data.len()
+            );
+        }
+        let mut buf = BytesMut::with_capacity(1 + data.len());
+        buf.put_u8((id << 4) | (data.len() as u8 - 1));
+        buf.put_slice(data);
+
+        Ok(OneByteHeaderExtension(buf.freeze()))
+    }
+
     pub fn type_matches(ext_type: u16) -> bool {
         ext_type == Self::TYPE
     }
@@ -70,20 +100,60 @@ impl OneByteHeaderExtension {
     }
 }
 
-pub fn read_one_byte_header_extension(buf: &mut Bytes) -> OneByteHeaderExtension {
-    let id = (buf[0] & 0xF0) >> 4;
+/// Parse a whole extension element (or the padding at the end of an extensions block), as it's
+/// serialized
+impl TryFrom<Bytes> for OneByteHeaderExtension {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Bytes) -> Result<Self> {
+        let mut buf = value;
+        let ext = read_one_byte_header_extension(&mut buf)?;
+        if !buf.is_empty() {
+            bail!(RtpParseError::invalid_field(
+                "one byte header extension length",
+                format!("{} bytes", ext.0.len()),
+                format!("{} bytes", ext.0.len() + buf.len())
+            ));
+        }
+
+        Ok(ext)
+    }
+}
+
+impl From<OneByteHeaderExtension> for Bytes {
+    fn from(value: OneByteHeaderExtension) -> Self {
+        value.0
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for OneByteHeaderExtension {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let id = u.int_in_range(1..=14)?;
+        let data_len = u.int_in_range(1..=16)?;
+        OneByteHeaderExtension::new(id, u.bytes(data_len)?)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+pub fn read_one_byte_header_extension(buf: &mut Bytes) -> Result<OneByteHeaderExtension> {
+    let Some(&first_byte) = buf.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    let id = (first_byte & 0xF0) >> 4;
 
     let length_bytes = match id {
         // A 0 id means we've hit the end of the actual extensions, so consume the rest of the
         // buffer
         0 => buf.len() - 1,
-        _ => ((buf[0] & 0xF) + 1) as usize,
+        _ => ((first_byte & 0xF) + 1) as usize,
     };
-
-    // TODO: here (and two byte) i think we need to validate against buf.len() before splitting
+    if buf.len() < 1 + length_bytes {
+        bail!(RtpParseError::truncated(1 + length_bytes, buf.len()));
+    }
     let he = buf.split_to(1 + length_bytes);
 
-    OneByteHeaderExtension(he)
+    Ok(OneByteHeaderExtension(he))
 }
 
 // https://datatracker.ietf.org/doc/html/rfc8285#section-4.3
@@ -130,13 +200,37 @@ pub fn read_one_byte_header_extension(buf: &mut Bytes) -> OneByteHeaderExtension
 //    The 8-bit length field is the length of extension data in bytes, not
 //    including the ID and length fields.  The value zero (0) indicates
 //    that there is no subsequent data.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "Bytes", into = "Bytes")
+)]
 pub struct TwoByteHeaderExtension(Bytes);
 
 impl TwoByteHeaderExtension {
     const TYPE_MASK: u16 = 0xFFF0;
     pub const TYPE: u16 = 0x1000;
 
+    /// Create an extension element with the given id (1-255) and data (0-255 bytes).
+    pub fn new(id: u8, data: &[u8]) -> Result<Self> {
+        if id == 0 {
+            bail!("Two byte header extension id must be 1-255, got {id}");
+        }
+        let length: u8 = data.len().try_into().with_context(|| {
+            format!(
+                "Two byte header extension data must be 0-255 bytes, got {}",
+                data.len()
+            )
+        })?;
+        let mut buf = BytesMut::with_capacity(2 + data.len());
+        buf.put_u8(id);
+        buf.put_u8(length);
+        buf.put_slice(data);
+
+        Ok(TwoByteHeaderExtension(buf.freeze()))
+    }
+
     pub fn type_matches(ext_type: u16) -> bool {
         (ext_type & Self::TYPE_MASK) == Self::TYPE
     }
@@ -146,30 +240,94 @@ impl TwoByteHeaderExtension {
     }
 
     pub fn data(&self) -> Bytes {
-        self.0.slice(2..)
+        // Padding may be shorter than the id and length fields
+        self.0.slice(self.0.len().min(2)..)
+    }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for TwoByteHeaderExtension {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let id = u.int_in_range(1..=255)?;
+        let data_len = u.int_in_range(0..=255)?;
+        TwoByteHeaderExtension::new(id, u.bytes(data_len)?)
+            .map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+/// Parse a whole extension element (or the padding at the end of an extensions block), as it's
+/// serialized
+impl TryFrom<Bytes> for TwoByteHeaderExtension {
+    type Error = anyhow::Error;
+
+    fn try_from(value: Bytes) -> Result<Self> {
+        let mut buf = value;
+        let ext = read_two_byte_header_extension(&mut buf)?;
+        if !buf.is_empty() {
+            bail!(RtpParseError::invalid_field(
+                "two byte header extension length",
+                format!("{} bytes", ext.0.len()),
+                format!("{} bytes", ext.0.len() + buf.len())
+            ));
+        }
+
+        Ok(ext)
+    }
+}
+
+impl From<TwoByteHeaderExtension> for Bytes {
+    fn from(value: TwoByteHeaderExtension) -> Self {
+        value.0
     }
 }
 
 /// [`buf`] should start at the beginning of the header extension (the id)
-pub fn read_two_byte_header_extension(buf: &mut Bytes) -> TwoByteHeaderExtension {
-    let id = buf[0];
+pub fn read_two_byte_header_extension(buf: &mut Bytes) -> Result<TwoByteHeaderExtension> {
+    let Some(&id) = buf.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
     let length_bytes = match id {
-        0 => 0,
-        _ => buf[1] + 1,
+        // A 0 id means we've hit the padding at the end of the actual extensions, so consume the
+        // rest of the buffer
+        0 => buf.len(),
+        // The length field is in the second byte, and the '2' is to account for the id and length
+        // field bytes before the actul data
+        _ => {
+            let Some(&data_length) = buf.get(1) else {
+                bail!(RtpParseError::truncated(2, buf.len()));
+            };
+            2 + data_length as usize
+        }
     };
-    // The length field is in the second byte, and the '2' is to account for the id and length
-    // field bytes before the actul data
-    let he = buf.split_to(2 + length_bytes as usize);
-    TwoByteHeaderExtension(he)
+    if buf.len() < length_bytes {
+        bail!(RtpParseError::truncated(length_bytes, buf.len()));
+    }
+    Ok(TwoByteHeaderExtension(buf.split_to(length_bytes)))
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum SomeHeaderExtension {
     OneByteHeaderExtension(OneByteHeaderExtension),
     TwoByteHeaderExtension(TwoByteHeaderExtension),
 }
 
 impl SomeHeaderExtension {
+    /// Create an extension element with the given id and data, using the one byte form if the
+    /// id and data fit in it and the two byte form otherwise.
+    pub fn new(id: u8, data: &[u8]) -> Result<Self> {
+        if (1..=14).contains(&id) && (1..=16).contains(&data.len()) {
+            Ok(SomeHeaderExtension::OneByteHeaderExtension(
+                OneByteHeaderExtension::new(id, data)?,
+            ))
+        } else {
+            Ok(SomeHeaderExtension::TwoByteHeaderExtension(
+                TwoByteHeaderExtension::new(id, data)?,
+            ))
+        }
+    }
+
     pub fn id(&self) -> u8 {
         match self {
             SomeHeaderExtension::OneByteHeaderExtension(e) => e.id(),
@@ -185,42 +343,519 @@ impl SomeHeaderExtension {
     }
 }
 
-pub fn read_header_extensions(buf: Bytes) -> HashMap<u8, SomeHeaderExtension> {
+/// A structured header extension value which can be parsed from, and encoded to, the data of an
+/// extension element.
+pub trait RtpHeaderExtensionValue: Sized {
+    /// The URI which identifies this extension in SDP (a=extmap)
+    const URI: &'static str;
+
+    /// Parse a value from the data of an extension element (not including the id and length)
+    fn parse(data: &[u8]) -> Result<Self>;
+
+    /// Encode this value as the data of an extension element
+    fn encode(&self) -> Vec<u8>;
+}
+
+/// The header extensions in a packet, kept in the order they were parsed or added so that they're
+/// written back out in the same order.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct HeaderExtensions(Vec<SomeHeaderExtension>);
+
+impl HeaderExtensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: u8) -> Option<&SomeHeaderExtension> {
+        self.0.iter().find(|ext| ext.id() == id)
+    }
+
+    /// Add the given extension.  If there's already an extension with the same id it's replaced
+    /// (keeping its position) and returned, otherwise the extension is added at the end.
+    pub fn insert(&mut self, ext: SomeHeaderExtension) -> Option<SomeHeaderExtension> {
+        match self.0.iter_mut().find(|existing| existing.id() == ext.id()) {
+            Some(existing) => Some(std::mem::replace(existing, ext)),
+            None => {
+                self.0.push(ext);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: u8) -> Option<SomeHeaderExtension> {
+        let index = self.0.iter().position(|ext| ext.id() == id)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Parse the extension with the given id as a `T`.  Returns None if there's no extension with
+    /// the given id.
+    pub fn get_typed<T: RtpHeaderExtensionValue>(&self, id: u8) -> Result<Option<T>> {
+        self.get(id)
+            .map(|ext| T::parse(&ext.data()).with_context(|| format!("{} (id {id})", T::URI)))
+            .transpose()
+    }
+
+    /// Encode the given value and add it as the extension with the given id, replacing any
+    /// existing extension with the same id.
+    pub fn set_typed<T: RtpHeaderExtensionValue>(&mut self, id: u8, value: &T) -> Result<()> {
+        let ext = SomeHeaderExtension::new(id, &value.encode())
+            .with_context(|| format!("{} (id {id})", T::URI))?;
+        self.insert(ext);
+
+        Ok(())
+    }
+
+    /// Like [`HeaderExtensions::get_typed`], but the extension's id is looked up from its URI in
+    /// the given map.  Returns None if the extension wasn't negotiated or isn't present.
+    pub fn get_mapped<T: RtpHeaderExtensionValue>(
+        &self,
+        extension_id_map: &ExtensionIdMap,
+    ) -> Result<Option<T>> {
+        match extension_id_map.id_of::<T>() {
+            Some(id) => self.get_typed(id),
+            None => Ok(None),
+        }
+    }
+
+    /// Like [`HeaderExtensions::set_typed`], but the extension's id is looked up from its URI in
+    /// the given map.  It's an error if the extension wasn't negotiated.
+    pub fn set_mapped<T: RtpHeaderExtensionValue>(
+        &mut self,
+        extension_id_map: &ExtensionIdMap,
+        value: &T,
+    ) -> Result<()> {
+        let Some(id) = extension_id_map.id_of::<T>() else {
+            bail!("Extension {} wasn't negotiated", T::URI);
+        };
+        self.set_typed(id, value)
+    }
+
+    /// Rewrite the ids of these extensions from those negotiated with one endpoint (`from`) to
+    /// those negotiated with another (`to`), e.g. when forwarding a packet.  Extensions are
+    /// matched by URI, and those which weren't negotiated with both endpoints are removed.
+    pub fn remap_ids(&mut self, from: &ExtensionIdMap, to: &ExtensionIdMap) -> Result<()> {
+        let mut remapped = Vec::with_capacity(self.0.len());
+        for ext in self.0.drain(..) {
+            let Some(id) = from.uri(ext.id()).and_then(|uri| to.id(uri)) else {
+                continue;
+            };
+            let ext = if id == ext.id() {
+                ext
+            } else {
+                SomeHeaderExtension::new(id, &ext.data())
+                    .with_context(|| format!("remapping extension {} to {id}", ext.id()))?
+            };
+            remapped.push(ext);
+        }
+        self.0 = remapped;
+
+        Ok(())
+    }
+
+    /// Iterate over the extensions (including any padding element) in order
+    pub fn iter(&self) -> impl Iterator<Item = &SomeHeaderExtension> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Options which affect how header extensions are read and written
+#[derive(Debug, Clone)]
+pub struct HeaderExtensionConfig {
+    /// Whether mixing one byte and two byte header extensions in a stream has been negotiated
+    /// (i.e. via a=extmap-allow-mixed, https://datatracker.ietf.org/doc/html/rfc8285#section-6).
+    /// When it hasn't, the two byte profile can still be used, but a block which mixes elements
+    /// that fit the one byte form with elements that need the two byte form is rejected when read
+    /// and is an error to write.
+    pub allow_mixed: bool,
+    /// The ids of extensions which have been negotiated to be encrypted
+    /// (https://datatracker.ietf.org/doc/html/rfc6904).  Their data is encrypted and decrypted in
+    /// place by the SRTP layer, using the ranges from
+    /// [`RawRtpPacketMut::encrypted_extension_data_ranges`](super::raw_rtp_packet::RawRtpPacketMut::encrypted_extension_data_ranges).
+    pub encrypted_ids: BTreeSet<u8>,
+}
+
+impl HeaderExtensionConfig {
+    /// Mark the extension with the given id as encrypted
+    pub fn with_encrypted_id(mut self, id: u8) -> Self {
+        self.encrypted_ids.insert(id);
+        self
+    }
+
+    pub fn is_encrypted(&self, id: u8) -> bool {
+        self.encrypted_ids.contains(&id)
+    }
+}
+
+impl Default for HeaderExtensionConfig {
+    /// The default config allows mixing, which matches the behavior of [`read_header_extensions`]
+    /// and [`write_header_extensions`], and has no encrypted extensions.
+    fn default() -> Self {
+        Self {
+            allow_mixed: true,
+            encrypted_ids: BTreeSet::new(),
+        }
+    }
+}
+
+pub fn read_header_extensions(buf: Bytes) -> Result<HeaderExtensions> {
+    read_header_extensions_with_config(buf, &HeaderExtensionConfig::default())
+}
+
+pub fn read_header_extensions_with_config(
+    buf: Bytes,
+    config: &HeaderExtensionConfig,
+) -> Result<HeaderExtensions> {
     // TODO: should be consistent with use of cursor/bitcursor and Vec<u8> and Bytes
+    if buf.len() < 4 {
+        bail!(RtpParseError::truncated(4, buf.len()));
+    }
     let mut cursor = Cursor::new(buf);
 
     let ext_type = cursor.get_u16();
     // Length field is length in 4 byte words
-    let length_bytes = cursor.get_u16() * 4;
+    let length_bytes = cursor.get_u16() as usize * 4;
     let buf = cursor.into_inner();
+    if buf.len() < 4 + length_bytes {
+        bail!(RtpParseError::truncated(4 + length_bytes, buf.len()));
+    }
+
+    if CryptexHeaderExtensions::type_matches(ext_type) {
+        bail!(RtpParseError::UnsupportedPacketType {
+            kind: "encrypted (cryptex) header extension profile",
+            packet_type: ext_type,
+        });
+    }
+    let two_byte = if TwoByteHeaderExtension::type_matches(ext_type) {
+        true
+    } else if OneByteHeaderExtension::type_matches(ext_type) {
+        false
+    } else {
+        bail!(RtpParseError::UnsupportedPacketType {
+            kind: "header extension profile",
+            packet_type: ext_type,
+        });
+    };
 
-    let mut header_extensions_bytes = buf.slice(4..).slice(..length_bytes as usize);
+    let header_extensions =
+        read_header_extension_elements(buf.slice(4..4 + length_bytes), two_byte)?;
+    if !config.allow_mixed && is_mixed(&header_extensions) {
+        bail!(RtpParseError::invalid_field(
+            "header extension elements",
+            "all one byte or all two byte, since extmap-allow-mixed wasn't negotiated",
+            "a mix of both"
+        ));
+    }
+
+    Ok(header_extensions)
+}
 
-    let mut header_extensions: HashMap<u8, SomeHeaderExtension> = HashMap::new();
+/// Read the extension elements (everything after the profile and length words) of a header
+/// extensions block
+fn read_header_extension_elements(
+    mut header_extensions_bytes: Bytes,
+    two_byte: bool,
+) -> Result<HeaderExtensions> {
+    let mut header_extensions = HeaderExtensions::new();
     while !header_extensions_bytes.is_empty() {
-        let ext = if TwoByteHeaderExtension::type_matches(ext_type) {
-            SomeHeaderExtension::TwoByteHeaderExtension(read_two_byte_header_extension(
-                &mut header_extensions_bytes,
-            ))
-        } else if OneByteHeaderExtension::type_matches(ext_type) {
-            SomeHeaderExtension::OneByteHeaderExtension(read_one_byte_header_extension(
-                &mut header_extensions_bytes,
-            ))
+        let ext = if two_byte {
+            SomeHeaderExtension::TwoByteHeaderExtension(
+                read_two_byte_header_extension(&mut header_extensions_bytes)
+                    .context("two byte header extension")?,
+            )
         } else {
-            // TODO: change this to result
-            panic!("Invalid header extension type: {ext_type:x?}");
+            // Id 15 is reserved: processing of the extensions must stop when it's encountered,
+            // whether or not mixing was negotiated
+            if header_extensions_bytes[0] >> 4 == 15 {
+                break;
+            }
+            SomeHeaderExtension::OneByteHeaderExtension(
+                read_one_byte_header_extension(&mut header_extensions_bytes)
+                    .context("one byte header extension")?,
+            )
         };
 
-        header_extensions.insert(ext.id(), ext);
+        header_extensions.insert(ext);
+    }
+
+    Ok(header_extensions)
+}
+
+// https://datatracker.ietf.org/doc/html/rfc9335#section-5.1
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  0xC0 or 0xC2 |    0xDE       |           length              |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                  encrypted extension elements                 |
+// |                             ....                              |
+//
+// With cryptex, the header extension elements (and the csrcs) are encrypted by SRTP along with
+// the payload.  0xC0DE replaces the one byte profile (0xBEDE) and 0xC2DE the two byte profile
+// (0x100x).  Packets which have csrcs but no extensions still have an empty extensions block,
+// to signal that the csrcs are encrypted.
+
+/// An encrypted (cryptex) header extensions block.  The elements can't be parsed until they've
+/// been decrypted by the SRTP layer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CryptexHeaderExtensions {
+    two_byte: bool,
+    data: Bytes,
+}
+
+impl CryptexHeaderExtensions {
+    pub const ONE_BYTE_TYPE: u16 = 0xC0DE;
+    pub const TWO_BYTE_TYPE: u16 = 0xC2DE;
+
+    /// Create a block from the given encrypted elements, which must have been padded to a 32 bit
+    /// boundary before they were encrypted.
+    pub fn new(two_byte: bool, data: Bytes) -> Result<Self> {
+        if data.len().next_multiple_of(4) != data.len() {
+            bail!(
+                "Encrypted header extensions must be a multiple of 4 bytes, got {}",
+                data.len()
+            );
+        }
+        if data.len() / 4 > u16::MAX as usize {
+            bail!(
+                "Encrypted header extensions are too long: {} bytes",
+                data.len()
+            );
+        }
+
+        Ok(Self { two_byte, data })
+    }
+
+    pub fn type_matches(ext_type: u16) -> bool {
+        ext_type == Self::ONE_BYTE_TYPE || ext_type == Self::TWO_BYTE_TYPE
+    }
+
+    /// Whether the elements use the two byte form once decrypted
+    pub fn two_byte(&self) -> bool {
+        self.two_byte
+    }
+
+    pub fn profile(&self) -> u16 {
+        if self.two_byte {
+            Self::TWO_BYTE_TYPE
+        } else {
+            Self::ONE_BYTE_TYPE
+        }
+    }
+
+    /// The encrypted extension elements (not including the profile and length words)
+    pub fn data(&self) -> &Bytes {
+        &self.data
+    }
+
+    /// Parse the extension elements once they've been decrypted
+    pub fn read_decrypted(&self, decrypted: Bytes) -> Result<HeaderExtensions> {
+        read_header_extension_elements(decrypted, self.two_byte)
+    }
+}
+
+impl LengthBytes for CryptexHeaderExtensions {
+    fn length_bytes(&self) -> usize {
+        4 + self.data.len()
     }
+}
+
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for CryptexHeaderExtensions {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let two_byte = u.arbitrary()?;
+        let data_len = u.int_in_range(0..=64)? * 4;
+        CryptexHeaderExtensions::new(two_byte, Bytes::copy_from_slice(u.bytes(data_len)?))
+            .map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+/// Read a cryptex header extensions block (including the profile and length words)
+pub fn read_cryptex_header_extensions(buf: Bytes) -> Result<CryptexHeaderExtensions> {
+    if buf.len() < 4 {
+        bail!(RtpParseError::truncated(4, buf.len()));
+    }
+    let ext_type = u16::from_be_bytes([buf[0], buf[1]]);
+    let length_bytes = u16::from_be_bytes([buf[2], buf[3]]) as usize * 4;
+    if !CryptexHeaderExtensions::type_matches(ext_type) {
+        bail!(RtpParseError::invalid_field(
+            "cryptex header extension profile",
+            "0xc0de or 0xc2de",
+            format!("{ext_type:#06x}")
+        ));
+    }
+    if buf.len() < 4 + length_bytes {
+        bail!(RtpParseError::truncated(4 + length_bytes, buf.len()));
+    }
+
+    Ok(CryptexHeaderExtensions {
+        two_byte: ext_type == CryptexHeaderExtensions::TWO_BYTE_TYPE,
+        data: buf.slice(4..4 + length_bytes),
+    })
+}
+
+pub fn write_cryptex_header_extensions<B: PacketBufferMut>(
+    buf: &mut B,
+    cryptex_header_extensions: &CryptexHeaderExtensions,
+) -> Result<()> {
+    buf.write_u16::<NetworkOrder>(cryptex_header_extensions.profile())
+        .context("profile")?;
+    buf.write_u16::<NetworkOrder>((cryptex_header_extensions.data.len() / 4) as u16)
+        .context("length")?;
+    std::io::Write::write_all(buf, &cryptex_header_extensions.data).context("data")?;
+
+    Ok(())
+}
+
+/// The unencrypted extension elements (padded to a 32 bit boundary, but without the profile and
+/// length words) that should be encrypted to create a [`CryptexHeaderExtensions`], and whether
+/// they use the two byte form.
+pub fn header_extension_elements(header_extensions: &HeaderExtensions) -> Result<(bool, Vec<u8>)> {
+    let length_bytes = header_extensions_length_bytes(header_extensions);
+    let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::repeat(false, length_bytes * 8));
+    write_header_extensions(&mut cursor, header_extensions)?;
+    let mut data = cursor.into_inner().into_vec();
+    data.drain(..4);
+
+    Ok((uses_two_byte_profile(header_extensions), data))
+}
+
+impl LengthBytes for HeaderExtensions {
+    fn length_bytes(&self) -> usize {
+        header_extensions_length_bytes(self)
+    }
+}
+
+/// The length in bytes of the header extensions block (including the profile and length words
+/// and padding) that [`write_header_extensions`] would write for the given extensions.  Padding
+/// elements (id 0) aren't counted.
+pub fn header_extensions_length_bytes(header_extensions: &HeaderExtensions) -> usize {
+    let two_byte = uses_two_byte_profile(header_extensions);
+    let elements_length_bytes: usize = header_extensions
+        .iter()
+        .filter(|ext| ext.id() != 0)
+        .map(|ext| if two_byte { 2 } else { 1 } + ext.data().len())
+        .sum();
+
+    4 + elements_length_bytes.next_multiple_of(4)
+}
+
+/// Whether the given extension element can be written using the one byte profile: its id must be
+/// 1-14 and it must have 1-16 bytes of data.
+fn fits_one_byte_profile(ext: &SomeHeaderExtension) -> bool {
+    (1..=14).contains(&ext.id()) && (1..=16).contains(&ext.data().len())
+}
+
+/// The one byte profile is used unless one of the (non-padding) elements can't be represented with
+/// it, regardless of which form the elements were parsed or created in.
+fn uses_two_byte_profile(header_extensions: &HeaderExtensions) -> bool {
     header_extensions
+        .iter()
+        .filter(|ext| ext.id() != 0)
+        .any(|ext| !fits_one_byte_profile(ext))
+}
+
+/// Whether the given extensions mix elements which fit the one byte form with elements which need
+/// the two byte form, which is only allowed if extmap-allow-mixed was negotiated.  Padding
+/// elements (id 0) aren't counted.
+fn is_mixed(header_extensions: &HeaderExtensions) -> bool {
+    let elements = || header_extensions.iter().filter(|ext| ext.id() != 0);
+    elements().any(fits_one_byte_profile) && !elements().all(fits_one_byte_profile)
+}
+
+/// Write the given header extensions, including the profile and length words and any padding
+/// needed to reach a 32 bit boundary.  The one byte profile (0xBEDE) is used if every extension
+/// has an id of 1-14 and 1-16 bytes of data; otherwise all of them are written using the two byte
+/// profile.  Padding elements (id 0) are skipped.
+pub fn write_header_extensions<B: PacketBufferMut>(
+    buf: &mut B,
+    header_extensions: &HeaderExtensions,
+) -> Result<()> {
+    write_header_extensions_with_config(buf, header_extensions, &HeaderExtensionConfig::default())
+}
+
+/// Like [`write_header_extensions`], but if the config doesn't allow mixing then it's an error
+/// to mix extensions which fit the one byte form with extensions which need the two byte form.
+pub fn write_header_extensions_with_config<B: PacketBufferMut>(
+    buf: &mut B,
+    header_extensions: &HeaderExtensions,
+    config: &HeaderExtensionConfig,
+) -> Result<()> {
+    if !config.allow_mixed && is_mixed(header_extensions) {
+        if let Some(ext) = header_extensions
+            .iter()
+            .filter(|ext| ext.id() != 0)
+            .find(|ext| !fits_one_byte_profile(ext))
+        {
+            bail!(
+                "Extension {} with {} bytes of data needs the two byte form (ids 1-14 with 1-16 \
+                bytes of data fit the one byte form), but the other extensions fit the one byte \
+                form and extmap-allow-mixed wasn't negotiated",
+                ext.id(),
+                ext.data().len()
+            );
+        }
+    }
+    let two_byte = uses_two_byte_profile(header_extensions);
+    let length_bytes = header_extensions_length_bytes(header_extensions);
+    let profile = if two_byte {
+        TwoByteHeaderExtension::TYPE
+    } else {
+        OneByteHeaderExtension::TYPE
+    };
+    buf.write_u16::<NetworkOrder>(profile).context("profile")?;
+    buf.write_u16::<NetworkOrder>(((length_bytes - 4) / 4) as u16)
+        .context("length")?;
+
+    let mut elements_length_bytes = 0;
+    for ext in header_extensions.iter().filter(|ext| ext.id() != 0) {
+        let id = ext.id();
+        let data = ext.data();
+        if two_byte {
+            let Ok(length) = u8::try_from(data.len()) else {
+                bail!(
+                    "Extension {id} has {} bytes of data, which is too long for the two byte \
+                    profile (max 255)",
+                    data.len()
+                );
+            };
+            buf.write_u8(id)
+                .with_context(|| format!("extension {id} id"))?;
+            buf.write_u8(length)
+                .with_context(|| format!("extension {id} length"))?;
+            elements_length_bytes += 2;
+        } else {
+            buf.write_u8((id << 4) | (data.len() as u8 - 1))
+                .with_context(|| format!("extension {id} id and length"))?;
+            elements_length_bytes += 1;
+        }
+        std::io::Write::write_all(buf, &data).with_context(|| format!("extension {id} data"))?;
+        elements_length_bytes += data.len();
+    }
+    for _ in elements_length_bytes..elements_length_bytes.next_multiple_of(4) {
+        buf.write_u8(0).context("padding")?;
+    }
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
     use bytes::Bytes;
 
-    use super::read_header_extensions;
+    use super::*;
 
     #[test]
     fn test_one_byte_header_extensions() {
@@ -231,11 +866,11 @@ mod test {
         ];
 
         let bytes = Bytes::from(data);
-        let he = read_header_extensions(bytes);
+        let he = read_header_extensions(bytes).unwrap();
         // The padding bytes are parsed as a header extension
         assert_eq!(he.len(), 2);
         let ext_one = he
-            .get(&1)
+            .get(1)
             .expect("should contain a header extension with ID 1");
         assert_eq!(ext_one.data(), Bytes::from_static(&[0xFF]));
     }
@@ -249,10 +884,10 @@ mod test {
 
         ];
         let bytes = Bytes::from(data);
-        let he = read_header_extensions(bytes);
+        let he = read_header_extensions(bytes).unwrap();
         assert_eq!(he.len(), 2);
         let ext_one = he
-            .get(&5)
+            .get(5)
             .expect("should contain a header extension with ID 1");
         assert_eq!(ext_one.data(), Bytes::from_static(&[0x00, 0x01]));
     }
@@ -266,11 +901,226 @@ mod test {
 
         ];
         let bytes = Bytes::from(data);
-        let he = read_header_extensions(bytes);
+        let he = read_header_extensions(bytes).unwrap();
         assert_eq!(he.len(), 2);
         let ext_one = he
-            .get(&1)
+            .get(1)
             .expect("should contain a header extension with ID 1");
         assert_eq!(ext_one.data(), Bytes::from_static(&[0xFF]));
     }
+
+    #[test]
+    fn test_write_header_extensions_roundtrip() {
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions.insert(SomeHeaderExtension::OneByteHeaderExtension(
+            OneByteHeaderExtension::new(1, &[0xAA, 0xBB]).unwrap(),
+        ));
+        assert_eq!(header_extensions_length_bytes(&header_extensions), 8);
+        assert_eq!(header_extensions.length_bytes(), 8);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 8]));
+        write_header_extensions(&mut cursor, &header_extensions).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(data, [0xBE, 0xDE, 0x00, 0x01, 0x11, 0xAA, 0xBB, 0x00]);
+
+        let he = read_header_extensions(Bytes::from(data)).unwrap();
+        assert_eq!(he.get(1).unwrap().data(), Bytes::from_static(&[0xAA, 0xBB]));
+    }
+
+    #[test]
+    fn test_write_two_byte_header_extensions() {
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions.insert(SomeHeaderExtension::OneByteHeaderExtension(
+            OneByteHeaderExtension::new(1, &[0xAA]).unwrap(),
+        ));
+        header_extensions.insert(SomeHeaderExtension::TwoByteHeaderExtension(
+            TwoByteHeaderExtension::new(20, &[]).unwrap(),
+        ));
+        // profile + length (4) + elements (3 + 2), padded to 32
+        assert_eq!(header_extensions_length_bytes(&header_extensions), 12);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 12]));
+        write_header_extensions(&mut cursor, &header_extensions).unwrap();
+        let he = read_header_extensions(Bytes::from(cursor.into_inner().into_vec())).unwrap();
+        assert_eq!(he.get(1).unwrap().data(), Bytes::from_static(&[0xAA]));
+        assert!(he.get(20).unwrap().data().is_empty());
+    }
+
+    #[test]
+    fn test_write_chooses_one_byte_profile() {
+        // A two byte extension which fits in the one byte profile
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions.insert(SomeHeaderExtension::TwoByteHeaderExtension(
+            TwoByteHeaderExtension::new(3, &[0xAA, 0xBB]).unwrap(),
+        ));
+        assert_eq!(header_extensions_length_bytes(&header_extensions), 8);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 8]));
+        write_header_extensions(&mut cursor, &header_extensions).unwrap();
+        assert_eq!(
+            cursor.into_inner().into_vec(),
+            [0xBE, 0xDE, 0x00, 0x01, 0x31, 0xAA, 0xBB, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_write_chooses_two_byte_profile() {
+        let two_byte_cases: [(u8, &[u8]); 3] = [
+            // Id too large
+            (15, &[0xAA]),
+            // Empty data
+            (1, &[]),
+            // Data too long
+            (1, &[0xAA; 17]),
+        ];
+        for (id, data) in two_byte_cases {
+            let mut header_extensions = HeaderExtensions::new();
+            header_extensions.insert(SomeHeaderExtension::TwoByteHeaderExtension(
+                TwoByteHeaderExtension::new(id, data).unwrap(),
+            ));
+            let length_bytes = header_extensions_length_bytes(&header_extensions);
+            assert_eq!(length_bytes, 4 + (2 + data.len()).next_multiple_of(4));
+
+            let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; length_bytes]));
+            write_header_extensions(&mut cursor, &header_extensions).unwrap();
+            let written = cursor.into_inner().into_vec();
+            assert_eq!(written[..2], [0x10, 0x00]);
+            assert_eq!(written[4..6], [id, data.len() as u8]);
+        }
+    }
+
+    #[test]
+    fn test_one_byte_id_15_terminates() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0xBE, 0xDE, 0x00, 0x02,
+            0x10, 0xFF, 0xF0, 0x20,
+            0xAA, 0x00, 0x00, 0x00,
+        ];
+        let he = read_header_extensions(Bytes::from(data)).unwrap();
+        assert_eq!(he.len(), 1);
+        assert_eq!(he.get(1).unwrap().data(), Bytes::from_static(&[0xFF]));
+    }
+
+    #[test]
+    fn test_read_two_byte_without_allow_mixed() {
+        let config = HeaderExtensionConfig {
+            allow_mixed: false,
+            ..Default::default()
+        };
+        // Using the two byte form throughout doesn't need extmap-allow-mixed
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0x10, 0x00, 0x00, 0x01,
+            0x14, 0x01, 0xAA, 0x00,
+        ];
+        let he = read_header_extensions_with_config(Bytes::from(data), &config).unwrap();
+        assert_eq!(he.get(20).unwrap().data(), Bytes::from_static(&[0xAA]));
+
+        // Id 1 with 1 byte of data fits the one byte form, id 20 doesn't
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0x10, 0x00, 0x00, 0x02,
+            0x01, 0x01, 0xBB, 0x14,
+            0x01, 0xAA, 0x00, 0x00,
+        ];
+        let err =
+            read_header_extensions_with_config(Bytes::from(data.clone()), &config).unwrap_err();
+        assert!(matches!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::InvalidFieldValue {
+                field: "header extension elements",
+                ..
+            })
+        ));
+        let he = read_header_extensions(Bytes::from(data)).unwrap();
+        assert_eq!(he.get(1).unwrap().data(), Bytes::from_static(&[0xBB]));
+        assert_eq!(he.get(20).unwrap().data(), Bytes::from_static(&[0xAA]));
+    }
+
+    #[test]
+    fn test_write_two_byte_without_allow_mixed() {
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions.insert(SomeHeaderExtension::TwoByteHeaderExtension(
+            TwoByteHeaderExtension::new(20, &[0xAA]).unwrap(),
+        ));
+        let config = HeaderExtensionConfig {
+            allow_mixed: false,
+            ..Default::default()
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 8]));
+        write_header_extensions_with_config(&mut cursor, &header_extensions, &config).unwrap();
+        assert_eq!(
+            cursor.into_inner().into_vec(),
+            vec![0x10, 0x00, 0x00, 0x01, 0x14, 0x01, 0xAA, 0x00]
+        );
+
+        header_extensions.insert(SomeHeaderExtension::new(1, &[0xBB]).unwrap());
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 12]));
+        let err = write_header_extensions_with_config(&mut cursor, &header_extensions, &config)
+            .unwrap_err();
+        assert!(err.to_string().contains("Extension 20"));
+    }
+
+    #[derive(Debug, PartialEq)]
+    struct TestValue(u16);
+
+    impl RtpHeaderExtensionValue for TestValue {
+        const URI: &'static str = "urn:test";
+
+        fn parse(data: &[u8]) -> Result<Self> {
+            let data: [u8; 2] = data.try_into().context("test value must be 2 bytes")?;
+            Ok(TestValue(u16::from_be_bytes(data)))
+        }
+
+        fn encode(&self) -> Vec<u8> {
+            self.0.to_be_bytes().to_vec()
+        }
+    }
+
+    #[test]
+    fn test_typed_extensions() {
+        let mut header_extensions = HeaderExtensions::new();
+        header_extensions.set_typed(3, &TestValue(0x1234)).unwrap();
+        assert!(matches!(
+            header_extensions.get(3),
+            Some(SomeHeaderExtension::OneByteHeaderExtension(_))
+        ));
+        assert_eq!(
+            header_extensions.get_typed::<TestValue>(3).unwrap(),
+            Some(TestValue(0x1234))
+        );
+        assert_eq!(header_extensions.get_typed::<TestValue>(4).unwrap(), None);
+
+        header_extensions.insert(SomeHeaderExtension::new(4, &[0x01]).unwrap());
+        assert!(header_extensions.get_typed::<TestValue>(4).is_err());
+    }
+    #[test]
+    fn test_extension_from_bytes() {
+        let ext =
+            OneByteHeaderExtension::try_from(Bytes::from_static(&[0x31, 0xAA, 0xBB])).unwrap();
+        assert_eq!(ext.id(), 3);
+        assert_eq!(Bytes::from(ext), Bytes::from_static(&[0x31, 0xAA, 0xBB]));
+        // Padding
+        assert!(OneByteHeaderExtension::try_from(Bytes::from_static(&[0x00])).is_ok());
+
+        assert!(OneByteHeaderExtension::try_from(Bytes::new()).is_err());
+        assert!(OneByteHeaderExtension::try_from(Bytes::from_static(&[0x31, 0xAA])).is_err());
+        let error =
+            OneByteHeaderExtension::try_from(Bytes::from_static(&[0x30, 0xAA, 0xBB])).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&error),
+            Some(RtpParseError::invalid_field(
+                "one byte header extension length",
+                "2 bytes",
+                "3 bytes"
+            ))
+        );
+
+        let ext = TwoByteHeaderExtension::try_from(Bytes::from_static(&[0x20, 0x00])).unwrap();
+        assert_eq!(ext.id(), 32);
+        assert!(TwoByteHeaderExtension::try_from(Bytes::new()).is_err());
+        assert!(TwoByteHeaderExtension::try_from(Bytes::from_static(&[0x20])).is_err());
+        assert!(TwoByteHeaderExtension::try_from(Bytes::from_static(&[0x20, 0x00, 0x01])).is_err());
+    }
 }
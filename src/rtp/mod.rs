@@ -1,5 +1,27 @@
+pub mod abs_capture_time_header_extension;
+pub mod abs_send_time_header_extension;
 pub mod audio_level_header_extension;
+pub mod color_space_header_extension;
+pub mod csrc_audio_levels_header_extension;
+pub mod dependency_descriptor_header_extension;
+pub mod extmap;
+pub mod flexfec_payload;
+pub mod frame_marking_header_extension;
 pub mod header_extensions;
+pub mod payloads;
+pub mod playout_delay_header_extension;
+pub mod raw_rtp_packet;
+pub mod red_payload;
 pub mod rtp_header;
 pub mod rtp_packet;
+pub mod rtx_packet;
+pub mod sdes_header_extensions;
+pub mod seq_num;
+pub mod srtp;
 pub mod tcc_header_extension;
+pub mod toffset_header_extension;
+pub mod ulpfec_payload;
+pub mod video_content_type_header_extension;
+pub mod video_layers_allocation_header_extension;
+pub mod video_orientation_header_extension;
+pub mod video_timing_header_extension;
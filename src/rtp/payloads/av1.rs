@@ -0,0 +1,445 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_write::BitWrite, bit_write_exts::BitWriteExts};
+use bytes::{Bytes, BytesMut};
+
+use crate::{
+    error::RtpParseError,
+    util::{read_leb128, write_leb128},
+    LengthBytes,
+};
+
+use super::{depacketizer::PayloadDepacketizer, packetizer::PayloadPacketizer};
+
+// https://aomediacodec.github.io/av1-rtp-spec/#44-av1-aggregation-header
+// The payload starts with the aggregation header:
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// |Z|Y| W |N|-|-|-|
+// +-+-+-+-+-+-+-+-+
+//
+// Z: the first OBU element continues an OBU fragment from the previous packet
+// Y: the last OBU element continues in the next packet
+// W: the number of OBU elements (0-3).  If 0, every element is preceded by its leb128 encoded
+//   length.  Otherwise, every element but the last is.
+// N: the packet is the first packet of a coded video sequence
+//
+// Followed by the OBU elements:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |Z|Y| W |N|-|-|-|  OBU element 1 size (leb128)  |               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+               |
+// |                                                               |
+// :                                                               :
+// :                      OBU element 1 data                       :
+// :                                                               :
+// |                                                               |
+// |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               |                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+// |                                                               |
+// :                                                               :
+// :                      OBU element 2 data                       :
+// :                                                               :
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// https://aomediacodec.github.io/av1-spec/#obu-header-syntax
+// Each OBU starts with a header:
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// |F| type  |X|S|-|
+// +-+-+-+-+-+-+-+-+
+
+const FIRST_OBU_CONTINUES_MASK: u8 = 0x80;
+const LAST_OBU_CONTINUES_MASK: u8 = 0x40;
+const NEW_CODED_VIDEO_SEQUENCE_MASK: u8 = 0x08;
+const MAX_OBU_COUNT: u8 = 3;
+pub const OBU_TYPE_SEQUENCE_HEADER: u8 = 1;
+pub const OBU_TYPE_TEMPORAL_DELIMITER: u8 = 2;
+pub const OBU_TYPE_FRAME_HEADER: u8 = 3;
+pub const OBU_TYPE_TILE_GROUP: u8 = 4;
+pub const OBU_TYPE_METADATA: u8 = 5;
+pub const OBU_TYPE_FRAME: u8 = 6;
+pub const OBU_TYPE_TILE_LIST: u8 = 8;
+pub const OBU_TYPE_PADDING: u8 = 15;
+
+/// The type of the given OBU
+pub fn obu_type(obu: &[u8]) -> Option<u8> {
+    obu.first().map(|header| (header >> 3) & 0xF)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Av1AggregationHeader {
+    /// Z
+    pub first_obu_continues: bool,
+    /// Y
+    pub last_obu_continues: bool,
+    /// W: the number of OBU elements, or 0 if every element has a length field
+    pub obu_count: u8,
+    /// N
+    pub new_coded_video_sequence: bool,
+}
+
+impl Av1AggregationHeader {
+    pub fn to_byte(&self) -> u8 {
+        let mut byte = (self.obu_count & 0x3) << 4;
+        for (set, mask) in [
+            (self.first_obu_continues, FIRST_OBU_CONTINUES_MASK),
+            (self.last_obu_continues, LAST_OBU_CONTINUES_MASK),
+            (self.new_coded_video_sequence, NEW_CODED_VIDEO_SEQUENCE_MASK),
+        ] {
+            if set {
+                byte |= mask;
+            }
+        }
+        byte
+    }
+}
+
+impl From<u8> for Av1AggregationHeader {
+    fn from(byte: u8) -> Self {
+        Self {
+            first_obu_continues: byte & FIRST_OBU_CONTINUES_MASK != 0,
+            last_obu_continues: byte & LAST_OBU_CONTINUES_MASK != 0,
+            obu_count: (byte >> 4) & 0x3,
+            new_coded_video_sequence: byte & NEW_CODED_VIDEO_SEQUENCE_MASK != 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Av1Payload {
+    pub aggregation_header: Av1AggregationHeader,
+    /// The OBU elements, which are whole OBUs or fragments of them (see
+    /// [`Av1AggregationHeader::first_obu_continues`] and
+    /// [`Av1AggregationHeader::last_obu_continues`])
+    pub obu_elements: Vec<Bytes>,
+}
+
+impl Av1Payload {
+    fn has_length_field(&self, index: usize) -> bool {
+        self.aggregation_header.obu_count == 0 || index + 1 < self.obu_elements.len()
+    }
+}
+
+impl LengthBytes for Av1Payload {
+    fn length_bytes(&self) -> usize {
+        1 + self
+            .obu_elements
+            .iter()
+            .enumerate()
+            .map(|(i, element)| {
+                let length_field_size = if self.has_length_field(i) {
+                    let mut length_field = Vec::new();
+                    write_leb128(&mut length_field, element.len() as u32);
+                    length_field.len()
+                } else {
+                    0
+                };
+                length_field_size + element.len()
+            })
+            .sum::<usize>()
+    }
+}
+
+/// Read an AV1 RTP payload.  The OBU elements are slices of `payload` rather than copies.
+pub fn read_av1_payload(payload: Bytes) -> Result<Av1Payload> {
+    let Some(first) = payload.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    let aggregation_header = Av1AggregationHeader::from(*first);
+    let mut obu_elements = Vec::new();
+    let mut offset = 1;
+    while offset < payload.len() {
+        let is_last = aggregation_header.obu_count != 0
+            && obu_elements.len() + 1 == aggregation_header.obu_count as usize;
+        let length = if is_last {
+            payload.len() - offset
+        } else {
+            let (length, length_field_size) = read_leb128(&payload[offset..])
+                .with_context(|| format!("OBU element {} length", obu_elements.len()))?;
+            offset += length_field_size;
+            length as usize
+        };
+        if offset + length > payload.len() {
+            return Err(RtpParseError::truncated(offset + length, payload.len()))
+                .with_context(|| format!("OBU element {}", obu_elements.len()));
+        }
+        obu_elements.push(payload.slice(offset..offset + length));
+        offset += length;
+    }
+    if aggregation_header.obu_count != 0
+        && obu_elements.len() != aggregation_header.obu_count as usize
+    {
+        bail!(RtpParseError::invalid_field(
+            "AV1 OBU element count",
+            aggregation_header.obu_count,
+            obu_elements.len()
+        ));
+    }
+
+    Ok(Av1Payload {
+        aggregation_header,
+        obu_elements,
+    })
+}
+
+pub fn write_av1_payload<W: BitWrite>(buf: &mut W, av1_payload: &Av1Payload) -> Result<()> {
+    let obu_count = av1_payload.aggregation_header.obu_count;
+    if obu_count > MAX_OBU_COUNT {
+        bail!("AV1 aggregation header OBU count must be at most {MAX_OBU_COUNT}, got {obu_count}");
+    }
+    if obu_count != 0 && obu_count as usize != av1_payload.obu_elements.len() {
+        bail!(
+            "AV1 aggregation header has {obu_count} OBU elements, but the payload has {}",
+            av1_payload.obu_elements.len()
+        );
+    }
+    buf.write_u8(av1_payload.aggregation_header.to_byte())
+        .context("aggregation header")?;
+    for (i, element) in av1_payload.obu_elements.iter().enumerate() {
+        if av1_payload.has_length_field(i) {
+            let mut length_field = Vec::new();
+            write_leb128(&mut length_field, element.len() as u32);
+            std::io::Write::write_all(buf, &length_field)
+                .with_context(|| format!("OBU element {i} length"))?;
+        }
+        std::io::Write::write_all(buf, element).with_context(|| format!("OBU element {i}"))?;
+    }
+
+    Ok(())
+}
+
+/// Whether the given AV1 RTP payload starts a key frame: whether it's the first packet of a coded
+/// video sequence, or starts with a sequence header
+pub fn is_keyframe(payload: &[u8]) -> Result<bool> {
+    let Some(first) = payload.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    let aggregation_header = Av1AggregationHeader::from(*first);
+    if aggregation_header.new_coded_video_sequence {
+        return Ok(true);
+    }
+    if aggregation_header.first_obu_continues {
+        return Ok(false);
+    }
+    let first_obu = if aggregation_header.obu_count == 1 {
+        &payload[1..]
+    } else {
+        let (_, length_field_size) = read_leb128(&payload[1..]).context("OBU element 0 length")?;
+        &payload[1 + length_field_size..]
+    };
+
+    Ok(obu_type(first_obu) == Some(OBU_TYPE_SEQUENCE_HEADER))
+}
+
+/// Reassembles AV1 OBUs from the payloads of their packets
+#[derive(Debug, Default)]
+pub struct Av1Depacketizer {
+    partial_obu: Option<BytesMut>,
+}
+
+impl PayloadDepacketizer for Av1Depacketizer {
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        let av1_payload = read_av1_payload(payload)?;
+        let aggregation_header = av1_payload.aggregation_header;
+        let num_elements = av1_payload.obu_elements.len();
+        let mut obus = Vec::new();
+        for (i, element) in av1_payload.obu_elements.into_iter().enumerate() {
+            if i == 0 && aggregation_header.first_obu_continues {
+                // If there's no partial OBU, its start was lost and this fragment is dropped
+                if let Some(partial_obu) = self.partial_obu.as_mut() {
+                    partial_obu.extend_from_slice(&element);
+                }
+            } else {
+                self.partial_obu = Some(BytesMut::from(element.as_ref()));
+            }
+            if i + 1 < num_elements || !aggregation_header.last_obu_continues {
+                obus.extend(self.partial_obu.take().map(|obu| obu.freeze()));
+            }
+        }
+
+        Ok(obus)
+    }
+
+    fn discard_frame(&mut self) {
+        self.partial_obu = None;
+    }
+}
+
+/// Splits AV1 frames into RTP payloads of at most `mtu` bytes, aggregating OBUs and fragmenting
+/// them across packets as needed.  Temporal delimiters are dropped, as recommended.
+#[derive(Debug, Clone)]
+pub struct Av1Packetizer {
+    mtu: usize,
+}
+
+impl Av1Packetizer {
+    pub fn new(mtu: usize) -> Result<Self> {
+        // The aggregation header, plus a 1 byte element with its length
+        if mtu < 3 {
+            bail!("MTU must be at least 3 bytes, got {mtu}");
+        }
+
+        Ok(Self { mtu })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+}
+
+fn leb128_size(value: usize) -> usize {
+    let mut length_field = Vec::new();
+    write_leb128(&mut length_field, value as u32);
+    length_field.len()
+}
+
+impl PayloadPacketizer for Av1Packetizer {
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>> {
+        let new_coded_video_sequence = units
+            .iter()
+            .any(|obu| obu_type(obu) == Some(OBU_TYPE_SEQUENCE_HEADER));
+        let mut payloads = Vec::new();
+        let mut elements = Vec::new();
+        let mut first_obu_continues = false;
+        let mut finish_payload = |elements: &mut Vec<u8>, last_obu_continues: bool| {
+            let aggregation_header = Av1AggregationHeader {
+                first_obu_continues,
+                last_obu_continues,
+                obu_count: 0,
+                new_coded_video_sequence: new_coded_video_sequence && payloads.is_empty(),
+            };
+            let mut payload = Vec::with_capacity(1 + elements.len());
+            payload.push(aggregation_header.to_byte());
+            payload.append(elements);
+            payloads.push(Bytes::from(payload));
+            first_obu_continues = last_obu_continues;
+        };
+        for obu in units {
+            if obu.len() > u32::MAX as usize {
+                bail!("OBU is {} bytes, which is too large", obu.len());
+            }
+            if obu_type(obu) == Some(OBU_TYPE_TEMPORAL_DELIMITER) {
+                continue;
+            }
+            let mut offset = 0;
+            while offset < obu.len() {
+                let remaining = obu.len() - offset;
+                let space = self.mtu - 1 - elements.len();
+                if leb128_size(remaining) + remaining <= space {
+                    write_leb128(&mut elements, remaining as u32);
+                    elements.extend_from_slice(&obu[offset..]);
+                    offset = obu.len();
+                    continue;
+                }
+                // Fill the rest of this payload with a fragment of the OBU
+                let fragment_size = space.saturating_sub(leb128_size(space));
+                if fragment_size == 0 {
+                    finish_payload(&mut elements, false);
+                    continue;
+                }
+                write_leb128(&mut elements, fragment_size as u32);
+                elements.extend_from_slice(&obu[offset..offset + fragment_size]);
+                offset += fragment_size;
+                finish_payload(&mut elements, true);
+            }
+        }
+        if !elements.is_empty() {
+            finish_payload(&mut elements, false);
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    fn roundtrip(av1_payload: &Av1Payload) -> Vec<u8> {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            av1_payload
+                .length_bytes()
+        ]));
+        write_av1_payload(&mut cursor, av1_payload).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(
+            &read_av1_payload(Bytes::from(data.clone())).unwrap(),
+            av1_payload
+        );
+        data
+    }
+
+    #[test]
+    fn test_obu_count() {
+        let av1_payload = Av1Payload {
+            aggregation_header: Av1AggregationHeader {
+                obu_count: 2,
+                new_coded_video_sequence: true,
+                ..Default::default()
+            },
+            obu_elements: vec![
+                Bytes::from_static(&[0x08, 0x00]),
+                Bytes::from_static(&[0x30, 1, 2, 3]),
+            ],
+        };
+        let data = roundtrip(&av1_payload);
+        assert_eq!(data, [0x28, 0x02, 0x08, 0x00, 0x30, 1, 2, 3]);
+        assert_eq!(
+            obu_type(&av1_payload.obu_elements[0]),
+            Some(OBU_TYPE_SEQUENCE_HEADER)
+        );
+        assert_eq!(obu_type(&av1_payload.obu_elements[1]), Some(OBU_TYPE_FRAME));
+    }
+
+    #[test]
+    fn test_length_fields() {
+        let av1_payload = Av1Payload {
+            aggregation_header: Av1AggregationHeader {
+                first_obu_continues: true,
+                last_obu_continues: true,
+                ..Default::default()
+            },
+            obu_elements: vec![Bytes::from(vec![1; 200]), Bytes::from_static(&[0x30, 2])],
+        };
+        let data = roundtrip(&av1_payload);
+        assert_eq!(&data[..3], [0xC0, 0xC8, 0x01]);
+
+        // Element longer than the payload
+        assert!(read_av1_payload(Bytes::from_static(&[0x00, 0x05, 0x01])).is_err());
+        // Fewer elements than the header says
+        assert!(read_av1_payload(Bytes::from_static(&[0x20, 0x01, 0x05])).is_err());
+    }
+
+    #[test]
+    fn test_depacketize() {
+        let mut depacketizer = Av1Depacketizer::default();
+        // A whole OBU, then the start of a fragmented one
+        let obus = depacketizer
+            .depacketize(Bytes::from_static(&[0x60, 0x02, 0x08, 0x00, 0x30, 1]))
+            .unwrap();
+        assert_eq!(obus, [&[0x08, 0x00][..]]);
+        // The middle and end of the fragmented OBU
+        assert!(depacketizer
+            .depacketize(Bytes::from_static(&[0xD0, 2]))
+            .unwrap()
+            .is_empty());
+        let obus = depacketizer
+            .depacketize(Bytes::from_static(&[0x90, 3]))
+            .unwrap();
+        assert_eq!(obus, [&[0x30, 1, 2, 3][..]]);
+
+        // The continuation of an OBU whose start was lost is dropped
+        depacketizer.discard_frame();
+        assert!(depacketizer
+            .depacketize(Bytes::from_static(&[0x90, 3]))
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,245 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use bytes::Bytes;
+
+use crate::rtp::rtp_packet::RtpPacket;
+
+use super::{
+    av1::Av1Depacketizer, h264::H264Depacketizer, h265::H265Depacketizer, vp8::Vp8Depacketizer,
+    vp9::Vp9Depacketizer, PayloadTypeKind,
+};
+
+/// A frame assembled from the payloads of its packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub timestamp: u32,
+    pub first_seq_num: u16,
+    pub last_seq_num: u16,
+    /// The frame's data, split into the codec's units: NAL units for H.264 and H.265, OBUs for
+    /// AV1, each spatial layer's frame for VP9 and the whole frame for VP8.
+    pub units: Vec<Bytes>,
+}
+
+/// Assembles the packets of a stream into frames
+pub trait Depacketizer {
+    /// Process the next packet of the stream.  Packets must be pushed in sequence number order
+    /// (i.e. after any jitter buffering).
+    fn push(&mut self, packet: &RtpPacket) -> Result<()>;
+
+    /// Take the next frame that's been assembled, if there is one
+    fn pop(&mut self) -> Option<Frame>;
+}
+
+/// The codec specific part of a [`Depacketizer`]: turning the payloads of a frame's packets into
+/// the frame's units.
+pub trait PayloadDepacketizer {
+    /// Process the payload of the next packet of the current frame, returning the units it
+    /// completes
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>>;
+
+    /// The current frame is complete, return any units that were waiting for the end of it
+    fn finish_frame(&mut self) -> Vec<Bytes> {
+        Vec::new()
+    }
+
+    /// Drop any partially assembled state of the current frame
+    fn discard_frame(&mut self);
+}
+
+#[derive(Debug)]
+struct PendingFrame {
+    timestamp: u32,
+    first_seq_num: u16,
+    last_seq_num: u16,
+    units: Vec<Bytes>,
+}
+
+/// A [`Depacketizer`] which groups packets into frames by their timestamp, with a frame ending
+/// when its marker bit is set or a packet with a new timestamp arrives.  When a packet is lost,
+/// the frame it belongs to is dropped, as are the rest of the packets with the timestamp that
+/// follows the loss.
+#[derive(Debug, Default)]
+pub struct FrameDepacketizer<P> {
+    payload_depacketizer: P,
+    last_seq_num: Option<u16>,
+    pending: Option<PendingFrame>,
+    /// Packets with this timestamp are dropped, since some of that frame was lost
+    discard_timestamp: Option<u32>,
+    frames: VecDeque<Frame>,
+}
+
+impl<P: PayloadDepacketizer> FrameDepacketizer<P> {
+    pub fn new(payload_depacketizer: P) -> Self {
+        Self {
+            payload_depacketizer,
+            last_seq_num: None,
+            pending: None,
+            discard_timestamp: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    fn finish_pending(&mut self) {
+        let Some(mut pending) = self.pending.take() else {
+            return;
+        };
+        pending
+            .units
+            .extend(self.payload_depacketizer.finish_frame());
+        if !pending.units.is_empty() {
+            self.frames.push_back(Frame {
+                timestamp: pending.timestamp,
+                first_seq_num: pending.first_seq_num,
+                last_seq_num: pending.last_seq_num,
+                units: pending.units,
+            });
+        }
+    }
+
+    fn discard(&mut self, timestamp: u32) {
+        self.pending = None;
+        self.payload_depacketizer.discard_frame();
+        self.discard_timestamp = Some(timestamp);
+    }
+}
+
+impl<P: PayloadDepacketizer> Depacketizer for FrameDepacketizer<P> {
+    fn push(&mut self, packet: &RtpPacket) -> Result<()> {
+        let seq_num = packet.seq_num();
+        let timestamp = packet.timestamp();
+        let lost = self
+            .last_seq_num
+            .is_some_and(|last_seq_num| seq_num != last_seq_num.wrapping_add(1));
+        self.last_seq_num = Some(seq_num);
+        if lost {
+            self.discard(timestamp);
+        }
+        if self.discard_timestamp == Some(timestamp) {
+            return Ok(());
+        }
+        self.discard_timestamp = None;
+        if self
+            .pending
+            .as_ref()
+            .is_some_and(|pending| pending.timestamp != timestamp)
+        {
+            self.finish_pending();
+        }
+
+        let units = match self
+            .payload_depacketizer
+            .depacketize(packet.payload_bytes())
+        {
+            Ok(units) => units,
+            Err(e) => {
+                self.discard(timestamp);
+                return Err(e);
+            }
+        };
+        let pending = self.pending.get_or_insert_with(|| PendingFrame {
+            timestamp,
+            first_seq_num: seq_num,
+            last_seq_num: seq_num,
+            units: Vec::new(),
+        });
+        pending.last_seq_num = seq_num;
+        pending.units.extend(units);
+        if packet.marked() {
+            self.finish_pending();
+        }
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<Frame> {
+        self.frames.pop_front()
+    }
+}
+
+/// Create a [`Depacketizer`] for the given payload format
+pub fn new_depacketizer(payload_type_kind: PayloadTypeKind) -> Box<dyn Depacketizer> {
+    match payload_type_kind {
+        PayloadTypeKind::Vp8 => Box::new(FrameDepacketizer::new(Vp8Depacketizer::default())),
+        PayloadTypeKind::Vp9 => Box::new(FrameDepacketizer::new(Vp9Depacketizer::default())),
+        PayloadTypeKind::H264 => Box::new(FrameDepacketizer::new(H264Depacketizer::default())),
+        PayloadTypeKind::H265 => Box::new(FrameDepacketizer::new(H265Depacketizer::default())),
+        PayloadTypeKind::Av1 => Box::new(FrameDepacketizer::new(Av1Depacketizer::default())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtp::rtp_packet::RtpPacketBuilder;
+
+    use super::*;
+
+    fn packet(seq_num: u16, timestamp: u32, marked: bool, payload: &[u8]) -> RtpPacket {
+        RtpPacketBuilder::new()
+            .seq_num(seq_num)
+            .timestamp(timestamp)
+            .marked(marked)
+            .payload(payload)
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_h264_frames() {
+        let mut depacketizer = new_depacketizer(PayloadTypeKind::H264);
+        // A STAP-A with an SPS and PPS, then an IDR in 2 FU-As
+        depacketizer
+            .push(&packet(
+                65535,
+                1000,
+                false,
+                &[0x78, 0x00, 0x02, 0x67, 0x42, 0x00, 0x02, 0x68, 0xCE],
+            ))
+            .unwrap();
+        depacketizer
+            .push(&packet(0, 1000, false, &[0x7C, 0x85, 1, 2]))
+            .unwrap();
+        assert!(depacketizer.pop().is_none());
+        depacketizer
+            .push(&packet(1, 1000, true, &[0x7C, 0x45, 3]))
+            .unwrap();
+        let frame = depacketizer.pop().unwrap();
+        assert_eq!(frame.timestamp, 1000);
+        assert_eq!((frame.first_seq_num, frame.last_seq_num), (65535, 1));
+        assert_eq!(
+            frame.units,
+            [&[0x67, 0x42][..], &[0x68, 0xCE][..], &[0x65, 1, 2, 3][..]]
+        );
+
+        // Seq nums 2 and 3 (the start of the next frame) are lost, so the rest of it is dropped
+        depacketizer
+            .push(&packet(4, 4000, true, &[0x7C, 0x41, 3]))
+            .unwrap();
+        assert!(depacketizer.pop().is_none());
+
+        // A frame that's ended by the next one's timestamp rather than the marker bit
+        depacketizer
+            .push(&packet(5, 7000, false, &[0x41, 1]))
+            .unwrap();
+        depacketizer
+            .push(&packet(6, 10000, true, &[0x41, 2]))
+            .unwrap();
+        assert_eq!(depacketizer.pop().unwrap().units, [&[0x41, 1][..]]);
+        assert_eq!(depacketizer.pop().unwrap().units, [&[0x41, 2][..]]);
+    }
+
+    #[test]
+    fn test_vp8_frames() {
+        let mut depacketizer = new_depacketizer(PayloadTypeKind::Vp8);
+        depacketizer
+            .push(&packet(10, 90, false, &[0x10, 0x00, 1]))
+            .unwrap();
+        depacketizer
+            .push(&packet(11, 90, true, &[0x00, 2, 3]))
+            .unwrap();
+        let frame = depacketizer.pop().unwrap();
+        assert_eq!(frame.units, [&[0x00, 1, 2, 3][..]]);
+        assert!(depacketizer.push(&packet(12, 180, true, &[])).is_err());
+        assert!(depacketizer.pop().is_none());
+    }
+}
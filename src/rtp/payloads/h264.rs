@@ -0,0 +1,433 @@
+use anyhow::{bail, Context, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::RtpParseError;
+
+use super::{depacketizer::PayloadDepacketizer, packetizer::PayloadPacketizer};
+
+// https://datatracker.ietf.org/doc/html/rfc6184#section-5.3
+// NAL unit header:
+//      +---------------+
+//      |0|1|2|3|4|5|6|7|
+//      +-+-+-+-+-+-+-+-+
+//      |F|NRI|  Type   |
+//      +---------------+
+//
+// https://datatracker.ietf.org/doc/html/rfc6184#section-5.7.1
+// STAP-A (type 24): the NAL unit header, followed by each aggregated NAL unit prefixed with its
+// size:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          RTP Header                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |STAP-A NAL HDR |         NALU 1 Size           | NALU 1 HDR    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         NALU 1 Data                           |
+// :                                                               :
+// +               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |               | NALU 2 Size                   | NALU 2 HDR    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         NALU 2 Data                           |
+// :                                                               :
+// |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               :...OPTIONAL RTP padding        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// https://datatracker.ietf.org/doc/html/rfc6184#section-5.8
+// FU-A (type 28):
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// | FU indicator  |   FU header   |                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+// |                                                               |
+// |                         FU payload                            |
+// |                                                               |
+// |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               :...OPTIONAL RTP padding        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The FU indicator has the F and NRI bits of the fragmented NAL unit, and type 28.  The FU
+// header is:
+//      +---------------+
+//      |0|1|2|3|4|5|6|7|
+//      +-+-+-+-+-+-+-+-+
+//      |S|E|R|  Type   |
+//      +---------------+
+// S: start of the NAL unit
+// E: end of the NAL unit
+// Type: the type of the fragmented NAL unit
+
+pub const STAP_A_TYPE: u8 = 24;
+pub const FU_A_TYPE: u8 = 28;
+pub const IDR_TYPE: u8 = 5;
+pub const SPS_TYPE: u8 = 7;
+const NAL_TYPE_MASK: u8 = 0x1F;
+const F_NRI_MASK: u8 = 0xE0;
+const FU_START_MASK: u8 = 0x80;
+const FU_END_MASK: u8 = 0x40;
+const FU_A_HEADER_SIZE_BYTES: usize = 2;
+
+/// The type of the given NAL unit (or RTP payload)
+pub fn nal_type(nal: &[u8]) -> Option<u8> {
+    nal.first().map(|header| header & NAL_TYPE_MASK)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuA {
+    /// The F and NRI bits of the fragmented NAL unit
+    pub f_nri: u8,
+    pub start: bool,
+    pub end: bool,
+    pub nal_type: u8,
+    pub data: Bytes,
+}
+
+impl FuA {
+    /// The header of the fragmented NAL unit
+    pub fn nal_header(&self) -> u8 {
+        (self.f_nri & F_NRI_MASK) | (self.nal_type & NAL_TYPE_MASK)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum H264Payload {
+    SingleNal(Bytes),
+    StapA(Vec<Bytes>),
+    FuA(FuA),
+}
+
+impl H264Payload {
+    /// The complete NAL units in this payload.  A FU-A has none: its fragments need to be
+    /// reassembled with a [`H264Depacketizer`].
+    pub fn nal_units(&self) -> NalUnits<'_> {
+        let nal_units = match self {
+            H264Payload::SingleNal(nal) => std::slice::from_ref(nal),
+            H264Payload::StapA(nals) => nals.as_slice(),
+            H264Payload::FuA(_) => &[],
+        };
+        NalUnits::new(nal_units)
+    }
+}
+
+/// An iterator over the complete NAL units in a [`H264Payload`] (or
+/// [`H265Payload`](super::h265::H265Payload))
+#[derive(Debug, Clone)]
+pub struct NalUnits<'a> {
+    inner: std::slice::Iter<'a, Bytes>,
+}
+
+impl<'a> NalUnits<'a> {
+    pub(crate) fn new(nal_units: &'a [Bytes]) -> Self {
+        Self {
+            inner: nal_units.iter(),
+        }
+    }
+}
+
+impl Iterator for NalUnits<'_> {
+    type Item = Bytes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().cloned()
+    }
+}
+
+/// Read a H.264 RTP payload.  The NAL units and fragments are slices of `payload` rather than
+/// copies.
+pub fn read_h264_payload(payload: Bytes) -> Result<H264Payload> {
+    let Some(nal_type) = nal_type(&payload) else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    match nal_type {
+        1..=23 => Ok(H264Payload::SingleNal(payload)),
+        STAP_A_TYPE => {
+            let mut nals = Vec::new();
+            let mut offset = 1;
+            while offset < payload.len() {
+                let Some(size) = payload.get(offset..offset + 2) else {
+                    bail!(RtpParseError::truncated(offset + 2, payload.len()));
+                };
+                let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+                offset += 2;
+                if size == 0 {
+                    bail!(RtpParseError::invalid_field(
+                        "STAP-A NAL unit size",
+                        "non-zero",
+                        size
+                    ));
+                }
+                if offset + size > payload.len() {
+                    bail!(RtpParseError::truncated(offset + size, payload.len()));
+                }
+                nals.push(payload.slice(offset..offset + size));
+                offset += size;
+            }
+            if nals.is_empty() {
+                bail!(RtpParseError::invalid_field(
+                    "STAP-A NAL unit count",
+                    "at least 1",
+                    0
+                ));
+            }
+
+            Ok(H264Payload::StapA(nals))
+        }
+        FU_A_TYPE => {
+            if payload.len() <= FU_A_HEADER_SIZE_BYTES {
+                bail!(RtpParseError::truncated(
+                    FU_A_HEADER_SIZE_BYTES + 1,
+                    payload.len()
+                ));
+            }
+            let fu_header = payload[1];
+            let fu_a = FuA {
+                f_nri: payload[0] & F_NRI_MASK,
+                start: fu_header & FU_START_MASK != 0,
+                end: fu_header & FU_END_MASK != 0,
+                nal_type: fu_header & NAL_TYPE_MASK,
+                data: payload.slice(FU_A_HEADER_SIZE_BYTES..),
+            };
+            if fu_a.start && fu_a.end {
+                bail!(RtpParseError::invalid_field(
+                    "FU-A start and end bits",
+                    "not both set",
+                    "both set"
+                ));
+            }
+
+            Ok(H264Payload::FuA(fu_a))
+        }
+        other => bail!(RtpParseError::UnsupportedPacketType {
+            kind: "H.264 NAL unit",
+            packet_type: other as u16,
+        }),
+    }
+}
+
+/// Whether the given H.264 RTP payload starts a key frame: whether it has an IDR slice or a
+/// sequence parameter set, or is the first fragment of one
+pub fn is_keyframe(payload: &[u8]) -> Result<bool> {
+    let is_keyframe_nal_type = |nal_type| nal_type == IDR_TYPE || nal_type == SPS_TYPE;
+    let Some(nal_type) = nal_type(payload) else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    match nal_type {
+        STAP_A_TYPE => {
+            let mut offset = 1;
+            while let Some(size) = payload.get(offset..offset + 2) {
+                let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+                let Some(nal_header) = payload.get(offset + 2) else {
+                    bail!(RtpParseError::truncated(offset + 3, payload.len()));
+                };
+                if is_keyframe_nal_type(nal_header & NAL_TYPE_MASK) {
+                    return Ok(true);
+                }
+                offset += 2 + size;
+            }
+
+            Ok(false)
+        }
+        FU_A_TYPE => {
+            let Some(fu_header) = payload.get(1) else {
+                bail!(RtpParseError::truncated(2, payload.len()));
+            };
+
+            Ok(fu_header & FU_START_MASK != 0 && is_keyframe_nal_type(fu_header & NAL_TYPE_MASK))
+        }
+        nal_type => Ok(is_keyframe_nal_type(nal_type)),
+    }
+}
+
+/// Turns H.264 RTP payloads back into NAL units, reassembling FU-A fragments.  Payloads must be
+/// pushed in sequence number order, and [`H264Depacketizer::reset`] should be called when a
+/// packet is lost so a partially reassembled NAL unit isn't completed with the wrong fragments.
+#[derive(Debug, Default)]
+pub struct H264Depacketizer {
+    fragmented_nal: Option<BytesMut>,
+}
+
+impl H264Depacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop any partially reassembled NAL unit
+    pub fn reset(&mut self) {
+        self.fragmented_nal = None;
+    }
+
+    /// Process the given RTP payload, returning the NAL units it completes
+    pub fn push(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        let fu_a = match read_h264_payload(payload)? {
+            H264Payload::FuA(fu_a) => fu_a,
+            payload => {
+                self.reset();
+                return Ok(payload.nal_units().collect());
+            }
+        };
+        if fu_a.start {
+            let mut nal = BytesMut::with_capacity(1 + fu_a.data.len());
+            nal.put_u8(fu_a.nal_header());
+            self.fragmented_nal = Some(nal);
+        }
+        let Some(nal) = self.fragmented_nal.as_mut() else {
+            // The start of this NAL unit was lost
+            return Ok(Vec::new());
+        };
+        nal.extend_from_slice(&fu_a.data);
+        if fu_a.end {
+            return Ok(self
+                .fragmented_nal
+                .take()
+                .map(|nal| nal.freeze())
+                .into_iter()
+                .collect());
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+/// Splits NAL units into RTP payloads of at most `mtu` bytes, sending NAL units that fit as
+/// single NAL unit packets and fragmenting the rest into FU-As.
+#[derive(Debug, Clone)]
+pub struct H264Packetizer {
+    mtu: usize,
+}
+
+impl H264Packetizer {
+    pub fn new(mtu: usize) -> Result<Self> {
+        if mtu <= FU_A_HEADER_SIZE_BYTES {
+            bail!("MTU must be more than {FU_A_HEADER_SIZE_BYTES} bytes, got {mtu}");
+        }
+
+        Ok(Self { mtu })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Packetize the given NAL unit (without an Annex B start code)
+    pub fn packetize(&self, nal: Bytes) -> Result<Vec<Bytes>> {
+        let Some(nal_header) = nal.first().copied() else {
+            bail!("NAL unit is empty");
+        };
+        if nal.len() <= self.mtu {
+            return Ok(vec![nal]);
+        }
+        let fu_indicator = (nal_header & F_NRI_MASK) | FU_A_TYPE;
+        let fragments = nal[1..].chunks(self.mtu - FU_A_HEADER_SIZE_BYTES);
+        let num_fragments = fragments.len();
+
+        Ok(fragments
+            .enumerate()
+            .map(|(i, fragment)| {
+                let mut fu_header = nal_header & NAL_TYPE_MASK;
+                if i == 0 {
+                    fu_header |= FU_START_MASK;
+                }
+                if i == num_fragments - 1 {
+                    fu_header |= FU_END_MASK;
+                }
+                let mut payload = BytesMut::with_capacity(FU_A_HEADER_SIZE_BYTES + fragment.len());
+                payload.put_u8(fu_indicator);
+                payload.put_u8(fu_header);
+                payload.extend_from_slice(fragment);
+                payload.freeze()
+            })
+            .collect())
+    }
+}
+
+impl PayloadDepacketizer for H264Depacketizer {
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        self.push(payload)
+    }
+
+    fn discard_frame(&mut self) {
+        self.reset();
+    }
+}
+
+impl PayloadPacketizer for H264Packetizer {
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>> {
+        let mut payloads = Vec::new();
+        for (i, nal) in units.iter().enumerate() {
+            payloads.extend(
+                self.packetize(nal.clone())
+                    .with_context(|| format!("NAL unit {i}"))?,
+            );
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stap_a() {
+        #[rustfmt::skip]
+        let payload = Bytes::from_static(&[
+            0x78,
+            0x00, 0x03, 0x67, 0x42, 0x00,
+            0x00, 0x02, 0x68, 0xCE,
+        ]);
+        let parsed = read_h264_payload(payload).unwrap();
+        let nals = parsed.nal_units().collect::<Vec<_>>();
+        assert_eq!(nals, [&[0x67, 0x42, 0x00][..], &[0x68, 0xCE][..]]);
+        assert_eq!(nal_type(&nals[0]), Some(7));
+
+        let err = read_h264_payload(Bytes::from_static(&[0x78, 0x00, 0x05, 0x67])).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(8, 4))
+        );
+        let err = read_h264_payload(Bytes::from_static(&[0x78])).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::invalid_field(
+                "STAP-A NAL unit count",
+                "at least 1",
+                0
+            ))
+        );
+    }
+
+    #[test]
+    fn test_packetize_and_depacketize() {
+        let nal = Bytes::from(
+            (0..100u8)
+                .map(|i| if i == 0 { 0x65 } else { i })
+                .collect::<Vec<_>>(),
+        );
+        let packetizer = H264Packetizer::new(40).unwrap();
+        let payloads = packetizer.packetize(nal.clone()).unwrap();
+        assert_eq!(payloads.len(), 3);
+        assert!(payloads.iter().all(|payload| payload.len() <= 40));
+        assert_eq!(&payloads[0][..2], [0x7C, 0x85]);
+        assert_eq!(&payloads[2][..2], [0x7C, 0x45]);
+
+        let mut depacketizer = H264Depacketizer::new();
+        assert!(depacketizer.push(payloads[0].clone()).unwrap().is_empty());
+        assert!(depacketizer.push(payloads[1].clone()).unwrap().is_empty());
+        assert_eq!(depacketizer.push(payloads[2].clone()).unwrap(), vec![nal]);
+
+        // Losing the first fragment means the NAL unit can't be reassembled
+        depacketizer.reset();
+        assert!(depacketizer.push(payloads[1].clone()).unwrap().is_empty());
+        assert!(depacketizer.push(payloads[2].clone()).unwrap().is_empty());
+
+        let small = Bytes::from_static(&[0x41, 1, 2, 3]);
+        assert_eq!(
+            packetizer.packetize(small.clone()).unwrap(),
+            vec![small.clone()]
+        );
+        assert_eq!(depacketizer.push(small.clone()).unwrap(), vec![small]);
+    }
+}
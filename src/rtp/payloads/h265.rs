@@ -0,0 +1,457 @@
+use anyhow::{bail, Context, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::error::RtpParseError;
+
+use super::{depacketizer::PayloadDepacketizer, h264::NalUnits, packetizer::PayloadPacketizer};
+
+// https://datatracker.ietf.org/doc/html/rfc7798#section-1.1.4
+// NAL unit header (and payload header):
+//      +---------------+---------------+
+//      |0|1|2|3|4|5|6|7|0|1|2|3|4|5|6|7|
+//      +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//      |F|   Type    |  LayerId  | TID |
+//      +-------------+-----------------+
+//
+// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.2
+// Aggregation packet (type 48): the payload header, followed by each aggregated NAL unit
+// prefixed with its size:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          RTP Header                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   PayloadHdr (Type=48)        |         NALU 1 Size           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          NALU 1 HDR           |                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+         NALU 1 Data           |
+// |                   . . .                                       |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  . . .        | NALU 2 Size                   | NALU 2 HDR    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// | NALU 2 HDR    |                                               |
+// +-+-+-+-+-+-+-+-+              NALU 2 Data                      |
+// |                   . . .                                       |
+// |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               :...OPTIONAL RTP padding        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.3
+// Fragmentation unit (type 49):
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |    PayloadHdr (Type=49)       |   FU header   |               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+               |
+// |                                                               |
+// |                         FU payload                            |
+// |                                                               |
+// |                               +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                               :...OPTIONAL RTP padding        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The FU header is:
+//      +---------------+
+//      |0|1|2|3|4|5|6|7|
+//      +-+-+-+-+-+-+-+-+
+//      |S|E|  FuType   |
+//      +---------------+
+//
+// The DONL fields that are present when sprop-max-don-diff is greater than 0 aren't supported.
+
+pub const AGGREGATION_PACKET_TYPE: u8 = 48;
+pub const FRAGMENTATION_UNIT_TYPE: u8 = 49;
+pub const PACI_TYPE: u8 = 50;
+pub const VPS_TYPE: u8 = 32;
+pub const SPS_TYPE: u8 = 33;
+const PAYLOAD_HEADER_SIZE_BYTES: usize = 2;
+const FU_HEADER_SIZE_BYTES: usize = 3;
+const FU_START_MASK: u8 = 0x80;
+const FU_END_MASK: u8 = 0x40;
+const FU_TYPE_MASK: u8 = 0x3F;
+/// Intra random access point pictures (BLA, IDR and CRA)
+const IRAP_TYPES: std::ops::RangeInclusive<u8> = 16..=23;
+
+/// The NAL unit header, which is also used as the payload header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct H265PayloadHeader {
+    pub forbidden_zero: bool,
+    /// 6 bits
+    pub nal_type: u8,
+    /// 6 bits
+    pub layer_id: u8,
+    /// 3 bits.  This is the temporal id + 1, so 0 is invalid.
+    pub tid: u8,
+}
+
+impl H265PayloadHeader {
+    pub fn temporal_id(&self) -> u8 {
+        self.tid.saturating_sub(1)
+    }
+
+    pub fn to_bytes(&self) -> [u8; 2] {
+        let value = ((self.forbidden_zero as u16) << 15)
+            | (((self.nal_type & 0x3F) as u16) << 9)
+            | (((self.layer_id & 0x3F) as u16) << 3)
+            | (self.tid & 0x7) as u16;
+        value.to_be_bytes()
+    }
+}
+
+/// Read the NAL unit (or payload) header from the start of `buf`
+pub fn read_h265_payload_header(buf: &[u8]) -> Result<H265PayloadHeader> {
+    let Some(header) = buf.get(..PAYLOAD_HEADER_SIZE_BYTES) else {
+        bail!(RtpParseError::truncated(
+            PAYLOAD_HEADER_SIZE_BYTES,
+            buf.len()
+        ));
+    };
+    let value = u16::from_be_bytes([header[0], header[1]]);
+    let header = H265PayloadHeader {
+        forbidden_zero: value & 0x8000 != 0,
+        nal_type: ((value >> 9) & 0x3F) as u8,
+        layer_id: ((value >> 3) & 0x3F) as u8,
+        tid: (value & 0x7) as u8,
+    };
+    if header.tid == 0 {
+        bail!(RtpParseError::invalid_field(
+            "H.265 payload header TID",
+            "non-zero",
+            0
+        ));
+    }
+
+    Ok(header)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct H265FragmentationUnit {
+    /// The payload header, whose layer id and TID are those of the fragmented NAL unit
+    pub payload_header: H265PayloadHeader,
+    pub start: bool,
+    pub end: bool,
+    /// 6 bits
+    pub nal_type: u8,
+    pub data: Bytes,
+}
+
+impl H265FragmentationUnit {
+    /// The header of the fragmented NAL unit
+    pub fn nal_header(&self) -> H265PayloadHeader {
+        H265PayloadHeader {
+            nal_type: self.nal_type,
+            ..self.payload_header
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum H265Payload {
+    SingleNal(Bytes),
+    Aggregation(Vec<Bytes>),
+    Fragmentation(H265FragmentationUnit),
+}
+
+impl H265Payload {
+    /// The complete NAL units in this payload.  A fragmentation unit has none: its fragments
+    /// need to be reassembled with a [`H265Depacketizer`].
+    pub fn nal_units(&self) -> NalUnits<'_> {
+        match self {
+            H265Payload::SingleNal(nal) => NalUnits::new(std::slice::from_ref(nal)),
+            H265Payload::Aggregation(nals) => NalUnits::new(nals),
+            H265Payload::Fragmentation(_) => NalUnits::new(&[]),
+        }
+    }
+}
+
+/// Read a H.265 RTP payload.  The NAL units and fragments are slices of `payload` rather than
+/// copies.
+pub fn read_h265_payload(payload: Bytes) -> Result<H265Payload> {
+    let payload_header = read_h265_payload_header(&payload)?;
+    match payload_header.nal_type {
+        AGGREGATION_PACKET_TYPE => {
+            let mut nals = Vec::new();
+            let mut offset = PAYLOAD_HEADER_SIZE_BYTES;
+            while offset < payload.len() {
+                let Some(size) = payload.get(offset..offset + 2) else {
+                    bail!(RtpParseError::truncated(offset + 2, payload.len()));
+                };
+                let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+                offset += 2;
+                if size < PAYLOAD_HEADER_SIZE_BYTES {
+                    bail!(RtpParseError::invalid_field(
+                        "aggregation packet NAL unit size",
+                        format!("at least {PAYLOAD_HEADER_SIZE_BYTES} bytes"),
+                        format!("{size} bytes")
+                    ));
+                }
+                if offset + size > payload.len() {
+                    bail!(RtpParseError::truncated(offset + size, payload.len()));
+                }
+                nals.push(payload.slice(offset..offset + size));
+                offset += size;
+            }
+            if nals.len() < 2 {
+                bail!(RtpParseError::invalid_field(
+                    "aggregation packet NAL unit count",
+                    "at least 2",
+                    nals.len()
+                ));
+            }
+
+            Ok(H265Payload::Aggregation(nals))
+        }
+        FRAGMENTATION_UNIT_TYPE => {
+            if payload.len() <= FU_HEADER_SIZE_BYTES {
+                bail!(RtpParseError::truncated(
+                    FU_HEADER_SIZE_BYTES + 1,
+                    payload.len()
+                ));
+            }
+            let fu_header = payload[PAYLOAD_HEADER_SIZE_BYTES];
+            let fu = H265FragmentationUnit {
+                payload_header,
+                start: fu_header & FU_START_MASK != 0,
+                end: fu_header & FU_END_MASK != 0,
+                nal_type: fu_header & FU_TYPE_MASK,
+                data: payload.slice(FU_HEADER_SIZE_BYTES..),
+            };
+            if fu.start && fu.end {
+                bail!(RtpParseError::invalid_field(
+                    "fragmentation unit start and end bits",
+                    "not both set",
+                    "both set"
+                ));
+            }
+
+            Ok(H265Payload::Fragmentation(fu))
+        }
+        PACI_TYPE => bail!(RtpParseError::UnsupportedPacketType {
+            kind: "H.265 NAL unit",
+            packet_type: PACI_TYPE as u16,
+        }),
+        _ => Ok(H265Payload::SingleNal(payload)),
+    }
+}
+
+/// Whether the given H.265 RTP payload starts a key frame: whether it has an IRAP picture's NAL
+/// unit or a video/sequence parameter set, or is the first fragment of one
+pub fn is_keyframe(payload: &[u8]) -> Result<bool> {
+    let is_keyframe_nal_type =
+        |nal_type| IRAP_TYPES.contains(&nal_type) || nal_type == VPS_TYPE || nal_type == SPS_TYPE;
+    match read_h265_payload_header(payload)?.nal_type {
+        AGGREGATION_PACKET_TYPE => {
+            let mut offset = PAYLOAD_HEADER_SIZE_BYTES;
+            while let Some(size) = payload.get(offset..offset + 2) {
+                let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+                let nal_header = read_h265_payload_header(&payload[offset + 2..])?;
+                if is_keyframe_nal_type(nal_header.nal_type) {
+                    return Ok(true);
+                }
+                offset += 2 + size;
+            }
+
+            Ok(false)
+        }
+        FRAGMENTATION_UNIT_TYPE => {
+            let Some(fu_header) = payload.get(PAYLOAD_HEADER_SIZE_BYTES) else {
+                bail!(RtpParseError::truncated(
+                    PAYLOAD_HEADER_SIZE_BYTES + 1,
+                    payload.len()
+                ));
+            };
+
+            Ok(fu_header & FU_START_MASK != 0 && is_keyframe_nal_type(fu_header & FU_TYPE_MASK))
+        }
+        nal_type => Ok(is_keyframe_nal_type(nal_type)),
+    }
+}
+
+/// Turns H.265 RTP payloads back into NAL units, reassembling fragmentation units.  Payloads
+/// must be pushed in sequence number order, and [`H265Depacketizer::reset`] should be called
+/// when a packet is lost.
+#[derive(Debug, Default)]
+pub struct H265Depacketizer {
+    fragmented_nal: Option<BytesMut>,
+}
+
+impl H265Depacketizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop any partially reassembled NAL unit
+    pub fn reset(&mut self) {
+        self.fragmented_nal = None;
+    }
+
+    /// Process the given RTP payload, returning the NAL units it completes
+    pub fn push(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        let fu = match read_h265_payload(payload)? {
+            H265Payload::Fragmentation(fu) => fu,
+            payload => {
+                self.reset();
+                return Ok(payload.nal_units().collect());
+            }
+        };
+        if fu.start {
+            let mut nal = BytesMut::with_capacity(PAYLOAD_HEADER_SIZE_BYTES + fu.data.len());
+            nal.extend_from_slice(&fu.nal_header().to_bytes());
+            self.fragmented_nal = Some(nal);
+        }
+        let Some(nal) = self.fragmented_nal.as_mut() else {
+            // The start of this NAL unit was lost
+            return Ok(Vec::new());
+        };
+        nal.extend_from_slice(&fu.data);
+        if fu.end {
+            return Ok(self
+                .fragmented_nal
+                .take()
+                .map(|nal| nal.freeze())
+                .into_iter()
+                .collect());
+        }
+
+        Ok(Vec::new())
+    }
+}
+
+/// Splits NAL units into RTP payloads of at most `mtu` bytes, sending NAL units that fit as
+/// single NAL unit packets and fragmenting the rest into fragmentation units.
+#[derive(Debug, Clone)]
+pub struct H265Packetizer {
+    mtu: usize,
+}
+
+impl H265Packetizer {
+    pub fn new(mtu: usize) -> Result<Self> {
+        if mtu <= FU_HEADER_SIZE_BYTES {
+            bail!("MTU must be more than {FU_HEADER_SIZE_BYTES} bytes, got {mtu}");
+        }
+
+        Ok(Self { mtu })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+
+    /// Packetize the given NAL unit (without an Annex B start code)
+    pub fn packetize(&self, nal: Bytes) -> Result<Vec<Bytes>> {
+        let nal_header = read_h265_payload_header(&nal)?;
+        if nal.len() <= self.mtu {
+            return Ok(vec![nal]);
+        }
+        let payload_header = H265PayloadHeader {
+            nal_type: FRAGMENTATION_UNIT_TYPE,
+            ..nal_header
+        }
+        .to_bytes();
+        let fragments = nal[PAYLOAD_HEADER_SIZE_BYTES..].chunks(self.mtu - FU_HEADER_SIZE_BYTES);
+        let num_fragments = fragments.len();
+
+        Ok(fragments
+            .enumerate()
+            .map(|(i, fragment)| {
+                let mut fu_header = nal_header.nal_type;
+                if i == 0 {
+                    fu_header |= FU_START_MASK;
+                }
+                if i == num_fragments - 1 {
+                    fu_header |= FU_END_MASK;
+                }
+                let mut payload = BytesMut::with_capacity(FU_HEADER_SIZE_BYTES + fragment.len());
+                payload.extend_from_slice(&payload_header);
+                payload.put_u8(fu_header);
+                payload.extend_from_slice(fragment);
+                payload.freeze()
+            })
+            .collect())
+    }
+}
+
+impl PayloadDepacketizer for H265Depacketizer {
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        self.push(payload)
+    }
+
+    fn discard_frame(&mut self) {
+        self.reset();
+    }
+}
+
+impl PayloadPacketizer for H265Packetizer {
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>> {
+        let mut payloads = Vec::new();
+        for (i, nal) in units.iter().enumerate() {
+            payloads.extend(
+                self.packetize(nal.clone())
+                    .with_context(|| format!("NAL unit {i}"))?,
+            );
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_header() {
+        // VPS, layer 0, TID 1
+        let header = read_h265_payload_header(&[0x40, 0x01]).unwrap();
+        assert_eq!(header.nal_type, 32);
+        assert_eq!(header.layer_id, 0);
+        assert_eq!(header.temporal_id(), 0);
+        assert_eq!(header.to_bytes(), [0x40, 0x01]);
+        assert!(read_h265_payload_header(&[0x40, 0x00]).is_err());
+    }
+
+    #[test]
+    fn test_aggregation_packet() {
+        #[rustfmt::skip]
+        let payload = Bytes::from_static(&[
+            0x60, 0x01,
+            0x00, 0x03, 0x40, 0x01, 0xAA,
+            0x00, 0x02, 0x42, 0x01,
+        ]);
+        let nals = read_h265_payload(payload)
+            .unwrap()
+            .nal_units()
+            .collect::<Vec<_>>();
+        assert_eq!(nals, [&[0x40, 0x01, 0xAA][..], &[0x42, 0x01][..]]);
+
+        assert!(
+            read_h265_payload(Bytes::from_static(&[0x60, 0x01, 0x00, 0x02, 0x40, 0x01])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_packetize_and_depacketize() {
+        // An IDR_W_RADL (type 19) NAL unit
+        let nal = Bytes::from(
+            (0..100u8)
+                .map(|i| match i {
+                    0 => 0x26,
+                    1 => 0x01,
+                    i => i,
+                })
+                .collect::<Vec<_>>(),
+        );
+        let packetizer = H265Packetizer::new(40).unwrap();
+        let payloads = packetizer.packetize(nal.clone()).unwrap();
+        assert_eq!(payloads.len(), 3);
+        assert!(payloads.iter().all(|payload| payload.len() <= 40));
+        assert_eq!(&payloads[0][..3], [0x62, 0x01, 0x93]);
+        assert_eq!(&payloads[2][..3], [0x62, 0x01, 0x53]);
+
+        let mut depacketizer = H265Depacketizer::new();
+        assert!(depacketizer.push(payloads[0].clone()).unwrap().is_empty());
+        assert!(depacketizer.push(payloads[1].clone()).unwrap().is_empty());
+        assert_eq!(depacketizer.push(payloads[2].clone()).unwrap(), vec![nal]);
+    }
+}
@@ -0,0 +1,80 @@
+pub mod av1;
+pub mod depacketizer;
+pub mod h264;
+pub mod h265;
+pub mod opus;
+pub mod packetizer;
+pub mod telephone_event;
+pub mod vp8;
+pub mod vp9;
+
+/// The video payload formats that can be depacketized and have their key frames detected.
+/// Which payload types map to which format is negotiated out of band (e.g. via SDP).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PayloadTypeKind {
+    Vp8,
+    Vp9,
+    H264,
+    H265,
+    Av1,
+}
+
+/// Whether the given RTP payload is the first packet of a key frame.  Payloads that can't be
+/// parsed aren't key frames.
+pub fn is_keyframe(payload_type_kind: PayloadTypeKind, payload: &[u8]) -> bool {
+    let result = match payload_type_kind {
+        PayloadTypeKind::Vp8 => vp8::is_keyframe(payload),
+        PayloadTypeKind::Vp9 => vp9::is_keyframe(payload),
+        PayloadTypeKind::H264 => h264::is_keyframe(payload),
+        PayloadTypeKind::H265 => h265::is_keyframe(payload),
+        PayloadTypeKind::Av1 => av1::is_keyframe(payload),
+    };
+    result.unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_keyframe() {
+        let cases: &[(PayloadTypeKind, &[u8], bool)] = &[
+            (PayloadTypeKind::Vp8, &[0x10, 0x00, 0x02, 0x00], true),
+            (PayloadTypeKind::Vp8, &[0x10, 0x01, 0x02, 0x00], false),
+            (PayloadTypeKind::Vp9, &[0x08, 0xAA], true),
+            (PayloadTypeKind::Vp9, &[0x48, 0xAA], false),
+            // IDR
+            (PayloadTypeKind::H264, &[0x65, 0xAA], true),
+            // Non-IDR slice
+            (PayloadTypeKind::H264, &[0x41, 0xAA], false),
+            // STAP-A with an SPS and a PPS
+            (
+                PayloadTypeKind::H264,
+                &[0x78, 0x00, 0x02, 0x67, 0x42, 0x00, 0x02, 0x68, 0xCE],
+                true,
+            ),
+            // First and middle FU-A fragments of an IDR
+            (PayloadTypeKind::H264, &[0x7C, 0x85, 0xAA], true),
+            (PayloadTypeKind::H264, &[0x7C, 0x05, 0xAA], false),
+            // IDR_W_RADL
+            (PayloadTypeKind::H265, &[0x26, 0x01, 0xAA], true),
+            // TRAIL_R
+            (PayloadTypeKind::H265, &[0x02, 0x01, 0xAA], false),
+            // First FU fragment of an IDR_W_RADL
+            (PayloadTypeKind::H265, &[0x62, 0x01, 0x93, 0xAA], true),
+            // New coded video sequence
+            (PayloadTypeKind::Av1, &[0x18, 0x30, 0xAA], true),
+            // Starts with a sequence header
+            (PayloadTypeKind::Av1, &[0x20, 0x01, 0x08, 0x30], true),
+            (PayloadTypeKind::Av1, &[0x10, 0x30, 0xAA], false),
+            (PayloadTypeKind::Av1, &[], false),
+        ];
+        for (payload_type_kind, payload, expected) in cases {
+            assert_eq!(
+                is_keyframe(*payload_type_kind, payload),
+                *expected,
+                "{payload_type_kind:?} {payload:x?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+// https://datatracker.ietf.org/doc/html/rfc6716#section-3.1
+// An Opus packet (the whole RTP payload) starts with the TOC byte:
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// | config  |s| c |
+// +-+-+-+-+-+-+-+-+
+//
+// config: the mode, bandwidth and frame size:
+// +-----------------------+-----------+-----------+-------------------+
+// | Configuration         | Mode      | Bandwidth | Frame Sizes       |
+// | Number(s)             |           |           |                   |
+// +-----------------------+-----------+-----------+-------------------+
+// | 0...3                 | SILK-only | NB        | 10, 20, 40, 60 ms |
+// | 4...7                 | SILK-only | MB        | 10, 20, 40, 60 ms |
+// | 8...11                | SILK-only | WB        | 10, 20, 40, 60 ms |
+// | 12...13               | Hybrid    | SWB       | 10, 20 ms         |
+// | 14...15               | Hybrid    | FB        | 10, 20 ms         |
+// | 16...19               | CELT-only | NB        | 2.5, 5, 10, 20 ms |
+// | 20...23               | CELT-only | WB        | 2.5, 5, 10, 20 ms |
+// | 24...27               | CELT-only | SWB       | 2.5, 5, 10, 20 ms |
+// | 28...31               | CELT-only | FB        | 2.5, 5, 10, 20 ms |
+// +-----------------------+-----------+-----------+-------------------+
+// s: stereo
+// c: the frame count code:
+//   0: 1 frame
+//   1: 2 frames of equal size
+//   2: 2 frames of different sizes
+//   3: an arbitrary number of frames, given by the next byte:
+//       0 1 2 3 4 5 6 7
+//      +-+-+-+-+-+-+-+-+
+//      |v|p|     M     |
+//      +-+-+-+-+-+-+-+-+
+
+/// Opus packets this short carry no audio, and are sent during discontinuous transmission
+const MAX_DTX_PACKET_SIZE_BYTES: usize = 2;
+/// Packets can't hold more than 120ms of audio
+const MAX_PACKET_DURATION_US: u32 = 120_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpusMode {
+    Silk,
+    Hybrid,
+    Celt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum OpusBandwidth {
+    /// 4kHz
+    Narrowband,
+    /// 6kHz
+    Mediumband,
+    /// 8kHz
+    Wideband,
+    /// 12kHz
+    SuperWideband,
+    /// 20kHz
+    Fullband,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusToc {
+    /// 5 bits
+    pub config: u8,
+    pub stereo: bool,
+    /// 2 bits
+    pub frame_count_code: u8,
+}
+
+impl From<u8> for OpusToc {
+    fn from(byte: u8) -> Self {
+        Self {
+            config: byte >> 3,
+            stereo: byte & 0x04 != 0,
+            frame_count_code: byte & 0x03,
+        }
+    }
+}
+
+impl OpusToc {
+    pub fn to_byte(&self) -> u8 {
+        ((self.config & 0x1F) << 3) | ((self.stereo as u8) << 2) | (self.frame_count_code & 0x3)
+    }
+
+    pub fn mode(&self) -> OpusMode {
+        match self.config {
+            0..=11 => OpusMode::Silk,
+            12..=15 => OpusMode::Hybrid,
+            _ => OpusMode::Celt,
+        }
+    }
+
+    pub fn bandwidth(&self) -> OpusBandwidth {
+        match self.config {
+            0..=3 | 16..=19 => OpusBandwidth::Narrowband,
+            4..=7 => OpusBandwidth::Mediumband,
+            8..=11 | 20..=23 => OpusBandwidth::Wideband,
+            12..=13 | 24..=27 => OpusBandwidth::SuperWideband,
+            _ => OpusBandwidth::Fullband,
+        }
+    }
+
+    /// The duration of each frame in the packet, in microseconds
+    pub fn frame_duration_us(&self) -> u32 {
+        match self.mode() {
+            OpusMode::Silk => [10_000, 20_000, 40_000, 60_000][(self.config & 0x3) as usize],
+            OpusMode::Hybrid => [10_000, 20_000][(self.config & 0x1) as usize],
+            OpusMode::Celt => [2_500, 5_000, 10_000, 20_000][(self.config & 0x3) as usize],
+        }
+    }
+}
+
+/// Read the TOC byte of the given Opus RTP payload
+pub fn read_opus_toc(payload: &[u8]) -> Result<OpusToc> {
+    let Some(toc) = payload.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+
+    Ok(OpusToc::from(*toc))
+}
+
+/// The number of frames in the given Opus RTP payload
+pub fn opus_frame_count(payload: &[u8]) -> Result<u8> {
+    let toc = read_opus_toc(payload)?;
+    match toc.frame_count_code {
+        0 => Ok(1),
+        1 | 2 => Ok(2),
+        _ => {
+            let Some(frame_count) = payload.get(1) else {
+                bail!(RtpParseError::truncated(2, payload.len()));
+            };
+            let frame_count = frame_count & 0x3F;
+            if frame_count == 0 {
+                bail!(RtpParseError::invalid_field(
+                    "Opus frame count",
+                    "non-zero",
+                    0
+                ));
+            }
+            let duration_us = frame_count as u32 * toc.frame_duration_us();
+            if duration_us > MAX_PACKET_DURATION_US {
+                bail!(RtpParseError::invalid_field(
+                    "Opus packet duration",
+                    format!("at most {MAX_PACKET_DURATION_US}us"),
+                    format!("{duration_us}us")
+                ));
+            }
+
+            Ok(frame_count)
+        }
+    }
+}
+
+/// The duration of the audio in the given Opus RTP payload, in microseconds
+pub fn opus_packet_duration_us(payload: &[u8]) -> Result<u32> {
+    let frame_count = opus_frame_count(payload)?;
+
+    Ok(frame_count as u32 * read_opus_toc(payload)?.frame_duration_us())
+}
+
+/// Whether the given Opus RTP payload is a discontinuous transmission packet, which carries no
+/// audio
+pub fn is_opus_dtx(payload: &[u8]) -> bool {
+    payload.len() <= MAX_DTX_PACKET_SIZE_BYTES
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_toc() {
+        // SILK WB 20ms, mono, 1 frame
+        let toc = read_opus_toc(&[0x48, 0xAA]).unwrap();
+        assert_eq!(toc.mode(), OpusMode::Silk);
+        assert_eq!(toc.bandwidth(), OpusBandwidth::Wideband);
+        assert_eq!(toc.frame_duration_us(), 20_000);
+        assert!(!toc.stereo);
+        assert_eq!(toc.to_byte(), 0x48);
+
+        // CELT FB 2.5ms, stereo, 2 frames
+        let toc = OpusToc::from(0xE5);
+        assert_eq!(toc.mode(), OpusMode::Celt);
+        assert_eq!(toc.bandwidth(), OpusBandwidth::Fullband);
+        assert_eq!(toc.frame_duration_us(), 2_500);
+        assert!(toc.stereo);
+
+        assert_eq!(OpusToc::from(0x68).mode(), OpusMode::Hybrid);
+        assert!(read_opus_toc(&[]).is_err());
+    }
+
+    #[test]
+    fn test_frame_count() {
+        assert_eq!(opus_packet_duration_us(&[0x48, 0xAA]).unwrap(), 20_000);
+        assert_eq!(opus_frame_count(&[0x49, 0xAA]).unwrap(), 2);
+        // Code 3, 3 frames of 20ms
+        assert_eq!(
+            opus_packet_duration_us(&[0x4B, 0x03, 0xAA]).unwrap(),
+            60_000
+        );
+        assert!(opus_frame_count(&[0x4B]).is_err());
+        // 7 frames of 20ms is more than 120ms
+        assert!(opus_frame_count(&[0x4B, 0x07]).is_err());
+
+        assert!(is_opus_dtx(&[0x48]));
+        assert!(!is_opus_dtx(&[0x48, 0xAA, 0xBB]));
+    }
+}
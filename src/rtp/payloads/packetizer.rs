@@ -0,0 +1,198 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::nsw_types::u7;
+use bytes::Bytes;
+
+use crate::{
+    rtp::rtp_packet::{RtpPacket, RtpPacketBuilder},
+    LengthBytes,
+};
+
+use super::{
+    av1::Av1Packetizer, h264::H264Packetizer, h265::H265Packetizer, vp8::Vp8Packetizer,
+    vp9::Vp9Packetizer, PayloadTypeKind,
+};
+
+/// The codec specific part of a [`Packetizer`]: splitting a frame into payloads that fit its MTU
+pub trait PayloadPacketizer {
+    /// Split the units of a frame (see [`Frame::units`](super::depacketizer::Frame::units)) into
+    /// payloads
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>>;
+}
+
+/// The header fields of the packets a [`Packetizer`] generates for a frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RtpHeaderTemplate {
+    pub payload_type: u7,
+    pub ssrc: u32,
+    pub timestamp: u32,
+}
+
+/// Turns frames into [`RtpPacket`]s which each fit in an MTU, with consecutive sequence numbers
+/// and the marker bit set on the last packet of each frame
+pub struct Packetizer {
+    payload_packetizer: Box<dyn PayloadPacketizer>,
+    seq_num: u16,
+}
+
+impl Packetizer {
+    /// Create a packetizer for the given payload format whose packets (header included) are at
+    /// most `mtu` bytes
+    pub fn new(
+        payload_type_kind: PayloadTypeKind,
+        mtu: usize,
+        initial_seq_num: u16,
+    ) -> Result<Self> {
+        let template_packet = RtpPacketBuilder::new().build().context("template packet")?;
+        let Some(max_payload_size) = mtu.checked_sub(template_packet.length_bytes()) else {
+            bail!("MTU {mtu} is smaller than the RTP header");
+        };
+        let payload_packetizer: Box<dyn PayloadPacketizer> = match payload_type_kind {
+            PayloadTypeKind::Vp8 => Box::new(Vp8Packetizer::new(max_payload_size)?),
+            PayloadTypeKind::Vp9 => Box::new(Vp9Packetizer::new(max_payload_size)?),
+            PayloadTypeKind::H264 => Box::new(H264Packetizer::new(max_payload_size)?),
+            PayloadTypeKind::H265 => Box::new(H265Packetizer::new(max_payload_size)?),
+            PayloadTypeKind::Av1 => Box::new(Av1Packetizer::new(max_payload_size)?),
+        };
+
+        Ok(Self::with_payload_packetizer(
+            payload_packetizer,
+            initial_seq_num,
+        ))
+    }
+
+    pub fn with_payload_packetizer(
+        payload_packetizer: Box<dyn PayloadPacketizer>,
+        initial_seq_num: u16,
+    ) -> Self {
+        Self {
+            payload_packetizer,
+            seq_num: initial_seq_num,
+        }
+    }
+
+    /// The sequence number the next packet will have
+    pub fn next_seq_num(&self) -> u16 {
+        self.seq_num
+    }
+
+    /// Packetize the given frame
+    pub fn packetize(
+        &mut self,
+        header: &RtpHeaderTemplate,
+        units: &[Bytes],
+    ) -> Result<Vec<RtpPacket>> {
+        let payloads = self.payload_packetizer.packetize_frame(units)?;
+        let num_payloads = payloads.len();
+        let mut packets = Vec::with_capacity(num_payloads);
+        for (i, payload) in payloads.into_iter().enumerate() {
+            let mut packet = RtpPacketBuilder::new()
+                .payload_type(header.payload_type)
+                .ssrc(header.ssrc)
+                .timestamp(header.timestamp)
+                .seq_num(self.seq_num.wrapping_add(i as u16))
+                .marked(i + 1 == num_payloads)
+                .build()
+                .with_context(|| format!("packet {i}"))?;
+            packet.set_payload_bytes(payload);
+            packets.push(packet);
+        }
+        self.seq_num = self.seq_num.wrapping_add(num_payloads as u16);
+
+        Ok(packets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtp::payloads::depacketizer::new_depacketizer;
+
+    use super::*;
+
+    fn roundtrip(payload_type_kind: PayloadTypeKind, units: &[Bytes]) -> Vec<RtpPacket> {
+        let mut packetizer = Packetizer::new(payload_type_kind, 100, 65534).unwrap();
+        let header = RtpHeaderTemplate {
+            payload_type: u7::new(96),
+            ssrc: 1234,
+            timestamp: 9000,
+        };
+        let packets = packetizer.packetize(&header, units).unwrap();
+        assert!(packets.iter().all(|packet| packet.length_bytes() <= 100));
+        assert!(packets.last().unwrap().marked());
+        assert!(packets[..packets.len() - 1]
+            .iter()
+            .all(|packet| !packet.marked()));
+        assert_eq!(
+            packetizer.next_seq_num(),
+            65534u16.wrapping_add(packets.len() as u16)
+        );
+
+        let mut depacketizer = new_depacketizer(payload_type_kind);
+        for packet in &packets {
+            depacketizer.push(packet).unwrap();
+        }
+        let frame = depacketizer.pop().unwrap();
+        assert_eq!(frame.timestamp, 9000);
+        assert_eq!(frame.units, units);
+        packets
+    }
+
+    fn unit(header: &[u8], len: usize) -> Bytes {
+        let mut unit = header.to_vec();
+        unit.extend((0..len).map(|i| i as u8));
+        Bytes::from(unit)
+    }
+
+    #[test]
+    fn test_h264() {
+        let packets = roundtrip(
+            PayloadTypeKind::H264,
+            &[unit(&[0x67], 10), unit(&[0x68], 4), unit(&[0x65], 500)],
+        );
+        assert_eq!(packets.len(), 8);
+        assert_eq!(packets[0].seq_num(), 65534);
+        assert_eq!(packets[2].seq_num(), 0);
+    }
+
+    #[test]
+    fn test_h265() {
+        roundtrip(PayloadTypeKind::H265, &[unit(&[0x26, 0x01], 300)]);
+    }
+
+    #[test]
+    fn test_vp8() {
+        roundtrip(PayloadTypeKind::Vp8, &[unit(&[0x10, 0x02, 0x00], 250)]);
+    }
+
+    #[test]
+    fn test_vp9() {
+        // A key frame
+        let packets = roundtrip(PayloadTypeKind::Vp9, &[unit(&[0x82, 0x49, 0x83], 250)]);
+        assert!(crate::rtp::payloads::is_keyframe(
+            PayloadTypeKind::Vp9,
+            packets[0].payload()
+        ));
+        // An inter frame
+        let packets = roundtrip(PayloadTypeKind::Vp9, &[unit(&[0x86], 50)]);
+        assert!(!crate::rtp::payloads::is_keyframe(
+            PayloadTypeKind::Vp9,
+            packets[0].payload()
+        ));
+    }
+
+    #[test]
+    fn test_av1() {
+        let packets = roundtrip(
+            PayloadTypeKind::Av1,
+            &[unit(&[0x08], 10), unit(&[0x30], 300), unit(&[0x30], 20)],
+        );
+        assert!(crate::rtp::payloads::is_keyframe(
+            PayloadTypeKind::Av1,
+            packets[0].payload()
+        ));
+    }
+
+    #[test]
+    fn test_mtu_too_small() {
+        assert!(Packetizer::new(PayloadTypeKind::H264, 12, 0).is_err());
+    }
+}
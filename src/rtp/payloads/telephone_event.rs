@@ -0,0 +1,207 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_write::BitWrite, bit_write_exts::BitWriteExts, byte_order::NetworkOrder};
+
+use crate::{error::RtpParseError, LengthBytes};
+
+// https://datatracker.ietf.org/doc/html/rfc4733#section-2.3
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     event     |E|R| volume    |          duration             |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// E: the end of the event
+// R: reserved
+// volume: the power level of the tone, in -dBm0 (0-63)
+// duration: the duration of the event so far, in RTP timestamp units.  Every packet for an event
+//   has the RTP timestamp of the event's start.  The final (E bit) packet is typically sent 3
+//   times.
+//
+// https://datatracker.ietf.org/doc/html/rfc4733#section-3.2
+// Events 0-15 are the DTMF digits 0-9, *, #, A-D.
+
+const TELEPHONE_EVENT_SIZE_BYTES: usize = 4;
+const END_MASK: u8 = 0x80;
+const RESERVED_MASK: u8 = 0x40;
+const VOLUME_MASK: u8 = 0x3F;
+const DTMF_EVENTS: &[u8; 16] = b"0123456789*#ABCD";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TelephoneEvent {
+    pub event: u8,
+    pub end: bool,
+    pub reserved: bool,
+    /// 6 bits
+    pub volume: u8,
+    pub duration: u16,
+}
+
+impl TelephoneEvent {
+    /// The DTMF digit of this event, if it is one
+    pub fn dtmf_digit(&self) -> Option<char> {
+        DTMF_EVENTS.get(self.event as usize).map(|c| *c as char)
+    }
+}
+
+impl LengthBytes for TelephoneEvent {
+    fn length_bytes(&self) -> usize {
+        TELEPHONE_EVENT_SIZE_BYTES
+    }
+}
+
+/// Read a telephone-event payload
+pub fn read_telephone_event(payload: &[u8]) -> Result<TelephoneEvent> {
+    let Some(data) = payload.get(..TELEPHONE_EVENT_SIZE_BYTES) else {
+        bail!(RtpParseError::truncated(
+            TELEPHONE_EVENT_SIZE_BYTES,
+            payload.len()
+        ));
+    };
+
+    Ok(TelephoneEvent {
+        event: data[0],
+        end: data[1] & END_MASK != 0,
+        reserved: data[1] & RESERVED_MASK != 0,
+        volume: data[1] & VOLUME_MASK,
+        duration: u16::from_be_bytes([data[2], data[3]]),
+    })
+}
+
+pub fn write_telephone_event<W: BitWrite>(buf: &mut W, event: &TelephoneEvent) -> Result<()> {
+    let mut flags = event.volume & VOLUME_MASK;
+    if event.end {
+        flags |= END_MASK;
+    }
+    if event.reserved {
+        flags |= RESERVED_MASK;
+    }
+    buf.write_u8(event.event).context("event")?;
+    buf.write_u8(flags).context("flags and volume")?;
+    buf.write_u16::<NetworkOrder>(event.duration)
+        .context("duration")?;
+
+    Ok(())
+}
+
+/// An event whose end has been detected by a [`TelephoneEventDetector`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompletedTelephoneEvent {
+    /// The RTP timestamp of the start of the event
+    pub timestamp: u32,
+    pub event: TelephoneEvent,
+    /// Whether a packet with the E bit was received.  If not, the event was ended by the start of
+    /// a new one (the end packets were lost) and the duration is the last one received.
+    pub end_received: bool,
+}
+
+/// Accumulates the telephone-event packets of a stream, reporting each event once when it
+/// ends.
+#[derive(Debug, Default)]
+pub struct TelephoneEventDetector {
+    /// The RTP timestamp and latest packet of the event in progress
+    current: Option<(u32, TelephoneEvent)>,
+    /// The RTP timestamp of the last event that was reported, so retransmissions of its end
+    /// packet are ignored
+    last_completed_timestamp: Option<u32>,
+}
+
+impl TelephoneEventDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The RTP timestamp and latest packet of the event in progress, if there is one
+    pub fn current_event(&self) -> Option<(u32, &TelephoneEvent)> {
+        self.current
+            .as_ref()
+            .map(|(timestamp, event)| (*timestamp, event))
+    }
+
+    /// Process a telephone-event packet with the given RTP timestamp, returning the events it
+    /// completes
+    pub fn push(&mut self, timestamp: u32, event: TelephoneEvent) -> Vec<CompletedTelephoneEvent> {
+        if self.last_completed_timestamp == Some(timestamp) {
+            return Vec::new();
+        }
+        let mut completed = Vec::new();
+        if let Some((current_timestamp, current_event)) = self.current.take() {
+            if current_timestamp != timestamp {
+                completed.push(CompletedTelephoneEvent {
+                    timestamp: current_timestamp,
+                    event: current_event,
+                    end_received: false,
+                });
+            }
+        }
+        if event.end {
+            self.last_completed_timestamp = Some(timestamp);
+            completed.push(CompletedTelephoneEvent {
+                timestamp,
+                event,
+                end_received: true,
+            });
+        } else {
+            self.current = Some((timestamp, event));
+        }
+
+        completed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let event = TelephoneEvent {
+            event: 11,
+            end: true,
+            reserved: false,
+            volume: 10,
+            duration: 1_600,
+        };
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; event.length_bytes()]));
+        write_telephone_event(&mut cursor, &event).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(data, [0x0B, 0x8A, 0x06, 0x40]);
+        assert_eq!(read_telephone_event(&data).unwrap(), event);
+        assert_eq!(event.dtmf_digit(), Some('#'));
+        assert!(read_telephone_event(&data[..3]).is_err());
+    }
+
+    #[test]
+    fn test_detector() {
+        let digit = |event, end, duration| TelephoneEvent {
+            event,
+            end,
+            volume: 10,
+            duration,
+            ..Default::default()
+        };
+        let mut detector = TelephoneEventDetector::new();
+        assert!(detector.push(1_000, digit(1, false, 160)).is_empty());
+        assert!(detector.push(1_000, digit(1, false, 320)).is_empty());
+        let completed = detector.push(1_000, digit(1, true, 480));
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].event.duration, 480);
+        assert!(completed[0].end_received);
+        // Retransmissions of the end packet are ignored
+        assert!(detector.push(1_000, digit(1, true, 480)).is_empty());
+
+        // The end of event 2 is lost, so it ends when event 3 starts
+        assert!(detector.push(5_000, digit(2, false, 160)).is_empty());
+        let completed = detector.push(9_000, digit(3, false, 160));
+        assert_eq!(completed.len(), 1);
+        assert_eq!(completed[0].timestamp, 5_000);
+        assert!(!completed[0].end_received);
+        assert_eq!(
+            detector.current_event().map(|(timestamp, _)| timestamp),
+            Some(9_000)
+        );
+    }
+}
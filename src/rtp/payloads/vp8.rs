@@ -0,0 +1,377 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_write::BitWrite, bit_write_exts::BitWriteExts, byte_order::NetworkOrder};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{error::RtpParseError, LengthBytes};
+
+use super::{depacketizer::PayloadDepacketizer, packetizer::PayloadPacketizer};
+
+// https://datatracker.ietf.org/doc/html/rfc7741#section-4.2
+//       0 1 2 3 4 5 6 7
+//      +-+-+-+-+-+-+-+-+
+//      |X|R|N|S|R| PID | (REQUIRED)
+//      +-+-+-+-+-+-+-+-+
+// X:   |I|L|T|K| RSV   | (OPTIONAL)
+//      +-+-+-+-+-+-+-+-+
+// I:   |M| PictureID   | (OPTIONAL)
+//      +-+-+-+-+-+-+-+-+
+//      |   PictureID   |
+//      +-+-+-+-+-+-+-+-+
+// L:   |   TL0PICIDX   | (OPTIONAL)
+//      +-+-+-+-+-+-+-+-+
+// T/K: |TID|Y| KEYIDX  | (OPTIONAL)
+//      +-+-+-+-+-+-+-+-+
+//
+// X: extended control bits present
+// N: non-reference frame
+// S: start of VP8 partition
+// PID: partition index
+// I: picture id present, M: picture id is 15 bits (otherwise 7)
+// L: TL0PICIDX present
+// T: TID and Y present
+// K: KEYIDX present
+//
+// https://datatracker.ietf.org/doc/html/rfc7741#section-4.3
+// The payload descriptor is followed by the VP8 payload, which (at the start of a frame) begins
+// with the VP8 payload header:
+//       0 1 2 3 4 5 6 7
+//      +-+-+-+-+-+-+-+-+
+//      |Size0|H| VER |P|
+//      +-+-+-+-+-+-+-+-+
+// P is 0 for key frames (the inverse key frame flag).
+
+const EXTENDED_MASK: u8 = 0x80;
+const NON_REFERENCE_MASK: u8 = 0x20;
+const START_OF_PARTITION_MASK: u8 = 0x10;
+const PARTITION_INDEX_MASK: u8 = 0x0F;
+const PICTURE_ID_PRESENT_MASK: u8 = 0x80;
+const TL0_PIC_IDX_PRESENT_MASK: u8 = 0x40;
+const TID_PRESENT_MASK: u8 = 0x20;
+const KEY_IDX_PRESENT_MASK: u8 = 0x10;
+const LONG_PICTURE_ID_MASK: u8 = 0x80;
+const INVERSE_KEY_FRAME_MASK: u8 = 0x01;
+
+/// A picture id, as used by the VP8 and VP9 payload descriptors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PictureId {
+    /// 7 bits
+    Short(u8),
+    /// 15 bits
+    Long(u16),
+}
+
+impl PictureId {
+    pub fn value(&self) -> u16 {
+        match self {
+            PictureId::Short(value) => *value as u16,
+            PictureId::Long(value) => *value,
+        }
+    }
+
+    pub(crate) fn length_bytes(&self) -> usize {
+        match self {
+            PictureId::Short(_) => 1,
+            PictureId::Long(_) => 2,
+        }
+    }
+
+    /// Read a picture id from the start of `buf`, where the M bit determines whether it's 7 or
+    /// 15 bits
+    pub(crate) fn read(buf: &[u8]) -> Result<Self> {
+        let Some(first) = buf.first() else {
+            bail!(RtpParseError::truncated(1, 0));
+        };
+        if first & LONG_PICTURE_ID_MASK == 0 {
+            return Ok(PictureId::Short(*first));
+        }
+        let Some(second) = buf.get(1) else {
+            bail!(RtpParseError::truncated(2, buf.len()));
+        };
+
+        Ok(PictureId::Long(u16::from_be_bytes([
+            first & !LONG_PICTURE_ID_MASK,
+            *second,
+        ])))
+    }
+
+    pub(crate) fn write<W: BitWrite>(&self, buf: &mut W) -> Result<()> {
+        match self {
+            PictureId::Short(value) => buf.write_u8(value & 0x7F)?,
+            PictureId::Long(value) => buf.write_u16::<NetworkOrder>(0x8000 | (value & 0x7FFF))?,
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vp8TemporalLayer {
+    /// 2 bits
+    pub id: u8,
+    /// Whether this frame only depends on the base temporal layer
+    pub layer_sync: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vp8PayloadDescriptor {
+    pub non_reference: bool,
+    pub start_of_partition: bool,
+    /// 4 bits (only 0-7 are valid)
+    pub partition_index: u8,
+    pub picture_id: Option<PictureId>,
+    pub tl0_pic_idx: Option<u8>,
+    pub temporal_layer: Option<Vp8TemporalLayer>,
+    /// 5 bits
+    pub key_idx: Option<u8>,
+}
+
+impl Vp8PayloadDescriptor {
+    /// Whether this packet starts a frame (the start of partition 0)
+    pub fn is_start_of_frame(&self) -> bool {
+        self.start_of_partition && self.partition_index == 0
+    }
+
+    fn is_extended(&self) -> bool {
+        self.picture_id.is_some()
+            || self.tl0_pic_idx.is_some()
+            || self.temporal_layer.is_some()
+            || self.key_idx.is_some()
+    }
+}
+
+impl LengthBytes for Vp8PayloadDescriptor {
+    fn length_bytes(&self) -> usize {
+        if !self.is_extended() {
+            return 1;
+        }
+        2 + self
+            .picture_id
+            .map_or(0, |picture_id| picture_id.length_bytes())
+            + self.tl0_pic_idx.map_or(0, |_| 1)
+            + (self.temporal_layer.is_some() || self.key_idx.is_some()) as usize
+    }
+}
+
+/// Read the payload descriptor from the start of a VP8 RTP payload.  The VP8 payload starts
+/// [`LengthBytes::length_bytes`] bytes in.
+pub fn read_vp8_payload_descriptor(payload: &[u8]) -> Result<Vp8PayloadDescriptor> {
+    let Some(first) = payload.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    let mut descriptor = Vp8PayloadDescriptor {
+        non_reference: first & NON_REFERENCE_MASK != 0,
+        start_of_partition: first & START_OF_PARTITION_MASK != 0,
+        partition_index: first & PARTITION_INDEX_MASK,
+        ..Default::default()
+    };
+    if first & EXTENDED_MASK == 0 {
+        return Ok(descriptor);
+    }
+    let Some(extended) = payload.get(1) else {
+        bail!(RtpParseError::truncated(2, payload.len()));
+    };
+    let mut offset = 2;
+    if extended & PICTURE_ID_PRESENT_MASK != 0 {
+        let picture_id = PictureId::read(&payload[offset..]).context("picture id")?;
+        offset += picture_id.length_bytes();
+        descriptor.picture_id = Some(picture_id);
+    }
+    if extended & TL0_PIC_IDX_PRESENT_MASK != 0 {
+        let Some(tl0_pic_idx) = payload.get(offset) else {
+            bail!(RtpParseError::truncated(offset + 1, payload.len()));
+        };
+        descriptor.tl0_pic_idx = Some(*tl0_pic_idx);
+        offset += 1;
+    }
+    if extended & (TID_PRESENT_MASK | KEY_IDX_PRESENT_MASK) != 0 {
+        let Some(byte) = payload.get(offset) else {
+            bail!(RtpParseError::truncated(offset + 1, payload.len()));
+        };
+        if extended & TID_PRESENT_MASK != 0 {
+            descriptor.temporal_layer = Some(Vp8TemporalLayer {
+                id: byte >> 6,
+                layer_sync: byte & 0x20 != 0,
+            });
+        }
+        if extended & KEY_IDX_PRESENT_MASK != 0 {
+            descriptor.key_idx = Some(byte & 0x1F);
+        }
+    }
+
+    Ok(descriptor)
+}
+
+pub fn write_vp8_payload_descriptor<W: BitWrite>(
+    buf: &mut W,
+    descriptor: &Vp8PayloadDescriptor,
+) -> Result<()> {
+    let mut first = descriptor.partition_index & PARTITION_INDEX_MASK;
+    if descriptor.is_extended() {
+        first |= EXTENDED_MASK;
+    }
+    if descriptor.non_reference {
+        first |= NON_REFERENCE_MASK;
+    }
+    if descriptor.start_of_partition {
+        first |= START_OF_PARTITION_MASK;
+    }
+    buf.write_u8(first).context("flags")?;
+    if !descriptor.is_extended() {
+        return Ok(());
+    }
+
+    let mut extended = 0;
+    for (present, mask) in [
+        (descriptor.picture_id.is_some(), PICTURE_ID_PRESENT_MASK),
+        (descriptor.tl0_pic_idx.is_some(), TL0_PIC_IDX_PRESENT_MASK),
+        (descriptor.temporal_layer.is_some(), TID_PRESENT_MASK),
+        (descriptor.key_idx.is_some(), KEY_IDX_PRESENT_MASK),
+    ] {
+        if present {
+            extended |= mask;
+        }
+    }
+    buf.write_u8(extended).context("extended control bits")?;
+    if let Some(picture_id) = &descriptor.picture_id {
+        picture_id.write(buf).context("picture id")?;
+    }
+    if let Some(tl0_pic_idx) = descriptor.tl0_pic_idx {
+        buf.write_u8(tl0_pic_idx).context("tl0 pic idx")?;
+    }
+    if descriptor.temporal_layer.is_some() || descriptor.key_idx.is_some() {
+        let mut byte = descriptor.key_idx.unwrap_or(0) & 0x1F;
+        if let Some(temporal_layer) = &descriptor.temporal_layer {
+            byte |= (temporal_layer.id & 0x3) << 6;
+            if temporal_layer.layer_sync {
+                byte |= 0x20;
+            }
+        }
+        buf.write_u8(byte).context("tid and key idx")?;
+    }
+
+    Ok(())
+}
+
+/// Whether the given VP8 RTP payload is the first packet of a key frame
+pub fn is_keyframe(payload: &[u8]) -> Result<bool> {
+    let descriptor = read_vp8_payload_descriptor(payload).context("payload descriptor")?;
+    if !descriptor.is_start_of_frame() {
+        return Ok(false);
+    }
+    let Some(payload_header) = payload.get(descriptor.length_bytes()) else {
+        bail!(RtpParseError::truncated(
+            descriptor.length_bytes() + 1,
+            payload.len()
+        ));
+    };
+
+    Ok(payload_header & INVERSE_KEY_FRAME_MASK == 0)
+}
+
+/// Reassembles VP8 frames from the payloads of their packets
+#[derive(Debug, Default)]
+pub struct Vp8Depacketizer {
+    frame: BytesMut,
+}
+
+impl PayloadDepacketizer for Vp8Depacketizer {
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        let descriptor = read_vp8_payload_descriptor(&payload).context("payload descriptor")?;
+        self.frame
+            .extend_from_slice(&payload[descriptor.length_bytes()..]);
+
+        Ok(Vec::new())
+    }
+
+    fn finish_frame(&mut self) -> Vec<Bytes> {
+        if self.frame.is_empty() {
+            return Vec::new();
+        }
+        vec![self.frame.split().freeze()]
+    }
+
+    fn discard_frame(&mut self) {
+        self.frame.clear();
+    }
+}
+
+/// Splits VP8 frames into RTP payloads of at most `mtu` bytes, each with a minimal payload
+/// descriptor
+#[derive(Debug, Clone)]
+pub struct Vp8Packetizer {
+    mtu: usize,
+}
+
+impl Vp8Packetizer {
+    pub fn new(mtu: usize) -> Result<Self> {
+        if mtu <= 1 {
+            bail!("MTU must be more than 1 byte, got {mtu}");
+        }
+
+        Ok(Self { mtu })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+}
+
+impl PayloadPacketizer for Vp8Packetizer {
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>> {
+        let mut payloads = Vec::new();
+        for frame in units {
+            for (i, chunk) in frame.chunks(self.mtu - 1).enumerate() {
+                let mut payload = BytesMut::with_capacity(1 + chunk.len());
+                payload.put_u8(if i == 0 { START_OF_PARTITION_MASK } else { 0 });
+                payload.extend_from_slice(chunk);
+                payloads.push(payload.freeze());
+            }
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let descriptor = Vp8PayloadDescriptor {
+            non_reference: false,
+            start_of_partition: true,
+            partition_index: 0,
+            picture_id: Some(PictureId::Long(0x1234)),
+            tl0_pic_idx: Some(5),
+            temporal_layer: Some(Vp8TemporalLayer {
+                id: 2,
+                layer_sync: true,
+            }),
+            key_idx: None,
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            descriptor
+                .length_bytes()
+        ]));
+        write_vp8_payload_descriptor(&mut cursor, &descriptor).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(data, [0x90, 0xE0, 0x92, 0x34, 0x05, 0xA0]);
+        assert_eq!(read_vp8_payload_descriptor(&data).unwrap(), descriptor);
+    }
+
+    #[test]
+    fn test_is_keyframe() {
+        // Short picture id, key frame payload header
+        assert!(is_keyframe(&[0x90, 0x80, 0x12, 0x10, 0x02, 0x00]).unwrap());
+        // Inter frame
+        assert!(!is_keyframe(&[0x90, 0x80, 0x12, 0x11, 0x02, 0x00]).unwrap());
+        // Not the start of the frame
+        assert!(!is_keyframe(&[0x00, 0x10]).unwrap());
+        assert!(is_keyframe(&[0x90, 0x80]).is_err());
+    }
+}
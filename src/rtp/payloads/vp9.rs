@@ -0,0 +1,660 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{bit_write::BitWrite, bit_write_exts::BitWriteExts, byte_order::NetworkOrder};
+use bytes::{BufMut, Bytes, BytesMut};
+
+use crate::{error::RtpParseError, LengthBytes};
+
+use super::{depacketizer::PayloadDepacketizer, packetizer::PayloadPacketizer, vp8::PictureId};
+
+// https://datatracker.ietf.org/doc/html/rfc9628#section-4.2
+// Flexible mode (F=1):
+//       0 1 2 3 4 5 6 7
+//      +-+-+-+-+-+-+-+-+
+//      |I|P|L|F|B|E|V|Z| (REQUIRED)
+//      +-+-+-+-+-+-+-+-+
+// I:   |M| PICTURE ID  | (REQUIRED)
+//      +-+-+-+-+-+-+-+-+
+// M:   | EXTENDED PID  | (RECOMMENDED)
+//      +-+-+-+-+-+-+-+-+
+// L:   | TID |U| SID |D| (Conditionally RECOMMENDED)
+//      +-+-+-+-+-+-+-+-+                             -\
+// P,F: | P_DIFF      |N| (Conditionally REQUIRED)    - up to 3 times
+//      +-+-+-+-+-+-+-+-+                             -/
+// V:   | SS            |
+//      | ..            |
+//      +-+-+-+-+-+-+-+-+
+//
+// Non-flexible mode (F=0):
+//       0 1 2 3 4 5 6 7
+//      +-+-+-+-+-+-+-+-+
+//      |I|P|L|F|B|E|V|Z| (REQUIRED)
+//      +-+-+-+-+-+-+-+-+
+// I:   |M| PICTURE ID  | (RECOMMENDED)
+//      +-+-+-+-+-+-+-+-+
+// M:   | EXTENDED PID  | (RECOMMENDED)
+//      +-+-+-+-+-+-+-+-+
+// L:   | TID |U| SID |D| (Conditionally RECOMMENDED)
+//      +-+-+-+-+-+-+-+-+
+//      |   TL0PICIDX   | (Conditionally REQUIRED)
+//      +-+-+-+-+-+-+-+-+
+// V:   | SS            |
+//      | ..            |
+//      +-+-+-+-+-+-+-+-+
+//
+// I: picture id present
+// P: inter-picture predicted
+// L: layer indices present
+// F: flexible mode
+// B: start of frame
+// E: end of frame
+// V: scalability structure present
+// Z: not used as a reference by upper spatial layers
+//
+// https://datatracker.ietf.org/doc/html/rfc9628#section-4.2.1
+// Scalability structure:
+//      +-+-+-+-+-+-+-+-+
+// V:   | N_S |Y|G|-|-|-|
+//      +-+-+-+-+-+-+-+-+              -\
+// Y:   |     WIDTH     | (OPTIONAL)    .
+//      +               +               .
+//      |               | (OPTIONAL)    .
+//      +-+-+-+-+-+-+-+-+               . - N_S + 1 times
+//      |     HEIGHT    | (OPTIONAL)    .
+//      +               +               .
+//      |               | (OPTIONAL)    .
+//      +-+-+-+-+-+-+-+-+              -/
+// G:   |      N_G      | (OPTIONAL)
+//      +-+-+-+-+-+-+-+-+                           -\
+// N_G: | TID |U| R |-|-| (OPTIONAL)                .
+//      +-+-+-+-+-+-+-+-+              -\           . - N_G times
+//      |    P_DIFF     | (OPTIONAL)    . - R times .
+//      +-+-+-+-+-+-+-+-+              -/          -/
+
+const PICTURE_ID_PRESENT_MASK: u8 = 0x80;
+const INTER_PICTURE_PREDICTED_MASK: u8 = 0x40;
+const LAYER_INDICES_PRESENT_MASK: u8 = 0x20;
+const FLEXIBLE_MODE_MASK: u8 = 0x10;
+const START_OF_FRAME_MASK: u8 = 0x08;
+const END_OF_FRAME_MASK: u8 = 0x04;
+const SCALABILITY_STRUCTURE_PRESENT_MASK: u8 = 0x02;
+const NOT_UPPER_SPATIAL_REFERENCE_MASK: u8 = 0x01;
+const MORE_REFERENCES_MASK: u8 = 0x01;
+const RESOLUTIONS_PRESENT_MASK: u8 = 0x10;
+const PICTURE_GROUP_PRESENT_MASK: u8 = 0x08;
+const MAX_REFERENCES: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vp9LayerIndices {
+    /// 3 bits
+    pub temporal_id: u8,
+    /// Whether an upper temporal layer can be switched up to at this frame
+    pub switching_up_point: bool,
+    /// 3 bits
+    pub spatial_id: u8,
+    /// Whether this frame depends on the frame of the next lower spatial layer
+    pub inter_layer_dependency: bool,
+    /// Only present in non-flexible mode
+    pub tl0_pic_idx: Option<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Vp9Resolution {
+    pub width: u16,
+    pub height: u16,
+}
+
+/// A frame in the picture group described by a scalability structure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9PictureGroupFrame {
+    /// 3 bits
+    pub temporal_id: u8,
+    pub switching_up_point: bool,
+    /// The differences between this frame's picture id and those of the frames it references.
+    /// At most 3.
+    pub reference_diffs: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Vp9ScalabilityStructure {
+    /// 1 to 8
+    pub num_spatial_layers: u8,
+    /// The resolution of each spatial layer, if present
+    pub resolutions: Option<Vec<Vp9Resolution>>,
+    /// The picture group description, if present
+    pub picture_group: Option<Vec<Vp9PictureGroupFrame>>,
+}
+
+impl LengthBytes for Vp9ScalabilityStructure {
+    fn length_bytes(&self) -> usize {
+        1 + self
+            .resolutions
+            .as_ref()
+            .map_or(0, |resolutions| resolutions.len() * 4)
+            + self.picture_group.as_ref().map_or(0, |picture_group| {
+                1 + picture_group
+                    .iter()
+                    .map(|frame| 1 + frame.reference_diffs.len())
+                    .sum::<usize>()
+            })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vp9PayloadDescriptor {
+    pub inter_picture_predicted: bool,
+    pub flexible_mode: bool,
+    pub start_of_frame: bool,
+    pub end_of_frame: bool,
+    pub not_upper_spatial_reference: bool,
+    pub picture_id: Option<PictureId>,
+    pub layer_indices: Option<Vp9LayerIndices>,
+    /// The differences between this frame's picture id and those of the frames it references
+    /// (7 bits each, at most 3).  Only present in flexible mode for inter-picture predicted
+    /// frames.
+    pub reference_diffs: Vec<u8>,
+    pub scalability_structure: Option<Vp9ScalabilityStructure>,
+}
+
+impl Vp9PayloadDescriptor {
+    pub fn spatial_id(&self) -> u8 {
+        self.layer_indices
+            .map_or(0, |layer_indices| layer_indices.spatial_id)
+    }
+
+    /// Whether this packet is the first packet of a key frame: the start of a frame that isn't
+    /// inter-picture predicted in the base spatial layer.
+    pub fn is_keyframe(&self) -> bool {
+        self.start_of_frame && !self.inter_picture_predicted && self.spatial_id() == 0
+    }
+}
+
+impl LengthBytes for Vp9PayloadDescriptor {
+    fn length_bytes(&self) -> usize {
+        1 + self
+            .picture_id
+            .map_or(0, |picture_id| picture_id.length_bytes())
+            + self.layer_indices.map_or(0, |layer_indices| {
+                1 + layer_indices.tl0_pic_idx.map_or(0, |_| 1)
+            })
+            + self.reference_diffs.len()
+            + self
+                .scalability_structure
+                .as_ref()
+                .map_or(0, |ss| ss.length_bytes())
+    }
+}
+
+fn read_byte(payload: &[u8], offset: &mut usize, field: &str) -> Result<u8> {
+    let Some(byte) = payload.get(*offset) else {
+        return Err(RtpParseError::truncated(*offset + 1, payload.len()))
+            .with_context(|| format!("VP9 payload descriptor {field}"));
+    };
+    *offset += 1;
+
+    Ok(*byte)
+}
+
+fn read_u16(payload: &[u8], offset: &mut usize, field: &str) -> Result<u16> {
+    Ok(u16::from_be_bytes([
+        read_byte(payload, offset, field)?,
+        read_byte(payload, offset, field)?,
+    ]))
+}
+
+fn read_scalability_structure(
+    payload: &[u8],
+    offset: &mut usize,
+) -> Result<Vp9ScalabilityStructure> {
+    let flags = read_byte(payload, offset, "flags")?;
+    let num_spatial_layers = (flags >> 5) + 1;
+    let resolutions = if flags & RESOLUTIONS_PRESENT_MASK != 0 {
+        Some(
+            (0..num_spatial_layers)
+                .map(|i| {
+                    read_u16(payload, offset, "width")
+                        .and_then(|width| {
+                            Ok(Vp9Resolution {
+                                width,
+                                height: read_u16(payload, offset, "height")?,
+                            })
+                        })
+                        .with_context(|| format!("spatial layer {i} resolution"))
+                })
+                .collect::<Result<Vec<_>>>()?,
+        )
+    } else {
+        None
+    };
+    let picture_group = if flags & PICTURE_GROUP_PRESENT_MASK != 0 {
+        let num_frames = read_byte(payload, offset, "picture group size")?;
+        Some(
+            (0..num_frames)
+                .map(|i| {
+                    let frame = read_byte(payload, offset, "picture group frame")
+                        .with_context(|| format!("picture group frame {i}"))?;
+                    let num_references = (frame >> 2) & 0x3;
+                    Ok(Vp9PictureGroupFrame {
+                        temporal_id: frame >> 5,
+                        switching_up_point: frame & 0x10 != 0,
+                        reference_diffs: (0..num_references)
+                            .map(|_| read_byte(payload, offset, "reference diff"))
+                            .collect::<Result<Vec<_>>>()
+                            .with_context(|| format!("picture group frame {i}"))?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?,
+        )
+    } else {
+        None
+    };
+
+    Ok(Vp9ScalabilityStructure {
+        num_spatial_layers,
+        resolutions,
+        picture_group,
+    })
+}
+
+/// Read the payload descriptor from the start of a VP9 RTP payload.  The VP9 payload starts
+/// [`LengthBytes::length_bytes`] bytes in.
+pub fn read_vp9_payload_descriptor(payload: &[u8]) -> Result<Vp9PayloadDescriptor> {
+    let Some(first) = payload.first() else {
+        bail!(RtpParseError::truncated(1, 0));
+    };
+    let mut descriptor = Vp9PayloadDescriptor {
+        inter_picture_predicted: first & INTER_PICTURE_PREDICTED_MASK != 0,
+        flexible_mode: first & FLEXIBLE_MODE_MASK != 0,
+        start_of_frame: first & START_OF_FRAME_MASK != 0,
+        end_of_frame: first & END_OF_FRAME_MASK != 0,
+        not_upper_spatial_reference: first & NOT_UPPER_SPATIAL_REFERENCE_MASK != 0,
+        ..Default::default()
+    };
+    let mut offset = 1;
+    if first & PICTURE_ID_PRESENT_MASK != 0 {
+        let picture_id = PictureId::read(&payload[offset..]).context("picture id")?;
+        offset += picture_id.length_bytes();
+        descriptor.picture_id = Some(picture_id);
+    }
+    if first & LAYER_INDICES_PRESENT_MASK != 0 {
+        let layer_indices = read_byte(payload, &mut offset, "layer indices")?;
+        let tl0_pic_idx = if descriptor.flexible_mode {
+            None
+        } else {
+            Some(read_byte(payload, &mut offset, "TL0PICIDX")?)
+        };
+        descriptor.layer_indices = Some(Vp9LayerIndices {
+            temporal_id: layer_indices >> 5,
+            switching_up_point: layer_indices & 0x10 != 0,
+            spatial_id: (layer_indices >> 1) & 0x7,
+            inter_layer_dependency: layer_indices & 0x1 != 0,
+            tl0_pic_idx,
+        });
+    }
+    if descriptor.flexible_mode && descriptor.inter_picture_predicted {
+        loop {
+            if descriptor.reference_diffs.len() == MAX_REFERENCES {
+                bail!(RtpParseError::invalid_field(
+                    "VP9 reference index count",
+                    format!("at most {MAX_REFERENCES}"),
+                    format!("more than {MAX_REFERENCES}")
+                ));
+            }
+            let reference = read_byte(payload, &mut offset, "reference index")?;
+            descriptor.reference_diffs.push(reference >> 1);
+            if reference & MORE_REFERENCES_MASK == 0 {
+                break;
+            }
+        }
+    }
+    if first & SCALABILITY_STRUCTURE_PRESENT_MASK != 0 {
+        descriptor.scalability_structure = Some(
+            read_scalability_structure(payload, &mut offset).context("scalability structure")?,
+        );
+    }
+
+    Ok(descriptor)
+}
+
+fn write_scalability_structure<W: BitWrite>(
+    buf: &mut W,
+    ss: &Vp9ScalabilityStructure,
+) -> Result<()> {
+    if !(1..=8).contains(&ss.num_spatial_layers) {
+        bail!(
+            "Number of spatial layers must be between 1 and 8, got {}",
+            ss.num_spatial_layers
+        );
+    }
+    let mut flags = (ss.num_spatial_layers - 1) << 5;
+    if let Some(resolutions) = &ss.resolutions {
+        if resolutions.len() != ss.num_spatial_layers as usize {
+            bail!(
+                "Scalability structure has {} spatial layers but {} resolutions",
+                ss.num_spatial_layers,
+                resolutions.len()
+            );
+        }
+        flags |= RESOLUTIONS_PRESENT_MASK;
+    }
+    if ss.picture_group.is_some() {
+        flags |= PICTURE_GROUP_PRESENT_MASK;
+    }
+    buf.write_u8(flags).context("flags")?;
+    for (i, resolution) in ss.resolutions.iter().flatten().enumerate() {
+        buf.write_u16::<NetworkOrder>(resolution.width)
+            .and_then(|_| buf.write_u16::<NetworkOrder>(resolution.height))
+            .with_context(|| format!("spatial layer {i} resolution"))?;
+    }
+    if let Some(picture_group) = &ss.picture_group {
+        let Ok(num_frames) = u8::try_from(picture_group.len()) else {
+            bail!(
+                "Picture group has {} frames, which is more than the max 255",
+                picture_group.len()
+            );
+        };
+        buf.write_u8(num_frames).context("picture group size")?;
+        for (i, frame) in picture_group.iter().enumerate() {
+            if frame.reference_diffs.len() > MAX_REFERENCES {
+                bail!(
+                    "Picture group frame {i} has {} references, which is more than the max \
+                    {MAX_REFERENCES}",
+                    frame.reference_diffs.len()
+                );
+            }
+            let mut byte =
+                ((frame.temporal_id & 0x7) << 5) | ((frame.reference_diffs.len() as u8) << 2);
+            if frame.switching_up_point {
+                byte |= 0x10;
+            }
+            buf.write_u8(byte)
+                .with_context(|| format!("picture group frame {i}"))?;
+            for reference_diff in &frame.reference_diffs {
+                buf.write_u8(*reference_diff)
+                    .with_context(|| format!("picture group frame {i} reference diff"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn write_vp9_payload_descriptor<W: BitWrite>(
+    buf: &mut W,
+    descriptor: &Vp9PayloadDescriptor,
+) -> Result<()> {
+    let mut first = 0;
+    for (set, mask) in [
+        (descriptor.picture_id.is_some(), PICTURE_ID_PRESENT_MASK),
+        (
+            descriptor.inter_picture_predicted,
+            INTER_PICTURE_PREDICTED_MASK,
+        ),
+        (
+            descriptor.layer_indices.is_some(),
+            LAYER_INDICES_PRESENT_MASK,
+        ),
+        (descriptor.flexible_mode, FLEXIBLE_MODE_MASK),
+        (descriptor.start_of_frame, START_OF_FRAME_MASK),
+        (descriptor.end_of_frame, END_OF_FRAME_MASK),
+        (
+            descriptor.scalability_structure.is_some(),
+            SCALABILITY_STRUCTURE_PRESENT_MASK,
+        ),
+        (
+            descriptor.not_upper_spatial_reference,
+            NOT_UPPER_SPATIAL_REFERENCE_MASK,
+        ),
+    ] {
+        if set {
+            first |= mask;
+        }
+    }
+    buf.write_u8(first).context("flags")?;
+    if let Some(picture_id) = &descriptor.picture_id {
+        picture_id.write(buf).context("picture id")?;
+    }
+    if let Some(layer_indices) = &descriptor.layer_indices {
+        let mut byte =
+            ((layer_indices.temporal_id & 0x7) << 5) | ((layer_indices.spatial_id & 0x7) << 1);
+        if layer_indices.switching_up_point {
+            byte |= 0x10;
+        }
+        if layer_indices.inter_layer_dependency {
+            byte |= 0x1;
+        }
+        buf.write_u8(byte).context("layer indices")?;
+        match (descriptor.flexible_mode, layer_indices.tl0_pic_idx) {
+            (false, Some(tl0_pic_idx)) => buf.write_u8(tl0_pic_idx).context("TL0PICIDX")?,
+            (false, None) => bail!("TL0PICIDX is required in non-flexible mode"),
+            (true, Some(_)) => bail!("TL0PICIDX isn't allowed in flexible mode"),
+            (true, None) => {}
+        }
+    }
+    let references_expected = descriptor.flexible_mode && descriptor.inter_picture_predicted;
+    if references_expected && !(1..=MAX_REFERENCES).contains(&descriptor.reference_diffs.len()) {
+        bail!(
+            "Inter-picture predicted frames in flexible mode must have between 1 and \
+            {MAX_REFERENCES} references, got {}",
+            descriptor.reference_diffs.len()
+        );
+    }
+    if !references_expected && !descriptor.reference_diffs.is_empty() {
+        bail!("Reference indices are only allowed for inter-picture predicted frames in flexible mode");
+    }
+    for (i, reference_diff) in descriptor.reference_diffs.iter().enumerate() {
+        let mut byte = reference_diff << 1;
+        if i + 1 < descriptor.reference_diffs.len() {
+            byte |= MORE_REFERENCES_MASK;
+        }
+        buf.write_u8(byte)
+            .with_context(|| format!("reference index {i}"))?;
+    }
+    if let Some(ss) = &descriptor.scalability_structure {
+        write_scalability_structure(buf, ss).context("scalability structure")?;
+    }
+
+    Ok(())
+}
+
+/// Whether the given VP9 RTP payload is the first packet of a key frame
+pub fn is_keyframe(payload: &[u8]) -> Result<bool> {
+    Ok(read_vp9_payload_descriptor(payload)
+        .context("payload descriptor")?
+        .is_keyframe())
+}
+
+/// Reassembles the frames of each VP9 spatial layer from the payloads of their packets
+#[derive(Debug, Default)]
+pub struct Vp9Depacketizer {
+    layer_frame: Option<BytesMut>,
+}
+
+impl PayloadDepacketizer for Vp9Depacketizer {
+    fn depacketize(&mut self, payload: Bytes) -> Result<Vec<Bytes>> {
+        let descriptor = read_vp9_payload_descriptor(&payload).context("payload descriptor")?;
+        if descriptor.start_of_frame {
+            self.layer_frame = Some(BytesMut::new());
+        }
+        let Some(layer_frame) = self.layer_frame.as_mut() else {
+            // The start of this layer's frame was lost
+            return Ok(Vec::new());
+        };
+        layer_frame.extend_from_slice(&payload[descriptor.length_bytes()..]);
+        if descriptor.end_of_frame {
+            return Ok(self.finish_frame());
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn finish_frame(&mut self) -> Vec<Bytes> {
+        self.layer_frame
+            .take()
+            .map(|layer_frame| layer_frame.freeze())
+            .into_iter()
+            .collect()
+    }
+
+    fn discard_frame(&mut self) {
+        self.layer_frame = None;
+    }
+}
+
+/// Whether the given VP9 frame is an inter frame, according to its uncompressed header
+fn is_inter_frame(frame: &[u8]) -> bool {
+    // https://storage.googleapis.com/downloads.webmproject.org/docs/vp9/vp9-bitstream-specification-v0.6-20160331-draft.pdf
+    // section 6.2:
+    // frame_marker (2 bits), profile_low_bit, profile_high_bit, reserved_zero (only for profile
+    // 3), show_existing_frame, frame_type (0 for key frames)
+    let Some(first) = frame.first() else {
+        return true;
+    };
+    let profile = ((first >> 5) & 0x1) | ((first >> 3) & 0x2);
+    let show_existing_frame_bit = if profile == 3 { 2 } else { 3 };
+    let show_existing_frame = (first >> show_existing_frame_bit) & 0x1 != 0;
+    let frame_type = (first >> (show_existing_frame_bit - 1)) & 0x1;
+    show_existing_frame || frame_type != 0
+}
+
+/// Splits VP9 frames into RTP payloads of at most `mtu` bytes, each with a minimal
+/// (non-flexible, without a picture id or layer indices) payload descriptor
+#[derive(Debug, Clone)]
+pub struct Vp9Packetizer {
+    mtu: usize,
+}
+
+impl Vp9Packetizer {
+    pub fn new(mtu: usize) -> Result<Self> {
+        if mtu <= 1 {
+            bail!("MTU must be more than 1 byte, got {mtu}");
+        }
+
+        Ok(Self { mtu })
+    }
+
+    pub fn mtu(&self) -> usize {
+        self.mtu
+    }
+}
+
+impl PayloadPacketizer for Vp9Packetizer {
+    fn packetize_frame(&self, units: &[Bytes]) -> Result<Vec<Bytes>> {
+        let mut payloads = Vec::new();
+        for frame in units {
+            let mut flags = 0;
+            if is_inter_frame(frame) {
+                flags |= INTER_PICTURE_PREDICTED_MASK;
+            }
+            let chunks = frame.chunks(self.mtu - 1);
+            let num_chunks = chunks.len();
+            for (i, chunk) in chunks.enumerate() {
+                let mut descriptor = flags;
+                if i == 0 {
+                    descriptor |= START_OF_FRAME_MASK;
+                }
+                if i + 1 == num_chunks {
+                    descriptor |= END_OF_FRAME_MASK;
+                }
+                let mut payload = BytesMut::with_capacity(1 + chunk.len());
+                payload.put_u8(descriptor);
+                payload.extend_from_slice(chunk);
+                payloads.push(payload.freeze());
+            }
+        }
+
+        Ok(payloads)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    fn roundtrip(descriptor: &Vp9PayloadDescriptor) -> Vec<u8> {
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            descriptor
+                .length_bytes()
+        ]));
+        write_vp9_payload_descriptor(&mut cursor, descriptor).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(&read_vp9_payload_descriptor(&data).unwrap(), descriptor);
+        data
+    }
+
+    #[test]
+    fn test_non_flexible_keyframe_with_ss() {
+        let descriptor = Vp9PayloadDescriptor {
+            start_of_frame: true,
+            picture_id: Some(PictureId::Long(300)),
+            layer_indices: Some(Vp9LayerIndices {
+                temporal_id: 0,
+                switching_up_point: false,
+                spatial_id: 0,
+                inter_layer_dependency: false,
+                tl0_pic_idx: Some(7),
+            }),
+            scalability_structure: Some(Vp9ScalabilityStructure {
+                num_spatial_layers: 2,
+                resolutions: Some(vec![
+                    Vp9Resolution {
+                        width: 640,
+                        height: 360,
+                    },
+                    Vp9Resolution {
+                        width: 1280,
+                        height: 720,
+                    },
+                ]),
+                picture_group: Some(vec![
+                    Vp9PictureGroupFrame {
+                        temporal_id: 0,
+                        switching_up_point: false,
+                        reference_diffs: vec![2],
+                    },
+                    Vp9PictureGroupFrame {
+                        temporal_id: 1,
+                        switching_up_point: true,
+                        reference_diffs: vec![1],
+                    },
+                ]),
+            }),
+            ..Default::default()
+        };
+        let data = roundtrip(&descriptor);
+        assert_eq!(&data[..6], [0xAA, 0x81, 0x2C, 0x00, 0x07, 0x38]);
+        assert!(descriptor.is_keyframe());
+        assert!(is_keyframe(&data).unwrap());
+    }
+
+    #[test]
+    fn test_flexible_references() {
+        let descriptor = Vp9PayloadDescriptor {
+            inter_picture_predicted: true,
+            flexible_mode: true,
+            start_of_frame: true,
+            end_of_frame: true,
+            picture_id: Some(PictureId::Short(5)),
+            layer_indices: Some(Vp9LayerIndices {
+                temporal_id: 1,
+                switching_up_point: true,
+                spatial_id: 1,
+                inter_layer_dependency: true,
+                tl0_pic_idx: None,
+            }),
+            reference_diffs: vec![1, 4],
+            ..Default::default()
+        };
+        let data = roundtrip(&descriptor);
+        assert_eq!(data, [0xFC, 0x05, 0x33, 0x03, 0x08]);
+        assert!(!descriptor.is_keyframe());
+
+        // Too many references
+        assert!(read_vp9_payload_descriptor(&[0x50, 0x03, 0x03, 0x03, 0x03]).is_err());
+        // Truncated
+        assert!(read_vp9_payload_descriptor(&[0x50, 0x03]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/playout-delay
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=2 |       MIN delay       |       MAX delay       |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The minimum and maximum delays are in units of 10ms.
+
+const GRANULARITY_MS: u64 = 10;
+const MAX_VALUE: u16 = (1 << 12) - 1;
+/// The largest delay which can be represented: 4095 * 10ms
+pub const MAX_PLAYOUT_DELAY: Duration = Duration::from_millis(MAX_VALUE as u64 * GRANULARITY_MS);
+
+/// The range of delays the sender would like the receiver to use between capture and render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayoutDelay {
+    min: u16,
+    max: u16,
+}
+
+impl PlayoutDelay {
+    /// The delays must be at most [`MAX_PLAYOUT_DELAY`], and `min` must not be larger than `max`.
+    /// They're rounded down to a multiple of 10ms.
+    pub fn new(min: Duration, max: Duration) -> Result<Self> {
+        if min > MAX_PLAYOUT_DELAY || max > MAX_PLAYOUT_DELAY {
+            bail!(
+                "Playout delays must be at most {MAX_PLAYOUT_DELAY:?}, got min {min:?} max {max:?}"
+            );
+        }
+        if min > max {
+            bail!("Minimum playout delay {min:?} is larger than maximum {max:?}");
+        }
+        let to_value = |delay: Duration| (delay.as_millis() as u64 / GRANULARITY_MS) as u16;
+
+        Ok(PlayoutDelay {
+            min: to_value(min),
+            max: to_value(max),
+        })
+    }
+
+    pub fn min(&self) -> Duration {
+        Duration::from_millis(self.min as u64 * GRANULARITY_MS)
+    }
+
+    pub fn max(&self) -> Duration {
+        Duration::from_millis(self.max as u64 * GRANULARITY_MS)
+    }
+}
+
+impl RtpHeaderExtensionValue for PlayoutDelay {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/playout-delay";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let [b0, b1, b2] = data else {
+            bail!(RtpParseError::invalid_field(
+                "playout delay length",
+                "3 bytes",
+                format!("{} bytes", data.len())
+            ));
+        };
+        let min = (*b0 as u16) << 4 | (*b1 as u16) >> 4;
+        let max = (*b1 as u16 & 0xF) << 8 | *b2 as u16;
+        if min > max {
+            bail!(RtpParseError::invalid_field(
+                "minimum playout delay",
+                format!("at most the maximum ({max})"),
+                min
+            ));
+        }
+
+        Ok(PlayoutDelay { min, max })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        vec![
+            (self.min >> 4) as u8,
+            ((self.min & 0xF) << 4) as u8 | (self.max >> 8) as u8,
+            self.max as u8,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_playout_delay_roundtrip() {
+        let playout_delay =
+            PlayoutDelay::new(Duration::from_millis(105), Duration::from_millis(40950)).unwrap();
+        assert_eq!(playout_delay.min(), Duration::from_millis(100));
+        assert_eq!(playout_delay.max(), MAX_PLAYOUT_DELAY);
+        assert_eq!(playout_delay.encode(), vec![0x00, 0xAF, 0xFF]);
+        assert_eq!(
+            PlayoutDelay::parse(&playout_delay.encode()).unwrap(),
+            playout_delay
+        );
+    }
+
+    #[test]
+    fn test_playout_delay_validation() {
+        assert!(PlayoutDelay::new(Duration::ZERO, Duration::from_millis(40960)).is_err());
+        assert!(PlayoutDelay::new(Duration::from_millis(20), Duration::from_millis(10)).is_err());
+        assert!(PlayoutDelay::parse(&[0x00, 0x20, 0x01]).is_err());
+    }
+}
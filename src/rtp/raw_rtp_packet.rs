@@ -0,0 +1,293 @@
+use std::ops::Range;
+
+use anyhow::{bail, Result};
+use bit_cursor::nsw_types::u7;
+
+use crate::error::RtpParseError;
+
+use super::{
+    header_extensions::{
+        HeaderExtensionConfig, OneByteHeaderExtension, RtpHeaderExtensionValue,
+        TwoByteHeaderExtension,
+    },
+    rtp_header::RtpHeader,
+};
+
+/// Where a header extension element is in a serialized packet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionElementRange {
+    pub id: u8,
+    /// The whole element, including its id and length
+    pub element: Range<usize>,
+    /// Just the element's data
+    pub data: Range<usize>,
+}
+
+/// A mutable view over a serialized RTP packet which reads and patches header fields directly in
+/// the buffer, without parsing or re-serializing the rest of the packet.  This is useful when
+/// forwarding packets, where only a few header fields need to be rewritten.
+#[derive(Debug)]
+pub struct RawRtpPacketMut<'a> {
+    buf: &'a mut [u8],
+}
+
+impl<'a> RawRtpPacketMut<'a> {
+    /// Fails if the buffer is too short to contain the fixed RTP header and its csrcs.
+    pub fn new(buf: &'a mut [u8]) -> Result<Self> {
+        if buf.len() < 12 {
+            bail!(RtpParseError::truncated(12, buf.len()));
+        }
+        let header_length_bytes = RtpHeader::extensions_start_offset(buf);
+        if buf.len() < header_length_bytes {
+            bail!(RtpParseError::truncated(header_length_bytes, buf.len()));
+        }
+
+        Ok(Self { buf })
+    }
+
+    pub fn marked(&self) -> bool {
+        RtpHeader::marked(self.buf)
+    }
+
+    pub fn set_marked(&mut self, marked: bool) {
+        RtpHeader::set_marked(self.buf, marked);
+    }
+
+    pub fn payload_type(&self) -> u7 {
+        RtpHeader::payload_type(self.buf)
+    }
+
+    pub fn set_payload_type(&mut self, payload_type: u7) {
+        RtpHeader::set_payload_type(self.buf, payload_type);
+    }
+
+    pub fn seq_num(&self) -> u16 {
+        RtpHeader::seq_num(self.buf)
+    }
+
+    pub fn set_seq_num(&mut self, seq_num: u16) {
+        RtpHeader::set_seq_num(self.buf, seq_num);
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        RtpHeader::timestamp(self.buf)
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        RtpHeader::set_timestamp(self.buf, timestamp);
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        RtpHeader::ssrc(self.buf)
+    }
+
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        RtpHeader::set_ssrc(self.buf, ssrc);
+    }
+
+    /// The data of the header extension with the given id, which can be modified in place.
+    /// Returns None if the packet has no extension with the given id (or its extensions are
+    /// malformed).
+    pub fn extension_data_mut(&mut self, id: u8) -> Option<&mut [u8]> {
+        let range = self.extension_data_range(id)?;
+        Some(&mut self.buf[range])
+    }
+
+    /// Encode the given value over the data of the existing header extension with the given id.
+    /// Because the packet isn't re-serialized, the encoded value must be the same length as the
+    /// existing data.
+    pub fn set_extension_in_place<T: RtpHeaderExtensionValue>(
+        &mut self,
+        id: u8,
+        value: &T,
+    ) -> Result<()> {
+        let encoded = value.encode();
+        let Some(data) = self.extension_data_mut(id) else {
+            bail!("Packet has no {} extension with id {id}", T::URI);
+        };
+        if data.len() != encoded.len() {
+            bail!(
+                "Can't replace {} byte extension {id} in place with {} bytes",
+                data.len(),
+                encoded.len()
+            );
+        }
+        data.copy_from_slice(&encoded);
+
+        Ok(())
+    }
+
+    /// The ranges in the buffer of the data of the extensions which are marked as encrypted in
+    /// the given config, which an SRTP layer should encrypt or decrypt in place (per
+    /// https://datatracker.ietf.org/doc/html/rfc6904).
+    pub fn encrypted_extension_data_ranges(
+        &self,
+        config: &HeaderExtensionConfig,
+    ) -> Vec<Range<usize>> {
+        self.extension_element_ranges()
+            .into_iter()
+            .filter(|range| config.is_encrypted(range.id))
+            .map(|range| range.data)
+            .collect()
+    }
+
+    /// The ranges in the buffer of each of the packet's extension elements (not including
+    /// padding), in order.  Returns an empty list if the packet has no extensions, they use an
+    /// unknown (or cryptex) profile, or they're malformed.
+    pub fn extension_element_ranges(&self) -> Vec<ExtensionElementRange> {
+        self.walk_extension_elements().unwrap_or_default()
+    }
+
+    fn extension_data_range(&self, id: u8) -> Option<Range<usize>> {
+        if id == 0 {
+            return None;
+        }
+        self.walk_extension_elements()?
+            .into_iter()
+            .find(|range| range.id == id)
+            .map(|range| range.data)
+    }
+
+    /// Find the ranges of the extension elements in the buffer.  Returns None if the extensions
+    /// are malformed.
+    fn walk_extension_elements(&self) -> Option<Vec<ExtensionElementRange>> {
+        let mut ranges = Vec::new();
+        if !RtpHeader::has_extensions(self.buf) {
+            return Some(ranges);
+        }
+        let start = RtpHeader::extensions_start_offset(self.buf);
+        let block_header = self.buf.get(start..start + 4)?;
+        let profile = u16::from_be_bytes([block_header[0], block_header[1]]);
+        let length_bytes = u16::from_be_bytes([block_header[2], block_header[3]]) as usize * 4;
+        let two_byte = if TwoByteHeaderExtension::type_matches(profile) {
+            true
+        } else if OneByteHeaderExtension::type_matches(profile) {
+            false
+        } else {
+            return None;
+        };
+        let end = (start + 4 + length_bytes).min(self.buf.len());
+
+        let mut position = start + 4;
+        while position < end {
+            let element_id = if two_byte {
+                self.buf[position]
+            } else {
+                self.buf[position] >> 4
+            };
+            if element_id == 0 {
+                // Padding
+                position += 1;
+                continue;
+            }
+            if !two_byte && element_id == 15 {
+                break;
+            }
+            let (data_start, data_length) = if two_byte {
+                (position + 2, *self.buf.get(position + 1)? as usize)
+            } else {
+                (position + 1, (self.buf[position] & 0xF) as usize + 1)
+            };
+            if data_start + data_length > end {
+                return None;
+            }
+            ranges.push(ExtensionElementRange {
+                id: element_id,
+                element: position..data_start + data_length,
+                data: data_start..data_start + data_length,
+            });
+            position = data_start + data_length;
+        }
+
+        Some(ranges)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.buf
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtp::rtp_packet::read_rtp_packet;
+
+    use super::*;
+
+    #[test]
+    fn test_set_fields() {
+        #[rustfmt::skip]
+        let mut data: Vec<u8> = vec![
+            0x80, 0x6f, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0xAA, 0xBB,
+        ];
+        let mut packet = RawRtpPacketMut::new(&mut data).unwrap();
+        packet.set_marked(true);
+        packet.set_payload_type(u7::new(96));
+        packet.set_seq_num(0x1234);
+        packet.set_timestamp(0x56789abc);
+        packet.set_ssrc(0xdeadbeef);
+        assert!(packet.marked());
+        assert_eq!(packet.payload_type(), u7::new(96));
+
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert!(read_packet.marked());
+        assert_eq!(read_packet.payload_type(), u7::new(96));
+        assert_eq!(read_packet.seq_num(), 0x1234);
+        assert_eq!(read_packet.timestamp(), 0x56789abc);
+        assert_eq!(read_packet.ssrc(), 0xdeadbeef);
+        assert_eq!(read_packet.payload(), [0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn test_too_short() {
+        let mut data = [0x81, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        assert!(RawRtpPacketMut::new(&mut data).is_err());
+        assert!(RawRtpPacketMut::new(&mut data[..4]).is_err());
+    }
+
+    #[test]
+    fn test_extension_element_ranges() {
+        #[rustfmt::skip]
+        let mut data: Vec<u8> = vec![
+            0x90, 0x6f, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0xbe, 0xde, 0x00, 0x02, 0x10, 0xAA, 0x21, 0xBB, 0xCC, 0x00, 0x00, 0x00,
+            0x01,
+        ];
+        let mut packet = RawRtpPacketMut::new(&mut data).unwrap();
+        assert_eq!(
+            packet.extension_element_ranges(),
+            vec![
+                ExtensionElementRange {
+                    id: 1,
+                    element: 16..18,
+                    data: 17..18,
+                },
+                ExtensionElementRange {
+                    id: 2,
+                    element: 18..21,
+                    data: 19..21,
+                },
+            ]
+        );
+
+        let config = HeaderExtensionConfig::default().with_encrypted_id(2);
+        for range in packet.encrypted_extension_data_ranges(&config) {
+            packet.as_bytes_mut()[range]
+                .iter_mut()
+                .for_each(|b| *b ^= 0xFF);
+        }
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert_eq!(
+            read_packet.get_extension_by_id(1).unwrap().data().as_ref(),
+            [0xAA]
+        );
+        assert_eq!(
+            read_packet.get_extension_by_id(2).unwrap().data().as_ref(),
+            [0x44, 0x33]
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_write::BitWrite, bit_write_exts::BitWriteExts, byte_order::NetworkOrder, nsw_types::u7,
+};
+use bytes::Bytes;
+
+use crate::{error::RtpParseError, LengthBytes};
+
+// https://datatracker.ietf.org/doc/html/rfc2198#section-3
+// Each redundant block has a 4 byte header:
+//  0                   1                    2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |F|   block PT  |  timestamp offset         |   block length    |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// And the final (primary) block has a 1 byte header, whose length is the rest of the payload:
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// |0|   Block PT  |
+// +-+-+-+-+-+-+-+-+
+//
+// The headers of all the blocks come first, followed by the data of each block in the same
+// order.  F is set if another header follows.  The timestamp offset is how much earlier the
+// redundant block's timestamp is than the packet's.
+
+const FOLLOWS_MASK: u8 = 0x80;
+const MAX_TIMESTAMP_OFFSET: u16 = 0x3FFF;
+const MAX_BLOCK_LENGTH: usize = 0x3FF;
+
+/// A redundant encoding carried in a RED payload
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedBlock {
+    pub payload_type: u7,
+    /// How much earlier (in RTP timestamp units) this block's timestamp is than the packet's.
+    /// 14 bits.
+    pub timestamp_offset: u16,
+    /// At most 1023 bytes
+    pub data: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedPayload {
+    /// The redundant blocks, oldest first
+    pub redundant_blocks: Vec<RedBlock>,
+    pub primary_payload_type: u7,
+    pub primary_data: Bytes,
+}
+
+impl LengthBytes for RedPayload {
+    fn length_bytes(&self) -> usize {
+        self.redundant_blocks
+            .iter()
+            .map(|block| 4 + block.data.len())
+            .sum::<usize>()
+            + 1
+            + self.primary_data.len()
+    }
+}
+
+/// Read a RED payload.  The blocks' data are slices of `payload` rather than copies.
+pub fn read_red_payload(payload: Bytes) -> Result<RedPayload> {
+    let mut headers = Vec::new();
+    let mut offset = 0;
+    loop {
+        let Some(first) = payload.get(offset) else {
+            bail!(RtpParseError::truncated(offset + 1, payload.len()));
+        };
+        let payload_type = u7::new(first & 0x7F);
+        if first & FOLLOWS_MASK == 0 {
+            offset += 1;
+            headers.push((payload_type, 0, None));
+            break;
+        }
+        let Some(header) = payload.get(offset..offset + 4) else {
+            return Err(RtpParseError::truncated(offset + 4, payload.len()))
+                .with_context(|| format!("block header {}", headers.len()));
+        };
+        let timestamp_offset = u16::from_be_bytes([header[1], header[2]]) >> 2;
+        let length = (((header[2] & 0x3) as usize) << 8) | header[3] as usize;
+        headers.push((payload_type, timestamp_offset, Some(length)));
+        offset += 4;
+    }
+
+    let mut redundant_blocks = Vec::with_capacity(headers.len() - 1);
+    let mut primary_payload_type = u7::new(0);
+    for (i, (payload_type, timestamp_offset, length)) in headers.into_iter().enumerate() {
+        match length {
+            Some(length) => {
+                let Some(data) = payload.get(offset..offset + length) else {
+                    return Err(RtpParseError::truncated(offset + length, payload.len()))
+                        .with_context(|| format!("block {i}"));
+                };
+                redundant_blocks.push(RedBlock {
+                    payload_type,
+                    timestamp_offset,
+                    data: payload.slice_ref(data),
+                });
+                offset += length;
+            }
+            None => primary_payload_type = payload_type,
+        }
+    }
+
+    Ok(RedPayload {
+        redundant_blocks,
+        primary_payload_type,
+        primary_data: payload.slice(offset..),
+    })
+}
+
+pub fn write_red_payload<W: BitWrite>(buf: &mut W, red_payload: &RedPayload) -> Result<()> {
+    for (i, block) in red_payload.redundant_blocks.iter().enumerate() {
+        if block.timestamp_offset > MAX_TIMESTAMP_OFFSET {
+            bail!(
+                "RED block {i} timestamp offset {} is larger than the max {MAX_TIMESTAMP_OFFSET}",
+                block.timestamp_offset
+            );
+        }
+        if block.data.len() > MAX_BLOCK_LENGTH {
+            bail!(
+                "RED block {i} is {} bytes, which is larger than the max {MAX_BLOCK_LENGTH}",
+                block.data.len()
+            );
+        }
+        buf.write_u8(FOLLOWS_MASK | u8::from(block.payload_type))
+            .with_context(|| format!("block {i} payload type"))?;
+        let offset_and_length = ((block.timestamp_offset as u32) << 10) | block.data.len() as u32;
+        buf.write_u8((offset_and_length >> 16) as u8)
+            .and_then(|_| buf.write_u16::<NetworkOrder>(offset_and_length as u16))
+            .with_context(|| format!("block {i} timestamp offset and length"))?;
+    }
+    buf.write_u8(u8::from(red_payload.primary_payload_type))
+        .context("primary payload type")?;
+    for (i, block) in red_payload.redundant_blocks.iter().enumerate() {
+        std::io::Write::write_all(buf, &block.data).with_context(|| format!("block {i} data"))?;
+    }
+    std::io::Write::write_all(buf, &red_payload.primary_data).context("primary data")?;
+
+    Ok(())
+}
+
+/// Builds a [`RedPayload`] from the timestamped encodings it should carry
+#[derive(Debug)]
+pub struct RedPayloadBuilder {
+    timestamp: u32,
+    primary_payload_type: u7,
+    primary_data: Bytes,
+    redundant_encodings: Vec<(u7, u32, Bytes)>,
+}
+
+impl RedPayloadBuilder {
+    /// Start a RED payload whose primary encoding has the given payload type and data.
+    /// `timestamp` is the RTP timestamp of the packet the payload will be sent in (and of the
+    /// primary encoding).
+    pub fn new(timestamp: u32, primary_payload_type: u7, primary_data: Bytes) -> Self {
+        Self {
+            timestamp,
+            primary_payload_type,
+            primary_data,
+            redundant_encodings: Vec::new(),
+        }
+    }
+
+    /// Add a redundant encoding with the given RTP timestamp.  Encodings should be added oldest
+    /// first.
+    pub fn redundant_encoding(mut self, payload_type: u7, timestamp: u32, data: Bytes) -> Self {
+        self.redundant_encodings
+            .push((payload_type, timestamp, data));
+        self
+    }
+
+    pub fn build(self) -> Result<RedPayload> {
+        let redundant_blocks = self
+            .redundant_encodings
+            .into_iter()
+            .map(|(payload_type, timestamp, data)| {
+                let timestamp_offset = self.timestamp.wrapping_sub(timestamp);
+                if timestamp_offset > MAX_TIMESTAMP_OFFSET as u32 {
+                    bail!(
+                        "Redundant encoding with timestamp {timestamp} is too old for a packet with \
+                        timestamp {}",
+                        self.timestamp
+                    );
+                }
+                if data.len() > MAX_BLOCK_LENGTH {
+                    bail!(
+                        "Redundant encoding is {} bytes, which is larger than the max {MAX_BLOCK_LENGTH}",
+                        data.len()
+                    );
+                }
+                Ok(RedBlock {
+                    payload_type,
+                    timestamp_offset: timestamp_offset as u16,
+                    data,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(RedPayload {
+            redundant_blocks,
+            primary_payload_type: self.primary_payload_type,
+            primary_data: self.primary_data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_read_rfc_example() {
+        // The example from RFC 2198 section 3: a primary and one redundant block
+        #[rustfmt::skip]
+        let payload = Bytes::from_static(&[
+            // F=1, PT=0, timestamp offset=320, length=4
+            0x80, 0x05, 0x00, 0x04,
+            // F=0, PT=5
+            0x05,
+            0x01, 0x02, 0x03, 0x04,
+            0x0A, 0x0B,
+        ]);
+        let red_payload = read_red_payload(payload.clone()).unwrap();
+        assert_eq!(
+            red_payload.redundant_blocks,
+            vec![RedBlock {
+                payload_type: u7::new(0),
+                timestamp_offset: 320,
+                data: Bytes::from_static(&[1, 2, 3, 4]),
+            }]
+        );
+        assert_eq!(red_payload.primary_payload_type, u7::new(5));
+        assert_eq!(red_payload.primary_data.as_ref(), [0x0A, 0x0B]);
+        assert_eq!(red_payload.length_bytes(), payload.len());
+
+        assert!(read_red_payload(Bytes::from_static(&[0x80, 0x05, 0x00, 0x04, 0x05])).is_err());
+        assert!(read_red_payload(Bytes::from_static(&[0x80])).is_err());
+    }
+
+    #[test]
+    fn test_builder_roundtrip() {
+        let red_payload = RedPayloadBuilder::new(10_000, u7::new(111), Bytes::from_static(&[3; 5]))
+            .redundant_encoding(u7::new(111), 9_040, Bytes::from_static(&[1; 3]))
+            .redundant_encoding(u7::new(111), 9_520, Bytes::from_static(&[2; 4]))
+            .build()
+            .unwrap();
+        assert_eq!(red_payload.redundant_blocks[0].timestamp_offset, 960);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            red_payload
+                .length_bytes()
+        ]));
+        write_red_payload(&mut cursor, &red_payload).unwrap();
+        let data = Bytes::from(cursor.into_inner().into_vec());
+        assert_eq!(read_red_payload(data).unwrap(), red_payload);
+
+        assert!(RedPayloadBuilder::new(100_000, u7::new(111), Bytes::new())
+            .redundant_encoding(u7::new(111), 0, Bytes::new())
+            .build()
+            .is_err());
+    }
+}
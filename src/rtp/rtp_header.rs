@@ -1,5 +1,6 @@
 use std::io::Seek;
 
+use anyhow::{bail, Result};
 use bit_cursor::{
     bit_cursor::BitCursor, bit_read_exts::BitReadExts, byte_order::NetworkOrder, nsw_types::*,
 };
@@ -47,7 +48,7 @@ impl RtpHeader {
     }
 
     pub fn seq_num(buf: &[u8]) -> u16 {
-        u16::from_be_bytes(buf[3..4].try_into().unwrap())
+        u16::from_be_bytes(buf[2..4].try_into().unwrap())
     }
 
     pub fn timestamp(buf: &[u8]) -> u32 {
@@ -58,6 +59,52 @@ impl RtpHeader {
         u32::from_be_bytes(buf[8..12].try_into().unwrap())
     }
 
+    pub fn set_marked(buf: &mut [u8], marked: bool) {
+        if marked {
+            buf[1] |= 0b10000000;
+        } else {
+            buf[1] &= 0b01111111;
+        }
+    }
+
+    pub fn set_payload_type(buf: &mut [u8], payload_type: u7) {
+        buf[1] = (buf[1] & 0b10000000) | u8::from(payload_type);
+    }
+
+    pub fn set_seq_num(buf: &mut [u8], seq_num: u16) {
+        buf[2..4].copy_from_slice(&seq_num.to_be_bytes());
+    }
+
+    pub fn set_timestamp(buf: &mut [u8], timestamp: u32) {
+        buf[4..8].copy_from_slice(&timestamp.to_be_bytes());
+    }
+
+    pub fn set_ssrc(buf: &mut [u8], ssrc: u32) {
+        buf[8..12].copy_from_slice(&ssrc.to_be_bytes());
+    }
+
+    /// Update the padding bit, csrc count and extensions bit of the given header (which must
+    /// contain exactly the fixed header and csrcs) to match the number of csrcs it contains and
+    /// whether or not the packet has padding and header extensions.
+    pub fn sync(buf: &mut [u8], has_padding: bool, has_extensions: bool) -> Result<()> {
+        if buf.len() < 12 || !(buf.len() - 12).is_multiple_of(4) {
+            bail!("Invalid RTP header length: {} bytes", buf.len());
+        }
+        let csrc_count = (buf.len() - 12) / 4;
+        if csrc_count > 15 {
+            bail!("RTP header can contain at most 15 csrcs, has {csrc_count}");
+        }
+        buf[0] = (buf[0] & 0b11000000) | csrc_count as u8;
+        if has_padding {
+            buf[0] |= 0b00100000;
+        }
+        if has_extensions {
+            buf[0] |= 0b00010000;
+        }
+
+        Ok(())
+    }
+
     /// Returns the offset into the given buffer where the top-level extensions header would
     /// start, if this packet contains extensions.
     pub fn extensions_start_offset(buf: &[u8]) -> usize {
@@ -66,13 +113,12 @@ impl RtpHeader {
     }
 
     pub fn payload_offset(buf: &[u8]) -> usize {
-        RtpHeader::extensions_start_offset(buf)
-            + RtpHeader::header_extensions_length_bytes(buf) as usize
+        RtpHeader::extensions_start_offset(buf) + RtpHeader::header_extensions_length_bytes(buf)
     }
 
     /// Returns the length of the extensions (including the extensions header) in bytes.  If
     /// has_extensions is false, returns 0.
-    pub fn header_extensions_length_bytes(buf: &[u8]) -> u16 {
+    pub fn header_extensions_length_bytes(buf: &[u8]) -> usize {
         if RtpHeader::has_extensions(buf) {
             let mut cursor = BitCursor::new(buf);
             let ext_offset = RtpHeader::extensions_start_offset(buf);
@@ -83,7 +129,7 @@ impl RtpHeader {
             let length_field = cursor.read_u16::<NetworkOrder>().unwrap();
 
             // 4 for the extensions header (type + length fields)
-            4 + length_field * 4
+            4 + length_field as usize * 4
         } else {
             0
         }
@@ -1,14 +1,26 @@
-use std::{
-    collections::HashMap,
-    fmt::{Debug, Display},
+use std::fmt::{Debug, Display};
+
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_write_exts::BitWriteExts,
+    nsw_types::{u2, u7},
 };
+use bytes::{BufMut, Bytes, BytesMut};
 
-use anyhow::Result;
-use bit_cursor::nsw_types::u7;
-use bytes::BytesMut;
+use crate::{
+    error::RtpParseError,
+    parse_mode::{ParseMode, ParseWarnings},
+    pretty::{FieldTree, HexBytes, PrettyPrint, Ssrc},
+    LengthBytes, PacketBufferMut,
+};
 
 use super::{
-    header_extensions::{read_header_extensions, SomeHeaderExtension},
+    header_extensions::{
+        header_extensions_length_bytes, read_cryptex_header_extensions,
+        read_header_extensions_with_config, write_cryptex_header_extensions,
+        write_header_extensions_with_config, CryptexHeaderExtensions, HeaderExtensionConfig,
+        HeaderExtensions, SomeHeaderExtension,
+    },
     rtp_header::RtpHeader,
 };
 
@@ -32,10 +44,16 @@ use super::{
 #[derive(Debug)]
 pub struct RtpPacket {
     // Includes the fixed header and csrcs
-    header: BytesMut,
-    header_exts_buf: BytesMut,
-    parsed_header_extensions: HashMap<u8, SomeHeaderExtension>,
-    payload: BytesMut,
+    header: Bytes,
+    header_exts_buf: Bytes,
+    parsed_header_extensions: HeaderExtensions,
+    /// Set if the header extensions (and csrcs) are encrypted using cryptex, in which case
+    /// `parsed_header_extensions` is empty
+    cryptex_header_extensions: Option<CryptexHeaderExtensions>,
+    payload: Bytes,
+    /// The number of padding bytes at the end of the packet (including the padding count byte),
+    /// or 0 if the packet isn't padded
+    padding_len: u8,
 }
 
 impl Display for RtpPacket {
@@ -53,42 +71,630 @@ impl RtpPacket {
         RtpHeader::ssrc(&self.header)
     }
 
+    pub fn seq_num(&self) -> u16 {
+        RtpHeader::seq_num(&self.header)
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        RtpHeader::timestamp(&self.header)
+    }
+
+    pub fn marked(&self) -> bool {
+        RtpHeader::marked(&self.header)
+    }
+
+    pub fn set_payload_type(&mut self, payload_type: u7) {
+        self.modify_header(|header| RtpHeader::set_payload_type(header, payload_type));
+    }
+
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        self.modify_header(|header| RtpHeader::set_ssrc(header, ssrc));
+    }
+
+    pub fn set_seq_num(&mut self, seq_num: u16) {
+        self.modify_header(|header| RtpHeader::set_seq_num(header, seq_num));
+    }
+
+    pub fn set_timestamp(&mut self, timestamp: u32) {
+        self.modify_header(|header| RtpHeader::set_timestamp(header, timestamp));
+    }
+
+    pub fn set_marked(&mut self, marked: bool) {
+        self.modify_header(|header| RtpHeader::set_marked(header, marked));
+    }
+
+    fn modify_header(&mut self, modify: impl FnOnce(&mut [u8])) {
+        // The header may share its buffer with the rest of the packet, so it's copied before being
+        // modified
+        let mut header = BytesMut::from(&self.header[..]);
+        modify(&mut header);
+        self.header = header.freeze();
+    }
+
+    pub fn header_extensions(&self) -> &HeaderExtensions {
+        &self.parsed_header_extensions
+    }
+
+    /// Mutable access to this packet's header extensions.  The extension bit in the header is
+    /// updated on [`RtpPacket::sync`].
+    pub fn header_extensions_mut(&mut self) -> &mut HeaderExtensions {
+        &mut self.parsed_header_extensions
+    }
+
     pub fn get_extension_by_id(&self, id: u8) -> Option<&SomeHeaderExtension> {
-        self.parsed_header_extensions.get(&id)
+        self.parsed_header_extensions.get(id)
+    }
+
+    pub fn csrcs(&self) -> impl Iterator<Item = u32> + '_ {
+        self.header[12..]
+            .chunks_exact(4)
+            .map(|csrc| u32::from_be_bytes(csrc.try_into().unwrap()))
+    }
+
+    /// Replace this packet's csrcs.  The csrc count in the header is updated on [`RtpPacket::sync`].
+    pub fn set_csrcs(&mut self, csrcs: &[u32]) {
+        let mut header = BytesMut::with_capacity(12 + 4 * csrcs.len());
+        header.put_slice(&self.header[..12]);
+        for csrc in csrcs {
+            header.put_u32(*csrc);
+        }
+        self.header = header.freeze();
+    }
+
+    /// Add the given header extension, replacing any existing extension with the same id.
+    pub fn set_extension(&mut self, ext: SomeHeaderExtension) {
+        self.parsed_header_extensions.insert(ext);
+    }
+
+    pub fn remove_extension(&mut self, id: u8) -> Option<SomeHeaderExtension> {
+        self.parsed_header_extensions.remove(id)
+    }
+
+    /// Whether this packet has any header extensions (not counting padding).  A cryptex packet
+    /// always has an (encrypted) extensions block.
+    pub fn has_extensions(&self) -> bool {
+        self.cryptex_header_extensions.is_some()
+            || self
+                .parsed_header_extensions
+                .iter()
+                .any(|ext| ext.id() != 0)
+    }
+
+    /// Whether this packet's header extensions and csrcs are encrypted using cryptex
+    /// (https://datatracker.ietf.org/doc/html/rfc9335)
+    pub fn is_cryptex(&self) -> bool {
+        self.cryptex_header_extensions.is_some()
+    }
+
+    /// The encrypted header extensions block of a cryptex packet.  Once the SRTP layer has
+    /// decrypted it, the elements can be parsed with [`CryptexHeaderExtensions::read_decrypted`].
+    pub fn cryptex_header_extensions(&self) -> Option<&CryptexHeaderExtensions> {
+        self.cryptex_header_extensions.as_ref()
+    }
+
+    /// Set (or clear) the encrypted header extensions block to write this packet in cryptex form.
+    /// While it's set, it's written instead of the packet's [`HeaderExtensions`].  The csrcs
+    /// should be replaced with their encrypted form via [`RtpPacket::set_csrcs`].
+    pub fn set_cryptex_header_extensions(
+        &mut self,
+        cryptex_header_extensions: Option<CryptexHeaderExtensions>,
+    ) {
+        self.cryptex_header_extensions = cryptex_header_extensions;
+    }
+
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// The payload as a [`Bytes`], which shares the packet's buffer rather than copying it.
+    pub fn payload_bytes(&self) -> Bytes {
+        self.payload.clone()
+    }
+
+    pub fn set_payload(&mut self, payload: &[u8]) {
+        self.payload = Bytes::copy_from_slice(payload);
+    }
+
+    /// Set the payload without copying it
+    pub fn set_payload_bytes(&mut self, payload: Bytes) {
+        self.payload = payload;
+    }
+
+    /// The number of padding bytes at the end of the packet (including the padding count byte),
+    /// or 0 if the packet isn't padded.  Padding isn't included in the payload.
+    pub fn padding_len(&self) -> u8 {
+        self.padding_len
+    }
+
+    /// Set the number of padding bytes (including the padding count byte) to write at the end of
+    /// the packet.  0 means no padding.  The padding bit in the header is updated on
+    /// [`RtpPacket::sync`].
+    pub fn set_padding_len(&mut self, padding_len: u8) {
+        self.padding_len = padding_len;
+    }
+
+    /// Update the header of this packet (padding bit, csrc count and extensions bit) to match its
+    /// contents.
+    pub fn sync(&mut self) -> Result<()> {
+        let has_extensions = self.has_extensions();
+        let has_padding = self.padding_len > 0;
+        let mut result = Ok(());
+        self.modify_header(|header| result = RtpHeader::sync(header, has_padding, has_extensions));
+
+        result.context("rtp header")
     }
 
-    // TODO: this will give the "original" size of the packet, is that best? It's what we want for
-    // incoming stats, but at other point we'll want the "actual" size of the packet (which may
-    // have changed)
+    /// The size of the packet as it was parsed, which is what we want for incoming stats.  If the
+    /// packet has been modified, [`LengthBytes::length_bytes`] gives the size it will be written
+    /// as.
     pub fn size_bytes(&self) -> usize {
-        self.header.len() + self.header_exts_buf.len() + self.payload.len()
+        self.header.len()
+            + self.header_exts_buf.len()
+            + self.payload.len()
+            + self.padding_len as usize
+    }
+}
+
+impl LengthBytes for RtpPacket {
+    fn length_bytes(&self) -> usize {
+        let header_extensions_length_bytes = if let Some(cryptex) = &self.cryptex_header_extensions
+        {
+            cryptex.length_bytes()
+        } else if self.has_extensions() {
+            header_extensions_length_bytes(&self.parsed_header_extensions)
+        } else {
+            0
+        };
+
+        self.header.len()
+            + header_extensions_length_bytes
+            + self.payload.len()
+            + self.padding_len as usize
     }
 }
 
 pub fn read_rtp_packet(buf: Vec<u8>) -> Result<RtpPacket> {
-    // TODO: eventaully I think we'll have it where this was already a BytesMut type and we don't
-    // have to copy it here
-    let mut bytes = BytesMut::with_capacity(buf.len());
-    bytes.extend_from_slice(&buf);
-    let csrc_count = Into::<usize>::into(RtpHeader::csrc_count(&bytes));
-    let header_length_bytes = 12 + 4 * csrc_count;
-    let header = bytes.split_to(header_length_bytes);
-    let header_extensions_length_bytes = ((((bytes[2] as u16) << 8) + bytes[3] as u16) + 1) * 4;
-
-    let header_exts = bytes.split_to(header_extensions_length_bytes as usize);
-    let parsed_header_extensions = read_header_extensions(header_exts.clone().into());
+    read_rtp_packet_bytes(Bytes::from(buf))
+}
+
+/// Read an RTP packet from the given buffer without copying it: the header, header extensions
+/// and payload of the returned packet are all slices of `bytes`.
+pub fn read_rtp_packet_bytes(bytes: Bytes) -> Result<RtpPacket> {
+    read_rtp_packet_bytes_with_config(bytes, &HeaderExtensionConfig::default())
+}
+
+/// Options which affect how RTP packets are parsed
+#[derive(Debug, Clone, Default)]
+pub struct RtpParseContext {
+    pub header_extensions: HeaderExtensionConfig,
+    /// Whether packets with a version other than 2, invalid padding or an extensions block longer
+    /// than the packet are rejected, or accepted with a warning
+    pub mode: ParseMode,
+}
+
+/// Like [`read_rtp_packet_bytes`], but using the given context.  Any warnings for violations
+/// which were accepted in [`ParseMode::Lenient`] are returned along with the packet.
+///
+/// In lenient mode, a packet with invalid padding is read as if it had no padding, and a packet
+/// whose extensions block is cut off is read as if it had no extensions, with the rest of the
+/// packet as its payload.  In both cases the header bit is cleared so the packet's header matches
+/// its contents.
+pub fn read_rtp_packet_bytes_with_context(
+    bytes: Bytes,
+    context: &RtpParseContext,
+) -> Result<(RtpPacket, ParseWarnings)> {
+    let mut warnings = ParseWarnings::new();
+    if !bytes.is_empty() && RtpHeader::version(&bytes) != u2::new(2) {
+        context.mode.on_violation(
+            &mut warnings,
+            RtpParseError::invalid_field("RTP version", 2, u8::from(RtpHeader::version(&bytes))),
+        )?;
+    }
+    let packet = read_rtp_packet_bytes_with_mode(
+        bytes,
+        &context.header_extensions,
+        context.mode,
+        &mut warnings,
+    )?;
+
+    Ok((packet, warnings))
+}
+
+/// Like [`read_rtp_packet_bytes`], but header extensions are read using the given config.  Invalid
+/// padding and extensions blocks which are longer than the packet are errors; use
+/// [`read_rtp_packet_bytes_with_context`] to accept them.
+pub fn read_rtp_packet_bytes_with_config(
+    bytes: Bytes,
+    config: &HeaderExtensionConfig,
+) -> Result<RtpPacket> {
+    read_rtp_packet_bytes_with_mode(bytes, config, ParseMode::Strict, &mut ParseWarnings::new())
+}
+
+fn read_rtp_packet_bytes_with_mode(
+    mut bytes: Bytes,
+    config: &HeaderExtensionConfig,
+    mode: ParseMode,
+    warnings: &mut ParseWarnings,
+) -> Result<RtpPacket> {
+    if bytes.len() < 12 {
+        bail!(RtpParseError::truncated(12, bytes.len()));
+    }
+    let header_length_bytes = RtpHeader::extensions_start_offset(&bytes);
+    if bytes.len() < header_length_bytes {
+        bail!(RtpParseError::truncated(header_length_bytes, bytes.len()));
+    }
+    let mut header = bytes.split_to(header_length_bytes);
+    let mut header_extensions_length_bytes = 0;
+    if RtpHeader::has_extensions(&header) {
+        // The extensions header (profile and length fields) must be present to read the
+        // extensions length
+        let needed_bytes = if bytes.len() < 4 {
+            header_length_bytes + 4
+        } else {
+            header_length_bytes + 4 + u16::from_be_bytes([bytes[2], bytes[3]]) as usize * 4
+        };
+        if header_length_bytes + bytes.len() < needed_bytes {
+            mode.on_violation(
+                warnings,
+                RtpParseError::truncated(needed_bytes, header_length_bytes + bytes.len()),
+            )?;
+            clear_header_bits(&mut header, 0b00010000);
+        } else {
+            header_extensions_length_bytes = needed_bytes - header_length_bytes;
+        }
+    }
+
+    let header_exts = bytes.split_to(header_extensions_length_bytes);
+    let is_cryptex = header_exts.len() >= 2
+        && CryptexHeaderExtensions::type_matches(u16::from_be_bytes([
+            header_exts[0],
+            header_exts[1],
+        ]));
+    let (parsed_header_extensions, cryptex_header_extensions) = if header_exts.is_empty() {
+        (HeaderExtensions::new(), None)
+    } else if is_cryptex {
+        let cryptex_header_extensions = read_cryptex_header_extensions(header_exts.clone())
+            .context("cryptex header extensions")?;
+        (HeaderExtensions::new(), Some(cryptex_header_extensions))
+    } else {
+        let parsed_header_extensions =
+            read_header_extensions_with_config(header_exts.clone(), config)
+                .context("header extensions")?;
+        (parsed_header_extensions, None)
+    };
+    let mut padding_len = 0;
+    if RtpHeader::has_padding(&header) {
+        match bytes.last().copied() {
+            Some(len) if len != 0 && len as usize <= bytes.len() => {
+                bytes.truncate(bytes.len() - len as usize);
+                padding_len = len;
+            }
+            len => {
+                let violation = match len {
+                    None => RtpParseError::truncated(1, 0),
+                    Some(len) => RtpParseError::invalid_field(
+                        "padding length",
+                        format!("1-{} bytes", bytes.len()),
+                        len,
+                    ),
+                };
+                mode.on_violation(warnings, violation)?;
+                clear_header_bits(&mut header, 0b00100000);
+            }
+        }
+    }
 
     Ok(RtpPacket {
         header,
         header_exts_buf: header_exts,
         parsed_header_extensions,
+        cryptex_header_extensions,
         payload: bytes,
+        padding_len,
     })
 }
 
+/// Clear the given bits in the first byte of a parsed header.  The header may share its buffer
+/// with the rest of the packet, so it's copied first.
+fn clear_header_bits(header: &mut Bytes, bits: u8) {
+    let mut modified = BytesMut::from(&header[..]);
+    modified[0] &= !bits;
+    *header = modified.freeze();
+}
+
+/// Builds an [`RtpPacket`] from its fields, for generating packets rather than parsing them.  The
+/// version, csrc count and extensions bit are filled in automatically.
+#[derive(Debug, Default)]
+pub struct RtpPacketBuilder {
+    marked: bool,
+    payload_type: u8,
+    seq_num: u16,
+    timestamp: u32,
+    ssrc: u32,
+    csrcs: Vec<u32>,
+    extensions: Vec<(u8, Vec<u8>)>,
+    payload: Vec<u8>,
+    padding_len: u8,
+}
+
+impl RtpPacketBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn marked(mut self, marked: bool) -> Self {
+        self.marked = marked;
+        self
+    }
+
+    pub fn payload_type(mut self, payload_type: u7) -> Self {
+        self.payload_type = payload_type.into();
+        self
+    }
+
+    pub fn seq_num(mut self, seq_num: u16) -> Self {
+        self.seq_num = seq_num;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: u32) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn ssrc(mut self, ssrc: u32) -> Self {
+        self.ssrc = ssrc;
+        self
+    }
+
+    pub fn csrc(mut self, csrc: u32) -> Self {
+        self.csrcs.push(csrc);
+        self
+    }
+
+    /// Add a header extension with the given id and data.  Extensions which fit in the one byte
+    /// form (id 1-14 and 1-16 bytes of data) are created as one byte extensions, all others as
+    /// two byte extensions.
+    pub fn extension(mut self, id: u8, data: &[u8]) -> Self {
+        self.extensions.push((id, data.to_vec()));
+        self
+    }
+
+    pub fn payload(mut self, payload: &[u8]) -> Self {
+        self.payload = payload.to_vec();
+        self
+    }
+
+    /// Pad the packet with the given number of bytes (including the padding count byte)
+    pub fn padding(mut self, padding_len: u8) -> Self {
+        self.padding_len = padding_len;
+        self
+    }
+
+    pub fn build(self) -> Result<RtpPacket> {
+        let mut header = BytesMut::with_capacity(12);
+        // Version 2
+        header.put_u8(0b10000000);
+        header.put_u8(((self.marked as u8) << 7) | self.payload_type);
+        header.put_u16(self.seq_num);
+        header.put_u32(self.timestamp);
+        header.put_u32(self.ssrc);
+
+        let mut packet = RtpPacket {
+            header: header.freeze(),
+            header_exts_buf: Bytes::new(),
+            parsed_header_extensions: HeaderExtensions::new(),
+            cryptex_header_extensions: None,
+            payload: Bytes::from(self.payload),
+            padding_len: self.padding_len,
+        };
+        packet.set_csrcs(&self.csrcs);
+        for (id, data) in self.extensions {
+            let ext =
+                SomeHeaderExtension::new(id, &data).with_context(|| format!("extension {id}"))?;
+            packet.set_extension(ext);
+        }
+        packet.sync().context("sync")?;
+
+        Ok(packet)
+    }
+}
+
+/// Generates packets through [`RtpPacketBuilder`], so they're always synced: any number of csrcs,
+/// one byte header extensions with unique ids, a payload and optional padding.
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for RtpPacket {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let mut builder = RtpPacketBuilder::new()
+            .marked(u.arbitrary()?)
+            .payload_type(crate::arbitrary_helpers::u7(u)?)
+            .seq_num(u.arbitrary()?)
+            .timestamp(u.arbitrary()?)
+            .ssrc(u.arbitrary()?);
+        for _ in 0..u.int_in_range(0..=15)? {
+            builder = builder.csrc(u.arbitrary()?);
+        }
+        for id in 1..=14 {
+            if u.arbitrary()? {
+                let data_len = u.int_in_range(1..=16)?;
+                builder = builder.extension(id, u.bytes(data_len)?);
+            }
+        }
+        builder = builder.payload(u.arbitrary()?);
+        if u.arbitrary()? {
+            builder = builder.padding(u.int_in_range(1..=255)?);
+        }
+
+        builder
+            .build()
+            .map_err(|_| arbitrary::Error::IncorrectFormat)
+    }
+}
+
+/// The fields a packet is serialized as.  Deserialized packets are rebuilt from these through
+/// [`RtpPacketBuilder`], so the header is always valid and in sync with the packet's contents
+/// (which means the version and the original extension bytes aren't preserved).
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RtpPacketFields {
+    marked: bool,
+    payload_type: u8,
+    seq_num: u16,
+    timestamp: u32,
+    ssrc: u32,
+    csrcs: Vec<u32>,
+    header_extensions: HeaderExtensions,
+    cryptex_header_extensions: Option<CryptexHeaderExtensions>,
+    payload: Bytes,
+    padding_len: u8,
+}
+
+#[cfg(feature = "serde")]
+impl RtpPacket {
+    fn to_fields(&self) -> RtpPacketFields {
+        RtpPacketFields {
+            marked: self.marked(),
+            payload_type: self.payload_type().into(),
+            seq_num: self.seq_num(),
+            timestamp: self.timestamp(),
+            ssrc: self.ssrc(),
+            csrcs: self.csrcs().collect(),
+            header_extensions: self.parsed_header_extensions.clone(),
+            cryptex_header_extensions: self.cryptex_header_extensions.clone(),
+            payload: self.payload.clone(),
+            padding_len: self.padding_len,
+        }
+    }
+
+    fn from_fields(fields: RtpPacketFields) -> Result<Self> {
+        if fields.payload_type > 0x7F {
+            bail!(RtpParseError::invalid_field(
+                "payload type",
+                "0-127",
+                fields.payload_type
+            ));
+        }
+        let mut builder = RtpPacketBuilder::new()
+            .marked(fields.marked)
+            .payload_type(u7::new(fields.payload_type))
+            .seq_num(fields.seq_num)
+            .timestamp(fields.timestamp)
+            .ssrc(fields.ssrc)
+            .padding(fields.padding_len);
+        for csrc in fields.csrcs {
+            builder = builder.csrc(csrc);
+        }
+        let mut packet = builder.build()?;
+        packet.set_payload_bytes(fields.payload);
+        for ext in fields.header_extensions.iter().cloned() {
+            packet.set_extension(ext);
+        }
+        if let Some(cryptex) = fields.cryptex_header_extensions {
+            let cryptex = CryptexHeaderExtensions::new(cryptex.two_byte(), cryptex.data().clone())
+                .context("cryptex header extensions")?;
+            packet.set_cryptex_header_extensions(Some(cryptex));
+        }
+        packet.sync().context("sync")?;
+
+        Ok(packet)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for RtpPacket {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_fields().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for RtpPacket {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = RtpPacketFields::deserialize(deserializer)?;
+        RtpPacket::from_fields(fields).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Write the given packet.  Header extensions are written from the packet's (possibly modified)
+/// extensions rather than the original extension bytes.  [`RtpPacket::sync`] should be called
+/// first so the header matches the packet's contents.
+pub fn write_rtp_packet<B: PacketBufferMut>(buf: &mut B, packet: &RtpPacket) -> Result<()> {
+    write_rtp_packet_with_config(buf, packet, &HeaderExtensionConfig::default())
+}
+
+/// Like [`write_rtp_packet`], but header extensions are written using the given config.
+pub fn write_rtp_packet_with_config<B: PacketBufferMut>(
+    buf: &mut B,
+    packet: &RtpPacket,
+    config: &HeaderExtensionConfig,
+) -> Result<()> {
+    std::io::Write::write_all(buf, &packet.header).context("header")?;
+    if let Some(cryptex) = &packet.cryptex_header_extensions {
+        write_cryptex_header_extensions(buf, cryptex).context("cryptex header extensions")?;
+    } else if packet.has_extensions() {
+        write_header_extensions_with_config(buf, &packet.parsed_header_extensions, config)
+            .context("header extensions")?;
+    }
+    std::io::Write::write_all(buf, &packet.payload).context("payload")?;
+    if packet.padding_len > 0 {
+        let padding = vec![0u8; packet.padding_len as usize - 1];
+        std::io::Write::write_all(buf, &padding).context("padding")?;
+        buf.write_u8(packet.padding_len).context("padding length")?;
+    }
+
+    Ok(())
+}
+
+impl PrettyPrint for RtpPacket {
+    fn pretty_print(&self, tree: &mut FieldTree<'_, '_>) -> std::fmt::Result {
+        tree.subtree("RTP packet", |tree| {
+            tree.field("Version", u8::from(RtpHeader::version(&self.header)))?;
+            tree.field("Padding", RtpHeader::has_padding(&self.header))?;
+            tree.field("Extension", RtpHeader::has_extensions(&self.header))?;
+            tree.field("CSRC count", u8::from(RtpHeader::csrc_count(&self.header)))?;
+            tree.field("Marker", self.marked())?;
+            tree.field("Payload type", u8::from(self.payload_type()))?;
+            tree.field("Sequence number", self.seq_num())?;
+            tree.field("Timestamp", self.timestamp())?;
+            tree.field("SSRC", Ssrc(self.ssrc()))?;
+            for csrc in self.csrcs() {
+                tree.field("CSRC", Ssrc(csrc))?;
+            }
+            if let Some(cryptex) = &self.cryptex_header_extensions {
+                tree.field(
+                    "Encrypted header extensions",
+                    format_args!("{} bytes", cryptex.length_bytes() - 4),
+                )?;
+            } else if self.has_extensions() {
+                tree.subtree("Header extensions", |tree| {
+                    for ext in self.parsed_header_extensions.iter() {
+                        tree.field(&format!("Id {}", ext.id()), HexBytes(&ext.data()))?;
+                    }
+                    Ok(())
+                })?;
+            }
+            tree.field("Payload", format_args!("{} bytes", self.payload.len()))?;
+            if self.padding_len > 0 {
+                tree.field("Padding length", self.padding_len)?;
+            }
+            Ok(())
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
-    use super::read_rtp_packet;
+    use bit_cursor::{
+        bit_cursor::BitCursor,
+        nsw_types::{u2, u4},
+    };
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtp::header_extensions::{
+        header_extension_elements, OneByteHeaderExtension, TwoByteHeaderExtension,
+    };
+
+    use super::*;
 
     #[test]
     fn test_read_rtp_packet2() {
@@ -107,4 +713,426 @@ mod test {
         println!("{:x?}", packet.payload.as_ref());
         // dbg!(packet);
     }
+
+    #[test]
+    fn test_modify_and_write() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0x90, 0xef, 0x16, 0xad, 0x65, 0xf3, 0xe1, 0x4e, 0x32, 0x0f, 0x22, 0x3a, 0xbe, 0xde,
+            0x00, 0x01, 0x10, 0xff, 0x00, 0x00, 0x01, 0x02, 0x03,
+        ];
+        let mut packet = read_rtp_packet(data).unwrap();
+        packet.set_csrcs(&[0x11223344]);
+        packet.set_extension(SomeHeaderExtension::OneByteHeaderExtension(
+            OneByteHeaderExtension::new(2, &[0xAA, 0xBB]).unwrap(),
+        ));
+        packet.remove_extension(1);
+        packet.set_payload(&[0x04, 0x05]);
+        packet.sync().unwrap();
+
+        // header (12) + csrc (4) + extensions (8) + payload (2)
+        assert_eq!(packet.length_bytes(), 26);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 26]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let read_packet = read_rtp_packet(cursor.into_inner().into_vec()).unwrap();
+        assert_eq!(read_packet.csrcs().collect::<Vec<_>>(), vec![0x11223344]);
+        assert_eq!(read_packet.ssrc(), 0x320f223a);
+        assert!(read_packet.get_extension_by_id(1).is_none());
+        assert_eq!(
+            read_packet.get_extension_by_id(2).unwrap().data().as_ref(),
+            [0xAA, 0xBB]
+        );
+        assert_eq!(read_packet.payload(), [0x04, 0x05]);
+
+        // Removing all the extensions clears the extension bit
+        packet.remove_extension(2);
+        packet.remove_extension(0);
+        packet.sync().unwrap();
+        assert_eq!(packet.length_bytes(), 18);
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 18]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        let read_packet = read_rtp_packet(cursor.into_inner().into_vec()).unwrap();
+        assert!(!read_packet.has_extensions());
+        assert_eq!(read_packet.payload(), [0x04, 0x05]);
+    }
+
+    #[test]
+    fn test_extension_order_roundtrip() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0x90, 0xef, 0x16, 0xad, 0x65, 0xf3, 0xe1, 0x4e, 0x32, 0x0f, 0x22, 0x3a,
+            0xbe, 0xde, 0x00, 0x02,
+            0x50, 0xAA, 0x10, 0xBB, 0x30, 0xCC, 0x00, 0x00,
+            0x01, 0x02,
+        ];
+        let packet = read_rtp_packet(data.clone()).unwrap();
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 26]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        assert_eq!(cursor.into_inner().into_vec(), data);
+    }
+
+    #[test]
+    fn test_builder() {
+        let packet = RtpPacketBuilder::new()
+            .marked(true)
+            .payload_type(u7::new(111))
+            .seq_num(1234)
+            .timestamp(5678)
+            .ssrc(42)
+            .csrc(1)
+            .csrc(2)
+            .extension(1, &[0xAA])
+            .payload(&[0x01, 0x02, 0x03])
+            .build()
+            .unwrap();
+
+        // header (12) + csrcs (8) + extensions (8) + payload (3)
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 31]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(RtpHeader::version(&data), u2::new(2));
+        assert_eq!(RtpHeader::csrc_count(&data), u4::new(2));
+        assert!(RtpHeader::has_extensions(&data));
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert!(read_packet.marked());
+        assert_eq!(read_packet.payload_type(), u7::new(111));
+        assert_eq!(read_packet.seq_num(), 1234);
+        assert_eq!(read_packet.timestamp(), 5678);
+        assert_eq!(read_packet.ssrc(), 42);
+        assert_eq!(read_packet.csrcs().collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(
+            read_packet.get_extension_by_id(1).unwrap().data().as_ref(),
+            [0xAA]
+        );
+        assert_eq!(read_packet.payload(), [0x01, 0x02, 0x03]);
+    }
+
+    #[test]
+    fn test_write_extensions_block_header() {
+        let mut packet = RtpPacketBuilder::new().payload(&[0x01]).build().unwrap();
+        assert!(!packet.has_extensions());
+        packet.set_extension(SomeHeaderExtension::TwoByteHeaderExtension(
+            TwoByteHeaderExtension::new(20, &[0xAA, 0xBB, 0xCC]).unwrap(),
+        ));
+        packet.sync().unwrap();
+
+        // header (12) + profile and length (4) + extension (5, padded to 8) + payload (1)
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 25]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        assert!(cursor.remaining_slice().is_empty());
+
+        let data = cursor.into_inner().into_vec();
+        assert!(RtpHeader::has_extensions(&data));
+        assert_eq!(RtpHeader::header_extensions_length_bytes(&data), 12);
+        #[rustfmt::skip]
+        assert_eq!(
+            data[12..24],
+            [
+                0x10, 0x00, 0x00, 0x02,
+                0x14, 0x03, 0xAA, 0xBB,
+                0xCC, 0x00, 0x00, 0x00,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_builder_two_byte_extension() {
+        let packet = RtpPacketBuilder::new()
+            .extension(20, &[0xAA])
+            .build()
+            .unwrap();
+        assert!(matches!(
+            packet.get_extension_by_id(20),
+            Some(SomeHeaderExtension::TwoByteHeaderExtension(_))
+        ));
+        assert!(RtpPacketBuilder::new().extension(0, &[]).build().is_err());
+    }
+
+    #[test]
+    fn test_padding_roundtrip() {
+        let packet = RtpPacketBuilder::new()
+            .payload(&[0x01, 0x02])
+            .padding(4)
+            .build()
+            .unwrap();
+        assert_eq!(packet.size_bytes(), 18);
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 18]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert!(RtpHeader::has_padding(&data));
+        assert_eq!(data[12..], [0x01, 0x02, 0x00, 0x00, 0x00, 0x04]);
+
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert_eq!(read_packet.padding_len(), 4);
+        assert_eq!(read_packet.payload(), [0x01, 0x02]);
+    }
+
+    #[test]
+    fn test_parse_mode() {
+        // Version 1
+        #[rustfmt::skip]
+        let data = Bytes::from_static(&[
+            0x40, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x01, 0x02,
+        ]);
+        let (packet, warnings) =
+            read_rtp_packet_bytes_with_context(data.clone(), &RtpParseContext::default()).unwrap();
+        assert_eq!(packet.payload(), &[0x01, 0x02]);
+        assert_eq!(
+            warnings,
+            vec![RtpParseError::invalid_field("RTP version", 2, 1)]
+        );
+
+        let context = RtpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let err = read_rtp_packet_bytes_with_context(data, &context).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::invalid_field("RTP version", 2, 1))
+        );
+    }
+
+    #[test]
+    fn test_invalid_padding() {
+        #[rustfmt::skip]
+        let data: Vec<u8> = vec![
+            0xa0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01,
+            0x01, 0x05,
+        ];
+        assert!(read_rtp_packet(data).is_err());
+    }
+
+    #[test]
+    fn test_parse_mode_invalid_padding() {
+        let strict = RtpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        // Padding length larger than the payload, then a padding length of 0
+        for (data, padding_len) in [
+            (
+                Bytes::from_static(&[
+                    0xa0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01,
+                    0x05,
+                ]),
+                5,
+            ),
+            (
+                Bytes::from_static(&[
+                    0xa0, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01,
+                    0x00,
+                ]),
+                0,
+            ),
+        ] {
+            let violation =
+                RtpParseError::invalid_field("padding length", "1-2 bytes", padding_len);
+            let (packet, warnings) =
+                read_rtp_packet_bytes_with_context(data.clone(), &RtpParseContext::default())
+                    .unwrap();
+            assert_eq!(warnings, vec![violation.clone()]);
+            // Read as if it had no padding
+            assert_eq!(packet.payload(), &data[12..]);
+            assert_eq!(packet.padding_len(), 0);
+            assert!(!RtpHeader::has_padding(&packet.header));
+
+            let err = read_rtp_packet_bytes_with_context(data, &strict).unwrap_err();
+            assert_eq!(RtpParseError::find(&err), Some(violation));
+        }
+    }
+
+    #[test]
+    fn test_parse_mode_truncated_extensions() {
+        let strict = RtpParseContext {
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        // Extension bit set, but no extensions header, then an extensions length longer than the
+        // packet
+        for (data, needed_bytes) in [
+            (
+                Bytes::from_static(&[
+                    0x90, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x01,
+                    0x02,
+                ]),
+                16,
+            ),
+            (
+                Bytes::from_static(&[
+                    0x90, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0xbe,
+                    0xde, 0x00, 0x02, 0x10, 0xaa, 0x00, 0x00,
+                ]),
+                24,
+            ),
+        ] {
+            let violation = RtpParseError::truncated(needed_bytes, data.len());
+            let (packet, warnings) =
+                read_rtp_packet_bytes_with_context(data.clone(), &RtpParseContext::default())
+                    .unwrap();
+            assert_eq!(warnings, vec![violation.clone()]);
+            // Read as if it had no extensions
+            assert!(packet.header_extensions().is_empty());
+            assert!(!RtpHeader::has_extensions(&packet.header));
+            assert_eq!(packet.payload(), &data[12..]);
+
+            let err = read_rtp_packet_bytes_with_context(data, &strict).unwrap_err();
+            assert_eq!(RtpParseError::find(&err), Some(violation));
+        }
+    }
+
+    #[test]
+    fn test_read_rtp_packet_bytes_zero_copy() {
+        #[rustfmt::skip]
+        let data = Bytes::from_static(&[
+            0x90, 0xef, 0x16, 0xad, 0x65, 0xf3, 0xe1, 0x4e, 0x32, 0x0f, 0x22, 0x3a, 0xbe, 0xde,
+            0x00, 0x01, 0x10, 0xff, 0x00, 0x00, 0x01, 0x02, 0x03,
+        ]);
+        let packet = read_rtp_packet_bytes(data.clone()).unwrap();
+        assert_eq!(packet.payload().as_ptr(), data[20..].as_ptr());
+        assert_eq!(packet.payload_bytes(), data.slice(20..));
+        assert_eq!(
+            packet.get_extension_by_id(1).unwrap().data().as_ptr(),
+            data[17..].as_ptr()
+        );
+    }
+
+    #[test]
+    fn test_read_rtp_packet_too_short() {
+        let err = read_rtp_packet(vec![0x80, 0x00, 0x00]).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(12, 3))
+        );
+        // Extension bit set, but no extensions header
+        let err = read_rtp_packet(vec![0x90, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(16, 12))
+        );
+        // Extensions length is longer than the buffer
+        let err = read_rtp_packet(vec![
+            0x90, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xbe, 0xde, 0x00, 0x01,
+        ])
+        .unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(20, 16))
+        );
+    }
+
+    #[test]
+    fn test_read_rtp_packet_invalid_extensions() {
+        #[rustfmt::skip]
+        let header = [
+            0x90, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01,
+        ];
+        // A one byte element whose data is longer than the extensions block
+        let err = read_rtp_packet(
+            [
+                &header[..],
+                &[0xbe, 0xde, 0x00, 0x01, 0x1f, 0xaa, 0x00, 0x00],
+            ]
+            .concat(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(17, 4))
+        );
+        // The same for a two byte element
+        let err = read_rtp_packet(
+            [
+                &header[..],
+                &[0x10, 0x00, 0x00, 0x01, 0x14, 0xff, 0x00, 0x00],
+            ]
+            .concat(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(257, 4))
+        );
+        // An extensions length which would overflow a u16 in bytes
+        let err = read_rtp_packet([&header[..], &[0xbe, 0xde, 0x40, 0x00]].concat()).unwrap_err();
+        assert_eq!(
+            RtpParseError::find(&err),
+            Some(RtpParseError::truncated(12 + 4 + 0x4000 * 4, 16))
+        );
+    }
+
+    #[test]
+    fn test_cryptex_roundtrip() {
+        let mut packet = RtpPacketBuilder::new()
+            .ssrc(1)
+            .extension(1, &[0xAA, 0xBB])
+            .payload(&[1, 2, 3])
+            .build()
+            .unwrap();
+        // "Encrypt" the extension elements by flipping their bits
+        let (two_byte, elements) = header_extension_elements(packet.header_extensions()).unwrap();
+        assert_eq!(elements, vec![0x11, 0xAA, 0xBB, 0x00]);
+        let encrypted = elements.iter().map(|b| !b).collect::<Vec<_>>();
+        packet.set_cryptex_header_extensions(Some(
+            CryptexHeaderExtensions::new(two_byte, Bytes::from(encrypted.clone())).unwrap(),
+        ));
+        packet.sync().unwrap();
+
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_slice(&[0u8; 23]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(&data[12..16], &[0xC0, 0xDE, 0x00, 0x01]);
+
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert!(read_packet.is_cryptex());
+        assert!(read_packet.header_extensions().is_empty());
+        assert_eq!(read_packet.payload(), &[1, 2, 3]);
+        let cryptex = read_packet.cryptex_header_extensions().unwrap();
+        assert_eq!(cryptex.data().as_ref(), encrypted.as_slice());
+        let decrypted = cryptex.data().iter().map(|b| !b).collect::<Vec<_>>();
+        let header_extensions = cryptex.read_decrypted(Bytes::from(decrypted)).unwrap();
+        assert_eq!(
+            header_extensions.get(1).unwrap().data().as_ref(),
+            &[0xAA, 0xBB]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_fields() {
+        let packet = RtpPacketBuilder::new()
+            .payload_type(u7::new(96))
+            .seq_num(1234)
+            .csrc(42)
+            .extension(1, &[0xAA, 0xBB])
+            .payload(&[1, 2, 3])
+            .padding(4)
+            .build()
+            .unwrap();
+        let packet = RtpPacket::from_fields(packet.to_fields()).unwrap();
+        assert_eq!(packet.payload_type(), u7::new(96));
+        assert_eq!(packet.seq_num(), 1234);
+        assert_eq!(packet.csrcs().collect::<Vec<_>>(), vec![42]);
+        assert_eq!(
+            packet.get_extension_by_id(1).unwrap().data().as_ref(),
+            &[0xAA, 0xBB]
+        );
+        assert_eq!(packet.payload(), &[1, 2, 3]);
+        assert_eq!(packet.padding_len(), 4);
+
+        let mut fields = packet.to_fields();
+        fields.payload_type = 0x80;
+        assert!(RtpPacket::from_fields(fields).is_err());
+        let mut fields = packet.to_fields();
+        fields.csrcs = vec![0; 16];
+        assert!(RtpPacket::from_fields(fields).is_err());
+    }
 }
@@ -0,0 +1,199 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::nsw_types::u7;
+use bytes::{BufMut, Bytes, BytesMut};
+
+use super::rtp_packet::RtpPacket;
+
+// https://datatracker.ietf.org/doc/html/rfc4588#section-4
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         RTP Header                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |            OSN                |                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+                               |
+// |                  Original RTP Packet Payload                  |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// An RTX packet is sent on its own ssrc, with its own sequence numbers and payload type.  The
+// payload starts with the original packet's sequence number (OSN), and the original packet's
+// payload type is given by the RTX payload type's apt parameter:
+//   a=fmtp:97 apt=96
+
+/// The mapping between RTX payload types and the payload types of the packets they retransmit
+/// (their "associated payload types")
+#[derive(Debug, Clone, Default)]
+pub struct AptMap(Vec<(u7, u7)>);
+
+impl AptMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a mapping from the given RTX payload type to the given original payload type
+    pub fn with_mapping(mut self, rtx_payload_type: u7, original_payload_type: u7) -> Self {
+        self.insert(rtx_payload_type, original_payload_type);
+        self
+    }
+
+    pub fn insert(&mut self, rtx_payload_type: u7, original_payload_type: u7) {
+        self.0.retain(|(rtx, _)| *rtx != rtx_payload_type);
+        self.0.push((rtx_payload_type, original_payload_type));
+    }
+
+    /// The original payload type for the given RTX payload type
+    pub fn original_payload_type(&self, rtx_payload_type: u7) -> Option<u7> {
+        self.0
+            .iter()
+            .find(|(rtx, _)| *rtx == rtx_payload_type)
+            .map(|(_, original)| *original)
+    }
+
+    /// The RTX payload type used to retransmit the given original payload type
+    pub fn rtx_payload_type(&self, original_payload_type: u7) -> Option<u7> {
+        self.0
+            .iter()
+            .find(|(_, original)| *original == original_payload_type)
+            .map(|(rtx, _)| *rtx)
+    }
+}
+
+/// An RTX packet: a retransmission of another packet, sent on a separate RTX stream
+#[derive(Debug)]
+pub struct RtxPacket(RtpPacket);
+
+impl RtxPacket {
+    /// Fails if the packet's payload is too short to contain the original sequence number (as is
+    /// the case for the padding-only packets which are also sent on RTX streams).
+    pub fn new(packet: RtpPacket) -> Result<Self> {
+        if packet.payload().len() < 2 {
+            bail!(
+                "RTX payload must be at least 2 bytes, got {}",
+                packet.payload().len()
+            );
+        }
+
+        Ok(Self(packet))
+    }
+
+    /// The sequence number of the packet being retransmitted
+    pub fn original_seq_num(&self) -> u16 {
+        u16::from_be_bytes([self.0.payload()[0], self.0.payload()[1]])
+    }
+
+    /// The payload of the packet being retransmitted
+    pub fn original_payload(&self) -> Bytes {
+        self.0.payload_bytes().slice(2..)
+    }
+
+    pub fn packet(&self) -> &RtpPacket {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> RtpPacket {
+        self.0
+    }
+
+    /// Recover the packet being retransmitted: its sequence number, ssrc and payload type are
+    /// restored and the original sequence number is removed from the payload.  The RTX packet's
+    /// padding isn't kept.
+    pub fn into_original(self, original_ssrc: u32, apt_map: &AptMap) -> Result<RtpPacket> {
+        let rtx_payload_type = self.0.payload_type();
+        let Some(original_payload_type) = apt_map.original_payload_type(rtx_payload_type) else {
+            bail!("No associated payload type for RTX payload type {rtx_payload_type}");
+        };
+        let original_seq_num = self.original_seq_num();
+        let original_payload = self.original_payload();
+        let mut packet = self.0;
+        packet.set_seq_num(original_seq_num);
+        packet.set_ssrc(original_ssrc);
+        packet.set_payload_type(original_payload_type);
+        packet.set_payload_bytes(original_payload);
+        packet.set_padding_len(0);
+        packet.sync().context("sync")?;
+
+        Ok(packet)
+    }
+
+    /// Build an RTX packet to retransmit the given packet on the RTX stream with the given ssrc,
+    /// using the given RTX sequence number.  The original packet's padding isn't kept.
+    pub fn from_original(
+        original: RtpPacket,
+        rtx_ssrc: u32,
+        rtx_seq_num: u16,
+        apt_map: &AptMap,
+    ) -> Result<Self> {
+        let original_payload_type = original.payload_type();
+        let Some(rtx_payload_type) = apt_map.rtx_payload_type(original_payload_type) else {
+            bail!("No RTX payload type for payload type {original_payload_type}");
+        };
+        let mut payload = BytesMut::with_capacity(2 + original.payload().len());
+        payload.put_u16(original.seq_num());
+        payload.put_slice(original.payload());
+        let mut packet = original;
+        packet.set_seq_num(rtx_seq_num);
+        packet.set_ssrc(rtx_ssrc);
+        packet.set_payload_type(rtx_payload_type);
+        packet.set_payload_bytes(payload.freeze());
+        packet.set_padding_len(0);
+        packet.sync().context("sync")?;
+
+        Ok(Self(packet))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rtp::rtp_packet::RtpPacketBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_rtx_roundtrip() {
+        let apt_map = AptMap::new().with_mapping(u7::new(97), u7::new(96));
+        let original = RtpPacketBuilder::new()
+            .payload_type(u7::new(96))
+            .seq_num(1000)
+            .timestamp(1234)
+            .ssrc(1)
+            .extension(1, &[0xAA])
+            .payload(&[1, 2, 3])
+            .padding(4)
+            .build()
+            .unwrap();
+
+        let rtx = RtxPacket::from_original(original, 2, 5, &apt_map).unwrap();
+        assert_eq!(rtx.packet().ssrc(), 2);
+        assert_eq!(rtx.packet().seq_num(), 5);
+        assert_eq!(rtx.packet().payload_type(), u7::new(97));
+        assert_eq!(rtx.packet().payload(), [0x03, 0xE8, 1, 2, 3]);
+        assert_eq!(rtx.packet().padding_len(), 0);
+        assert_eq!(rtx.original_seq_num(), 1000);
+
+        let original = rtx.into_original(1, &apt_map).unwrap();
+        assert_eq!(original.ssrc(), 1);
+        assert_eq!(original.seq_num(), 1000);
+        assert_eq!(original.timestamp(), 1234);
+        assert_eq!(original.payload_type(), u7::new(96));
+        assert_eq!(original.payload(), [1, 2, 3]);
+        assert_eq!(
+            original.get_extension_by_id(1).unwrap().data().as_ref(),
+            [0xAA]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rtx() {
+        let padding_only = RtpPacketBuilder::new().padding(4).build().unwrap();
+        assert!(RtxPacket::new(padding_only).is_err());
+
+        let rtx = RtpPacketBuilder::new()
+            .payload_type(u7::new(100))
+            .payload(&[0, 1])
+            .build()
+            .unwrap();
+        let rtx = RtxPacket::new(rtx).unwrap();
+        assert!(rtx.into_original(1, &AptMap::new()).is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// https://datatracker.ietf.org/doc/html/rfc7941#section-4
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   |  len  | SDES item text value ...                      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// SDES items carried in header extensions contain only the item's UTF-8 text value: the item type
+// is given by the extension's id, and the value isn't null terminated.
+
+/// Parse the text value of an SDES item header extension.  `name` is used in errors.
+fn parse_sdes_value(data: &[u8], name: &'static str) -> Result<String> {
+    if data.is_empty() {
+        bail!(RtpParseError::truncated(1, 0));
+    }
+    let value = std::str::from_utf8(data)
+        .map_err(|_| RtpParseError::invalid_field(name, "UTF-8", format!("{data:x?}")))?;
+
+    Ok(value.to_owned())
+}
+
+/// The media identification (MID) of the media section a packet belongs to, used to demux
+/// BUNDLEd streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mid(pub String);
+
+impl RtpHeaderExtensionValue for Mid {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:sdes:mid";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        Ok(Mid(parse_sdes_value(data, "MID")?))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+/// Parse a RID value, which must follow the rid-id syntax from
+/// https://datatracker.ietf.org/doc/html/rfc8851#section-10: letters, digits, '-' and '_'.
+fn parse_rid_value(data: &[u8], name: &'static str) -> Result<String> {
+    let value = parse_sdes_value(data, name)?;
+    if !value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        bail!(RtpParseError::invalid_field(
+            name,
+            "only letters, digits, '-' and '_'",
+            format!("{value:?}")
+        ));
+    }
+
+    Ok(value)
+}
+
+/// The RTP stream id (RID) of the stream a packet belongs to, e.g. to identify simulcast layers.
+/// https://datatracker.ietf.org/doc/html/rfc8852#section-3.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rid(pub String);
+
+impl RtpHeaderExtensionValue for Rid {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:sdes:rtp-stream-id";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        Ok(Rid(parse_rid_value(data, "RID")?))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+/// The RID of the stream a redundancy (e.g. RTX or FEC) packet is repairing.
+/// https://datatracker.ietf.org/doc/html/rfc8852#section-3.2
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RepairedRid(pub String);
+
+impl RtpHeaderExtensionValue for RepairedRid {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:sdes:repaired-rtp-stream-id";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        Ok(RepairedRid(parse_rid_value(data, "Repaired RID")?))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.as_bytes().to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mid() {
+        let mid = Mid::parse(b"audio0").unwrap();
+        assert_eq!(mid, Mid("audio0".to_owned()));
+        assert_eq!(mid.encode(), b"audio0");
+        assert!(Mid::parse(&[]).is_err());
+        assert!(Mid::parse(&[0xFF, 0xFE]).is_err());
+    }
+
+    #[test]
+    fn test_rid() {
+        assert_eq!(Rid::parse(b"hi-res_1").unwrap(), Rid("hi-res_1".to_owned()));
+        assert!(Rid::parse(b"hi res").is_err());
+        let repaired_rid = RepairedRid("lo".to_owned());
+        assert_eq!(
+            RepairedRid::parse(&repaired_rid.encode()).unwrap(),
+            repaired_rid
+        );
+    }
+}
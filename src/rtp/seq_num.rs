@@ -0,0 +1,165 @@
+use std::fmt::Display;
+
+/// An RTP sequence number, which wraps from 65535 back to 0.  Comparisons use serial number
+/// arithmetic (https://datatracker.ietf.org/doc/html/rfc1982): a sequence number is newer than
+/// another if it's less than half the sequence number space ahead of it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SeqNum(pub u16);
+
+impl SeqNum {
+    pub fn value(&self) -> u16 {
+        self.0
+    }
+
+    /// Whether this sequence number comes after `other`.  When they're exactly half the sequence
+    /// number space apart, the numerically larger one is considered newer.
+    pub fn is_newer_than(&self, other: SeqNum) -> bool {
+        match self.distance_from(other) {
+            0 => false,
+            0x8000 => self.0 > other.0,
+            distance => distance < 0x8000,
+        }
+    }
+
+    /// How far ahead of `other` this sequence number is, wrapping around if it's behind, e.g. 1 is
+    /// 2 ahead of 65535.
+    pub fn distance_from(&self, other: SeqNum) -> u16 {
+        self.0.wrapping_sub(other.0)
+    }
+
+    /// How far ahead of (positive) or behind (negative) `other` this sequence number is, taking
+    /// whichever is closer.
+    pub fn signed_distance_from(&self, other: SeqNum) -> i16 {
+        self.distance_from(other) as i16
+    }
+
+    pub fn wrapping_add(&self, n: u16) -> SeqNum {
+        SeqNum(self.0.wrapping_add(n))
+    }
+
+    pub fn wrapping_sub(&self, n: u16) -> SeqNum {
+        SeqNum(self.0.wrapping_sub(n))
+    }
+
+    /// Iterate from `start` up to and including `end`, wrapping around from 65535 to 0 if `end`
+    /// is before `start`.
+    pub fn range_inclusive(start: SeqNum, end: SeqNum) -> impl Iterator<Item = SeqNum> {
+        (0..=end.distance_from(start) as u32).map(move |i| start.wrapping_add(i as u16))
+    }
+}
+
+impl From<u16> for SeqNum {
+    fn from(value: u16) -> Self {
+        SeqNum(value)
+    }
+}
+
+impl From<SeqNum> for u16 {
+    fn from(seq_num: SeqNum) -> Self {
+        seq_num.0
+    }
+}
+
+impl Display for SeqNum {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Maps the wrapping 16 bit sequence numbers of an RTP stream to monotonically increasing
+/// extended sequence numbers, whose upper bits are the rollover counter (ROC) used by SRTP
+/// (https://datatracker.ietf.org/doc/html/rfc3711#section-3.3.1) and by the extended highest
+/// sequence number in reception reports.  Each sequence number is assumed to be the closest
+/// possible value to the highest one seen so far, so reordered packets from before a wrap are
+/// given the previous rollover count.
+#[derive(Debug, Default)]
+pub struct SeqNumUnwrapper {
+    highest: Option<u64>,
+}
+
+impl SeqNumUnwrapper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Unwrap the given sequence number into an extended sequence number.  The first sequence
+    /// number has a rollover count of 0, and since extended sequence numbers can't be negative, a
+    /// packet reordered from before the wrap preceding it is given a rollover count of 0 as well.
+    pub fn unwrap(&mut self, seq_num: u16) -> u64 {
+        let Some(highest) = self.highest else {
+            self.highest = Some(seq_num as u64);
+            return seq_num as u64;
+        };
+        let diff = seq_num.wrapping_sub(highest as u16) as i16 as i64;
+        let Ok(unwrapped) = u64::try_from(highest as i64 + diff) else {
+            return seq_num as u64;
+        };
+        if unwrapped > highest {
+            self.highest = Some(unwrapped);
+        }
+
+        unwrapped
+    }
+
+    /// The highest extended sequence number seen so far
+    pub fn highest(&self) -> Option<u64> {
+        self.highest
+    }
+
+    /// The rollover counter of the highest sequence number seen so far
+    pub fn roc(&self) -> Option<u32> {
+        self.highest.map(|highest| (highest >> 16) as u32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_num() {
+        assert!(SeqNum(1).is_newer_than(SeqNum(65535)));
+        assert!(!SeqNum(65535).is_newer_than(SeqNum(1)));
+        assert!(!SeqNum(5).is_newer_than(SeqNum(5)));
+        assert!(SeqNum(0x8000).is_newer_than(SeqNum(0)));
+        assert!(!SeqNum(0).is_newer_than(SeqNum(0x8000)));
+        assert_eq!(SeqNum(1).distance_from(SeqNum(65535)), 2);
+        assert_eq!(SeqNum(65535).signed_distance_from(SeqNum(1)), -2);
+        assert_eq!(
+            SeqNum::range_inclusive(SeqNum(65534), SeqNum(1))
+                .map(u16::from)
+                .collect::<Vec<_>>(),
+            [65534, 65535, 0, 1]
+        );
+        assert_eq!(SeqNum::range_inclusive(SeqNum(7), SeqNum(7)).count(), 1);
+    }
+
+    #[test]
+    fn test_unwrap() {
+        let mut unwrapper = SeqNumUnwrapper::new();
+        assert_eq!(unwrapper.unwrap(65534), 65534);
+        assert_eq!(unwrapper.unwrap(65535), 65535);
+        assert_eq!(unwrapper.unwrap(1), 65537);
+        assert_eq!(unwrapper.roc(), Some(1));
+        // Reordered from before the wrap
+        assert_eq!(unwrapper.unwrap(0), 65536);
+        assert_eq!(unwrapper.unwrap(65533), 65533);
+        assert_eq!(unwrapper.highest(), Some(65537));
+        assert_eq!(unwrapper.unwrap(2), 65538);
+        // A large jump forward
+        assert_eq!(unwrapper.unwrap(30_000), 95_536);
+        assert_eq!(unwrapper.unwrap(60_000), 125_536);
+        assert_eq!(unwrapper.roc(), Some(1));
+        assert_eq!(unwrapper.unwrap(100), 131_172);
+        assert_eq!(unwrapper.roc(), Some(2));
+    }
+
+    #[test]
+    fn test_reordered_before_first() {
+        let mut unwrapper = SeqNumUnwrapper::new();
+        assert_eq!(unwrapper.unwrap(1), 1);
+        assert_eq!(unwrapper.unwrap(65535), 65535);
+        assert_eq!(unwrapper.highest(), Some(1));
+        assert_eq!(unwrapper.unwrap(2), 2);
+    }
+}
@@ -0,0 +1,158 @@
+use std::ops::Range;
+
+use anyhow::{bail, Context, Result};
+use bytes::Bytes;
+
+use crate::{error::RtpParseError, LengthBytes, PacketBufferMut};
+
+use super::{
+    header_extensions::HeaderExtensionConfig,
+    rtp_packet::{read_rtp_packet_bytes_with_config, write_rtp_packet_with_config, RtpPacket},
+};
+
+// https://datatracker.ietf.org/doc/html/rfc3711#section-3.1
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+<+
+//  |V=2|P|X|  CC   |M|     PT      |       sequence number         | |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |                           timestamp                           | |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |           synchronization source (SSRC) identifier            | |
+//  +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+ |
+//  |            contributing source (CSRC) identifiers             | |
+//  |                               ....                            | |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |                   RTP extension (OPTIONAL)                    | |
+//  +>+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | |                          payload  ...                         | |
+//  | |                               +-------------------------------+ |
+//  | |                               | RTP padding   | RTP pad count | |
+//  +>+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+<+
+//  | ~                     SRTP MKI (OPTIONAL)                       ~ |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  | :                 authentication tag (RECOMMENDED)              : |
+//  | +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ |
+//  |                                                                   |
+//  +- Encrypted Portion*                      Authenticated Portion ---+
+//
+// The encryption and authentication itself is left to an SRTP implementation: a packet is read
+// from the (decrypted) SRTP packet with its trailer split off, and written with space for the
+// trailer, which the SRTP implementation fills in when protecting it.
+
+/// An RTP packet along with its SRTP trailer: the MKI (if the session uses one) and the
+/// authentication tag.
+#[derive(Debug)]
+pub struct SrtpPacket {
+    pub packet: RtpPacket,
+    /// The MKI and authentication tag
+    pub trailer: Bytes,
+}
+
+impl SrtpPacket {
+    /// Wrap the given packet with a zeroed trailer of the given length, reserving space for an
+    /// SRTP implementation to write the MKI and authentication tag into when protecting it.
+    pub fn new(packet: RtpPacket, trailer_length_bytes: usize) -> Self {
+        Self {
+            packet,
+            trailer: Bytes::from(vec![0; trailer_length_bytes]),
+        }
+    }
+
+    /// Where the trailer will be in the written packet
+    pub fn trailer_range(&self) -> Range<usize> {
+        let packet_length_bytes = self.packet.length_bytes();
+        packet_length_bytes..packet_length_bytes + self.trailer.len()
+    }
+}
+
+impl LengthBytes for SrtpPacket {
+    fn length_bytes(&self) -> usize {
+        self.packet.length_bytes() + self.trailer.len()
+    }
+}
+
+/// Read an SRTP packet whose trailer (MKI and authentication tag) is `trailer_length_bytes` long.
+/// The RTP packet is read from everything before the trailer, so the payload should already have
+/// been decrypted.
+pub fn read_srtp_packet_bytes(bytes: Bytes, trailer_length_bytes: usize) -> Result<SrtpPacket> {
+    read_srtp_packet_bytes_with_config(
+        bytes,
+        trailer_length_bytes,
+        &HeaderExtensionConfig::default(),
+    )
+}
+
+/// Like [`read_srtp_packet_bytes`], but header extensions are read using the given config.
+pub fn read_srtp_packet_bytes_with_config(
+    mut bytes: Bytes,
+    trailer_length_bytes: usize,
+    config: &HeaderExtensionConfig,
+) -> Result<SrtpPacket> {
+    let Some(packet_length_bytes) = bytes.len().checked_sub(trailer_length_bytes) else {
+        bail!(RtpParseError::truncated(trailer_length_bytes, bytes.len()));
+    };
+    let trailer = bytes.split_off(packet_length_bytes);
+    let packet = read_rtp_packet_bytes_with_config(bytes, config).context("rtp packet")?;
+
+    Ok(SrtpPacket { packet, trailer })
+}
+
+/// Write the given packet followed by its trailer.  [`RtpPacket::sync`] should be called first so
+/// the header matches the packet's contents.
+pub fn write_srtp_packet<B: PacketBufferMut>(buf: &mut B, packet: &SrtpPacket) -> Result<()> {
+    write_srtp_packet_with_config(buf, packet, &HeaderExtensionConfig::default())
+}
+
+/// Like [`write_srtp_packet`], but header extensions are written using the given config.
+pub fn write_srtp_packet_with_config<B: PacketBufferMut>(
+    buf: &mut B,
+    packet: &SrtpPacket,
+    config: &HeaderExtensionConfig,
+) -> Result<()> {
+    write_rtp_packet_with_config(buf, &packet.packet, config).context("rtp packet")?;
+    std::io::Write::write_all(buf, &packet.trailer).context("srtp trailer")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::rtp::rtp_packet::RtpPacketBuilder;
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let packet = RtpPacketBuilder::new()
+            .seq_num(10)
+            .extension(1, &[0xAA])
+            .payload(&[1, 2, 3])
+            .padding(2)
+            .build()
+            .unwrap();
+        let srtp_packet = SrtpPacket::new(packet, 10);
+        // header (12) + extensions (8) + payload (3) + padding (2) + trailer (10)
+        assert_eq!(srtp_packet.length_bytes(), 35);
+        assert_eq!(srtp_packet.trailer_range(), 25..35);
+
+        let length_bytes = srtp_packet.length_bytes();
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; length_bytes]));
+        write_srtp_packet(&mut cursor, &srtp_packet).unwrap();
+        let mut data = cursor.into_inner().into_vec();
+        assert_eq!(data[23..25], [0x00, 0x02]);
+        // Fill in the tag, as an SRTP implementation would
+        data[srtp_packet.trailer_range()].fill(0xFF);
+
+        let read_packet = read_srtp_packet_bytes(Bytes::from(data), 10).unwrap();
+        assert_eq!(read_packet.trailer.as_ref(), [0xFF; 10]);
+        assert_eq!(read_packet.packet.seq_num(), 10);
+        assert_eq!(read_packet.packet.payload(), [1, 2, 3]);
+        assert_eq!(read_packet.packet.padding_len(), 2);
+
+        assert!(read_srtp_packet_bytes(Bytes::from_static(&[0; 8]), 10).is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use super::header_extensions::SomeHeaderExtension;
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::{RtpHeaderExtensionValue, SomeHeaderExtension};
 
 //
 // https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01#section-2.2
@@ -9,8 +13,100 @@ use super::header_extensions::SomeHeaderExtension;
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 //
 
+/// The transport-wide sequence number of a packet, which TCC feedback refers to packets by.  When
+/// forwarding, it can be rewritten without re-serializing the packet using
+/// [`RawRtpPacketMut::set_extension_in_place`](super::raw_rtp_packet::RawRtpPacketMut::set_extension_in_place).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransportWideSeqNum(pub u16);
+
+impl RtpHeaderExtensionValue for TransportWideSeqNum {
+    const URI: &'static str =
+        "http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let [b0, b1] = data else {
+            bail!(RtpParseError::invalid_field(
+                "transport-wide sequence number length",
+                "2 bytes",
+                format!("{} bytes", data.len())
+            ));
+        };
+        Ok(TransportWideSeqNum(u16::from_be_bytes([*b0, *b1])))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.to_be_bytes().to_vec()
+    }
+}
+
+#[deprecated(note = "use TransportWideSeqNum via HeaderExtensions::get_typed")]
 pub fn get_tcc_seq_num(ext: &SomeHeaderExtension) -> u16 {
     let data = ext.data();
 
     (data[0] as u16) << 8 | data[1] as u16
 }
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::{
+        rtp::{
+            raw_rtp_packet::RawRtpPacketMut,
+            rtp_packet::{read_rtp_packet, write_rtp_packet, RtpPacketBuilder},
+        },
+        LengthBytes,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_rewrite_in_place() {
+        #[rustfmt::skip]
+        let mut data: Vec<u8> = vec![
+            0x90, 0x6f, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x03,
+            0xBE, 0xDE, 0x00, 0x02,
+            // Audio level (id 1), then transport-wide seq num (id 5)
+            0x10, 0xFF, 0x51, 0x12, 0x34, 0x00, 0x00, 0x00,
+            0xAA,
+        ];
+        let mut packet = RawRtpPacketMut::new(&mut data).unwrap();
+        packet
+            .set_extension_in_place(5, &TransportWideSeqNum(0xABCD))
+            .unwrap();
+        assert!(packet
+            .set_extension_in_place(2, &TransportWideSeqNum(0))
+            .is_err());
+
+        let read_packet = read_rtp_packet(data).unwrap();
+        assert_eq!(
+            read_packet
+                .header_extensions()
+                .get_typed::<TransportWideSeqNum>(5)
+                .unwrap(),
+            Some(TransportWideSeqNum(0xABCD))
+        );
+        assert_eq!(read_packet.payload(), [0xAA]);
+    }
+
+    #[test]
+    fn test_rewrite_in_place_two_byte() {
+        let mut packet = RtpPacketBuilder::new()
+            .extension(20, &[0x00])
+            .extension(3, &[0x12, 0x34])
+            .build()
+            .unwrap();
+        packet.sync().unwrap();
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![0; packet.length_bytes()]));
+        write_rtp_packet(&mut cursor, &packet).unwrap();
+        let mut data = cursor.into_inner().into_vec();
+
+        let mut raw_packet = RawRtpPacketMut::new(&mut data).unwrap();
+        raw_packet
+            .set_extension_in_place(3, &TransportWideSeqNum(7))
+            .unwrap();
+        assert_eq!(raw_packet.extension_data_mut(3).unwrap(), [0x00, 0x07]);
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// https://datatracker.ietf.org/doc/html/rfc5450#section-3
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=2 |              transmission offset              |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The transmission offset is a signed 24 bit offset, in the same units as the RTP timestamp, from
+// the packet's RTP timestamp to the time it was actually sent.
+
+const MIN_OFFSET: i32 = -(1 << 23);
+const MAX_OFFSET: i32 = (1 << 23) - 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransmissionOffset(i32);
+
+impl TransmissionOffset {
+    /// `offset` is in RTP timestamp units and must fit in a signed 24 bit value.
+    pub fn new(offset: i32) -> Result<Self> {
+        if !(MIN_OFFSET..=MAX_OFFSET).contains(&offset) {
+            bail!("Transmission offset must be {MIN_OFFSET} to {MAX_OFFSET}, got {offset}");
+        }
+        Ok(TransmissionOffset(offset))
+    }
+
+    /// Create a transmission offset from a number of milliseconds, using the given RTP clock rate
+    /// (in Hz).
+    pub fn from_millis(millis: i64, clock_rate: u32) -> Result<Self> {
+        let offset = millis * clock_rate as i64 / 1000;
+        match i32::try_from(offset) {
+            Ok(offset) => Self::new(offset),
+            Err(_) => bail!("Transmission offset of {millis}ms is too large"),
+        }
+    }
+
+    /// The offset in RTP timestamp units
+    pub fn offset(&self) -> i32 {
+        self.0
+    }
+
+    /// The offset in milliseconds, using the given RTP clock rate (in Hz).
+    pub fn as_millis(&self, clock_rate: u32) -> i64 {
+        self.0 as i64 * 1000 / clock_rate as i64
+    }
+}
+
+impl RtpHeaderExtensionValue for TransmissionOffset {
+    const URI: &'static str = "urn:ietf:params:rtp-hdrext:toffset";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let [b0, b1, b2] = data else {
+            bail!(RtpParseError::invalid_field(
+                "transmission offset length",
+                "3 bytes",
+                format!("{} bytes", data.len())
+            ));
+        };
+        // Shift the 24 bits into the top of an i32 and back down to sign extend them
+        let offset = i32::from_be_bytes([*b0, *b1, *b2, 0]) >> 8;
+        Ok(TransmissionOffset(offset))
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        self.0.to_be_bytes()[1..].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_offset_roundtrip() {
+        let toffset = TransmissionOffset::new(-2).unwrap();
+        assert_eq!(toffset.encode(), vec![0xFF, 0xFF, 0xFE]);
+        assert_eq!(
+            TransmissionOffset::parse(&[0xFF, 0xFF, 0xFE]).unwrap(),
+            toffset
+        );
+        assert_eq!(
+            TransmissionOffset::parse(&[0x7F, 0xFF, 0xFF])
+                .unwrap()
+                .offset(),
+            MAX_OFFSET
+        );
+    }
+
+    #[test]
+    fn test_millis_conversion() {
+        let toffset = TransmissionOffset::from_millis(-20, 90_000).unwrap();
+        assert_eq!(toffset.offset(), -1800);
+        assert_eq!(toffset.as_millis(90_000), -20);
+        assert!(TransmissionOffset::new(MAX_OFFSET + 1).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use anyhow::{bail, Context, Result};
+use bit_cursor::{
+    bit_write::BitWrite,
+    bit_write_exts::BitWriteExts,
+    byte_order::NetworkOrder,
+    nsw_types::{u4, u7},
+};
+use bytes::Bytes;
+
+use crate::{error::RtpParseError, LengthBytes};
+
+// https://datatracker.ietf.org/doc/html/rfc5109#section-7.3
+// FEC header:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |E|L|P|X|  CC   |M| PT recovery |            SN base            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          TS recovery                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |        length recovery        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// https://datatracker.ietf.org/doc/html/rfc5109#section-7.4
+// Each FEC level has a header, followed by that level's FEC payload:
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |       Protection Length       |             mask              |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |              mask cont. (present only when L = 1)             |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Bit i of the mask (counting from the most significant bit) is set if the media packet with
+// sequence number SN base + i is protected by the level.
+
+const FEC_HEADER_SIZE_BYTES: usize = 10;
+const EXTENSION_MASK: u8 = 0x80;
+const LONG_MASK_MASK: u8 = 0x40;
+const PADDING_RECOVERY_MASK: u8 = 0x20;
+const EXTENSION_RECOVERY_MASK: u8 = 0x10;
+const MARKER_RECOVERY_MASK: u8 = 0x80;
+
+/// The fields used to recover the protected packets' headers, which are the XOR of the
+/// corresponding fields of the protected packets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UlpfecHeader {
+    /// Whether the masks are 48 bits (rather than 16)
+    pub long_mask: bool,
+    pub padding_recovery: bool,
+    pub extension_recovery: bool,
+    pub csrc_count_recovery: u4,
+    pub marker_recovery: bool,
+    pub payload_type_recovery: u7,
+    pub seq_num_base: u16,
+    pub timestamp_recovery: u32,
+    pub length_recovery: u16,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UlpfecLevel {
+    pub protection_length: u16,
+    /// The 16 (or 48, if the header's long mask bit is set) bit mask of protected packets,
+    /// stored in the low bits
+    pub mask: u64,
+    pub payload: Bytes,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UlpfecPayload {
+    pub header: UlpfecHeader,
+    pub levels: Vec<UlpfecLevel>,
+}
+
+impl UlpfecPayload {
+    fn mask_length_bits(&self) -> u32 {
+        if self.header.long_mask {
+            48
+        } else {
+            16
+        }
+    }
+
+    /// The sequence numbers of the media packets protected by the given level
+    pub fn protected_seq_nums(&self, level: usize) -> Vec<u16> {
+        let Some(level) = self.levels.get(level) else {
+            return Vec::new();
+        };
+        let mask_length_bits = self.mask_length_bits();
+        (0..mask_length_bits)
+            .filter(|i| level.mask & (1 << (mask_length_bits - 1 - i)) != 0)
+            .map(|i| self.header.seq_num_base.wrapping_add(i as u16))
+            .collect()
+    }
+
+    /// Whether any level protects the media packet with the given sequence number
+    pub fn protects(&self, seq_num: u16) -> bool {
+        let i = seq_num.wrapping_sub(self.header.seq_num_base) as u32;
+        let mask_length_bits = self.mask_length_bits();
+        i < mask_length_bits
+            && self
+                .levels
+                .iter()
+                .any(|level| level.mask & (1 << (mask_length_bits - 1 - i)) != 0)
+    }
+}
+
+impl LengthBytes for UlpfecPayload {
+    fn length_bytes(&self) -> usize {
+        let level_header_size_bytes = 2 + self.mask_length_bits() as usize / 8;
+        FEC_HEADER_SIZE_BYTES
+            + self
+                .levels
+                .iter()
+                .map(|level| level_header_size_bytes + level.payload.len())
+                .sum::<usize>()
+    }
+}
+
+/// Read a ULPFEC payload.  The levels' payloads are slices of `payload` rather than copies.
+pub fn read_ulpfec_payload(payload: Bytes) -> Result<UlpfecPayload> {
+    if payload.len() < FEC_HEADER_SIZE_BYTES {
+        bail!(RtpParseError::truncated(
+            FEC_HEADER_SIZE_BYTES,
+            payload.len()
+        ));
+    }
+    if payload[0] & EXTENSION_MASK != 0 {
+        bail!(RtpParseError::invalid_field(
+            "ULPFEC E bit",
+            "0, since no extension is defined",
+            1
+        ));
+    }
+    let header = UlpfecHeader {
+        long_mask: payload[0] & LONG_MASK_MASK != 0,
+        padding_recovery: payload[0] & PADDING_RECOVERY_MASK != 0,
+        extension_recovery: payload[0] & EXTENSION_RECOVERY_MASK != 0,
+        csrc_count_recovery: u4::new(payload[0] & 0xF),
+        marker_recovery: payload[1] & MARKER_RECOVERY_MASK != 0,
+        payload_type_recovery: u7::new(payload[1] & 0x7F),
+        seq_num_base: u16::from_be_bytes([payload[2], payload[3]]),
+        timestamp_recovery: u32::from_be_bytes(payload[4..8].try_into().unwrap()),
+        length_recovery: u16::from_be_bytes([payload[8], payload[9]]),
+    };
+    let mask_length_bytes = if header.long_mask { 6 } else { 2 };
+
+    let mut levels = Vec::new();
+    let mut offset = FEC_HEADER_SIZE_BYTES;
+    while offset < payload.len() {
+        let Some(level_header) = payload.get(offset..offset + 2 + mask_length_bytes) else {
+            return Err(RtpParseError::truncated(
+                offset + 2 + mask_length_bytes,
+                payload.len(),
+            ))
+            .with_context(|| format!("level {} header", levels.len()));
+        };
+        let protection_length = u16::from_be_bytes([level_header[0], level_header[1]]);
+        let mask = level_header[2..]
+            .iter()
+            .fold(0u64, |mask, byte| (mask << 8) | *byte as u64);
+        offset += level_header.len();
+        let Some(level_payload) = payload.get(offset..offset + protection_length as usize) else {
+            return Err(RtpParseError::truncated(
+                offset + protection_length as usize,
+                payload.len(),
+            ))
+            .with_context(|| format!("level {} payload", levels.len()));
+        };
+        levels.push(UlpfecLevel {
+            protection_length,
+            mask,
+            payload: payload.slice_ref(level_payload),
+        });
+        offset += protection_length as usize;
+    }
+
+    Ok(UlpfecPayload { header, levels })
+}
+
+pub fn write_ulpfec_payload<W: BitWrite>(
+    buf: &mut W,
+    ulpfec_payload: &UlpfecPayload,
+) -> Result<()> {
+    let header = &ulpfec_payload.header;
+    let mut first = u8::from(header.csrc_count_recovery);
+    for (set, mask) in [
+        (header.long_mask, LONG_MASK_MASK),
+        (header.padding_recovery, PADDING_RECOVERY_MASK),
+        (header.extension_recovery, EXTENSION_RECOVERY_MASK),
+    ] {
+        if set {
+            first |= mask;
+        }
+    }
+    let mut second = u8::from(header.payload_type_recovery);
+    if header.marker_recovery {
+        second |= MARKER_RECOVERY_MASK;
+    }
+    buf.write_u8(first).context("flags")?;
+    buf.write_u8(second)
+        .context("marker and payload type recovery")?;
+    buf.write_u16::<NetworkOrder>(header.seq_num_base)
+        .context("seq num base")?;
+    buf.write_u32::<NetworkOrder>(header.timestamp_recovery)
+        .context("timestamp recovery")?;
+    buf.write_u16::<NetworkOrder>(header.length_recovery)
+        .context("length recovery")?;
+
+    let mask_length_bytes = ulpfec_payload.mask_length_bits() as usize / 8;
+    for (i, level) in ulpfec_payload.levels.iter().enumerate() {
+        if level.protection_length as usize != level.payload.len() {
+            bail!(
+                "ULPFEC level {i} protection length is {} but its payload is {} bytes",
+                level.protection_length,
+                level.payload.len()
+            );
+        }
+        buf.write_u16::<NetworkOrder>(level.protection_length)
+            .with_context(|| format!("level {i} protection length"))?;
+        std::io::Write::write_all(buf, &level.mask.to_be_bytes()[8 - mask_length_bytes..])
+            .with_context(|| format!("level {i} mask"))?;
+        std::io::Write::write_all(buf, &level.payload)
+            .with_context(|| format!("level {i} payload"))?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let ulpfec_payload = UlpfecPayload {
+            header: UlpfecHeader {
+                long_mask: false,
+                padding_recovery: false,
+                extension_recovery: true,
+                csrc_count_recovery: u4::new(0),
+                marker_recovery: true,
+                payload_type_recovery: u7::new(96),
+                seq_num_base: 65534,
+                timestamp_recovery: 0x12345678,
+                length_recovery: 100,
+            },
+            levels: vec![UlpfecLevel {
+                protection_length: 3,
+                mask: 0b1010_0000_0000_0001,
+                payload: Bytes::from_static(&[1, 2, 3]),
+            }],
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(vec![
+            0;
+            ulpfec_payload
+                .length_bytes()
+        ]));
+        write_ulpfec_payload(&mut cursor, &ulpfec_payload).unwrap();
+        let data = cursor.into_inner().into_vec();
+        assert_eq!(&data[..2], [0x10, 0xE0]);
+        let parsed = read_ulpfec_payload(Bytes::from(data)).unwrap();
+        assert_eq!(parsed, ulpfec_payload);
+
+        assert_eq!(parsed.protected_seq_nums(0), vec![65534, 0, 13]);
+        assert!(parsed.protects(0));
+        assert!(!parsed.protects(65535));
+        assert!(!parsed.protects(14));
+    }
+
+    #[test]
+    fn test_long_mask() {
+        #[rustfmt::skip]
+        let data = Bytes::from_static(&[
+            0x40, 0x00, 0x00, 0x0A, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            // Protection length 0, bits 0 and 47 of the mask
+            0x00, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x01,
+        ]);
+        let parsed = read_ulpfec_payload(data).unwrap();
+        assert_eq!(parsed.protected_seq_nums(0), vec![10, 57]);
+        assert!(read_ulpfec_payload(Bytes::from_static(&[0x80; 10])).is_err());
+    }
+}
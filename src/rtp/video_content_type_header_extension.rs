@@ -0,0 +1,56 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/video-content-type
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=0 | Content type  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// The content type is 0 for unspecified content and 1 for screenshare.
+
+/// The type of content a video stream is carrying, which receivers (and SFUs) can use to favor
+/// e.g. resolution over framerate for screenshare.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VideoContentType {
+    Unspecified,
+    Screenshare,
+}
+
+impl VideoContentType {
+    pub fn is_screenshare(&self) -> bool {
+        *self == VideoContentType::Screenshare
+    }
+}
+
+impl RtpHeaderExtensionValue for VideoContentType {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/video-content-type";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        match data {
+            [0] => Ok(VideoContentType::Unspecified),
+            [1] => Ok(VideoContentType::Screenshare),
+            [value] => bail!(RtpParseError::invalid_field(
+                "video content type",
+                "0 or 1",
+                value
+            )),
+            _ => bail!(RtpParseError::invalid_field(
+                "video content type length",
+                "1 byte",
+                format!("{} bytes", data.len())
+            )),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            VideoContentType::Unspecified => vec![0],
+            VideoContentType::Screenshare => vec![1],
+        }
+    }
+}
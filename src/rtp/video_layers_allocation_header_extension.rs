@@ -0,0 +1,305 @@
+use anyhow::{bail, Context, Result};
+
+use crate::{
+    error::RtpParseError,
+    util::{read_leb128, write_leb128},
+};
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/video-layers-allocation00
+//                            +-+-+-+-+-+-+-+-+
+//                            |RID| NS| sl_bm |
+//                            +-+-+-+-+-+-+-+-+
+// Spatial layer bitmask      |sl0_bm |sl1_bm |
+//   up to 2 bytes            |---------------|
+//   when sl_bm == 0          |sl2_bm |sl3_bm |
+//                            +-+-+-+-+-+-+-+-+
+// Number of temporal layers  |#tl|#tl|#tl|#tl|
+// per spatial layer          |   |   |   |   |
+//                            +-+-+-+-+-+-+-+-+
+//  Target bitrate in kbps    |               |
+//   per temporal layer       :      ...      :
+//    leb128 encoded          |               |
+//                            +-+-+-+-+-+-+-+-+
+// Resolution and framerate   |               |
+//  5 bytes per spatial layer + width-1 for   +
+//       (optional)           | rid=0, sid=0  |
+//                            +---------------+
+//                            |               |
+//                            + height-1 for  +
+//                            | rid=0, sid=0  |
+//                            +---------------+
+//                            | max framerate |
+//                            +-+-+-+-+-+-+-+-+
+//                            :      ...      :
+//                            +-+-+-+-+-+-+-+-+
+//
+// RID: the index of the rtp stream this packet belongs to
+// NS: the number of rtp streams - 1
+// sl_bm: the active spatial layers bitmask, if it's the same for all streams.  If it's 0, there's
+//   a bitmask for each stream instead (padded to a whole byte).
+// #tl: the number of temporal layers - 1 of each active spatial layer, ordered by stream and then
+//   spatial id (padded to a whole byte)
+// The target bitrates are cumulative: each temporal layer's includes the lower layers.
+//
+// A single 0 byte means there are no active layers (e.g. the video is paused).
+
+const MAX_RTP_STREAMS: usize = 4;
+const MAX_SPATIAL_LAYERS: u8 = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpatialLayer {
+    /// 0-3
+    pub rtp_stream_index: u8,
+    /// 0-3
+    pub spatial_id: u8,
+    /// The cumulative target bitrate of each temporal layer (1-4 layers)
+    pub target_bitrates_kbps: Vec<u32>,
+    /// Only meaningful if the allocation's `resolution_and_frame_rate_is_valid` is set
+    pub width: u16,
+    pub height: u16,
+    pub frame_rate_fps: u8,
+}
+
+/// The layers the sender is currently producing.  The active spatial layers must be ordered by
+/// rtp stream index and then spatial id.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VideoLayersAllocation {
+    /// The rtp stream this packet belongs to
+    pub rtp_stream_index: u8,
+    pub resolution_and_frame_rate_is_valid: bool,
+    pub active_spatial_layers: Vec<SpatialLayer>,
+}
+
+impl VideoLayersAllocation {
+    /// The active spatial layers bitmask of each rtp stream
+    fn spatial_layer_bitmasks(&self) -> Vec<u8> {
+        let num_rtp_streams = self
+            .active_spatial_layers
+            .iter()
+            .map(|layer| layer.rtp_stream_index as usize + 1)
+            .max()
+            .unwrap_or(1)
+            .min(MAX_RTP_STREAMS);
+        let mut bitmasks = vec![0u8; num_rtp_streams];
+        for layer in &self.active_spatial_layers {
+            if let Some(bitmask) = bitmasks.get_mut(layer.rtp_stream_index as usize) {
+                *bitmask |= 1 << (layer.spatial_id % MAX_SPATIAL_LAYERS);
+            }
+        }
+
+        bitmasks
+    }
+}
+
+impl RtpHeaderExtensionValue for VideoLayersAllocation {
+    const URI: &'static str =
+        "http://www.webrtc.org/experiments/rtp-hdrext/video-layers-allocation00";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let Some(first) = data.first() else {
+            bail!(RtpParseError::truncated(1, 0));
+        };
+        if data == [0] {
+            return Ok(VideoLayersAllocation {
+                resolution_and_frame_rate_is_valid: true,
+                ..Default::default()
+            });
+        }
+        let rtp_stream_index = first >> 6;
+        let num_rtp_streams = ((first >> 4) & 0x3) as usize + 1;
+        let shared_bitmask = first & 0xF;
+        let mut offset = 1;
+
+        let bitmasks = if shared_bitmask != 0 {
+            vec![shared_bitmask; num_rtp_streams]
+        } else {
+            let num_bytes = num_rtp_streams.div_ceil(2);
+            let Some(bytes) = data.get(offset..offset + num_bytes) else {
+                bail!(RtpParseError::truncated(offset + num_bytes, data.len()));
+            };
+            offset += num_bytes;
+            (0..num_rtp_streams)
+                .map(|i| (bytes[i / 2] >> (4 * (1 - i % 2))) & 0xF)
+                .collect()
+        };
+        let mut active_spatial_layers = Vec::new();
+        for (rtp_stream_index, bitmask) in bitmasks.iter().enumerate() {
+            for spatial_id in 0..MAX_SPATIAL_LAYERS {
+                if bitmask & (1 << spatial_id) != 0 {
+                    active_spatial_layers.push(SpatialLayer {
+                        rtp_stream_index: rtp_stream_index as u8,
+                        spatial_id,
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        if active_spatial_layers.is_empty() {
+            bail!(RtpParseError::invalid_field(
+                "spatial layer bitmasks",
+                "at least one active spatial layer",
+                "none"
+            ));
+        }
+
+        let num_bytes = active_spatial_layers.len().div_ceil(4);
+        let Some(bytes) = data.get(offset..offset + num_bytes) else {
+            bail!(RtpParseError::truncated(offset + num_bytes, data.len()));
+        };
+        offset += num_bytes;
+        let num_temporal_layers = (0..active_spatial_layers.len())
+            .map(|i| ((bytes[i / 4] >> (2 * (3 - i % 4))) & 0x3) + 1)
+            .collect::<Vec<_>>();
+
+        for (layer, num_temporal_layers) in
+            active_spatial_layers.iter_mut().zip(num_temporal_layers)
+        {
+            for _ in 0..num_temporal_layers {
+                let (bitrate, len) = read_leb128(&data[offset..]).with_context(|| {
+                    format!(
+                        "target bitrate for stream {} spatial layer {}",
+                        layer.rtp_stream_index, layer.spatial_id
+                    )
+                })?;
+                layer.target_bitrates_kbps.push(bitrate);
+                offset += len;
+            }
+        }
+
+        let remaining = &data[offset..];
+        let resolution_and_frame_rate_is_valid = !remaining.is_empty();
+        if resolution_and_frame_rate_is_valid {
+            if remaining.len() != 5 * active_spatial_layers.len() {
+                bail!(RtpParseError::invalid_field(
+                    "resolutions and frame rates length",
+                    format!("{} bytes", 5 * active_spatial_layers.len()),
+                    format!("{} bytes", remaining.len())
+                ));
+            }
+            for (layer, chunk) in active_spatial_layers.iter_mut().zip(remaining.chunks(5)) {
+                layer.width = u16::from_be_bytes([chunk[0], chunk[1]]).saturating_add(1);
+                layer.height = u16::from_be_bytes([chunk[2], chunk[3]]).saturating_add(1);
+                layer.frame_rate_fps = chunk[4];
+            }
+        }
+
+        Ok(VideoLayersAllocation {
+            rtp_stream_index,
+            resolution_and_frame_rate_is_valid,
+            active_spatial_layers,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        if self.active_spatial_layers.is_empty() {
+            return vec![0];
+        }
+        let bitmasks = self.spatial_layer_bitmasks();
+        let shared_bitmask = if bitmasks.iter().all(|bitmask| *bitmask == bitmasks[0]) {
+            bitmasks[0]
+        } else {
+            0
+        };
+        let mut data = vec![
+            (self.rtp_stream_index & 0x3) << 6 | ((bitmasks.len() - 1) as u8) << 4 | shared_bitmask,
+        ];
+        if shared_bitmask == 0 {
+            for pair in bitmasks.chunks(2) {
+                data.push(pair[0] << 4 | pair.get(1).copied().unwrap_or(0));
+            }
+        }
+        for layers in self.active_spatial_layers.chunks(4) {
+            let mut byte = 0;
+            for (i, layer) in layers.iter().enumerate() {
+                let num_temporal_layers = layer.target_bitrates_kbps.len().clamp(1, 4) as u8;
+                byte |= (num_temporal_layers - 1) << (2 * (3 - i));
+            }
+            data.push(byte);
+        }
+        for layer in &self.active_spatial_layers {
+            let bitrates = &layer.target_bitrates_kbps;
+            if bitrates.is_empty() {
+                write_leb128(&mut data, 0);
+            }
+            for bitrate in bitrates.iter().take(4) {
+                write_leb128(&mut data, *bitrate);
+            }
+        }
+        if self.resolution_and_frame_rate_is_valid {
+            for layer in &self.active_spatial_layers {
+                data.extend_from_slice(&layer.width.saturating_sub(1).to_be_bytes());
+                data.extend_from_slice(&layer.height.saturating_sub(1).to_be_bytes());
+                data.push(layer.frame_rate_fps);
+            }
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layer(rtp_stream_index: u8, spatial_id: u8, target_bitrates_kbps: &[u32]) -> SpatialLayer {
+        SpatialLayer {
+            rtp_stream_index,
+            spatial_id,
+            target_bitrates_kbps: target_bitrates_kbps.to_vec(),
+            width: 320 << spatial_id,
+            height: 180 << spatial_id,
+            frame_rate_fps: 30,
+        }
+    }
+
+    #[test]
+    fn test_shared_bitmask() {
+        let allocation = VideoLayersAllocation {
+            rtp_stream_index: 1,
+            resolution_and_frame_rate_is_valid: false,
+            active_spatial_layers: vec![
+                layer(0, 0, &[50, 100]),
+                layer(1, 0, &[150, 300]),
+                layer(2, 0, &[500, 1_000]),
+            ],
+        };
+        let data = allocation.encode();
+        // RID=1, NS=2, sl_bm=0b0001
+        assert_eq!(data[0], 0b0110_0001);
+        // One temporal layer count byte, then 2 bitrates per layer
+        assert_eq!(data[1], 0b0101_0100);
+        assert_eq!(data.len(), 2 + 1 + 1 + 2 + 2 + 2 + 2);
+        let parsed = VideoLayersAllocation::parse(&data).unwrap();
+        assert_eq!(parsed.active_spatial_layers.len(), 3);
+        assert_eq!(
+            parsed.active_spatial_layers[2].target_bitrates_kbps,
+            vec![500, 1_000]
+        );
+    }
+
+    #[test]
+    fn test_per_stream_bitmasks_with_resolutions() {
+        let allocation = VideoLayersAllocation {
+            rtp_stream_index: 0,
+            resolution_and_frame_rate_is_valid: true,
+            active_spatial_layers: vec![
+                layer(0, 0, &[100]),
+                layer(0, 1, &[200, 400, 600]),
+                layer(1, 2, &[1_500]),
+            ],
+        };
+        let data = allocation.encode();
+        assert_eq!(data[0], 0b0001_0000);
+        assert_eq!(data[1], 0b0011_0100);
+        assert_eq!(VideoLayersAllocation::parse(&data).unwrap(), allocation);
+    }
+
+    #[test]
+    fn test_no_active_layers() {
+        let allocation = VideoLayersAllocation::parse(&[0]).unwrap();
+        assert!(allocation.active_spatial_layers.is_empty());
+        assert_eq!(allocation.encode(), vec![0]);
+    }
+}
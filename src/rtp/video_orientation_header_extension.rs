@@ -0,0 +1,100 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// Coordination of Video Orientation (CVO), 3GPP TS 26.114 section 7.4.5
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=0 |0 0 0 0 C F R R|
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// C: which camera captured the video
+// F: whether the video is flipped horizontally
+// R: the counter-clockwise rotation to apply to the video before displaying it
+
+const CAMERA_MASK: u8 = 0b1000;
+const FLIP_MASK: u8 = 0b0100;
+const ROTATION_MASK: u8 = 0b0011;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Camera {
+    Front,
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl Rotation {
+    pub fn degrees(&self) -> u16 {
+        match self {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 90,
+            Rotation::Deg180 => 180,
+            Rotation::Deg270 => 270,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VideoOrientation {
+    pub camera: Camera,
+    pub flip: bool,
+    pub rotation: Rotation,
+}
+
+impl RtpHeaderExtensionValue for VideoOrientation {
+    const URI: &'static str = "urn:3gpp:video-orientation";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let [value] = data else {
+            bail!(RtpParseError::invalid_field(
+                "video orientation length",
+                "1 byte",
+                format!("{} bytes", data.len())
+            ));
+        };
+        let camera = if value & CAMERA_MASK != 0 {
+            Camera::Back
+        } else {
+            Camera::Front
+        };
+        let rotation = match value & ROTATION_MASK {
+            0 => Rotation::Deg0,
+            1 => Rotation::Deg90,
+            2 => Rotation::Deg180,
+            _ => Rotation::Deg270,
+        };
+
+        Ok(VideoOrientation {
+            camera,
+            flip: value & FLIP_MASK != 0,
+            rotation,
+        })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut value = match self.rotation {
+            Rotation::Deg0 => 0,
+            Rotation::Deg90 => 1,
+            Rotation::Deg180 => 2,
+            Rotation::Deg270 => 3,
+        };
+        if self.camera == Camera::Back {
+            value |= CAMERA_MASK;
+        }
+        if self.flip {
+            value |= FLIP_MASK;
+        }
+
+        vec![value]
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::{bail, Result};
+
+use crate::error::RtpParseError;
+
+use super::header_extensions::RtpHeaderExtensionValue;
+
+// http://www.webrtc.org/experiments/rtp-hdrext/video-timing
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ID   | len=12|     flags     |     encode start ms delta     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |    encode finish ms delta     |  packetizer finish ms delta   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     pacer exit ms delta       |  network timestamp ms delta   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  network2 timestamp ms delta  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Each delta is the number of milliseconds since the frame was captured.  A legacy form without
+// the flags byte (12 bytes) may also be received.
+
+/// The frame's timing was reported because the periodic timer fired
+pub const FLAG_TRIGGERED_BY_TIMER: u8 = 0x01;
+/// The frame's timing was reported because the frame was unusually large
+pub const FLAG_TRIGGERED_BY_SIZE: u8 = 0x02;
+
+const ENCODE_START: usize = 0;
+const ENCODE_FINISH: usize = 1;
+const PACKETIZATION_FINISH: usize = 2;
+const PACER_EXIT: usize = 3;
+const NETWORK_TIMESTAMP: usize = 4;
+const NETWORK2_TIMESTAMP: usize = 5;
+
+/// Timestamps of each stage of sending a frame, as millisecond offsets from its capture time.
+/// The stages happen in order, so the setters reject an offset which is earlier than that of a
+/// previous stage, or later than that of a following stage which has been set (i.e. is
+/// non-zero).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VideoTiming {
+    pub flags: u8,
+    offsets: [u16; 6],
+}
+
+impl VideoTiming {
+    pub fn encode_start_ms(&self) -> u16 {
+        self.offsets[ENCODE_START]
+    }
+
+    pub fn encode_finish_ms(&self) -> u16 {
+        self.offsets[ENCODE_FINISH]
+    }
+
+    pub fn packetization_finish_ms(&self) -> u16 {
+        self.offsets[PACKETIZATION_FINISH]
+    }
+
+    pub fn pacer_exit_ms(&self) -> u16 {
+        self.offsets[PACER_EXIT]
+    }
+
+    pub fn network_timestamp_ms(&self) -> u16 {
+        self.offsets[NETWORK_TIMESTAMP]
+    }
+
+    pub fn network2_timestamp_ms(&self) -> u16 {
+        self.offsets[NETWORK2_TIMESTAMP]
+    }
+
+    pub fn set_encode_start_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(ENCODE_START, offset_ms)
+    }
+
+    pub fn set_encode_finish_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(ENCODE_FINISH, offset_ms)
+    }
+
+    pub fn set_packetization_finish_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(PACKETIZATION_FINISH, offset_ms)
+    }
+
+    pub fn set_pacer_exit_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(PACER_EXIT, offset_ms)
+    }
+
+    pub fn set_network_timestamp_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(NETWORK_TIMESTAMP, offset_ms)
+    }
+
+    pub fn set_network2_timestamp_ms(&mut self, offset_ms: u16) -> Result<()> {
+        self.set_offset(NETWORK2_TIMESTAMP, offset_ms)
+    }
+
+    fn set_offset(&mut self, stage: usize, offset_ms: u16) -> Result<()> {
+        if let Some(previous) = self.offsets[..stage].iter().max() {
+            if offset_ms < *previous {
+                bail!("Offset {offset_ms}ms is before a previous stage's offset ({previous}ms)");
+            }
+        }
+        if let Some(next) = self.offsets[stage + 1..]
+            .iter()
+            .filter(|offset| **offset != 0)
+            .min()
+        {
+            if offset_ms > *next {
+                bail!("Offset {offset_ms}ms is after a following stage's offset ({next}ms)");
+            }
+        }
+        self.offsets[stage] = offset_ms;
+
+        Ok(())
+    }
+}
+
+impl RtpHeaderExtensionValue for VideoTiming {
+    const URI: &'static str = "http://www.webrtc.org/experiments/rtp-hdrext/video-timing";
+
+    fn parse(data: &[u8]) -> Result<Self> {
+        let (flags, offsets_data) = match data.len() {
+            13 => (data[0], &data[1..]),
+            12 => (0, data),
+            len => bail!(RtpParseError::invalid_field(
+                "video timing length",
+                "12 or 13 bytes",
+                format!("{len} bytes")
+            )),
+        };
+        let mut offsets = [0u16; 6];
+        for (offset, bytes) in offsets.iter_mut().zip(offsets_data.chunks_exact(2)) {
+            *offset = u16::from_be_bytes([bytes[0], bytes[1]]);
+        }
+
+        Ok(VideoTiming { flags, offsets })
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(13);
+        data.push(self.flags);
+        for offset in self.offsets {
+            data.extend_from_slice(&offset.to_be_bytes());
+        }
+
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_video_timing_roundtrip() {
+        let mut video_timing = VideoTiming {
+            flags: FLAG_TRIGGERED_BY_SIZE,
+            ..Default::default()
+        };
+        video_timing.set_encode_start_ms(1).unwrap();
+        video_timing.set_encode_finish_ms(5).unwrap();
+        video_timing.set_pacer_exit_ms(9).unwrap();
+        // Before the pacer exit
+        assert!(video_timing.set_packetization_finish_ms(10).is_err());
+        video_timing.set_packetization_finish_ms(7).unwrap();
+        // Before the encode finish
+        assert!(video_timing.set_network_timestamp_ms(4).is_err());
+
+        let data = video_timing.encode();
+        assert_eq!(
+            data,
+            vec![0x02, 0x00, 0x01, 0x00, 0x05, 0x00, 0x07, 0x00, 0x09, 0x00, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(VideoTiming::parse(&data).unwrap(), video_timing);
+        // Legacy form without flags
+        let legacy = VideoTiming::parse(&data[1..]).unwrap();
+        assert_eq!(legacy.flags, 0);
+        assert_eq!(legacy.pacer_exit_ms(), 9);
+    }
+}
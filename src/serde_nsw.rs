@@ -0,0 +1,56 @@
+//! Serde helpers for the fields with non-standard-width integer types (e.g. `u13`).  Each
+//! module serializes the value as the smallest standard integer type which can hold it, and
+//! rejects out of range values when deserializing, e.g.:
+//!
+//! ```ignore
+//! #[serde(with = "crate::serde_nsw::u13")]
+//! pub run_length: u13,
+//! ```
+
+macro_rules! nsw_serde {
+    ($($name:ident: $inner:ty),* $(,)?) => {
+        $(
+            pub mod $name {
+                use bit_cursor::nsw_types;
+                use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+                pub fn serialize<S: Serializer>(
+                    value: &nsw_types::$name,
+                    serializer: S,
+                ) -> Result<S::Ok, S::Error> {
+                    <$inner>::from(*value).serialize(serializer)
+                }
+
+                pub fn deserialize<'de, D: Deserializer<'de>>(
+                    deserializer: D,
+                ) -> Result<nsw_types::$name, D::Error> {
+                    let value = <$inner>::deserialize(deserializer)?;
+                    if value > <$inner>::from(nsw_types::$name::MAX) {
+                        return Err(D::Error::custom(format!(
+                            concat!("{} is out of range for a ", stringify!($name)),
+                            value
+                        )));
+                    }
+                    Ok(nsw_types::$name::new(value))
+                }
+            }
+        )*
+    };
+}
+
+nsw_serde!(
+    u2: u8,
+    u3: u8,
+    u4: u8,
+    u5: u8,
+    u6: u8,
+    u7: u8,
+    u9: u16,
+    u12: u16,
+    u13: u16,
+    u14: u16,
+    u15: u16,
+    u17: u32,
+    u18: u32,
+    u24: u32,
+);
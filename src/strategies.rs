@@ -0,0 +1,450 @@
+//! [proptest](https://docs.rs/proptest) strategies which generate valid RTP and RTCP packets.
+//! Every generated packet is synced, so it can be written as-is, and compound packets follow the
+//! rules in https://datatracker.ietf.org/doc/html/rfc3550#section-6.1.
+
+use std::time::Duration;
+
+use bit_cursor::nsw_types::{u18, u2, u24, u5, u6, u7};
+use proptest::{
+    collection::{btree_map, btree_set, vec},
+    option,
+    prelude::*,
+};
+
+use crate::{
+    rtcp::{
+        rtcp_app::RtcpAppPacket,
+        rtcp_bye::RtcpByePacket,
+        rtcp_compound_builder::RtcpCompoundBuilder,
+        rtcp_fb_header::RtcpFbHeader,
+        rtcp_fb_nack::RtcpFbNackPacket,
+        rtcp_fb_pli::RtcpFbPliPacket,
+        rtcp_fb_remb::RtcpFbRembPacket,
+        rtcp_fb_tcc::RtcpFbTccPacket,
+        rtcp_fb_tcc_builder::RtcpFbTccBuilder,
+        rtcp_header::RtcpHeader,
+        rtcp_packet::SomeRtcpPacket,
+        rtcp_report_block::RtcpReportBlock,
+        rtcp_rr::RtcpRrPacket,
+        rtcp_sdes::{RtcpSdesPacket, SdesChunk, SdesItem},
+        rtcp_sender_info::RtcpSenderInfo,
+        rtcp_sr::RtcpSrPacket,
+    },
+    rtp::rtp_packet::{RtpPacket, RtpPacketBuilder},
+};
+
+/// A header to be filled in when the packet it belongs to is synced
+fn unsynced_header(report_count: u5) -> RtcpHeader {
+    RtcpHeader {
+        version: u2::new(2),
+        has_padding: false,
+        report_count,
+        packet_type: 0,
+        length_field: 0,
+    }
+}
+
+/// RTP packets with any number of csrcs, up to 4 one byte header extensions, a payload of up to
+/// 1200 bytes and optional padding
+pub fn rtp_packet() -> impl Strategy<Value = RtpPacket> {
+    (
+        any::<bool>(),
+        0..=u8::from(u7::MAX),
+        any::<u16>(),
+        any::<u32>(),
+        any::<u32>(),
+        vec(any::<u32>(), 0..=15),
+        btree_map(1..=14u8, vec(any::<u8>(), 1..=16), 0..=4),
+        vec(any::<u8>(), 0..=1200),
+        prop_oneof![Just(0u8), 1..=u8::MAX],
+    )
+        .prop_map(
+            |(
+                marked,
+                payload_type,
+                seq_num,
+                timestamp,
+                ssrc,
+                csrcs,
+                extensions,
+                payload,
+                padding,
+            )| {
+                let mut builder = RtpPacketBuilder::new()
+                    .marked(marked)
+                    .payload_type(u7::new(payload_type))
+                    .seq_num(seq_num)
+                    .timestamp(timestamp)
+                    .ssrc(ssrc)
+                    .payload(&payload)
+                    .padding(padding);
+                for csrc in csrcs {
+                    builder = builder.csrc(csrc);
+                }
+                for (id, data) in extensions {
+                    builder = builder.extension(id, &data);
+                }
+                builder.build().expect("generated RTP packet is valid")
+            },
+        )
+}
+
+pub fn rtcp_report_block() -> impl Strategy<Value = RtcpReportBlock> {
+    (
+        any::<u32>(),
+        any::<u8>(),
+        0..=u32::from(u24::MAX),
+        any::<u32>(),
+        any::<u32>(),
+        any::<u32>(),
+        any::<u32>(),
+    )
+        .prop_map(
+            |(
+                ssrc,
+                fraction_lost,
+                cumulative_lost,
+                extended_highest_seq_num,
+                interarrival_jitter,
+                last_sr_timestamp,
+                delay_since_last_sr,
+            )| RtcpReportBlock {
+                ssrc,
+                fraction_lost,
+                cumulative_lost: u24::new(cumulative_lost),
+                extended_highest_seq_num,
+                interarrival_jitter,
+                last_sr_timestamp,
+                delay_since_last_sr,
+            },
+        )
+}
+
+/// Profile-specific extensions, which are a multiple of 4 bytes
+fn profile_extensions() -> impl Strategy<Value = Vec<u8>> {
+    vec(any::<[u8; 4]>(), 0..=2).prop_map(|words| words.concat())
+}
+
+pub fn rtcp_sr_packet() -> impl Strategy<Value = RtcpSrPacket> {
+    (
+        any::<u32>(),
+        any::<[u32; 5]>(),
+        vec(rtcp_report_block(), 0..=31),
+        profile_extensions(),
+    )
+        .prop_map(
+            |(sender_ssrc, sender_info, report_blocks, profile_extensions)| {
+                let mut sr = RtcpSrPacket {
+                    header: unsynced_header(u5::new(0)),
+                    sender_ssrc,
+                    sender_info: RtcpSenderInfo {
+                        ntp_timestamp_msw: sender_info[0],
+                        ntp_timestamp_lsw: sender_info[1],
+                        rtp_timestamp: sender_info[2],
+                        sender_packet_count: sender_info[3],
+                        sender_octet_count: sender_info[4],
+                    },
+                    report_blocks,
+                    profile_extensions,
+                };
+                sr.sync().expect("generated SR is valid");
+                sr
+            },
+        )
+}
+
+pub fn rtcp_rr_packet() -> impl Strategy<Value = RtcpRrPacket> {
+    (
+        any::<u32>(),
+        vec(rtcp_report_block(), 0..=31),
+        profile_extensions(),
+    )
+        .prop_map(|(sender_ssrc, report_blocks, profile_extensions)| {
+            let mut rr = RtcpRrPacket {
+                header: unsynced_header(u5::new(0)),
+                sender_ssrc,
+                report_blocks,
+                profile_extensions,
+            };
+            rr.sync().expect("generated RR is valid");
+            rr
+        })
+}
+
+/// An SDES chunk with a CNAME item, optionally followed by items of other types
+fn sdes_chunk() -> impl Strategy<Value = SdesChunk> {
+    (
+        any::<u32>(),
+        "[a-zA-Z0-9@.-]{1,64}",
+        vec((2..=8u8, vec(any::<u8>(), 0..=32)), 0..=2),
+    )
+        .prop_map(|(ssrc, cname, other_items)| {
+            let mut sdes_items = vec![SdesItem::Cname(cname)];
+            sdes_items.extend(
+                other_items
+                    .into_iter()
+                    .map(|(item_type, data)| SdesItem::Unknown { item_type, data }),
+            );
+            SdesChunk { ssrc, sdes_items }
+        })
+}
+
+pub fn rtcp_sdes_packet() -> impl Strategy<Value = RtcpSdesPacket> {
+    vec(sdes_chunk(), 1..=4).prop_map(|chunks| {
+        let mut sdes = RtcpSdesPacket {
+            header: unsynced_header(u5::new(0)),
+            chunks,
+        };
+        sdes.sync().expect("generated SDES is valid");
+        sdes
+    })
+}
+
+pub fn rtcp_bye_packet() -> impl Strategy<Value = RtcpByePacket> {
+    (vec(any::<u32>(), 0..=31), option::of("[ -~]{0,64}")).prop_map(|(ssrcs, reason)| {
+        let mut bye = RtcpByePacket {
+            header: unsynced_header(u5::new(0)),
+            ssrcs,
+            reason,
+        };
+        bye.sync().expect("generated BYE is valid");
+        bye
+    })
+}
+
+pub fn rtcp_app_packet() -> impl Strategy<Value = RtcpAppPacket> {
+    (
+        0..=u8::from(u5::MAX),
+        any::<u32>(),
+        "[A-Z]{4}",
+        vec(any::<[u8; 4]>(), 0..=8),
+    )
+        .prop_map(|(subtype, ssrc, name, data)| {
+            let mut app = RtcpAppPacket {
+                header: unsynced_header(u5::new(subtype)),
+                ssrc,
+                name: name.as_bytes().try_into().expect("name is 4 bytes"),
+                data: data.concat(),
+            };
+            app.sync().expect("generated APP is valid");
+            app
+        })
+}
+
+fn rtcp_fb_header() -> impl Strategy<Value = RtcpFbHeader> {
+    (any::<u32>(), any::<u32>()).prop_map(|(sender_ssrc, media_source_ssrc)| RtcpFbHeader {
+        sender_ssrc,
+        media_source_ssrc,
+    })
+}
+
+/// NACKs for up to 64 sequence numbers within a window of 512, which may wrap around
+pub fn rtcp_fb_nack_packet() -> impl Strategy<Value = RtcpFbNackPacket> {
+    (rtcp_fb_header(), any::<u16>(), btree_set(0..512u16, 1..=64)).prop_map(
+        |(fb_header, base_seq_num, offsets)| {
+            let mut nack = RtcpFbNackPacket {
+                header: unsynced_header(u5::new(0)),
+                fb_header,
+                missing_seq_nums: offsets
+                    .into_iter()
+                    .map(|offset| base_seq_num.wrapping_add(offset))
+                    .collect(),
+            };
+            nack.sync().expect("generated NACK is valid");
+            nack
+        },
+    )
+}
+
+pub fn rtcp_fb_pli_packet() -> impl Strategy<Value = RtcpFbPliPacket> {
+    rtcp_fb_header().prop_map(|fb_header| {
+        let mut pli = RtcpFbPliPacket {
+            header: unsynced_header(u5::new(0)),
+            fb_header,
+        };
+        pli.sync();
+        pli
+    })
+}
+
+pub fn rtcp_fb_remb_packet() -> impl Strategy<Value = RtcpFbRembPacket> {
+    (
+        rtcp_fb_header(),
+        0..=u8::from(u6::MAX),
+        0..=u32::from(u18::MAX),
+        vec(any::<u32>(), 0..=8),
+    )
+        .prop_map(|(fb_header, br_exp, br_mantissa, ssrcs)| {
+            let mut remb = RtcpFbRembPacket {
+                header: unsynced_header(u5::new(0)),
+                fb_header,
+                br_exp: u6::new(br_exp),
+                br_mantissa: u18::new(br_mantissa),
+                ssrcs,
+            };
+            remb.sync().expect("generated REMB is valid");
+            remb
+        })
+}
+
+/// TCC feedback built from runs of received and lost packets, so it contains a mix of run
+/// length and status vector chunks, with small, large and negative receive deltas
+pub fn rtcp_fb_tcc_packet() -> impl Strategy<Value = RtcpFbTccPacket> {
+    (
+        any::<u32>(),
+        any::<u32>(),
+        any::<u8>(),
+        any::<u16>(),
+        0..=1_000_000_000u64,
+        vec((any::<bool>(), 1..=20u16, -10_000..=100_000i64), 0..=20),
+    )
+        .prop_map(
+            |(
+                sender_ssrc,
+                media_source_ssrc,
+                feedback_packet_count,
+                base_seq_num,
+                first_arrival_time_us,
+                runs,
+            )| {
+                let mut builder =
+                    RtcpFbTccBuilder::new(sender_ssrc, media_source_ssrc, feedback_packet_count);
+                let mut seq_num = base_seq_num;
+                let mut arrival_time_us = first_arrival_time_us as i64;
+                builder.add_received_packet(seq_num, Duration::from_micros(first_arrival_time_us));
+                for (received, run_length, delta_us) in runs {
+                    for _ in 0..run_length {
+                        seq_num = seq_num.wrapping_add(1);
+                        if received {
+                            arrival_time_us = (arrival_time_us + delta_us).max(0);
+                            builder.add_received_packet(
+                                seq_num,
+                                Duration::from_micros(arrival_time_us as u64),
+                            );
+                        }
+                    }
+                }
+                builder
+                    .build()
+                    .expect("generated TCC feedback is valid")
+                    .remove(0)
+            },
+        )
+}
+
+/// Any of the (non-compound) packet types there are strategies for
+pub fn rtcp_packet() -> impl Strategy<Value = SomeRtcpPacket> {
+    prop_oneof![
+        rtcp_sr_packet().prop_map(SomeRtcpPacket::RtcpSrPacket),
+        rtcp_rr_packet().prop_map(SomeRtcpPacket::RtcpRrPacket),
+        rtcp_sdes_packet().prop_map(SomeRtcpPacket::RtcpSdesPacket),
+        rtcp_bye_packet().prop_map(SomeRtcpPacket::RtcpByePacket),
+        rtcp_app_packet().prop_map(SomeRtcpPacket::RtcpAppPacket),
+        rtcp_fb_nack_packet().prop_map(SomeRtcpPacket::RtcpFbNackPacket),
+        rtcp_fb_pli_packet().prop_map(SomeRtcpPacket::RtcpFbPliPacket),
+        rtcp_fb_remb_packet().prop_map(SomeRtcpPacket::RtcpFbRembPacket),
+        rtcp_fb_tcc_packet().prop_map(SomeRtcpPacket::RtcpFbTccPacket),
+    ]
+}
+
+/// Compound packets made up of an SR or RR, an SDES with a CNAME, up to 4 feedback or APP
+/// packets and optionally a BYE
+pub fn compound_rtcp_packet() -> impl Strategy<Value = SomeRtcpPacket> {
+    (
+        prop_oneof![
+            rtcp_sr_packet().prop_map(SomeRtcpPacket::RtcpSrPacket),
+            rtcp_rr_packet().prop_map(SomeRtcpPacket::RtcpRrPacket),
+        ],
+        rtcp_sdes_packet(),
+        vec(
+            prop_oneof![
+                rtcp_app_packet().prop_map(SomeRtcpPacket::RtcpAppPacket),
+                rtcp_fb_nack_packet().prop_map(SomeRtcpPacket::RtcpFbNackPacket),
+                rtcp_fb_pli_packet().prop_map(SomeRtcpPacket::RtcpFbPliPacket),
+                rtcp_fb_remb_packet().prop_map(SomeRtcpPacket::RtcpFbRembPacket),
+                rtcp_fb_tcc_packet().prop_map(SomeRtcpPacket::RtcpFbTccPacket),
+            ],
+            0..=4,
+        ),
+        option::of(rtcp_bye_packet()),
+    )
+        .prop_map(|(report, sdes, others, bye)| {
+            let mut builder = RtcpCompoundBuilder::new()
+                .add_packet(report)
+                .add_packet(SomeRtcpPacket::RtcpSdesPacket(sdes));
+            for packet in others {
+                builder = builder.add_packet(packet);
+            }
+            if let Some(bye) = bye {
+                builder = builder.add_packet(SomeRtcpPacket::RtcpByePacket(bye));
+            }
+            builder.build().expect("generated compound packet is valid")
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use bit_cursor::bit_cursor::BitCursor;
+    use bitvec::{order::Msb0, vec::BitVec};
+
+    use crate::{
+        parse_mode::ParseMode,
+        rtcp::rtcp_packet::{
+            parse_rtcp_packet_with_context, write_some_rtcp_packet, RtcpParseContext,
+        },
+        rtp::rtp_packet::{read_rtp_packet, write_rtp_packet},
+        LengthBytes,
+    };
+
+    use super::*;
+
+    fn write_rtp(packet: &RtpPacket) -> Vec<u8> {
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::repeat(false, packet.length_bytes() * 8));
+        write_rtp_packet(&mut cursor, packet).unwrap();
+        cursor.into_inner().into_vec()
+    }
+
+    fn write_rtcp(packet: &SomeRtcpPacket) -> Vec<u8> {
+        let mut cursor =
+            BitCursor::new(BitVec::<u8, Msb0>::repeat(false, packet.length_bytes() * 8));
+        write_some_rtcp_packet(&mut cursor, packet).unwrap();
+        cursor.into_inner().into_vec()
+    }
+
+    /// Parse the written packet in strict mode and check that it's written back out the same
+    fn check_rtcp_round_trip(packet: &SomeRtcpPacket, reduced_size: bool) {
+        let data = write_rtcp(packet);
+        let context = RtcpParseContext {
+            reduced_size,
+            mode: ParseMode::Strict,
+            ..Default::default()
+        };
+        let mut cursor = BitCursor::new(BitVec::<u8, Msb0>::from_vec(data.clone()));
+        let (read_packet, warnings) = parse_rtcp_packet_with_context(&mut cursor, &context)
+            .unwrap_or_else(|e| panic!("{packet:?} didn't parse: {e:?}"));
+        assert!(warnings.is_empty());
+        assert_eq!(write_rtcp(&read_packet), data);
+    }
+
+    proptest! {
+        #[test]
+        fn test_rtp_packet_round_trip(packet in rtp_packet()) {
+            let data = write_rtp(&packet);
+            let read_packet = read_rtp_packet(data.clone()).unwrap();
+            prop_assert_eq!(read_packet.payload(), packet.payload());
+            prop_assert_eq!(read_packet.padding_len(), packet.padding_len());
+            prop_assert_eq!(write_rtp(&read_packet), data);
+        }
+
+        #[test]
+        fn test_rtcp_packet_round_trip(packet in rtcp_packet()) {
+            check_rtcp_round_trip(&packet, true);
+        }
+
+        #[test]
+        fn test_compound_rtcp_packet_round_trip(packet in compound_rtcp_packet()) {
+            check_rtcp_round_trip(&packet, false);
+        }
+    }
+}
@@ -3,7 +3,9 @@ use std::{
     ops::RangeInclusive,
 };
 
-use crate::rtcp::rtcp_header::RtcpHeader;
+use anyhow::{bail, Result};
+
+use crate::{error::RtpParseError, rtcp::rtcp_header::RtcpHeader};
 
 pub fn consume_padding<R: Read + Seek>(buf: &mut R) {
     let mut data_buf = [0u8; 1];
@@ -68,3 +70,53 @@ pub fn looks_like_dtls(buf: &[u8]) -> bool {
 
     DTLS_RANGE.contains(&buf[0])
 }
+
+/// Read a leb128 encoded value, returning it and the number of bytes it took
+pub fn read_leb128(data: &[u8]) -> Result<(u32, usize)> {
+    let mut value = 0u64;
+    for (i, byte) in data.iter().enumerate().take(5) {
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            let Ok(value) = u32::try_from(value) else {
+                bail!(RtpParseError::invalid_field(
+                    "leb128 value",
+                    "at most 32 bits",
+                    value
+                ));
+            };
+            return Ok((value, i + 1));
+        }
+    }
+
+    if data.len() >= 5 {
+        bail!(RtpParseError::invalid_field(
+            "leb128 length",
+            "at most 5 bytes",
+            "more than 5 bytes"
+        ));
+    }
+    bail!(RtpParseError::truncated(data.len() + 1, data.len()))
+}
+
+pub fn write_leb128(data: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        data.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    data.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leb128_roundtrip() {
+        for value in [0, 1, 127, 128, 300, 16_384, u32::MAX] {
+            let mut data = Vec::new();
+            write_leb128(&mut data, value);
+            assert_eq!(read_leb128(&data).unwrap(), (value, data.len()));
+        }
+        assert!(read_leb128(&[0x80, 0x80]).is_err());
+    }
+}